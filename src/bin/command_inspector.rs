@@ -0,0 +1,81 @@
+use std::env;
+use std::path::PathBuf;
+use theta_engine::engine::Engine;
+use theta_engine::network::command_log::AuthorId;
+use theta_engine::network::inspector::{
+    CommandCapture, CommandFilter, CommandInspector, parse_command_id, parse_scope,
+};
+
+const USAGE: &str = "usage: command_inspector <capture.jsonl> [--author <id>] \
+[--scope global|entity:<index>:<generation>|tool:<name>] [--type <type>[*]] \
+[--until <lamport>:<author>] [--state] [--json]";
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("[inspector] error: {err}");
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = env::args().skip(1);
+    let mut capture_path = None;
+    let mut filter = CommandFilter::new();
+    let mut until = None;
+    let mut state = false;
+    let mut json = false;
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("{arg} needs a value\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--author" => filter = filter.author(AuthorId(value()?.parse()?)),
+            "--scope" => {
+                let text = value()?;
+                let scope = parse_scope(&text).ok_or_else(|| format!("invalid scope `{text}`"))?;
+                filter = filter.scope(scope);
+            }
+            "--type" => filter = filter.command_type(value()?),
+            "--until" => until = Some(parse_command_id(&value()?)?),
+            "--state" => state = true,
+            "--json" => json = true,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            _ if capture_path.is_none() && !arg.starts_with("--") => {
+                capture_path = Some(PathBuf::from(arg))
+            }
+            _ => return Err(format!("unexpected argument `{arg}`\n{USAGE}").into()),
+        }
+    }
+
+    let capture_path = capture_path.ok_or(USAGE)?;
+    let capture = CommandCapture::read(&capture_path)?;
+    let entries = match &until {
+        Some(id) => capture.entries_until(id)?,
+        None => capture.entries(),
+    };
+
+    if state {
+        let world_state = Engine::replay(entries).world_state();
+        println!("{}", serde_json::to_string_pretty(&world_state)?);
+        return Ok(());
+    }
+
+    let inspector = CommandInspector::new(CommandCapture::new(entries.to_vec()));
+    #[cfg(feature = "network-quic")]
+    let inspector = inspector.with_verifier(std::sync::Arc::new(
+        theta_engine::network::command_log::Ed25519SignatureVerifier,
+    ));
+    for entry in inspector.inspect(&filter) {
+        if json {
+            println!("{}", serde_json::to_string(&entry)?);
+        } else {
+            println!("{entry}");
+        }
+    }
+    Ok(())
+}
@@ -61,6 +61,10 @@ impl<T: Component> ComponentMap<T> {
         self.entries.get_mut(&entity)
     }
 
+    fn take(&mut self, entity: Entity) -> Option<T> {
+        self.entries.remove(&entity)
+    }
+
     fn iter(&self) -> impl Iterator<Item = (&Entity, &T)> {
         self.entries.iter()
     }
@@ -92,6 +96,9 @@ impl<T: Component> AnyStorage for ComponentMap<T> {
 #[derive(Debug)]
 pub enum EcsError {
     NoSuchEntity(Entity),
+    EntityOccupied(Entity),
+    IndexOutOfRange(Entity),
+    StaleGeneration(Entity),
 }
 
 impl fmt::Display for EcsError {
@@ -100,12 +107,24 @@ impl fmt::Display for EcsError {
             EcsError::NoSuchEntity(entity) => {
                 write!(f, "entity {entity:?} is not alive in this world")
             }
+            EcsError::EntityOccupied(entity) => {
+                write!(f, "entity slot {} is already alive", entity.index)
+            }
+            EcsError::IndexOutOfRange(entity) => {
+                write!(f, "entity slot {} is too far past the table", entity.index)
+            }
+            EcsError::StaleGeneration(entity) => {
+                write!(f, "entity {entity:?} predates its slot's generation")
+            }
         }
     }
 }
 
 impl std::error::Error for EcsError {}
 
+/// Most slots [`World::spawn_at`] may add to the entity table in one call.
+pub const MAX_SPAWN_AT_GROWTH: usize = 4096;
+
 /// Central ECS storage containing entity state and component tables.
 #[derive(Default)]
 pub struct World {
@@ -134,6 +153,45 @@ impl World {
         }
     }
 
+    /// Returns the handle the next call to [`World::spawn`] will produce.
+    pub fn next_entity(&self) -> Entity {
+        match self.free_list.last() {
+            Some(index) => Entity::new(*index, self.entities[*index as usize].generation),
+            None => Entity::new(self.entities.len() as u32, 0),
+        }
+    }
+
+    /// Spawns an entity with an exact handle, e.g. one chosen by a remote peer.
+    /// Fails when the slot is already alive, lies more than [`MAX_SPAWN_AT_GROWTH`]
+    /// slots past the table, or has seen a later generation.
+    pub fn spawn_at(&mut self, entity: Entity) -> Result<Entity, EcsError> {
+        let index = entity.index as usize;
+        if index >= self.entities.len() + MAX_SPAWN_AT_GROWTH {
+            return Err(EcsError::IndexOutOfRange(entity));
+        }
+        if let Some(record) = self.entities.get(index) {
+            if record.alive {
+                return Err(EcsError::EntityOccupied(entity));
+            }
+            if entity.generation < record.generation {
+                return Err(EcsError::StaleGeneration(entity));
+            }
+        }
+        while self.entities.len() <= index {
+            let hole = self.entities.len() as u32;
+            self.entities.push(EntityRecord::default());
+            if hole as usize != index {
+                self.free_list.push(hole);
+            }
+        }
+
+        let record = &mut self.entities[index];
+        record.alive = true;
+        record.generation = entity.generation;
+        self.free_list.retain(|free| *free != entity.index);
+        Ok(entity)
+    }
+
     pub fn despawn(&mut self, entity: Entity) -> Result<(), EcsError> {
         self.validate_entity(entity)?;
         let record = &mut self.entities[entity.index as usize];
@@ -183,6 +241,13 @@ impl World {
         self.typed_storage_mut::<T>()?.get_mut(entity)
     }
 
+    pub fn remove<T: Component>(&mut self, entity: Entity) -> Option<T> {
+        if !self.contains(entity) {
+            return None;
+        }
+        self.typed_storage_mut::<T>()?.take(entity)
+    }
+
     pub fn contains(&self, entity: Entity) -> bool {
         self.entities
             .get(entity.index as usize)
@@ -272,6 +337,19 @@ mod tests {
         assert!(world.get::<Velocity>(entity).is_none());
     }
 
+    #[test]
+    fn remove_detaches_single_component() {
+        let mut world = World::new();
+        let entity = world.spawn();
+        world.insert(entity, Health(7)).unwrap();
+        world.insert(entity, Velocity(1.0, 0.0, 0.0)).unwrap();
+
+        assert_eq!(world.remove::<Health>(entity), Some(Health(7)));
+        assert!(world.get::<Health>(entity).is_none());
+        assert!(world.get::<Velocity>(entity).is_some());
+        assert!(world.remove::<Health>(entity).is_none());
+    }
+
     #[test]
     fn generations_increment_after_despawn() {
         let mut world = World::new();
@@ -284,6 +362,47 @@ mod tests {
         assert_ne!(entity_a.generation(), entity_b.generation());
     }
 
+    #[test]
+    fn spawn_at_claims_exact_slots() {
+        let mut world = World::new();
+        let first = world.spawn();
+        assert_eq!(world.next_entity(), Entity::new(1, 0));
+
+        let remote = Entity::new(3, 2);
+        assert_eq!(world.spawn_at(remote).unwrap(), remote);
+        assert!(world.contains(remote));
+        assert!(matches!(
+            world.spawn_at(first),
+            Err(EcsError::EntityOccupied(_))
+        ));
+
+        // the skipped slots are handed out by regular spawns
+        let mut spawned = vec![world.spawn(), world.spawn()];
+        spawned.sort_by_key(|entity| entity.index());
+        assert_eq!(spawned, vec![Entity::new(1, 0), Entity::new(2, 0)]);
+        assert_eq!(world.next_entity(), Entity::new(4, 0));
+    }
+
+    #[test]
+    fn spawn_at_rejects_far_and_stale_slots() {
+        let mut world = World::new();
+        let far = Entity::new(MAX_SPAWN_AT_GROWTH as u32, 0);
+        assert!(matches!(
+            world.spawn_at(far),
+            Err(EcsError::IndexOutOfRange(_))
+        ));
+        assert_eq!(world.next_entity(), Entity::new(0, 0));
+
+        let entity = world.spawn();
+        world.despawn(entity).unwrap();
+        assert!(matches!(
+            world.spawn_at(entity),
+            Err(EcsError::StaleGeneration(_))
+        ));
+        let reused = Entity::new(entity.index(), entity.generation() + 1);
+        assert_eq!(world.spawn_at(reused).unwrap(), reused);
+    }
+
     #[test]
     fn insert_auto_registers_storage() {
         let mut world = World::new();
@@ -304,6 +423,7 @@ mod tests {
         let err = world.despawn(stale).expect_err("stale entity should error");
         match err {
             EcsError::NoSuchEntity(entity) => assert_eq!(entity, stale),
+            other => panic!("unexpected error {other}"),
         }
     }
 }
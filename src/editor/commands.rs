@@ -1,5 +1,7 @@
+use crate::editor::mesh_io::{MeshData, NodeTransform};
+use crate::editor::primitives::PrimitiveShape;
 use crate::network::EntityHandle;
-use crate::network::command_log::{CommandBatch, CommandPacket};
+use crate::network::command_log::{CommandBatch, CommandPacket, PacketEncoding};
 use serde::de::Deserializer;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -12,8 +14,21 @@ pub const CMD_TOOL_DEACTIVATE: &str = "editor.tool.deactivate";
 pub const CMD_MESH_VERTEX_CREATE: &str = "editor.mesh.vertex_create";
 pub const CMD_MESH_EDGE_EXTRUDE: &str = "editor.mesh.edge_extrude";
 pub const CMD_MESH_FACE_SUBDIVIDE: &str = "editor.mesh.face_subdivide";
+pub const CMD_MESH_CREATE_PRIMITIVE: &str = "editor.mesh.create_primitive";
+pub const CMD_MESH_FACE_EXTRUDE: &str = "editor.mesh.face_extrude";
+pub const CMD_MESH_FACE_INSET: &str = "editor.mesh.face_inset";
+pub const CMD_MESH_EDGE_BEVEL: &str = "editor.mesh.edge_bevel";
+pub const CMD_MESH_LOOP_CUT: &str = "editor.mesh.loop_cut";
+pub const CMD_MESH_BRIDGE_LOOPS: &str = "editor.mesh.bridge_loops";
+pub const CMD_MESH_MERGE_BY_DISTANCE: &str = "editor.mesh.merge_by_distance";
+pub const CMD_MESH_DELETE: &str = "editor.mesh.delete";
+pub const CMD_MESH_IMPORT: &str = "editor.mesh.import";
 
 pub const CMD_SELECTION_HIGHLIGHT: &str = "editor.selection.highlight";
+pub const CMD_SELECTION_ADD: &str = "editor.selection.add";
+pub const CMD_SELECTION_REMOVE: &str = "editor.selection.remove";
+pub const CMD_SELECTION_TOGGLE: &str = "editor.selection.toggle";
+pub const CMD_SELECTION_CLEAR: &str = "editor.selection.clear";
 
 fn deserialize_metadata<'de, D>(deserializer: D) -> Result<HashMap<String, String>, D::Error>
 where
@@ -35,6 +50,49 @@ impl SelectionHighlightCommand {
     }
 }
 
+/// Adds `entities` to the selection set; the last one becomes active.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SelectionAddCommand {
+    pub entities: Vec<EntityHandle>,
+}
+
+impl SelectionAddCommand {
+    pub fn new(entities: Vec<EntityHandle>) -> Self {
+        Self { entities }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SelectionRemoveCommand {
+    pub entities: Vec<EntityHandle>,
+}
+
+impl SelectionRemoveCommand {
+    pub fn new(entities: Vec<EntityHandle>) -> Self {
+        Self { entities }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SelectionToggleCommand {
+    pub entities: Vec<EntityHandle>,
+}
+
+impl SelectionToggleCommand {
+    pub fn new(entities: Vec<EntityHandle>) -> Self {
+        Self { entities }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SelectionClearCommand {}
+
+impl SelectionClearCommand {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct EntityTranslateCommand {
     pub entity: EntityHandle,
@@ -102,23 +160,46 @@ pub struct VertexCreateCommand {
     pub position: [f32; 3],
     #[serde(default, deserialize_with = "deserialize_metadata")]
     pub metadata: HashMap<String, String>,
+    /// Entity carrying the target mesh; `None` edits the editor's scratch mesh.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<EntityHandle>,
 }
 
 impl VertexCreateCommand {
     pub fn new(position: [f32; 3], metadata: HashMap<String, String>) -> Self {
-        Self { position, metadata }
+        Self {
+            position,
+            metadata,
+            mesh: None,
+        }
+    }
+
+    pub fn on_mesh(mut self, mesh: EntityHandle) -> Self {
+        self.mesh = Some(mesh);
+        self
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct EdgeExtrudeCommand {
-    pub edge_id: u32,
+    pub edge_id: u64,
     pub direction: [f32; 3],
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<EntityHandle>,
 }
 
 impl EdgeExtrudeCommand {
-    pub fn new(edge_id: u32, direction: [f32; 3]) -> Self {
-        Self { edge_id, direction }
+    pub fn new(edge_id: u64, direction: [f32; 3]) -> Self {
+        Self {
+            edge_id,
+            direction,
+            mesh: None,
+        }
+    }
+
+    pub fn on_mesh(mut self, mesh: EntityHandle) -> Self {
+        self.mesh = Some(mesh);
+        self
     }
 }
 
@@ -139,14 +220,239 @@ impl Default for SubdivideParams {
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct FaceSubdivideCommand {
-    pub face_id: u32,
+    pub face_id: u64,
     #[serde(default)]
     pub params: SubdivideParams,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<EntityHandle>,
 }
 
 impl FaceSubdivideCommand {
-    pub fn new(face_id: u32, params: SubdivideParams) -> Self {
-        Self { face_id, params }
+    pub fn new(face_id: u64, params: SubdivideParams) -> Self {
+        Self {
+            face_id,
+            params,
+            mesh: None,
+        }
+    }
+
+    pub fn on_mesh(mut self, mesh: EntityHandle) -> Self {
+        self.mesh = Some(mesh);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FaceExtrudeCommand {
+    pub face_id: u64,
+    pub distance: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<EntityHandle>,
+}
+
+impl FaceExtrudeCommand {
+    pub fn new(face_id: u64, distance: f32) -> Self {
+        Self {
+            face_id,
+            distance,
+            mesh: None,
+        }
+    }
+
+    pub fn on_mesh(mut self, mesh: EntityHandle) -> Self {
+        self.mesh = Some(mesh);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FaceInsetCommand {
+    pub face_id: u64,
+    pub amount: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<EntityHandle>,
+}
+
+impl FaceInsetCommand {
+    pub fn new(face_id: u64, amount: f32) -> Self {
+        Self {
+            face_id,
+            amount,
+            mesh: None,
+        }
+    }
+
+    pub fn on_mesh(mut self, mesh: EntityHandle) -> Self {
+        self.mesh = Some(mesh);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EdgeBevelCommand {
+    pub edge_id: u64,
+    pub width: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<EntityHandle>,
+}
+
+impl EdgeBevelCommand {
+    pub fn new(edge_id: u64, width: f32) -> Self {
+        Self {
+            edge_id,
+            width,
+            mesh: None,
+        }
+    }
+
+    pub fn on_mesh(mut self, mesh: EntityHandle) -> Self {
+        self.mesh = Some(mesh);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LoopCutCommand {
+    pub edge_id: u64,
+    pub factor: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<EntityHandle>,
+}
+
+impl LoopCutCommand {
+    pub fn new(edge_id: u64, factor: f32) -> Self {
+        Self {
+            edge_id,
+            factor,
+            mesh: None,
+        }
+    }
+
+    pub fn on_mesh(mut self, mesh: EntityHandle) -> Self {
+        self.mesh = Some(mesh);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BridgeEdgeLoopsCommand {
+    pub first: Vec<u64>,
+    pub second: Vec<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<EntityHandle>,
+}
+
+impl BridgeEdgeLoopsCommand {
+    pub fn new(first: Vec<u64>, second: Vec<u64>) -> Self {
+        Self {
+            first,
+            second,
+            mesh: None,
+        }
+    }
+
+    pub fn on_mesh(mut self, mesh: EntityHandle) -> Self {
+        self.mesh = Some(mesh);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MergeByDistanceCommand {
+    pub distance: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<EntityHandle>,
+}
+
+impl MergeByDistanceCommand {
+    pub fn new(distance: f32) -> Self {
+        Self {
+            distance,
+            mesh: None,
+        }
+    }
+
+    pub fn on_mesh(mut self, mesh: EntityHandle) -> Self {
+        self.mesh = Some(mesh);
+        self
+    }
+}
+
+/// Mesh element addressed by a delete command.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", content = "id", rename_all = "snake_case")]
+pub enum MeshElement {
+    Vertex(u64),
+    Edge(u64),
+    Face(u64),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MeshDeleteCommand {
+    pub element: MeshElement,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<EntityHandle>,
+}
+
+impl MeshDeleteCommand {
+    pub fn new(element: MeshElement) -> Self {
+        Self {
+            element,
+            mesh: None,
+        }
+    }
+
+    pub fn on_mesh(mut self, mesh: EntityHandle) -> Self {
+        self.mesh = Some(mesh);
+        self
+    }
+}
+
+/// Spawns `entity` carrying a generated primitive mesh. The entity index is chosen by the
+/// author so every peer materialises the primitive in the same slot.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CreatePrimitiveCommand {
+    pub entity: EntityHandle,
+    pub shape: PrimitiveShape,
+    #[serde(default)]
+    pub position: [f32; 3],
+    #[serde(default)]
+    pub rotation: Quaternion,
+}
+
+impl CreatePrimitiveCommand {
+    pub fn new(entity: EntityHandle, shape: PrimitiveShape, position: [f32; 3]) -> Self {
+        Self {
+            entity,
+            shape,
+            position,
+            rotation: Quaternion::default(),
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: Quaternion) -> Self {
+        self.rotation = rotation;
+        self
+    }
+}
+
+/// Spawns `entity` with imported geometry. The mesh travels in full, so peers do not
+/// need the source file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ImportMeshCommand {
+    pub entity: EntityHandle,
+    pub data: MeshData,
+    #[serde(default)]
+    pub transform: NodeTransform,
+}
+
+impl ImportMeshCommand {
+    pub fn new(entity: EntityHandle, data: MeshData, transform: NodeTransform) -> Self {
+        Self {
+            entity,
+            data,
+            transform,
+        }
     }
 }
 
@@ -162,6 +468,32 @@ impl Quaternion {
     pub fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
         Self { x, y, z, w }
     }
+
+    /// Hamilton product `self * other`: applies `other` first, then `self`.
+    pub fn compose(self, other: Quaternion) -> Quaternion {
+        let (a, b) = (self, other);
+        Quaternion {
+            x: a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+            y: a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+            z: a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+            w: a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+        }
+    }
+
+    pub fn rotate(self, vector: [f32; 3]) -> [f32; 3] {
+        let Quaternion { x, y, z, w } = self;
+        // t = 2 * cross(q.xyz, v); v' = v + w * t + cross(q.xyz, t)
+        let t = [
+            2.0 * (y * vector[2] - z * vector[1]),
+            2.0 * (z * vector[0] - x * vector[2]),
+            2.0 * (x * vector[1] - y * vector[0]),
+        ];
+        [
+            vector[0] + w * t[0] + (y * t[2] - z * t[1]),
+            vector[1] + w * t[1] + (z * t[0] - x * t[2]),
+            vector[2] + w * t[2] + (x * t[1] - y * t[0]),
+        ]
+    }
 }
 
 impl Default for Quaternion {
@@ -178,6 +510,7 @@ impl Default for Quaternion {
 #[derive(Debug, Default, Clone)]
 pub struct CommandOutbox {
     pending: Vec<CommandBatch>,
+    encoded: Vec<CommandPacket>,
     history: Vec<CommandBatch>,
     transmissions: Vec<CommandPacket>,
 }
@@ -197,6 +530,28 @@ impl CommandOutbox {
         }
     }
 
+    /// Queues packets that were already encoded (and split) upstream. Fragments are
+    /// forwarded untouched; complete packets are also decoded into the batch history.
+    pub fn ingest_packets<I>(&mut self, packets: I)
+    where
+        I: IntoIterator<Item = CommandPacket>,
+    {
+        for packet in packets {
+            if packet.fragment.is_none() {
+                match packet.decode() {
+                    Ok(batch) => self.history.push(batch),
+                    Err(err) => {
+                        log::error!(
+                            "[commands] failed to decode command packet seq {}: {err}",
+                            packet.sequence
+                        );
+                    }
+                }
+            }
+            self.encoded.push(packet);
+        }
+    }
+
     pub fn drain_pending(&mut self) -> Vec<CommandBatch> {
         self.pending.drain(..).collect()
     }
@@ -223,13 +578,12 @@ impl CommandOutbox {
 
     pub fn drain_packets(&mut self) -> Vec<CommandPacket> {
         let pending = self.drain_pending();
-        let mut packets = Vec::with_capacity(pending.len());
+        let mut packets: Vec<CommandPacket> = self.encoded.drain(..).collect();
         for batch in pending {
-            match CommandPacket::from_batch(&batch) {
-                Ok(packet) => {
-                    self.transmissions.push(packet.clone());
-                    packets.push(packet);
-                }
+            // Batches here already carry a nonce, so oversized ones are fragmented rather
+            // than split into renumbered batches.
+            match CommandPacket::encode_fragmented(&batch, PacketEncoding::Json) {
+                Ok(encoded) => packets.extend(encoded),
                 Err(err) => {
                     log::error!(
                         "[commands] failed to serialize command batch {}: {err}",
@@ -238,6 +592,7 @@ impl CommandOutbox {
                 }
             }
         }
+        self.transmissions.extend(packets.iter().cloned());
         packets
     }
 }
@@ -285,7 +640,7 @@ mod tests {
     use super::*;
     use crate::network::command_log::{
         AuthorId, CommandAuthor, CommandBatch, CommandEntry, CommandId, CommandPayload,
-        CommandRole, CommandScope, ConflictStrategy,
+        CommandRole, CommandScope, ConflictStrategy, PacketEncoding,
     };
     use std::collections::HashMap;
 
@@ -377,6 +732,8 @@ mod tests {
             nonce: 1,
             timestamp_ms: 100,
             payload: vec![1, 2, 3],
+            encoding: PacketEncoding::Json,
+            fragment: None,
         };
 
         queue.enqueue(vec![packet.clone()]);
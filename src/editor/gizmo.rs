@@ -0,0 +1,476 @@
+use crate::editor::commands::{
+    EntityRotateCommand, EntityScaleCommand, EntityTranslateCommand, Quaternion,
+};
+use crate::editor::selection::SelectionRay;
+use crate::network::EntityHandle;
+use crate::vr::ControllerState;
+
+/// Scale drags never shrink an axis below this fraction of its starting size.
+const MIN_SCALE_FACTOR: f32 = 0.01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GizmoMode {
+    #[default]
+    Translate,
+    Rotate,
+    Scale,
+}
+
+impl GizmoMode {
+    pub fn next(self) -> Self {
+        match self {
+            GizmoMode::Translate => GizmoMode::Rotate,
+            GizmoMode::Rotate => GizmoMode::Scale,
+            GizmoMode::Scale => GizmoMode::Translate,
+        }
+    }
+}
+
+/// World-space handle axis; the gizmo does not follow the target's rotation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl GizmoAxis {
+    pub const ALL: [GizmoAxis; 3] = [GizmoAxis::X, GizmoAxis::Y, GizmoAxis::Z];
+
+    pub fn vector(self) -> [f32; 3] {
+        match self {
+            GizmoAxis::X => [1.0, 0.0, 0.0],
+            GizmoAxis::Y => [0.0, 1.0, 0.0],
+            GizmoAxis::Z => [0.0, 0.0, 1.0],
+        }
+    }
+}
+
+/// Snapping steps applied to drags. Zero disables snapping for that mode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SnapIncrements {
+    pub translate: f32,
+    pub rotate_degrees: f32,
+    pub scale: f32,
+}
+
+impl Default for SnapIncrements {
+    fn default() -> Self {
+        Self {
+            translate: 0.05,
+            rotate_degrees: 15.0,
+            scale: 0.1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GizmoConfig {
+    pub handle_length: f32,
+    pub handle_radius: f32,
+    /// Trigger/grip value that starts a drag.
+    pub press_threshold: f32,
+    /// Value below which a held trigger/grip counts as released.
+    pub release_threshold: f32,
+    /// Minimum seconds between commands emitted for one drag; the release always flushes.
+    pub commit_interval: f32,
+    pub snap: SnapIncrements,
+}
+
+impl Default for GizmoConfig {
+    fn default() -> Self {
+        Self {
+            handle_length: 0.3,
+            handle_radius: 0.04,
+            press_threshold: 0.6,
+            release_threshold: 0.4,
+            commit_interval: 0.1,
+            snap: SnapIncrements::default(),
+        }
+    }
+}
+
+/// Current placement of the entity the gizmo is attached to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GizmoTarget {
+    pub entity: EntityHandle,
+    pub position: [f32; 3],
+    pub rotation: Quaternion,
+    pub scale: [f32; 3],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GizmoCommand {
+    Translate(EntityTranslateCommand),
+    Rotate(EntityRotateCommand),
+    Scale(EntityScaleCommand),
+}
+
+/// Turns controller input into transform commands. Trigger drags on a handle translate,
+/// rotate or scale along that axis depending on [`GizmoMode`]; grip drags move the target
+/// freely with the controller.
+#[derive(Debug, Clone, Default)]
+pub struct TransformGizmo {
+    pub mode: GizmoMode,
+    pub config: GizmoConfig,
+    hovered: Option<GizmoAxis>,
+    drag: Option<Drag>,
+    trigger_held: bool,
+    grip_held: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Drag {
+    /// `None` for a free grip drag.
+    axis: Option<GizmoAxis>,
+    mode: GizmoMode,
+    start: GizmoTarget,
+    start_controller: [f32; 3],
+    emitted: DragValue,
+    since_commit: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DragValue {
+    Offset([f32; 3]),
+    Angle(f32),
+    Scale([f32; 3]),
+}
+
+impl TransformGizmo {
+    pub fn new(config: GizmoConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    pub fn hovered(&self) -> Option<GizmoAxis> {
+        self.hovered
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Advances the gizmo by one frame and returns the commands to record, if any.
+    pub fn update(
+        &mut self,
+        controller: &ControllerState,
+        target: Option<GizmoTarget>,
+        delta_seconds: f32,
+    ) -> Vec<GizmoCommand> {
+        let trigger_pressed = press_edge(&mut self.trigger_held, controller.trigger, &self.config);
+        let grip_pressed = press_edge(&mut self.grip_held, controller.grip, &self.config);
+
+        let Some(target) = target else {
+            self.hovered = None;
+            self.drag = None;
+            return Vec::new();
+        };
+
+        if let Some(drag) = self.drag.as_mut() {
+            if drag.start.entity != target.entity {
+                self.drag = None;
+                return Vec::new();
+            }
+            let released = if drag.axis.is_some() {
+                !self.trigger_held
+            } else {
+                !self.grip_held
+            };
+            drag.since_commit += delta_seconds;
+            let value = drag.value(controller.pose.position, &self.config);
+            let due = released || drag.since_commit >= self.config.commit_interval;
+            let mut commands = Vec::new();
+            if due && value != drag.emitted {
+                commands.push(drag.command(value));
+                drag.emitted = value;
+                drag.since_commit = 0.0;
+            }
+            if released {
+                self.drag = None;
+            }
+            return commands;
+        }
+
+        let ray = SelectionRay::from_pose(&controller.pose);
+        self.hovered = self.pick_handle(&ray, target.position);
+        let start = |axis, mode| Drag {
+            axis,
+            mode,
+            start: target,
+            start_controller: controller.pose.position,
+            emitted: DragValue::initial(mode, &target),
+            since_commit: 0.0,
+        };
+        if trigger_pressed && let Some(axis) = self.hovered {
+            self.drag = Some(start(Some(axis), self.mode));
+        } else if grip_pressed {
+            self.drag = Some(start(None, GizmoMode::Translate));
+        }
+        Vec::new()
+    }
+
+    /// Nearest handle whose segment passes within `handle_radius` of the ray.
+    fn pick_handle(&self, ray: &SelectionRay, center: [f32; 3]) -> Option<GizmoAxis> {
+        GizmoAxis::ALL
+            .into_iter()
+            .filter_map(|axis| {
+                let (distance, along) =
+                    ray_segment_distance(ray, center, axis.vector(), self.config.handle_length)?;
+                (distance <= self.config.handle_radius).then_some((axis, along))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(axis, _)| axis)
+    }
+}
+
+impl DragValue {
+    fn initial(mode: GizmoMode, target: &GizmoTarget) -> Self {
+        match mode {
+            GizmoMode::Translate => DragValue::Offset([0.0; 3]),
+            GizmoMode::Rotate => DragValue::Angle(0.0),
+            GizmoMode::Scale => DragValue::Scale(target.scale),
+        }
+    }
+}
+
+impl Drag {
+    fn value(&self, controller: [f32; 3], config: &GizmoConfig) -> DragValue {
+        let moved = sub(controller, self.start_controller);
+        let snap = config.snap;
+        match (self.mode, self.axis) {
+            (_, None) => DragValue::Offset(moved.map(|value| snap_to(value, snap.translate))),
+            (GizmoMode::Translate, Some(axis)) => {
+                let distance = snap_to(dot(moved, axis.vector()), snap.translate);
+                DragValue::Offset(axis.vector().map(|value| value * distance))
+            }
+            (GizmoMode::Rotate, Some(axis)) => {
+                let normal = axis.vector();
+                let from = reject(sub(self.start_controller, self.start.position), normal);
+                let to = reject(sub(controller, self.start.position), normal);
+                if dot(from, from) <= f32::EPSILON || dot(to, to) <= f32::EPSILON {
+                    return self.emitted;
+                }
+                let angle = dot(normal, cross(from, to)).atan2(dot(from, to));
+                let step = snap.rotate_degrees.to_radians();
+                DragValue::Angle(snap_to(angle, step))
+            }
+            (GizmoMode::Scale, Some(axis)) => {
+                let stretch = dot(moved, axis.vector()) / config.handle_length.max(f32::EPSILON);
+                let factor = (1.0 + snap_to(stretch, snap.scale)).max(MIN_SCALE_FACTOR);
+                let mut scale = self.start.scale;
+                let index = axis as usize;
+                scale[index] *= factor;
+                DragValue::Scale(scale)
+            }
+        }
+    }
+
+    fn command(&self, value: DragValue) -> GizmoCommand {
+        let entity = self.start.entity;
+        match value {
+            DragValue::Offset(offset) => {
+                let DragValue::Offset(previous) = self.emitted else {
+                    unreachable!("translate drags only emit offsets")
+                };
+                GizmoCommand::Translate(EntityTranslateCommand::new(entity, sub(offset, previous)))
+            }
+            DragValue::Angle(angle) => {
+                let axis = self.axis.map_or([0.0, 1.0, 0.0], GizmoAxis::vector);
+                let (sin, cos) = (angle * 0.5).sin_cos();
+                let turn = Quaternion::new(axis[0] * sin, axis[1] * sin, axis[2] * sin, cos);
+                GizmoCommand::Rotate(EntityRotateCommand::new(
+                    entity,
+                    turn.compose(self.start.rotation),
+                ))
+            }
+            DragValue::Scale(scale) => GizmoCommand::Scale(EntityScaleCommand::new(entity, scale)),
+        }
+    }
+}
+
+/// Updates the held flag with hysteresis and reports a fresh press.
+fn press_edge(held: &mut bool, value: f32, config: &GizmoConfig) -> bool {
+    let was_held = *held;
+    *held = if was_held {
+        value >= config.release_threshold
+    } else {
+        value >= config.press_threshold
+    };
+    *held && !was_held
+}
+
+fn snap_to(value: f32, step: f32) -> f32 {
+    if step > 0.0 {
+        (value / step).round() * step
+    } else {
+        value
+    }
+}
+
+/// Closest approach between a ray and the segment `start + s * axis`, `s` in `[0, length]`.
+/// Returns the distance and how far along the ray it occurs.
+fn ray_segment_distance(
+    ray: &SelectionRay,
+    start: [f32; 3],
+    axis: [f32; 3],
+    length: f32,
+) -> Option<(f32, f32)> {
+    let w = sub(ray.origin, start);
+    let b = dot(ray.direction, axis);
+    let d = dot(ray.direction, w);
+    let e = dot(axis, w);
+    let denominator = 1.0 - b * b;
+    if denominator <= 1e-6 {
+        return None;
+    }
+    let t = (b * e - d) / denominator;
+    let s = (e + t * b).clamp(0.0, length);
+    let t = (s * b - d).max(0.0);
+    let gap = [
+        w[0] + ray.direction[0] * t - axis[0] * s,
+        w[1] + ray.direction[1] * t - axis[1] * s,
+        w[2] + ray.direction[2] * t - axis[2] * s,
+    ];
+    Some((dot(gap, gap).sqrt(), t))
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+/// Removes the component of `vector` along the unit `normal`.
+fn reject(vector: [f32; 3], normal: [f32; 3]) -> [f32; 3] {
+    let along = dot(vector, normal);
+    sub(vector, normal.map(|value| value * along))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vr::TrackedPose;
+
+    fn target() -> GizmoTarget {
+        GizmoTarget {
+            entity: EntityHandle {
+                index: 4,
+                generation: 0,
+            },
+            position: [0.0, 1.0, -1.0],
+            rotation: Quaternion::default(),
+            scale: [1.0; 3],
+        }
+    }
+
+    /// Controller held in front of the target, pointing at `aim` down -Z.
+    fn controller(aim: [f32; 3], trigger: f32) -> ControllerState {
+        ControllerState {
+            pose: TrackedPose {
+                position: [aim[0], aim[1], aim[2] + 0.5],
+                orientation: [0.0, 0.0, 0.0, 1.0],
+            },
+            trigger,
+            ..ControllerState::default()
+        }
+    }
+
+    #[test]
+    fn trigger_drag_on_a_handle_emits_snapped_coalesced_translations() {
+        let mut gizmo = TransformGizmo::default();
+        let on_x = [0.2, 1.0, -1.0];
+        assert!(
+            gizmo
+                .update(&controller(on_x, 0.0), Some(target()), 0.016)
+                .is_empty()
+        );
+        assert_eq!(gizmo.hovered(), Some(GizmoAxis::X));
+        assert!(
+            gizmo
+                .update(&controller(on_x, 1.0), Some(target()), 0.016)
+                .is_empty()
+        );
+        assert!(gizmo.is_dragging());
+
+        // Thirty small frames of motion collapse into a handful of commands.
+        let mut total = [0.0f32; 3];
+        let mut emitted = 0;
+        for frame in 1..=30 {
+            let offset = frame as f32 * 0.01;
+            let aim = [on_x[0] + offset, on_x[1] + offset, on_x[2]];
+            for command in gizmo.update(&controller(aim, 1.0), Some(target()), 0.016) {
+                let GizmoCommand::Translate(command) = command else {
+                    panic!("expected translation, got {command:?}");
+                };
+                for (sum, delta) in total.iter_mut().zip(command.delta) {
+                    *sum += delta;
+                }
+                emitted += 1;
+            }
+        }
+        let release = [on_x[0] + 0.31, on_x[1], on_x[2]];
+        for command in gizmo.update(&controller(release, 0.0), Some(target()), 0.016) {
+            let GizmoCommand::Translate(command) = command else {
+                panic!("expected translation");
+            };
+            total[0] += command.delta[0];
+            emitted += 1;
+        }
+        assert!(!gizmo.is_dragging());
+        assert!(emitted <= 6, "emitted {emitted} commands");
+        assert!((total[0] - 0.3).abs() < 1e-5, "total {total:?}");
+        assert_eq!(total[1], 0.0);
+    }
+
+    #[test]
+    fn rotate_and_scale_drags_snap_to_increments() {
+        let mut gizmo = TransformGizmo {
+            mode: GizmoMode::Rotate,
+            ..TransformGizmo::default()
+        };
+        let on_x = [0.2, 1.0, -1.0];
+        gizmo.update(&controller(on_x, 0.0), Some(target()), 0.016);
+        gizmo.update(&controller(on_x, 1.0), Some(target()), 0.016);
+        // Dragging the X handle rotates about X: swing the hand from +Z towards +Y.
+        let start = [0.2, 1.0, -0.5];
+        let angle = 40f32.to_radians();
+        let swung = [start[0], 1.0 + 0.5 * angle.sin(), -1.0 + 0.5 * angle.cos()];
+        let mut pose = controller(on_x, 0.0);
+        pose.pose.position = swung;
+        let commands = gizmo.update(&pose, Some(target()), 0.016);
+        let [GizmoCommand::Rotate(command)] = commands.as_slice() else {
+            panic!("expected one rotation, got {commands:?}");
+        };
+        // 40 degrees snaps to 45; the quaternion's x component is sin(22.5 degrees), negated
+        // because +Z towards +Y is a negative turn about X.
+        let expected = -(22.5f32.to_radians().sin());
+        assert!((command.rotation.x - expected).abs() < 1e-5, "{command:?}");
+
+        gizmo.mode = GizmoMode::Scale;
+        let on_y = [0.0, 1.2, -1.0];
+        gizmo.update(&controller(on_y, 0.0), Some(target()), 0.016);
+        assert_eq!(gizmo.hovered(), Some(GizmoAxis::Y));
+        gizmo.update(&controller(on_y, 1.0), Some(target()), 0.016);
+        let mut stretched = controller(on_y, 0.0);
+        stretched.pose.position[1] += 0.14;
+        let commands = gizmo.update(&stretched, Some(target()), 0.016);
+        let [GizmoCommand::Scale(command)] = commands.as_slice() else {
+            panic!("expected one scale, got {commands:?}");
+        };
+        assert_eq!(command.scale[0], 1.0);
+        assert!((command.scale[1] - 1.5).abs() < 1e-5, "{command:?}");
+    }
+}
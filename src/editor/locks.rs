@@ -0,0 +1,43 @@
+use crate::network::command_log::AuthorId;
+use crate::network::locks::EntityLease;
+
+/// Attached to entities while another author (or the local user) holds their lock.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntityLock {
+    pub holder: AuthorId,
+    pub expires_at_ms: u64,
+}
+
+impl EntityLock {
+    pub fn tint(&self) -> [f32; 4] {
+        lock_tint(&self.holder)
+    }
+}
+
+impl From<&EntityLease> for EntityLock {
+    fn from(lease: &EntityLease) -> Self {
+        Self {
+            holder: lease.holder.clone(),
+            expires_at_ms: lease.expires_at_ms,
+        }
+    }
+}
+
+/// Stable per-author highlight colour so every peer tints a locked object the same way.
+pub fn lock_tint(holder: &AuthorId) -> [f32; 4] {
+    const GOLDEN_RATIO: f32 = 0.618_034;
+    let hue = (holder.0 as f32 * GOLDEN_RATIO).fract();
+    let sector = hue * 6.0;
+    let x = 1.0 - (sector % 2.0 - 1.0).abs();
+    let (r, g, b) = match sector as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    [r, g, b, 0.35]
+}
+
+crate::register_component_types!(EntityLock);
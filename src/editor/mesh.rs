@@ -0,0 +1,599 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use thiserror::Error;
+
+mod operations;
+mod subdivision;
+
+pub use subdivision::{MAX_SUBDIVIDE_LEVELS, SubdivisionScheme};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct VertexId(pub u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct HalfEdgeId(pub u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct EdgeId(pub u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct FaceId(pub u64);
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum MeshError {
+    #[error("unknown vertex {0:?}")]
+    UnknownVertex(VertexId),
+    #[error("unknown edge {0:?}")]
+    UnknownEdge(EdgeId),
+    #[error("unknown face {0:?}")]
+    UnknownFace(FaceId),
+    #[error("face needs at least three distinct vertices")]
+    DegenerateFace,
+    #[error("half-edge {from:?} -> {to:?} already belongs to a face")]
+    NonManifoldEdge { from: VertexId, to: VertexId },
+    #[error("edge {0:?} is not on the mesh boundary")]
+    EdgeNotBoundary(EdgeId),
+    #[error("edge {0:?} has only one adjacent face")]
+    BoundaryEdge(EdgeId),
+    #[error("edges do not form a single boundary loop or chain")]
+    InvalidEdgeLoop,
+    #[error("edge loops differ in length or closure ({first} vs {second} vertices)")]
+    LoopMismatch { first: usize, second: usize },
+    #[error("mesh invariant violated: {0}")]
+    Corrupt(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshVertex {
+    pub position: [f32; 3],
+    pub metadata: HashMap<String, String>,
+    /// Imported shading normal; exporters derive one from the faces when absent.
+    pub normal: Option<[f32; 3]>,
+    pub uv: Option<[f32; 2]>,
+    half_edge: Option<HalfEdgeId>,
+}
+
+/// Directed edge owned by exactly one face. Boundary half-edges have no twin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HalfEdge {
+    pub origin: VertexId,
+    pub twin: Option<HalfEdgeId>,
+    pub next: HalfEdgeId,
+    pub prev: HalfEdgeId,
+    pub face: FaceId,
+    pub edge: EdgeId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MeshEdge {
+    half_edge: HalfEdgeId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MeshFace {
+    half_edge: HalfEdgeId,
+}
+
+/// Editable polygon mesh stored as a half-edge structure.
+///
+/// Element ids are allocated from monotonic counters, skipping ids in use, so peers that
+/// apply the same edits in the same order end up with identical ids and topology. Edits
+/// that may be applied in different orders allocate from a seed instead; see
+/// [`EditableMesh::with_id_seed`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EditableMesh {
+    vertices: BTreeMap<VertexId, MeshVertex>,
+    half_edges: BTreeMap<HalfEdgeId, HalfEdge>,
+    edges: BTreeMap<EdgeId, MeshEdge>,
+    faces: BTreeMap<FaceId, MeshFace>,
+    directed: BTreeMap<(VertexId, VertexId), HalfEdgeId>,
+    next_vertex: u64,
+    next_half_edge: u64,
+    next_edge: u64,
+    next_face: u64,
+}
+
+impl EditableMesh {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a mesh from positions and polygons indexing into them. Vertex ids follow
+    /// the order of `positions`, face ids the order of `polygons`.
+    pub fn from_polygons(positions: &[[f32; 3]], polygons: &[Vec<u32>]) -> Result<Self, MeshError> {
+        let mut mesh = Self::new();
+        let ids: Vec<VertexId> = positions
+            .iter()
+            .map(|position| mesh.add_vertex(*position, HashMap::new()))
+            .collect();
+        for polygon in polygons {
+            let face: Vec<VertexId> = polygon
+                .iter()
+                .map(|index| {
+                    ids.get(*index as usize)
+                        .copied()
+                        .ok_or(MeshError::UnknownVertex(VertexId(u64::from(*index))))
+                })
+                .collect::<Result<_, _>>()?;
+            mesh.add_face(&face)?;
+        }
+        Ok(mesh)
+    }
+
+    /// Single quad in the XZ plane centred on the origin, facing +Y.
+    pub fn quad(size: f32) -> Self {
+        let half = size * 0.5;
+        Self::from_polygons(
+            &[
+                [-half, 0.0, -half],
+                [-half, 0.0, half],
+                [half, 0.0, half],
+                [half, 0.0, -half],
+            ],
+            &[vec![0, 1, 2, 3]],
+        )
+        .expect("quad topology is valid")
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.faces.len()
+    }
+
+    pub fn half_edge_count(&self) -> usize {
+        self.half_edges.len()
+    }
+
+    pub fn vertex(&self, id: VertexId) -> Option<&MeshVertex> {
+        self.vertices.get(&id)
+    }
+
+    pub fn vertex_mut(&mut self, id: VertexId) -> Option<&mut MeshVertex> {
+        self.vertices.get_mut(&id)
+    }
+
+    pub fn half_edge(&self, id: HalfEdgeId) -> Option<&HalfEdge> {
+        self.half_edges.get(&id)
+    }
+
+    pub fn vertex_ids(&self) -> impl Iterator<Item = VertexId> + '_ {
+        self.vertices.keys().copied()
+    }
+
+    pub fn edge_ids(&self) -> impl Iterator<Item = EdgeId> + '_ {
+        self.edges.keys().copied()
+    }
+
+    pub fn face_ids(&self) -> impl Iterator<Item = FaceId> + '_ {
+        self.faces.keys().copied()
+    }
+
+    pub fn face_vertices(&self, face: FaceId) -> Result<Vec<VertexId>, MeshError> {
+        Ok(self
+            .face_half_edges(face)?
+            .into_iter()
+            .map(|id| self.half_edges[&id].origin)
+            .collect())
+    }
+
+    pub fn edge_vertices(&self, edge: EdgeId) -> Result<(VertexId, VertexId), MeshError> {
+        let half_edge = self.edge_half_edge(edge)?;
+        Ok((
+            self.half_edges[&half_edge].origin,
+            self.destination(half_edge),
+        ))
+    }
+
+    pub fn is_boundary_edge(&self, edge: EdgeId) -> Result<bool, MeshError> {
+        let half_edge = self.edge_half_edge(edge)?;
+        Ok(self.half_edges[&half_edge].twin.is_none())
+    }
+
+    /// Looks up the edge joining two vertices, in either direction.
+    pub fn find_edge(&self, a: VertexId, b: VertexId) -> Option<EdgeId> {
+        self.directed
+            .get(&(a, b))
+            .or_else(|| self.directed.get(&(b, a)))
+            .map(|id| self.half_edges[id].edge)
+    }
+
+    pub fn add_vertex(
+        &mut self,
+        position: [f32; 3],
+        metadata: HashMap<String, String>,
+    ) -> VertexId {
+        let id = next_free(&mut self.next_vertex, &self.vertices, VertexId);
+        self.vertices.insert(
+            id,
+            MeshVertex {
+                position,
+                metadata,
+                normal: None,
+                uv: None,
+                half_edge: None,
+            },
+        );
+        id
+    }
+
+    /// Adds a polygon wound counter-clockwise around its outward normal.
+    pub fn add_face(&mut self, vertices: &[VertexId]) -> Result<FaceId, MeshError> {
+        self.check_polygon(vertices)?;
+        let face = self.allocate_face();
+        self.link_face(face, vertices);
+        Ok(face)
+    }
+
+    /// Extrudes a boundary edge along `direction`, returning the new quad.
+    pub fn extrude_edge(&mut self, edge: EdgeId, direction: [f32; 3]) -> Result<FaceId, MeshError> {
+        if !self.is_boundary_edge(edge)? {
+            return Err(MeshError::EdgeNotBoundary(edge));
+        }
+        let (a, b) = self.edge_vertices(edge)?;
+        let a_moved = self.add_vertex(
+            offset(self.vertices[&a].position, direction),
+            HashMap::new(),
+        );
+        let b_moved = self.add_vertex(
+            offset(self.vertices[&b].position, direction),
+            HashMap::new(),
+        );
+        self.add_face(&[b, a, a_moved, b_moved])
+    }
+
+    /// Inserts a vertex at the midpoint of `edge`, splitting it in every adjacent face.
+    /// The original edge id keeps the half nearest its first vertex.
+    pub fn split_edge(&mut self, edge: EdgeId) -> Result<VertexId, MeshError> {
+        let half_edge = self.edge_half_edge(edge)?;
+        let twin = self.half_edges[&half_edge].twin;
+        let a = self.half_edges[&half_edge].origin;
+        let b = self.destination(half_edge);
+        let midpoint = self.add_vertex(
+            lerp(self.vertices[&a].position, self.vertices[&b].position, 0.5),
+            HashMap::new(),
+        );
+
+        let new_edge = self.allocate_edge();
+        let forward = self.insert_after(half_edge, midpoint, new_edge);
+        self.directed.remove(&(a, b));
+        self.directed.insert((a, midpoint), half_edge);
+        self.directed.insert((midpoint, b), forward);
+        self.edges.insert(new_edge, MeshEdge { half_edge: forward });
+
+        if let Some(twin) = twin {
+            let backward = self.insert_after(twin, midpoint, edge);
+            self.half_edges.get_mut(&twin).expect("twin").edge = new_edge;
+            self.directed.remove(&(b, a));
+            self.directed.insert((b, midpoint), twin);
+            self.directed.insert((midpoint, a), backward);
+            self.pair(half_edge, backward);
+            self.pair(forward, twin);
+        }
+
+        self.vertices
+            .get_mut(&midpoint)
+            .expect("midpoint")
+            .half_edge = Some(forward);
+        Ok(midpoint)
+    }
+
+    /// Checks every structural invariant; used after imports and in tests.
+    pub fn validate(&self) -> Result<(), MeshError> {
+        let corrupt = |message: String| Err(MeshError::Corrupt(message));
+        for (id, half_edge) in &self.half_edges {
+            let Some(next) = self.half_edges.get(&half_edge.next) else {
+                return corrupt(format!("{id:?} has dangling next"));
+            };
+            if next.prev != *id || next.face != half_edge.face {
+                return corrupt(format!("{id:?} next/prev mismatch"));
+            }
+            if !self.faces.contains_key(&half_edge.face) {
+                return corrupt(format!("{id:?} points at missing face"));
+            }
+            if self.directed.get(&(half_edge.origin, next.origin)) != Some(id) {
+                return corrupt(format!("{id:?} missing from the directed lookup"));
+            }
+            if let Some(twin) = half_edge.twin {
+                let Some(other) = self.half_edges.get(&twin) else {
+                    return corrupt(format!("{id:?} has dangling twin"));
+                };
+                if other.twin != Some(*id)
+                    || other.edge != half_edge.edge
+                    || other.origin != next.origin
+                {
+                    return corrupt(format!("{id:?} twin mismatch"));
+                }
+            }
+            if !self.edges.contains_key(&half_edge.edge) {
+                return corrupt(format!("{id:?} points at missing edge"));
+            }
+        }
+        for (id, edge) in &self.edges {
+            if self.half_edges.get(&edge.half_edge).map(|h| h.edge) != Some(*id) {
+                return corrupt(format!("{id:?} half-edge mismatch"));
+            }
+        }
+        for (id, vertex) in &self.vertices {
+            if let Some(half_edge) = vertex.half_edge
+                && self.half_edges.get(&half_edge).map(|h| h.origin) != Some(*id)
+            {
+                return corrupt(format!("{id:?} outgoing half-edge mismatch"));
+            }
+        }
+        let mut visited = 0;
+        for id in self.faces.keys() {
+            let half_edges = self.face_half_edges(*id)?;
+            if half_edges.len() < 3 {
+                return corrupt(format!("{id:?} has fewer than three sides"));
+            }
+            visited += half_edges.len();
+        }
+        if visited != self.half_edges.len() || self.directed.len() != self.half_edges.len() {
+            return corrupt("half-edges not owned by exactly one face".to_string());
+        }
+        Ok(())
+    }
+
+    fn check_polygon(&self, vertices: &[VertexId]) -> Result<(), MeshError> {
+        if vertices.len() < 3 {
+            return Err(MeshError::DegenerateFace);
+        }
+        for (index, vertex) in vertices.iter().enumerate() {
+            if !self.vertices.contains_key(vertex) {
+                return Err(MeshError::UnknownVertex(*vertex));
+            }
+            if vertices[..index].contains(vertex) {
+                return Err(MeshError::DegenerateFace);
+            }
+        }
+        for (from, to) in loop_pairs(vertices) {
+            if self.directed.contains_key(&(from, to)) {
+                return Err(MeshError::NonManifoldEdge { from, to });
+            }
+        }
+        Ok(())
+    }
+
+    /// Wires a face through `vertices`, reusing existing half-edges for the directed
+    /// pairs it already owns and twinning new ones with their reverse.
+    fn link_face(&mut self, face: FaceId, vertices: &[VertexId]) {
+        let mut ids = Vec::with_capacity(vertices.len());
+        for (from, to) in loop_pairs(vertices) {
+            if let Some(existing) = self.directed.get(&(from, to)) {
+                ids.push(*existing);
+                continue;
+            }
+
+            let id = next_free(&mut self.next_half_edge, &self.half_edges, HalfEdgeId);
+            let twin = self.directed.get(&(to, from)).copied();
+            let edge = match twin {
+                Some(twin) => {
+                    let edge = self.half_edges[&twin].edge;
+                    self.half_edges.get_mut(&twin).expect("twin").twin = Some(id);
+                    edge
+                }
+                None => {
+                    let edge = self.allocate_edge();
+                    self.edges.insert(edge, MeshEdge { half_edge: id });
+                    edge
+                }
+            };
+            self.half_edges.insert(
+                id,
+                HalfEdge {
+                    origin: from,
+                    twin,
+                    next: id,
+                    prev: id,
+                    face,
+                    edge,
+                },
+            );
+            self.directed.insert((from, to), id);
+            ids.push(id);
+        }
+
+        let count = ids.len();
+        for (index, id) in ids.iter().enumerate() {
+            let half_edge = self.half_edges.get_mut(id).expect("face half-edge");
+            half_edge.next = ids[(index + 1) % count];
+            half_edge.prev = ids[(index + count - 1) % count];
+            half_edge.face = face;
+        }
+        for (vertex, id) in vertices.iter().zip(&ids) {
+            let vertex = self.vertices.get_mut(vertex).expect("face vertex");
+            if vertex.half_edge.is_none() {
+                vertex.half_edge = Some(*id);
+            }
+        }
+        self.faces.insert(face, MeshFace { half_edge: ids[0] });
+    }
+
+    /// Inserts a half-edge starting at `vertex` right after `half_edge` in its face.
+    fn insert_after(
+        &mut self,
+        half_edge: HalfEdgeId,
+        vertex: VertexId,
+        edge: EdgeId,
+    ) -> HalfEdgeId {
+        let id = next_free(&mut self.next_half_edge, &self.half_edges, HalfEdgeId);
+        let current = self.half_edges[&half_edge];
+        self.half_edges.insert(
+            id,
+            HalfEdge {
+                origin: vertex,
+                twin: None,
+                next: current.next,
+                prev: half_edge,
+                face: current.face,
+                edge,
+            },
+        );
+        self.half_edges.get_mut(&current.next).expect("next").prev = id;
+        self.half_edges.get_mut(&half_edge).expect("split").next = id;
+        id
+    }
+
+    fn pair(&mut self, a: HalfEdgeId, b: HalfEdgeId) {
+        self.half_edges.get_mut(&a).expect("pair").twin = Some(b);
+        self.half_edges.get_mut(&b).expect("pair").twin = Some(a);
+        let edge = self.half_edges[&a].edge;
+        self.edges.insert(edge, MeshEdge { half_edge: a });
+    }
+
+    fn face_half_edges(&self, face: FaceId) -> Result<Vec<HalfEdgeId>, MeshError> {
+        let start = self
+            .faces
+            .get(&face)
+            .ok_or(MeshError::UnknownFace(face))?
+            .half_edge;
+        let mut ids = vec![start];
+        let mut current = self.half_edges[&start].next;
+        while current != start {
+            if ids.len() > self.half_edges.len() {
+                return Err(MeshError::Corrupt(format!("{face:?} loop does not close")));
+            }
+            ids.push(current);
+            current = self.half_edges[&current].next;
+        }
+        Ok(ids)
+    }
+
+    fn edge_half_edge(&self, edge: EdgeId) -> Result<HalfEdgeId, MeshError> {
+        self.edges
+            .get(&edge)
+            .map(|edge| edge.half_edge)
+            .ok_or(MeshError::UnknownEdge(edge))
+    }
+
+    fn destination(&self, half_edge: HalfEdgeId) -> VertexId {
+        self.half_edges[&self.half_edges[&half_edge].next].origin
+    }
+
+    fn allocate_edge(&mut self) -> EdgeId {
+        next_free(&mut self.next_edge, &self.edges, EdgeId)
+    }
+
+    fn allocate_face(&mut self) -> FaceId {
+        next_free(&mut self.next_face, &self.faces, FaceId)
+    }
+
+    /// Runs `edit` with the ids of the elements it creates counted up from `seed`, so
+    /// peers applying concurrent edits in different orders agree on them. The mesh's own
+    /// counters resume afterwards.
+    pub fn with_id_seed<T>(&mut self, seed: u64, edit: impl FnOnce(&mut Self) -> T) -> T {
+        let counters = [
+            self.next_vertex,
+            self.next_half_edge,
+            self.next_edge,
+            self.next_face,
+        ];
+        [
+            self.next_vertex,
+            self.next_half_edge,
+            self.next_edge,
+            self.next_face,
+        ] = [seed; 4];
+        let result = edit(self);
+        [
+            self.next_vertex,
+            self.next_half_edge,
+            self.next_edge,
+            self.next_face,
+        ] = counters;
+        result
+    }
+}
+
+/// Takes the next id from `counter` that `taken` does not hold.
+fn next_free<K: Ord, V>(counter: &mut u64, taken: &BTreeMap<K, V>, id: fn(u64) -> K) -> K {
+    loop {
+        let candidate = id(*counter);
+        *counter = counter.wrapping_add(1);
+        if !taken.contains_key(&candidate) {
+            return candidate;
+        }
+    }
+}
+
+fn loop_pairs(vertices: &[VertexId]) -> impl Iterator<Item = (VertexId, VertexId)> + '_ {
+    vertices
+        .iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .map(|(from, to)| (*from, *to))
+}
+
+fn offset(position: [f32; 3], delta: [f32; 3]) -> [f32; 3] {
+    [
+        position[0] + delta[0],
+        position[1] + delta[1],
+        position[2] + delta[2],
+    ]
+}
+
+fn lerp(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    [
+        a[0] + (b[0] - a[0]) * t,
+        a[1] + (b[1] - a[1]) * t,
+        a[2] + (b[2] - a[2]) * t,
+    ]
+}
+
+crate::register_component_types!(EditableMesh);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quad_has_boundary_edges_and_valid_topology() {
+        let mesh = EditableMesh::quad(2.0);
+        mesh.validate().expect("valid quad");
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_eq!(mesh.edge_count(), 4);
+        assert_eq!(mesh.face_count(), 1);
+        assert!(
+            mesh.edge_ids()
+                .all(|edge| mesh.is_boundary_edge(edge).unwrap())
+        );
+    }
+
+    #[test]
+    fn extruding_a_boundary_edge_adds_a_shared_quad() {
+        let mut mesh = EditableMesh::quad(1.0);
+        let face = mesh
+            .extrude_edge(EdgeId(0), [0.0, 1.0, 0.0])
+            .expect("extrude");
+        mesh.validate().expect("valid after extrude");
+        assert_eq!(face, FaceId(1));
+        assert_eq!(mesh.vertex_count(), 6);
+        assert_eq!(mesh.edge_count(), 7);
+        assert!(!mesh.is_boundary_edge(EdgeId(0)).unwrap());
+        assert_eq!(
+            mesh.extrude_edge(EdgeId(0), [0.0, 1.0, 0.0]),
+            Err(MeshError::EdgeNotBoundary(EdgeId(0)))
+        );
+    }
+
+    #[test]
+    fn rejects_non_manifold_and_degenerate_faces() {
+        let mut mesh = EditableMesh::quad(1.0);
+        let ids: Vec<VertexId> = mesh.vertex_ids().collect();
+        assert!(matches!(
+            mesh.add_face(&ids),
+            Err(MeshError::NonManifoldEdge { .. })
+        ));
+        assert_eq!(
+            mesh.add_face(&[ids[0], ids[1], ids[0]]),
+            Err(MeshError::DegenerateFace)
+        );
+        assert_eq!(mesh.add_face(&ids[..2]), Err(MeshError::DegenerateFace));
+    }
+}
@@ -0,0 +1,799 @@
+use super::subdivision::scale;
+use super::{
+    EdgeId, EditableMesh, FaceId, HalfEdgeId, MeshEdge, MeshError, VertexId, lerp, loop_pairs,
+    offset,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+impl EditableMesh {
+    /// Unit normal of `face` (Newell's method), or zero for a degenerate face.
+    pub fn face_normal(&self, face: FaceId) -> Result<[f32; 3], MeshError> {
+        let ring = self.face_vertices(face)?;
+        let mut normal = [0.0f32; 3];
+        for (a, b) in loop_pairs(&ring) {
+            let (p, q) = (self.vertices[&a].position, self.vertices[&b].position);
+            normal[0] += (p[1] - q[1]) * (p[2] + q[2]);
+            normal[1] += (p[2] - q[2]) * (p[0] + q[0]);
+            normal[2] += (p[0] - q[0]) * (p[1] + q[1]);
+        }
+        let length = normal.iter().map(|axis| axis * axis).sum::<f32>().sqrt();
+        if length <= f32::EPSILON {
+            return Ok([0.0; 3]);
+        }
+        Ok(scale(normal, 1.0 / length))
+    }
+
+    /// Faces on either side of `edge`, the one owning its first half-edge first.
+    pub fn edge_faces(&self, edge: EdgeId) -> Result<Vec<FaceId>, MeshError> {
+        let half_edge = self.half_edges[&self.edge_half_edge(edge)?];
+        let mut faces = vec![half_edge.face];
+        faces.extend(half_edge.twin.map(|twin| self.half_edges[&twin].face));
+        Ok(faces)
+    }
+
+    /// Pushes `face` out along its normal. The face keeps its id on the moved cap; the
+    /// returned side quads connect it back to the old boundary.
+    pub fn extrude_face(&mut self, face: FaceId, distance: f32) -> Result<Vec<FaceId>, MeshError> {
+        let delta = scale(self.face_normal(face)?, distance);
+        self.transact(|mesh| mesh.ring_face(face, |position| offset(position, delta)))
+    }
+
+    /// Shrinks `face` towards its centroid by `amount` (0.0 keeps it, 1.0 collapses it)
+    /// and fills the gap with a ring of quads, which are returned.
+    pub fn inset_face(&mut self, face: FaceId, amount: f32) -> Result<Vec<FaceId>, MeshError> {
+        self.face_half_edges(face)?;
+        let center = self.centroid(face);
+        let amount = unit(amount);
+        self.transact(|mesh| mesh.ring_face(face, |position| lerp(position, center, amount)))
+    }
+
+    /// Chamfers an interior edge into a quad `width` wide on each side. End vertices of
+    /// valence three are replaced outright; busier ones keep a corner triangle so the
+    /// surrounding fan stays untouched. Returns the bevel face.
+    pub fn bevel_edge(&mut self, edge: EdgeId, width: f32) -> Result<FaceId, MeshError> {
+        let forward = self.edge_half_edge(edge)?;
+        let backward = self.half_edges[&forward]
+            .twin
+            .ok_or(MeshError::BoundaryEdge(edge))?;
+        let width = if width.is_finite() {
+            width.max(0.0)
+        } else {
+            0.0
+        };
+        self.transact(|mesh| {
+            let (f1, f2) = (mesh.half_edges[&forward], mesh.half_edges[&backward]);
+            let (a, b) = (f1.origin, f2.origin);
+            // neighbours of each end along the two faces being chamfered
+            let p1 = mesh.half_edges[&f1.prev].origin;
+            let n1 = mesh.destination(f1.next);
+            let p2 = mesh.half_edges[&f2.prev].origin;
+            let n2 = mesh.destination(f2.next);
+
+            let a1 = mesh.slide(a, p1, width);
+            let b1 = mesh.slide(b, n1, width);
+            let a2 = mesh.slide(a, n2, width);
+            let b2 = mesh.slide(b, p2, width);
+
+            let mut rings = RingEdits::default();
+            rings.splice(mesh, f1.face, a, &[a1])?;
+            rings.splice(mesh, f1.face, b, &[b1])?;
+            rings.splice(mesh, f2.face, b, &[b2])?;
+            rings.splice(mesh, f2.face, a, &[a2])?;
+
+            let mut corners = Vec::new();
+            let g = mesh.twin_face(f1.prev);
+            let h = mesh.twin_face(f2.next);
+            match (g, h) {
+                (Some(g), Some(h)) if g == h => rings.splice(mesh, g, a, &[a2, a1])?,
+                _ => {
+                    if let Some(g) = g {
+                        rings.splice(mesh, g, a, &[a, a1])?;
+                    }
+                    if let Some(h) = h {
+                        rings.splice(mesh, h, a, &[a2, a])?;
+                    }
+                    corners.push(vec![a1, a, a2]);
+                }
+            }
+            let j = mesh.twin_face(f1.next);
+            let k = mesh.twin_face(f2.prev);
+            match (j, k) {
+                (Some(j), Some(k)) if j == k => rings.splice(mesh, j, b, &[b1, b2])?,
+                _ => {
+                    if let Some(j) = j {
+                        rings.splice(mesh, j, b, &[b1, b])?;
+                    }
+                    if let Some(k) = k {
+                        rings.splice(mesh, k, b, &[b, b2])?;
+                    }
+                    corners.push(vec![b, b1, b2]);
+                }
+            }
+
+            mesh.rewrite_faces(rings.into_rewrites())?;
+            let bevel = mesh.add_face(&[a2, b2, b1, a1])?;
+            for corner in corners {
+                mesh.add_face(&corner)?;
+            }
+            mesh.remove_loose(&[a, b]);
+            Ok(bevel)
+        })
+    }
+
+    /// Cuts a loop through the ring of quads crossing `edge`, splitting every crossed
+    /// edge at `factor` and each quad in two. The walk stops at boundaries and at
+    /// non-quad faces, which only gain the split vertex. Returns the new vertices.
+    pub fn loop_cut(&mut self, edge: EdgeId, factor: f32) -> Result<Vec<VertexId>, MeshError> {
+        let start = self.edge_half_edge(edge)?;
+        let factor = unit(factor);
+
+        // crossed half-edges with the cut parameter measured from their origin
+        let mut cuts = vec![(start, factor)];
+        let mut quads = Vec::new();
+        let mut seen_edges = BTreeSet::from([edge]);
+        let mut seen_faces = BTreeSet::new();
+        let walks = [
+            (Some(start), factor),
+            (self.half_edges[&start].twin, 1.0 - factor),
+        ];
+        for (first, t) in walks {
+            let mut current = first;
+            while let Some(entering) = current {
+                let face = self.half_edges[&entering].face;
+                if !seen_faces.insert(face) || self.face_half_edges(face)?.len() != 4 {
+                    break;
+                }
+                let opposite = self.half_edges[&self.half_edges[&entering].next].next;
+                quads.push((face, entering, opposite));
+                if !seen_edges.insert(self.half_edges[&opposite].edge) {
+                    break;
+                }
+                cuts.push((opposite, 1.0 - t));
+                current = self.half_edges[&opposite].twin;
+            }
+        }
+
+        self.transact(|mesh| {
+            let mut rings = RingEdits::default();
+            let mut inserted = BTreeMap::new();
+            let mut vertices = Vec::with_capacity(cuts.len());
+            for (half_edge, t) in &cuts {
+                let current = mesh.half_edges[half_edge];
+                let (a, b) = (current.origin, mesh.destination(*half_edge));
+                let vertex = mesh.add_vertex(
+                    lerp(mesh.vertices[&a].position, mesh.vertices[&b].position, *t),
+                    HashMap::new(),
+                );
+                rings.splice(mesh, current.face, a, &[a, vertex])?;
+                if let Some(twin) = current.twin {
+                    rings.splice(mesh, mesh.half_edges[&twin].face, b, &[b, vertex])?;
+                }
+                inserted.insert(current.edge, vertex);
+                vertices.push(vertex);
+            }
+
+            let mut rewrites = rings.into_rewrites();
+            for (face, entering, opposite) in &quads {
+                let (a, b) = (
+                    mesh.half_edges[entering].origin,
+                    mesh.destination(*entering),
+                );
+                let (c, d) = (
+                    mesh.half_edges[opposite].origin,
+                    mesh.destination(*opposite),
+                );
+                let m1 = inserted[&mesh.half_edges[entering].edge];
+                let m2 = inserted[&mesh.half_edges[opposite].edge];
+                rewrites.insert(*face, vec![vec![a, m1, m2, d], vec![m1, b, c, m2]]);
+            }
+            mesh.rewrite_faces(rewrites)?;
+            Ok(vertices)
+        })
+    }
+
+    /// Joins two boundary edge loops, or two open chains, of equal length with quads.
+    /// Closed loops are aligned by the rotation with the smallest summed squared
+    /// distance, so the bridge does not twist.
+    pub fn bridge_edge_loops(
+        &mut self,
+        first: &[EdgeId],
+        second: &[EdgeId],
+    ) -> Result<Vec<FaceId>, MeshError> {
+        let (first, first_closed) = self.boundary_chain(first)?;
+        let (second, second_closed) = self.boundary_chain(second)?;
+        if first.len() != second.len() || first_closed != second_closed {
+            return Err(MeshError::LoopMismatch {
+                first: first.len(),
+                second: second.len(),
+            });
+        }
+
+        // the second loop runs the other way round for the quads to share its half-edges
+        let reversed: Vec<VertexId> = second.into_iter().rev().collect();
+        let count = first.len();
+        let shift = if first_closed {
+            let cost = |shift: usize| -> f32 {
+                (0..count)
+                    .map(|index| {
+                        distance_squared(
+                            self.vertices[&first[index]].position,
+                            self.vertices[&reversed[(index + shift) % count]].position,
+                        )
+                    })
+                    .sum()
+            };
+            (0..count)
+                .min_by(|x, y| cost(*x).total_cmp(&cost(*y)))
+                .unwrap_or(0)
+        } else {
+            0
+        };
+        let paired = |index: usize| reversed[(index + shift) % count];
+        let segments = if first_closed { count } else { count - 1 };
+
+        self.transact(|mesh| {
+            (0..segments)
+                .map(|index| {
+                    let next = (index + 1) % count;
+                    mesh.add_face(&[first[next], first[index], paired(index), paired(next)])
+                })
+                .collect()
+        })
+    }
+
+    /// Welds every vertex within `distance` of a lower-id vertex onto it. Faces that
+    /// collapse below three corners are dropped. Returns how many vertices were welded.
+    pub fn merge_by_distance(&mut self, distance: f32) -> Result<usize, MeshError> {
+        let distance = if distance.is_finite() {
+            distance.max(0.0)
+        } else {
+            0.0
+        };
+        let threshold = distance.powi(2);
+        // Vertices close enough to weld sit in the same or a neighbouring cell.
+        let cell_size = if distance > 0.0 { distance } else { 1.0 };
+        let cell_of = |position: [f32; 3]| position.map(|axis| (axis / cell_size).floor() as i64);
+        let mut cells: HashMap<[i64; 3], Vec<VertexId>> = HashMap::new();
+        for (id, vertex) in &self.vertices {
+            cells.entry(cell_of(vertex.position)).or_default().push(*id);
+        }
+
+        let mut welds: BTreeMap<VertexId, VertexId> = BTreeMap::new();
+        for (keep, vertex) in &self.vertices {
+            if welds.contains_key(keep) {
+                continue;
+            }
+            let origin = vertex.position;
+            let [x, y, z] = cell_of(origin);
+            for offset in NEIGHBOUR_CELLS {
+                let cell = [x + offset[0], y + offset[1], z + offset[2]];
+                for other in cells.get(&cell).into_iter().flatten() {
+                    if other > keep
+                        && !welds.contains_key(other)
+                        && distance_squared(origin, self.vertices[other].position) <= threshold
+                    {
+                        welds.insert(*other, *keep);
+                    }
+                }
+            }
+        }
+        if welds.is_empty() {
+            return Ok(0);
+        }
+
+        self.transact(|mesh| {
+            let faces: BTreeSet<FaceId> = welds
+                .keys()
+                .flat_map(|vertex| mesh.outgoing(*vertex))
+                .map(|half_edge| mesh.half_edges[&half_edge].face)
+                .collect();
+            let mut rewrites = BTreeMap::new();
+            for face in faces {
+                let mut ring: Vec<VertexId> = mesh
+                    .face_vertices(face)?
+                    .into_iter()
+                    .map(|vertex| welds.get(&vertex).copied().unwrap_or(vertex))
+                    .collect();
+                ring.dedup();
+                while ring.len() > 1 && ring.first() == ring.last() {
+                    ring.pop();
+                }
+                let polygons = if ring.len() >= 3 {
+                    vec![ring]
+                } else {
+                    Vec::new()
+                };
+                rewrites.insert(face, polygons);
+            }
+            mesh.rewrite_faces(rewrites)?;
+            for vertex in welds.keys() {
+                mesh.vertices.remove(vertex);
+            }
+            let survivors: Vec<VertexId> = welds.values().copied().collect();
+            mesh.remove_loose(&survivors);
+            Ok(welds.len())
+        })
+    }
+
+    /// Deletes `face`, along with any of its vertices left without faces.
+    pub fn delete_face(&mut self, face: FaceId) -> Result<(), MeshError> {
+        let ring = self.remove_face(face)?;
+        self.remove_loose(&ring);
+        Ok(())
+    }
+
+    /// Deletes `edge` and the faces using it. Returns the removed faces.
+    pub fn delete_edge(&mut self, edge: EdgeId) -> Result<Vec<FaceId>, MeshError> {
+        let faces = self.edge_faces(edge)?;
+        self.delete_faces(&faces)?;
+        Ok(faces)
+    }
+
+    /// Deletes `vertex` and every face around it. Returns the removed faces.
+    pub fn delete_vertex(&mut self, vertex: VertexId) -> Result<Vec<FaceId>, MeshError> {
+        if !self.vertices.contains_key(&vertex) {
+            return Err(MeshError::UnknownVertex(vertex));
+        }
+        let faces: Vec<FaceId> = self
+            .outgoing(vertex)
+            .into_iter()
+            .map(|half_edge| self.half_edges[&half_edge].face)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        self.delete_faces(&faces)?;
+        self.vertices.remove(&vertex);
+        Ok(faces)
+    }
+
+    fn delete_faces(&mut self, faces: &[FaceId]) -> Result<(), MeshError> {
+        let mut touched = Vec::new();
+        for face in faces {
+            touched.extend(self.remove_face(*face)?);
+        }
+        self.remove_loose(&touched);
+        Ok(())
+    }
+
+    /// Runs `edit` on a copy and keeps the result only when it succeeds, so a failed
+    /// operation never leaves a half-rebuilt mesh behind.
+    fn transact<T>(
+        &mut self,
+        edit: impl FnOnce(&mut Self) -> Result<T, MeshError>,
+    ) -> Result<T, MeshError> {
+        let mut draft = self.clone();
+        let result = edit(&mut draft)?;
+        *self = draft;
+        Ok(result)
+    }
+
+    /// Replaces `face` with a copy of its ring moved by `place` and bridges the two
+    /// rings with quads. The face id stays on the moved copy.
+    fn ring_face(
+        &mut self,
+        face: FaceId,
+        place: impl Fn([f32; 3]) -> [f32; 3],
+    ) -> Result<Vec<FaceId>, MeshError> {
+        let outer = self.face_vertices(face)?;
+        let inner: Vec<VertexId> = outer
+            .iter()
+            .map(|vertex| {
+                let position = place(self.vertices[vertex].position);
+                self.add_vertex(position, HashMap::new())
+            })
+            .collect();
+        self.rewrite_faces(BTreeMap::from([(face, vec![inner.clone()])]))?;
+        loop_pairs(&outer)
+            .zip(loop_pairs(&inner))
+            .map(|((a, b), (a_moved, b_moved))| self.add_face(&[a, b, b_moved, a_moved]))
+            .collect()
+    }
+
+    /// Replaces each listed face by its polygons: the first keeps the face id, the rest
+    /// get fresh ids and an empty list deletes the face.
+    fn rewrite_faces(
+        &mut self,
+        rewrites: BTreeMap<FaceId, Vec<Vec<VertexId>>>,
+    ) -> Result<Vec<FaceId>, MeshError> {
+        for face in rewrites.keys() {
+            self.remove_face(*face)?;
+        }
+        let mut created = Vec::new();
+        for (face, polygons) in rewrites {
+            for (index, polygon) in polygons.iter().enumerate() {
+                self.check_polygon(polygon)?;
+                let id = if index == 0 {
+                    face
+                } else {
+                    self.allocate_face()
+                };
+                self.link_face(id, polygon);
+                created.push(id);
+            }
+        }
+        Ok(created)
+    }
+
+    /// Unlinks `face`, dropping its half-edges and any edge left without a side.
+    /// Returns the face's vertex ring.
+    fn remove_face(&mut self, face: FaceId) -> Result<Vec<VertexId>, MeshError> {
+        let half_edges = self.face_half_edges(face)?;
+        let mut ring = Vec::with_capacity(half_edges.len());
+        for id in &half_edges {
+            let half_edge = self.half_edges[id];
+            self.directed
+                .remove(&(half_edge.origin, self.destination(*id)));
+            match half_edge.twin {
+                Some(twin) => {
+                    self.half_edges.get_mut(&twin).expect("twin").twin = None;
+                    self.edges
+                        .insert(half_edge.edge, MeshEdge { half_edge: twin });
+                }
+                None => {
+                    self.edges.remove(&half_edge.edge);
+                }
+            }
+            ring.push(half_edge.origin);
+        }
+        for id in &half_edges {
+            self.half_edges.remove(id);
+        }
+        for vertex in &ring {
+            let outgoing = self.outgoing(*vertex).first().copied();
+            self.vertices
+                .get_mut(vertex)
+                .expect("ring vertex")
+                .half_edge = outgoing;
+        }
+        self.faces.remove(&face);
+        Ok(ring)
+    }
+
+    /// Drops the given vertices if no face uses them any more.
+    fn remove_loose(&mut self, vertices: &[VertexId]) {
+        for vertex in vertices {
+            if self
+                .vertices
+                .get(vertex)
+                .is_some_and(|vertex| vertex.half_edge.is_none())
+            {
+                self.vertices.remove(vertex);
+            }
+        }
+    }
+
+    /// Orders boundary `edges` into a vertex chain following their half-edges.
+    /// Returns the chain and whether it closes on itself.
+    fn boundary_chain(&self, edges: &[EdgeId]) -> Result<(Vec<VertexId>, bool), MeshError> {
+        let mut next = BTreeMap::new();
+        for edge in edges {
+            let half_edge = self.edge_half_edge(*edge)?;
+            if self.half_edges[&half_edge].twin.is_some() {
+                return Err(MeshError::EdgeNotBoundary(*edge));
+            }
+            let origin = self.half_edges[&half_edge].origin;
+            if next.insert(origin, self.destination(half_edge)).is_some() {
+                return Err(MeshError::InvalidEdgeLoop);
+            }
+        }
+
+        let targets: BTreeSet<VertexId> = next.values().copied().collect();
+        let open_start = next
+            .keys()
+            .copied()
+            .find(|vertex| !targets.contains(vertex));
+        let Some(start) = open_start.or_else(|| next.keys().next().copied()) else {
+            return Err(MeshError::InvalidEdgeLoop);
+        };
+        let mut chain = vec![start];
+        let mut current = start;
+        while let Some(to) = next.get(&current).copied() {
+            if to == start || chain.len() > next.len() {
+                break;
+            }
+            chain.push(to);
+            current = to;
+        }
+
+        let closed = open_start.is_none();
+        let expected = if closed { next.len() } else { next.len() + 1 };
+        if chain.len() != expected {
+            return Err(MeshError::InvalidEdgeLoop);
+        }
+        Ok((chain, closed))
+    }
+
+    /// Adds a vertex `width` along the edge from `from` towards `toward`, stopping at
+    /// the edge midpoint so opposite bevels never cross.
+    fn slide(&mut self, from: VertexId, toward: VertexId, width: f32) -> VertexId {
+        let (a, b) = (
+            self.vertices[&from].position,
+            self.vertices[&toward].position,
+        );
+        let length = distance_squared(a, b).sqrt();
+        let t = if length > f32::EPSILON {
+            (width / length).min(0.5)
+        } else {
+            0.0
+        };
+        self.add_vertex(lerp(a, b, t), HashMap::new())
+    }
+
+    fn twin_face(&self, half_edge: HalfEdgeId) -> Option<FaceId> {
+        self.half_edges[&half_edge]
+            .twin
+            .map(|twin| self.half_edges[&twin].face)
+    }
+}
+
+/// Working copies of face rings that several edits may touch before a rewrite.
+#[derive(Default)]
+struct RingEdits {
+    rings: BTreeMap<FaceId, Vec<VertexId>>,
+}
+
+impl RingEdits {
+    /// Replaces `vertex` in `face`'s ring with `replacement`.
+    fn splice(
+        &mut self,
+        mesh: &EditableMesh,
+        face: FaceId,
+        vertex: VertexId,
+        replacement: &[VertexId],
+    ) -> Result<(), MeshError> {
+        let ring = match self.rings.entry(face) {
+            std::collections::btree_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::btree_map::Entry::Vacant(entry) => {
+                entry.insert(mesh.face_vertices(face)?)
+            }
+        };
+        let index = ring
+            .iter()
+            .position(|candidate| *candidate == vertex)
+            .ok_or_else(|| MeshError::Corrupt(format!("{vertex:?} missing from {face:?}")))?;
+        ring.splice(index..=index, replacement.iter().copied());
+        Ok(())
+    }
+
+    fn into_rewrites(self) -> BTreeMap<FaceId, Vec<Vec<VertexId>>> {
+        self.rings
+            .into_iter()
+            .map(|(face, ring)| (face, vec![ring]))
+            .collect()
+    }
+}
+
+fn unit(value: f32) -> f32 {
+    if value.is_finite() {
+        value.clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+const NEIGHBOUR_CELLS: [[i64; 3]; 27] = {
+    let mut cells = [[0; 3]; 27];
+    let mut index = 0;
+    while index < 27 {
+        cells[index] = [
+            (index % 3) as i64 - 1,
+            (index / 3 % 3) as i64 - 1,
+            (index / 9) as i64 - 1,
+        ];
+        index += 1;
+    }
+    cells
+};
+
+fn distance_squared(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (0..3).map(|axis| (a[axis] - b[axis]).powi(2)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::primitives::PrimitiveShape;
+
+    fn cube() -> EditableMesh {
+        PrimitiveShape::Cube { size: 2.0 }.build()
+    }
+
+    fn assert_closed(mesh: &EditableMesh) {
+        mesh.validate().expect("valid mesh");
+        assert!(
+            mesh.edge_ids()
+                .all(|edge| !mesh.is_boundary_edge(edge).unwrap()),
+            "mesh has boundary edges"
+        );
+    }
+
+    #[test]
+    fn extrude_and_inset_keep_the_face_id_on_the_new_cap() {
+        let mut mesh = cube();
+        let top = FaceId(3);
+        assert_eq!(mesh.face_normal(top).unwrap(), [0.0, 1.0, 0.0]);
+        let sides = mesh.extrude_face(top, 1.5).expect("extrude");
+        assert_eq!(sides.len(), 4);
+        assert_closed(&mesh);
+        assert_eq!((mesh.vertex_count(), mesh.face_count()), (12, 10));
+        for vertex in mesh.face_vertices(top).unwrap() {
+            assert_eq!(mesh.vertex(vertex).unwrap().position[1], 2.5);
+        }
+
+        let ring = mesh.inset_face(top, 0.5).expect("inset");
+        assert_eq!(ring.len(), 4);
+        assert_closed(&mesh);
+        assert_eq!((mesh.vertex_count(), mesh.face_count()), (16, 14));
+        for vertex in mesh.face_vertices(top).unwrap() {
+            let position = mesh.vertex(vertex).unwrap().position;
+            assert_eq!((position[0].abs(), position[2].abs()), (0.5, 0.5));
+        }
+    }
+
+    #[test]
+    fn bevel_and_loop_cut_keep_a_cube_closed() {
+        let mut beveled = cube();
+        let edge = beveled
+            .find_edge(VertexId(2), VertexId(3))
+            .expect("top front edge");
+        beveled.bevel_edge(edge, 0.25).expect("bevel");
+        assert_closed(&beveled);
+        assert_eq!((beveled.vertex_count(), beveled.face_count()), (10, 7));
+        assert!(beveled.vertex(VertexId(2)).is_none());
+
+        let boundary = EditableMesh::quad(1.0);
+        let mut open = boundary.clone();
+        assert_eq!(
+            open.bevel_edge(EdgeId(0), 0.1),
+            Err(MeshError::BoundaryEdge(EdgeId(0)))
+        );
+        assert_eq!(open, boundary);
+
+        let mut cut = cube();
+        let inserted = cut.loop_cut(EdgeId(0), 0.25).expect("loop cut");
+        assert_eq!(inserted.len(), 4);
+        assert_closed(&cut);
+        assert_eq!((cut.vertex_count(), cut.face_count()), (12, 10));
+    }
+
+    #[test]
+    fn bridging_two_open_quads_closes_a_box() {
+        let positions: Vec<[f32; 3]> = (0..8u32)
+            .map(|bits| {
+                let axis = |bit: u32| if bits & bit == 0 { -1.0 } else { 1.0 };
+                [axis(1), axis(2), axis(4)]
+            })
+            .collect();
+        let mut mesh =
+            EditableMesh::from_polygons(&positions, &[vec![0, 1, 5, 4], vec![2, 6, 7, 3]]).unwrap();
+        let bottom: Vec<EdgeId> = (0..4).map(EdgeId).collect();
+        let top: Vec<EdgeId> = (4..8).map(EdgeId).collect();
+
+        assert_eq!(
+            mesh.bridge_edge_loops(&bottom, &top[..3]),
+            Err(MeshError::LoopMismatch {
+                first: 4,
+                second: 4
+            })
+        );
+        let sides = mesh.bridge_edge_loops(&bottom, &top).expect("bridge");
+        assert_eq!(sides.len(), 4);
+        assert_closed(&mesh);
+        assert_eq!(mesh.face_count(), 6);
+        for face in sides {
+            let normal = mesh.face_normal(face).unwrap();
+            assert_eq!(normal[1], 0.0);
+        }
+    }
+
+    #[test]
+    fn merge_by_distance_welds_coincident_corners() {
+        let mut mesh = EditableMesh::from_polygons(
+            &[
+                [0.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0],
+                [1.0, 0.0, 1.0],
+                [1.0, 0.0, 0.0],
+                [1.0005, 0.0, 0.0],
+                [1.0, 0.0, 1.0],
+                [2.0, 0.0, 1.0],
+                [2.0, 0.0, 0.0],
+            ],
+            &[vec![0, 1, 2, 3], vec![4, 5, 6, 7]],
+        )
+        .unwrap();
+        assert_eq!(mesh.merge_by_distance(0.0001).unwrap(), 1);
+        assert_eq!(mesh.merge_by_distance(0.001).unwrap(), 1);
+        mesh.validate().expect("valid after merge");
+        assert_eq!((mesh.vertex_count(), mesh.edge_count()), (6, 7));
+        let shared = mesh.find_edge(VertexId(2), VertexId(3)).unwrap();
+        assert!(!mesh.is_boundary_edge(shared).unwrap());
+
+        // A grid of quads split along every edge welds back into one sheet.
+        let size = 40;
+        let mut positions = Vec::new();
+        let mut polygons = Vec::new();
+        for row in 0..size {
+            for column in 0..size {
+                let base = positions.len() as u32;
+                let (x, z) = (column as f32, row as f32);
+                positions.extend([[x, 0.0, z], [x, 0.0, z + 1.0], [x + 1.0, 0.0, z + 1.0]]);
+                positions.push([x + 1.0, 0.0, z]);
+                polygons.push(vec![base, base + 1, base + 2, base + 3]);
+            }
+        }
+        let mut sheet = EditableMesh::from_polygons(&positions, &polygons).unwrap();
+        let welded = sheet.merge_by_distance(0.01).unwrap();
+        assert_eq!(welded, positions.len() - (size + 1) * (size + 1));
+        sheet.validate().expect("valid after merging the sheet");
+    }
+
+    #[test]
+    fn seeded_edits_get_the_same_ids_in_either_order() {
+        let extrude = |mesh: &mut EditableMesh| {
+            mesh.with_id_seed(1 << 40, |mesh| mesh.extrude_face(FaceId(0), 1.0))
+                .unwrap()
+        };
+        let inset = |mesh: &mut EditableMesh| {
+            mesh.with_id_seed(2 << 40, |mesh| mesh.inset_face(FaceId(3), 0.25))
+                .unwrap()
+        };
+
+        // Which half-edge a vertex or face points at may differ; the topology may not.
+        let topology = |mesh: &EditableMesh| {
+            let faces: Vec<(FaceId, Vec<VertexId>)> = mesh
+                .face_ids()
+                .map(|face| {
+                    let mut ring = mesh.face_vertices(face).unwrap();
+                    let start = (0..ring.len()).min_by_key(|index| ring[*index]).unwrap();
+                    ring.rotate_left(start);
+                    (face, ring)
+                })
+                .collect();
+            let edges: Vec<(EdgeId, VertexId, VertexId)> = mesh
+                .edge_ids()
+                .map(|edge| {
+                    let (a, b) = mesh.edge_vertices(edge).unwrap();
+                    (edge, a.min(b), a.max(b))
+                })
+                .collect();
+            let positions: Vec<(VertexId, [f32; 3])> = mesh
+                .vertex_ids()
+                .map(|vertex| (vertex, mesh.vertex(vertex).unwrap().position))
+                .collect();
+            (faces, edges, positions)
+        };
+
+        let mut first = cube();
+        let mut second = cube();
+        let extruded = extrude(&mut first);
+        let inset_first = inset(&mut first);
+        assert_eq!(inset(&mut second), inset_first);
+        assert_eq!(extrude(&mut second), extruded);
+        assert_eq!(topology(&first), topology(&second));
+        assert!(extruded.iter().all(|face| face.0 >= 1 << 40));
+
+        // Unseeded edits continue from the mesh's own counters.
+        let vertex = first.add_vertex([0.0; 3], HashMap::new());
+        assert_eq!(vertex, VertexId(8));
+    }
+
+    #[test]
+    fn deletes_clean_up_loose_elements() {
+        let mut mesh = cube();
+        let removed = mesh.delete_vertex(VertexId(7)).expect("delete vertex");
+        assert_eq!(removed.len(), 3);
+        mesh.validate().expect("valid after vertex delete");
+        assert_eq!((mesh.vertex_count(), mesh.face_count()), (7, 3));
+
+        let edge = mesh.find_edge(VertexId(0), VertexId(4)).unwrap();
+        assert_eq!(mesh.delete_edge(edge).unwrap().len(), 2);
+        mesh.validate().expect("valid after edge delete");
+        assert_eq!((mesh.vertex_count(), mesh.face_count()), (4, 1));
+
+        let last = mesh.face_ids().next().unwrap();
+        mesh.delete_face(last).expect("delete face");
+        assert_eq!((mesh.vertex_count(), mesh.edge_count()), (0, 0));
+        assert_eq!(mesh.delete_face(last), Err(MeshError::UnknownFace(last)));
+    }
+}
@@ -0,0 +1,405 @@
+use super::{EdgeId, EditableMesh, FaceId, HalfEdgeId, MeshError, VertexId, lerp, offset};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Upper bound on recursive face subdivision so one command cannot explode a mesh.
+pub const MAX_SUBDIVIDE_LEVELS: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubdivisionScheme {
+    /// Used for quads and other polygons; every face becomes one quad per corner.
+    CatmullClark,
+    /// Used when every refined face is a triangle; each becomes four triangles.
+    Loop,
+}
+
+impl EditableMesh {
+    /// Refines `face` `levels` times. Triangles use Loop subdivision, everything else
+    /// Catmull-Clark. `smoothness` blends between plain midpoint splitting (0.0) and the
+    /// full smoothing rules (1.0).
+    ///
+    /// Refinement is local: neighbours receive the new edge vertices so the surface stays
+    /// closed. Positions are computed in id order from the state before each level, so
+    /// the result is bit-for-bit identical on every peer.
+    pub fn subdivide_face(
+        &mut self,
+        face: FaceId,
+        levels: u32,
+        smoothness: f32,
+    ) -> Result<Vec<FaceId>, MeshError> {
+        if !self.faces.contains_key(&face) {
+            return Err(MeshError::UnknownFace(face));
+        }
+        let smoothness = if smoothness.is_finite() {
+            smoothness.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        let mut faces = vec![face];
+        for _ in 0..levels.min(MAX_SUBDIVIDE_LEVELS) {
+            faces = self.subdivide_region(&faces, smoothness)?;
+        }
+        Ok(faces)
+    }
+
+    pub fn subdivision_scheme(&self, faces: &[FaceId]) -> Result<SubdivisionScheme, MeshError> {
+        for face in faces {
+            if self.face_half_edges(*face)?.len() != 3 {
+                return Ok(SubdivisionScheme::CatmullClark);
+            }
+        }
+        Ok(SubdivisionScheme::Loop)
+    }
+
+    fn subdivide_region(
+        &mut self,
+        faces: &[FaceId],
+        smoothness: f32,
+    ) -> Result<Vec<FaceId>, MeshError> {
+        let scheme = self.subdivision_scheme(faces)?;
+        let mut corners = BTreeSet::new();
+        let mut edges = BTreeSet::new();
+        for face in faces {
+            for half_edge in self.face_half_edges(*face)? {
+                corners.insert(self.half_edges[&half_edge].origin);
+                edges.insert(self.half_edges[&half_edge].edge);
+            }
+        }
+
+        let face_points: BTreeMap<FaceId, [f32; 3]> = faces
+            .iter()
+            .map(|face| (*face, self.centroid(*face)))
+            .collect();
+        let edge_points: BTreeMap<EdgeId, [f32; 3]> = edges
+            .iter()
+            .map(|edge| {
+                let linear = {
+                    let (a, b) = self.edge_vertices(*edge).expect("region edge");
+                    lerp(self.vertices[&a].position, self.vertices[&b].position, 0.5)
+                };
+                let smooth = self.smooth_edge_point(*edge, scheme);
+                (*edge, lerp(linear, smooth, smoothness))
+            })
+            .collect();
+        let vertex_points: BTreeMap<VertexId, [f32; 3]> = corners
+            .iter()
+            .map(|vertex| {
+                let linear = self.vertices[vertex].position;
+                let smooth = self.smooth_vertex_point(*vertex, scheme);
+                (*vertex, lerp(linear, smooth, smoothness))
+            })
+            .collect();
+
+        let mut midpoints = BTreeMap::new();
+        for edge in &edges {
+            midpoints.insert(*edge, self.split_edge(*edge)?);
+        }
+
+        let mut created = Vec::with_capacity(faces.len() * 4);
+        for face in faces {
+            // every side was split once, so the loop alternates corner, midpoint, ...
+            let ring = self.face_vertices(*face)?;
+            let corners: Vec<VertexId> = ring.iter().step_by(2).copied().collect();
+            let mids: Vec<VertexId> = ring.iter().skip(1).step_by(2).copied().collect();
+            let count = corners.len();
+            self.faces.remove(face);
+
+            let mut polygons = Vec::with_capacity(count + 1);
+            match scheme {
+                SubdivisionScheme::CatmullClark => {
+                    let center = self.add_vertex(face_points[face], HashMap::new());
+                    for index in 0..count {
+                        let previous = mids[(index + count - 1) % count];
+                        polygons.push(vec![corners[index], mids[index], center, previous]);
+                    }
+                }
+                SubdivisionScheme::Loop => {
+                    for index in 0..count {
+                        let previous = mids[(index + count - 1) % count];
+                        polygons.push(vec![corners[index], mids[index], previous]);
+                    }
+                    polygons.push(mids.clone());
+                }
+            }
+
+            for (index, polygon) in polygons.iter().enumerate() {
+                let id = if index == 0 {
+                    *face
+                } else {
+                    self.allocate_face()
+                };
+                self.link_face(id, polygon);
+                created.push(id);
+            }
+        }
+
+        for (vertex, position) in vertex_points {
+            self.vertices.get_mut(&vertex).expect("corner").position = position;
+        }
+        for (edge, position) in edge_points {
+            self.vertices
+                .get_mut(&midpoints[&edge])
+                .expect("midpoint")
+                .position = position;
+        }
+        Ok(created)
+    }
+
+    pub(super) fn centroid(&self, face: FaceId) -> [f32; 3] {
+        let ring = self.face_vertices(face).expect("known face");
+        average(ring.iter().map(|vertex| self.vertices[vertex].position))
+    }
+
+    fn smooth_edge_point(&self, edge: EdgeId, scheme: SubdivisionScheme) -> [f32; 3] {
+        let half_edge = self.edges[&edge].half_edge;
+        let current = self.half_edges[&half_edge];
+        let a = self.vertices[&current.origin].position;
+        let b = self.vertices[&self.destination(half_edge)].position;
+        let Some(twin) = current.twin else {
+            return lerp(a, b, 0.5);
+        };
+
+        match scheme {
+            SubdivisionScheme::CatmullClark => average(
+                [
+                    a,
+                    b,
+                    self.centroid(current.face),
+                    self.centroid(self.half_edges[&twin].face),
+                ]
+                .into_iter(),
+            ),
+            SubdivisionScheme::Loop => {
+                let opposite = |id: HalfEdgeId| {
+                    let prev = self.half_edges[&id].prev;
+                    self.vertices[&self.half_edges[&prev].origin].position
+                };
+                offset(
+                    scale(offset(a, b), 3.0 / 8.0),
+                    scale(offset(opposite(half_edge), opposite(twin)), 1.0 / 8.0),
+                )
+            }
+        }
+    }
+
+    fn smooth_vertex_point(&self, vertex: VertexId, scheme: SubdivisionScheme) -> [f32; 3] {
+        let position = self.vertices[&vertex].position;
+        let mut neighbours = BTreeSet::new();
+        let mut faces = BTreeSet::new();
+        let mut boundary = BTreeSet::new();
+        for outgoing in self.outgoing(vertex) {
+            let half_edge = self.half_edges[&outgoing];
+            let incoming = self.half_edges[&half_edge.prev];
+            faces.insert(half_edge.face);
+            let to = self.destination(outgoing);
+            neighbours.insert(to);
+            neighbours.insert(incoming.origin);
+            if half_edge.twin.is_none() {
+                boundary.insert(to);
+            }
+            if incoming.twin.is_none() {
+                boundary.insert(incoming.origin);
+            }
+        }
+
+        if !boundary.is_empty() {
+            if boundary.len() != 2 {
+                return position;
+            }
+            let ends = average(boundary.iter().map(|id| self.vertices[id].position));
+            return offset(scale(position, 0.75), scale(ends, 0.25));
+        }
+
+        let valence = neighbours.len();
+        if valence < 3 {
+            return position;
+        }
+        let n = valence as f32;
+        match scheme {
+            SubdivisionScheme::CatmullClark => {
+                let q = average(faces.iter().map(|face| self.centroid(*face)));
+                let r = average(
+                    neighbours
+                        .iter()
+                        .map(|id| lerp(position, self.vertices[id].position, 0.5)),
+                );
+                scale(
+                    offset(offset(q, scale(r, 2.0)), scale(position, n - 3.0)),
+                    1.0 / n,
+                )
+            }
+            SubdivisionScheme::Loop => {
+                let beta = if valence == 3 {
+                    3.0 / 16.0
+                } else {
+                    3.0 / (8.0 * n)
+                };
+                let sum = neighbours
+                    .iter()
+                    .map(|id| self.vertices[id].position)
+                    .fold([0.0; 3], offset);
+                offset(scale(position, 1.0 - n * beta), scale(sum, beta))
+            }
+        }
+    }
+
+    pub(super) fn outgoing(&self, vertex: VertexId) -> Vec<HalfEdgeId> {
+        self.directed
+            .range((vertex, VertexId(0))..=(vertex, VertexId(u64::MAX)))
+            .map(|(_, id)| *id)
+            .collect()
+    }
+}
+
+pub(super) fn scale(position: [f32; 3], factor: f32) -> [f32; 3] {
+    [
+        position[0] * factor,
+        position[1] * factor,
+        position[2] * factor,
+    ]
+}
+
+pub(super) fn average(points: impl Iterator<Item = [f32; 3]>) -> [f32; 3] {
+    let mut count = 0usize;
+    let sum = points.fold([0.0; 3], |sum, point| {
+        count += 1;
+        offset(sum, point)
+    });
+    if count == 0 {
+        return sum;
+    }
+    scale(sum, 1.0 / count as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(size: u32) -> EditableMesh {
+        let mut positions = Vec::new();
+        for z in 0..=size {
+            for x in 0..=size {
+                positions.push([x as f32, 0.0, z as f32]);
+            }
+        }
+        let stride = size + 1;
+        let polygons: Vec<Vec<u32>> = (0..size)
+            .flat_map(|z| {
+                (0..size).map(move |x| {
+                    let base = z * stride + x;
+                    vec![base, base + stride, base + stride + 1, base + 1]
+                })
+            })
+            .collect();
+        EditableMesh::from_polygons(&positions, &polygons).expect("grid")
+    }
+
+    fn tetrahedron() -> EditableMesh {
+        EditableMesh::from_polygons(
+            &[
+                [1.0, 1.0, 1.0],
+                [1.0, -1.0, -1.0],
+                [-1.0, 1.0, -1.0],
+                [-1.0, -1.0, 1.0],
+            ],
+            &[vec![0, 1, 2], vec![0, 3, 1], vec![0, 2, 3], vec![1, 3, 2]],
+        )
+        .expect("tetrahedron")
+    }
+
+    fn position_bits(mesh: &EditableMesh) -> Vec<(VertexId, [u32; 3])> {
+        mesh.vertex_ids()
+            .map(|id| {
+                let position = mesh.vertex(id).unwrap().position;
+                (id, position.map(f32::to_bits))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn local_refinement_keeps_neighbours_crack_free() {
+        let mut mesh = EditableMesh::quad(1.0);
+        mesh.extrude_edge(EdgeId(1), [0.0, 0.0, 1.0])
+            .expect("extrude");
+
+        let faces = mesh.subdivide_face(FaceId(0), 1, 0.0).expect("subdivide");
+        mesh.validate().expect("valid after subdivide");
+        assert_eq!(faces.len(), 4);
+        assert_eq!(faces[0], FaceId(0));
+        assert_eq!(mesh.face_count(), 5);
+        // the neighbour picked up the midpoint of the shared edge
+        assert_eq!(mesh.face_vertices(FaceId(1)).unwrap().len(), 5);
+
+        let deeper = mesh.subdivide_face(FaceId(0), 2, 1.0).expect("two levels");
+        mesh.validate().expect("valid after two levels");
+        assert_eq!(deeper.len(), 16);
+    }
+
+    #[test]
+    fn catmull_clark_smooths_interior_quads() {
+        let mut mesh = grid(3);
+        // centre face of the grid is FaceId(4); lift one of its corners
+        let lifted = VertexId(5);
+        mesh.vertices.get_mut(&lifted).unwrap().position[1] = 1.0;
+        assert_eq!(
+            mesh.subdivision_scheme(&[FaceId(4)]),
+            Ok(SubdivisionScheme::CatmullClark)
+        );
+
+        let faces = mesh.subdivide_face(FaceId(4), 1, 1.0).expect("subdivide");
+        mesh.validate().expect("valid");
+        assert_eq!(faces.len(), 4);
+
+        // valence-4 interior vertex: (Q + 2R + P) / 4 with Q = 0.25 and R = 0.5
+        let height = mesh.vertex(lifted).unwrap().position[1];
+        assert!((height - 0.5625).abs() < 1e-6, "height {height}");
+
+        // untouched corners of the grid keep their position
+        assert_eq!(mesh.vertex(VertexId(0)).unwrap().position, [0.0; 3]);
+    }
+
+    #[test]
+    fn loop_subdivides_triangles_into_four() {
+        let mut mesh = tetrahedron();
+        assert_eq!(
+            mesh.subdivision_scheme(&[FaceId(0)]),
+            Ok(SubdivisionScheme::Loop)
+        );
+
+        let faces = mesh.subdivide_face(FaceId(0), 1, 1.0).expect("subdivide");
+        mesh.validate().expect("valid");
+        assert_eq!(faces.len(), 4);
+        assert!(
+            faces
+                .iter()
+                .all(|face| mesh.face_vertices(*face).unwrap().len() == 3)
+        );
+        assert_eq!(mesh.face_vertices(FaceId(1)).unwrap().len(), 4);
+
+        // valence-3 vertex: 7/16 P + 3/16 * sum of neighbours (which is -P here)
+        let corner = mesh.vertex(VertexId(0)).unwrap().position;
+        for axis in corner {
+            assert!((axis - 0.25).abs() < 1e-6, "corner {corner:?}");
+        }
+    }
+
+    #[test]
+    fn subdivision_is_reproducible_bit_for_bit() {
+        let run = || {
+            let mut mesh = grid(2);
+            mesh.vertices.get_mut(&VertexId(4)).unwrap().position[1] = 0.3;
+            mesh.subdivide_face(FaceId(0), 2, 0.7).expect("quads");
+            let mut triangles = tetrahedron();
+            triangles
+                .subdivide_face(FaceId(2), 3, 0.9)
+                .expect("triangles");
+            (mesh, triangles)
+        };
+        let (first_quads, first_tris) = run();
+        let (second_quads, second_tris) = run();
+        assert_eq!(position_bits(&first_quads), position_bits(&second_quads));
+        assert_eq!(position_bits(&first_tris), position_bits(&second_tris));
+        assert_eq!(first_quads, second_quads);
+        first_tris.validate().expect("valid triangles");
+    }
+}
@@ -0,0 +1,466 @@
+use crate::editor::mesh::{EditableMesh, MeshError, VertexId};
+use crate::network::command_log::CommandLogError;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use thiserror::Error;
+
+mod glb;
+mod obj;
+mod ply;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MeshFormat {
+    Obj,
+    Glb,
+    Ply,
+}
+
+impl MeshFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "obj" => Some(MeshFormat::Obj),
+            "glb" => Some(MeshFormat::Glb),
+            "ply" => Some(MeshFormat::Ply),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum MeshIoError {
+    #[error("i/o error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("unrecognised mesh file extension: {0}")]
+    UnknownFormat(String),
+    #[error("{format:?} parse error: {message}")]
+    Parse { format: MeshFormat, message: String },
+    #[error("unsupported {format:?} content: {message}")]
+    Unsupported { format: MeshFormat, message: String },
+    #[error(transparent)]
+    Mesh(#[from] MeshError),
+    #[error(transparent)]
+    Command(#[from] CommandLogError),
+}
+
+impl MeshIoError {
+    fn parse(format: MeshFormat, message: impl Into<String>) -> Self {
+        MeshIoError::Parse {
+            format,
+            message: message.into(),
+        }
+    }
+
+    fn unsupported(format: MeshFormat, message: impl Into<String>) -> Self {
+        MeshIoError::Unsupported {
+            format,
+            message: message.into(),
+        }
+    }
+}
+
+/// Translation, rotation (xyzw quaternion) and scale of an imported or exported node.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NodeTransform {
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+}
+
+impl Default for NodeTransform {
+    fn default() -> Self {
+        Self {
+            translation: [0.0; 3],
+            rotation: [0.0, 0.0, 0.0, 1.0],
+            scale: [1.0; 3],
+        }
+    }
+}
+
+impl NodeTransform {
+    /// Applies scale, rotation and translation to a point.
+    pub fn apply(&self, point: [f32; 3]) -> [f32; 3] {
+        let scaled = [
+            point[0] * self.scale[0],
+            point[1] * self.scale[1],
+            point[2] * self.scale[2],
+        ];
+        let rotated = rotate(self.rotation, scaled);
+        [
+            rotated[0] + self.translation[0],
+            rotated[1] + self.translation[1],
+            rotated[2] + self.translation[2],
+        ]
+    }
+}
+
+/// One mesh read from a file, ready to be spawned as an entity.
+#[derive(Debug, Clone)]
+pub struct ImportedMesh {
+    pub name: Option<String>,
+    pub mesh: EditableMesh,
+    pub transform: NodeTransform,
+    /// Faces dropped because they were degenerate or non-manifold.
+    pub skipped_faces: usize,
+}
+
+/// Mesh handed to an exporter.
+#[derive(Debug, Clone, Copy)]
+pub struct ExportMesh<'a> {
+    pub name: &'a str,
+    pub mesh: &'a EditableMesh,
+    pub transform: NodeTransform,
+}
+
+/// Serialisable snapshot of an [`EditableMesh`], used to replicate imported geometry.
+/// Vertex and face ids are reassigned densely in id order when rebuilt.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MeshData {
+    pub vertices: Vec<MeshDataVertex>,
+    pub polygons: Vec<Vec<u32>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeshDataVertex {
+    pub position: [f32; 3],
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normal: Option<[f32; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uv: Option<[f32; 2]>,
+}
+
+impl MeshData {
+    pub fn from_mesh(mesh: &EditableMesh) -> Self {
+        let (indices, ids) = dense_indices(mesh);
+        let vertices = ids
+            .iter()
+            .map(|id| {
+                let vertex = mesh.vertex(*id).expect("listed vertex");
+                MeshDataVertex {
+                    position: vertex.position,
+                    normal: vertex.normal,
+                    uv: vertex.uv,
+                }
+            })
+            .collect();
+        let polygons = mesh
+            .face_ids()
+            .map(|face| {
+                mesh.face_vertices(face)
+                    .expect("listed face")
+                    .iter()
+                    .map(|vertex| indices[vertex])
+                    .collect()
+            })
+            .collect();
+        Self { vertices, polygons }
+    }
+
+    pub fn build(&self) -> Result<EditableMesh, MeshError> {
+        let positions: Vec<[f32; 3]> = self.vertices.iter().map(|v| v.position).collect();
+        let mut mesh = EditableMesh::from_polygons(&positions, &self.polygons)?;
+        for (index, source) in self.vertices.iter().enumerate() {
+            let vertex = mesh
+                .vertex_mut(VertexId(index as u64))
+                .expect("vertex ids follow positions");
+            vertex.normal = source.normal;
+            vertex.uv = source.uv;
+        }
+        Ok(mesh)
+    }
+}
+
+pub fn import_file(path: &Path) -> Result<Vec<ImportedMesh>, MeshIoError> {
+    let format = MeshFormat::from_path(path)
+        .ok_or_else(|| MeshIoError::UnknownFormat(path.display().to_string()))?;
+    let bytes = std::fs::read(path)?;
+    import_bytes(&bytes, format)
+}
+
+pub fn import_bytes(bytes: &[u8], format: MeshFormat) -> Result<Vec<ImportedMesh>, MeshIoError> {
+    match format {
+        MeshFormat::Obj => obj::import(bytes),
+        MeshFormat::Glb => glb::import(bytes),
+        MeshFormat::Ply => ply::import(bytes),
+    }
+}
+
+pub fn export_file(path: &Path, meshes: &[ExportMesh<'_>]) -> Result<(), MeshIoError> {
+    let format = MeshFormat::from_path(path)
+        .ok_or_else(|| MeshIoError::UnknownFormat(path.display().to_string()))?;
+    std::fs::write(path, export_bytes(meshes, format)?)?;
+    Ok(())
+}
+
+/// Writes `meshes` in `format`. OBJ and PLY have no node hierarchy, so transforms are
+/// baked into the positions; PLY additionally merges everything into one mesh.
+pub fn export_bytes(meshes: &[ExportMesh<'_>], format: MeshFormat) -> Result<Vec<u8>, MeshIoError> {
+    match format {
+        MeshFormat::Obj => Ok(obj::export(meshes)),
+        MeshFormat::Glb => glb::export(meshes),
+        MeshFormat::Ply => Ok(ply::export(meshes)),
+    }
+}
+
+/// Builds a mesh from raw polygons, dropping faces the half-edge structure rejects.
+/// Returns the mesh and the number of skipped faces.
+fn build_mesh(
+    vertices: Vec<MeshDataVertex>,
+    polygons: impl IntoIterator<Item = Vec<u32>>,
+) -> Result<(EditableMesh, usize), MeshError> {
+    let mut mesh = EditableMesh::new();
+    let ids: Vec<VertexId> = vertices
+        .iter()
+        .map(|source| {
+            let id = mesh.add_vertex(source.position, HashMap::new());
+            let vertex = mesh.vertex_mut(id).expect("new vertex");
+            vertex.normal = source.normal;
+            vertex.uv = source.uv;
+            id
+        })
+        .collect();
+
+    let mut skipped = 0;
+    for polygon in polygons {
+        let face: Vec<VertexId> = polygon
+            .iter()
+            .map(|index| {
+                ids.get(*index as usize)
+                    .copied()
+                    .ok_or(MeshError::UnknownVertex(VertexId(u64::from(*index))))
+            })
+            .collect::<Result<_, _>>()?;
+        match mesh.add_face(&face) {
+            Ok(_) => {}
+            Err(MeshError::DegenerateFace | MeshError::NonManifoldEdge { .. }) => skipped += 1,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok((mesh, skipped))
+}
+
+/// Maps vertex ids to dense indices in id order.
+fn dense_indices(mesh: &EditableMesh) -> (BTreeMap<VertexId, u32>, Vec<VertexId>) {
+    let ids: Vec<VertexId> = mesh.vertex_ids().collect();
+    let indices = ids
+        .iter()
+        .enumerate()
+        .map(|(index, id)| (*id, index as u32))
+        .collect();
+    (indices, ids)
+}
+
+/// Area-weighted vertex normal from the faces around each vertex, keyed by dense index.
+fn vertex_normals(mesh: &EditableMesh, indices: &BTreeMap<VertexId, u32>) -> Vec<[f32; 3]> {
+    let mut normals = vec![[0.0f32; 3]; indices.len()];
+    for face in mesh.face_ids() {
+        let ring = mesh.face_vertices(face).expect("listed face");
+        let position = |id: VertexId| mesh.vertex(id).expect("face vertex").position;
+        let mut normal = [0.0f32; 3];
+        for (a, b) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+            let (p, q) = (position(*a), position(*b));
+            normal[0] += (p[1] - q[1]) * (p[2] + q[2]);
+            normal[1] += (p[2] - q[2]) * (p[0] + q[0]);
+            normal[2] += (p[0] - q[0]) * (p[1] + q[1]);
+        }
+        for vertex in &ring {
+            let slot = &mut normals[indices[vertex] as usize];
+            for axis in 0..3 {
+                slot[axis] += normal[axis];
+            }
+        }
+    }
+    for normal in &mut normals {
+        let length = normal.iter().map(|axis| axis * axis).sum::<f32>().sqrt();
+        *normal = if length > f32::EPSILON {
+            normal.map(|axis| axis / length)
+        } else {
+            [0.0, 1.0, 0.0]
+        };
+    }
+    normals
+}
+
+fn rotate(rotation: [f32; 4], point: [f32; 3]) -> [f32; 3] {
+    let [x, y, z, w] = rotation;
+    // t = 2 * cross(q.xyz, v); v' = v + w * t + cross(q.xyz, t)
+    let t = [
+        2.0 * (y * point[2] - z * point[1]),
+        2.0 * (z * point[0] - x * point[2]),
+        2.0 * (x * point[1] - y * point[0]),
+    ];
+    [
+        point[0] + w * t[0] + (y * t[2] - z * t[1]),
+        point[1] + w * t[1] + (z * t[0] - x * t[2]),
+        point[2] + w * t[2] + (x * t[1] - y * t[0]),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::primitives::PrimitiveShape;
+
+    fn export_round_trip(format: MeshFormat) -> Vec<ImportedMesh> {
+        let mut mesh = PrimitiveShape::Cylinder {
+            radius: 1.0,
+            depth: 2.0,
+            segments: 8,
+        }
+        .build();
+        let ids: Vec<VertexId> = mesh.vertex_ids().collect();
+        for id in ids {
+            mesh.vertex_mut(id).unwrap().uv = Some([0.25, 0.75]);
+        }
+        let transform = NodeTransform {
+            translation: [1.0, 2.0, 3.0],
+            ..NodeTransform::default()
+        };
+        let bytes = export_bytes(
+            &[ExportMesh {
+                name: "cylinder",
+                mesh: &mesh,
+                transform,
+            }],
+            format,
+        )
+        .expect("export");
+        import_bytes(&bytes, format).expect("import")
+    }
+
+    #[test]
+    fn polygon_formats_round_trip_topology() {
+        for format in [MeshFormat::Obj, MeshFormat::Ply] {
+            let imported = export_round_trip(format);
+            assert_eq!(imported.len(), 1, "{format:?}");
+            let mesh = &imported[0].mesh;
+            mesh.validate().expect("valid import");
+            assert_eq!(
+                (
+                    mesh.vertex_count(),
+                    mesh.face_count(),
+                    imported[0].skipped_faces
+                ),
+                (16, 10, 0),
+                "{format:?}"
+            );
+            let first = mesh.vertex(VertexId(0)).unwrap();
+            assert_eq!(
+                first.position,
+                [2.0, 3.0, 3.0],
+                "{format:?} bakes transforms"
+            );
+            assert_eq!(first.uv, Some([0.25, 0.75]), "{format:?}");
+        }
+    }
+
+    #[test]
+    fn glb_round_trip_keeps_node_transforms() {
+        let imported = export_round_trip(MeshFormat::Glb);
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].name.as_deref(), Some("cylinder"));
+        assert_eq!(imported[0].transform.translation, [1.0, 2.0, 3.0]);
+        let mesh = &imported[0].mesh;
+        mesh.validate().expect("valid import");
+        // caps are fanned into triangles on export
+        assert_eq!((mesh.vertex_count(), mesh.face_count()), (16, 28));
+        let first = mesh.vertex(VertexId(0)).unwrap();
+        assert_eq!(first.position, [1.0, 1.0, 0.0]);
+        assert_eq!(first.uv, Some([0.25, 0.75]));
+        assert!(first.normal.is_some());
+    }
+
+    #[test]
+    fn mesh_data_rebuilds_identical_meshes() {
+        let mesh = PrimitiveShape::Torus {
+            major_radius: 1.0,
+            minor_radius: 0.25,
+            major_segments: 6,
+            minor_segments: 4,
+        }
+        .build();
+        let data = MeshData::from_mesh(&mesh);
+        let json = serde_json::to_vec(&data).unwrap();
+        let decoded: MeshData = serde_json::from_slice(&json).unwrap();
+        assert_eq!(decoded.build().unwrap(), mesh);
+    }
+
+    #[test]
+    fn ply_lists_longer_than_the_data_are_rejected() {
+        let mut binary = b"ply\nformat binary_little_endian 1.0\nelement face 1\n\
+            property list uint uint vertex_indices\nend_header\n"
+            .to_vec();
+        binary.extend(u32::MAX.to_le_bytes());
+        let ascii = b"ply\nformat ascii 1.0\nelement face 1\n\
+            property list uint uint vertex_indices\nend_header\n4294967295 0 1\n";
+        for bytes in [&binary[..], &ascii[..]] {
+            assert!(matches!(
+                import_bytes(bytes, MeshFormat::Ply),
+                Err(MeshIoError::Parse {
+                    format: MeshFormat::Ply,
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn glb_indices_past_the_vertex_range_are_rejected() {
+        // Two primitives share three positions; the second one's first index overflows
+        // once it is offset past the first primitive's vertices.
+        let mut bin: Vec<u8> = [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+            .iter()
+            .flatten()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        bin.extend(
+            [u32::MAX, 0, 1]
+                .iter()
+                .flat_map(|index| index.to_le_bytes()),
+        );
+        let document = serde_json::json!({
+            "asset": { "version": "2.0" },
+            "scene": 0,
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [{ "mesh": 0 }],
+            "meshes": [{ "primitives": [
+                { "attributes": { "POSITION": 0 } },
+                { "attributes": { "POSITION": 0 }, "indices": 1 },
+            ] }],
+            "accessors": [
+                { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                  "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0] },
+                { "bufferView": 1, "componentType": 5125, "count": 3, "type": "SCALAR" },
+            ],
+            "bufferViews": [
+                { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+                { "buffer": 0, "byteOffset": 36, "byteLength": 12 },
+            ],
+            "buffers": [{ "byteLength": bin.len() }],
+        });
+        let mut json = serde_json::to_vec(&document).unwrap();
+        while !json.len().is_multiple_of(4) {
+            json.push(b' ');
+        }
+        let total = 12 + 8 + json.len() + 8 + bin.len();
+        let mut bytes = Vec::new();
+        bytes.extend(0x4654_6C67u32.to_le_bytes());
+        bytes.extend(2u32.to_le_bytes());
+        bytes.extend((total as u32).to_le_bytes());
+        bytes.extend((json.len() as u32).to_le_bytes());
+        bytes.extend(0x4E4F_534Au32.to_le_bytes());
+        bytes.extend(json);
+        bytes.extend((bin.len() as u32).to_le_bytes());
+        bytes.extend(0x004E_4942u32.to_le_bytes());
+        bytes.extend(bin);
+
+        assert!(matches!(
+            import_bytes(&bytes, MeshFormat::Glb),
+            Err(MeshIoError::Parse {
+                format: MeshFormat::Glb,
+                ..
+            })
+        ));
+    }
+}
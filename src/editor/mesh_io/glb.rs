@@ -0,0 +1,279 @@
+use super::{
+    ExportMesh, ImportedMesh, MeshDataVertex, MeshFormat, MeshIoError, NodeTransform, build_mesh,
+    dense_indices, vertex_normals,
+};
+use gltf::buffer::Source;
+use gltf::mesh::Mode;
+use serde_json::{Value, json};
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+
+type Matrix = [[f32; 4]; 4];
+
+const IDENTITY: Matrix = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+/// Imports every mesh node of the default scene. Triangle primitives of one mesh are
+/// merged; glTF vertices are kept as stored, so UV and normal seams stay split.
+pub(super) fn import(bytes: &[u8]) -> Result<Vec<ImportedMesh>, MeshIoError> {
+    let gltf = gltf::Gltf::from_slice(bytes)
+        .map_err(|err| MeshIoError::parse(MeshFormat::Glb, err.to_string()))?;
+    if gltf
+        .buffers()
+        .any(|buffer| matches!(buffer.source(), Source::Uri(_)))
+    {
+        return Err(MeshIoError::unsupported(
+            MeshFormat::Glb,
+            "buffers outside the binary chunk",
+        ));
+    }
+    let blob = gltf.blob.as_deref();
+    let roots: Vec<gltf::Node<'_>> = match gltf.default_scene().or_else(|| gltf.scenes().next()) {
+        Some(scene) => scene.nodes().collect(),
+        None => gltf.nodes().collect(),
+    };
+
+    let mut stack: Vec<(gltf::Node<'_>, Matrix)> = roots
+        .into_iter()
+        .rev()
+        .map(|node| (node, IDENTITY))
+        .collect();
+    let mut imported = Vec::new();
+    while let Some((node, parent)) = stack.pop() {
+        let world = multiply(&parent, &node.transform().matrix());
+        for child in node.children().collect::<Vec<_>>().into_iter().rev() {
+            stack.push((child, world));
+        }
+        let Some(mesh) = node.mesh() else {
+            continue;
+        };
+
+        let mut vertices = Vec::new();
+        let mut polygons = Vec::new();
+        for primitive in mesh.primitives() {
+            if primitive.mode() != Mode::Triangles {
+                return Err(MeshIoError::unsupported(
+                    MeshFormat::Glb,
+                    format!("primitive mode {:?}", primitive.mode()),
+                ));
+            }
+            let reader = primitive.reader(|_| blob);
+            let positions: Vec<[f32; 3]> = reader
+                .read_positions()
+                .ok_or_else(|| MeshIoError::parse(MeshFormat::Glb, "primitive without positions"))?
+                .collect();
+            let normals: Option<Vec<[f32; 3]>> = reader.read_normals().map(Iterator::collect);
+            let uvs: Option<Vec<[f32; 2]>> = reader
+                .read_tex_coords(0)
+                .map(|coords| coords.into_f32().collect());
+            let indices: Vec<u32> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..positions.len() as u32).collect(),
+            };
+
+            let base = vertices.len() as u32;
+            for (index, position) in positions.iter().enumerate() {
+                vertices.push(MeshDataVertex {
+                    position: *position,
+                    normal: normals.as_ref().and_then(|all| all.get(index).copied()),
+                    uv: uvs.as_ref().and_then(|all| all.get(index).copied()),
+                });
+            }
+            for triangle in indices.chunks_exact(3) {
+                let polygon = triangle
+                    .iter()
+                    .map(|index| base.checked_add(*index))
+                    .collect::<Option<Vec<u32>>>()
+                    .ok_or_else(|| MeshIoError::parse(MeshFormat::Glb, "index out of range"))?;
+                polygons.push(polygon);
+            }
+        }
+
+        let (translation, rotation, scale) =
+            gltf::scene::Transform::Matrix { matrix: world }.decomposed();
+        let (mesh, skipped_faces) = build_mesh(vertices, polygons)?;
+        imported.push(ImportedMesh {
+            name: node.name().or(mesh_name(&node)).map(str::to_string),
+            mesh,
+            transform: NodeTransform {
+                translation,
+                rotation,
+                scale,
+            },
+            skipped_faces,
+        });
+    }
+    Ok(imported)
+}
+
+fn mesh_name<'a>(node: &gltf::Node<'a>) -> Option<&'a str> {
+    node.mesh().and_then(|mesh| mesh.name())
+}
+
+/// Writes one node per mesh. Polygons are fanned into triangles; normals come from the
+/// vertices or are derived from the faces, and UVs are written when every vertex has one.
+pub(super) fn export(meshes: &[ExportMesh<'_>]) -> Result<Vec<u8>, MeshIoError> {
+    let mut bin: Vec<u8> = Vec::new();
+    let mut buffer_views: Vec<Value> = Vec::new();
+    let mut accessors: Vec<Value> = Vec::new();
+    let mut gltf_meshes: Vec<Value> = Vec::new();
+    let mut nodes: Vec<Value> = Vec::new();
+
+    let mut push_view = |bin: &mut Vec<u8>, data: &[u8], target: u32| -> usize {
+        while !bin.len().is_multiple_of(4) {
+            bin.push(0);
+        }
+        buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": bin.len(),
+            "byteLength": data.len(),
+            "target": target,
+        }));
+        bin.extend_from_slice(data);
+        buffer_views.len() - 1
+    };
+
+    for export in meshes {
+        let (indices, ids) = dense_indices(export.mesh);
+        let derived = vertex_normals(export.mesh, &indices);
+        let vertices: Vec<_> = ids
+            .iter()
+            .map(|id| export.mesh.vertex(*id).expect("listed vertex"))
+            .collect();
+        if vertices.is_empty() {
+            continue;
+        }
+
+        let positions: Vec<[f32; 3]> = vertices.iter().map(|vertex| vertex.position).collect();
+        let normals: Vec<[f32; 3]> = vertices
+            .iter()
+            .zip(&derived)
+            .map(|(vertex, derived)| vertex.normal.unwrap_or(*derived))
+            .collect();
+        let uvs: Option<Vec<[f32; 2]>> = vertices.iter().map(|vertex| vertex.uv).collect();
+        let mut triangles: Vec<u32> = Vec::new();
+        for face in export.mesh.face_ids() {
+            let ring = export.mesh.face_vertices(face).expect("listed face");
+            for pair in ring[1..].windows(2) {
+                triangles.extend([indices[&ring[0]], indices[&pair[0]], indices[&pair[1]]]);
+            }
+        }
+
+        let (min, max) = bounds(&positions);
+        let mut attributes = serde_json::Map::new();
+        let view = push_view(&mut bin, &floats(&positions), ARRAY_BUFFER);
+        accessors.push(json!({
+            "bufferView": view, "componentType": FLOAT, "count": positions.len(),
+            "type": "VEC3", "min": min, "max": max,
+        }));
+        attributes.insert("POSITION".into(), json!(accessors.len() - 1));
+        let view = push_view(&mut bin, &floats(&normals), ARRAY_BUFFER);
+        accessors.push(json!({
+            "bufferView": view, "componentType": FLOAT, "count": normals.len(), "type": "VEC3",
+        }));
+        attributes.insert("NORMAL".into(), json!(accessors.len() - 1));
+        if let Some(uvs) = uvs {
+            let view = push_view(&mut bin, &floats(&uvs), ARRAY_BUFFER);
+            accessors.push(json!({
+                "bufferView": view, "componentType": FLOAT, "count": uvs.len(), "type": "VEC2",
+            }));
+            attributes.insert("TEXCOORD_0".into(), json!(accessors.len() - 1));
+        }
+        let index_bytes: Vec<u8> = triangles.iter().flat_map(|i| i.to_le_bytes()).collect();
+        let view = push_view(&mut bin, &index_bytes, ELEMENT_ARRAY_BUFFER);
+        accessors.push(json!({
+            "bufferView": view, "componentType": UNSIGNED_INT, "count": triangles.len(),
+            "type": "SCALAR",
+        }));
+
+        gltf_meshes.push(json!({
+            "name": export.name,
+            "primitives": [{ "attributes": attributes, "indices": accessors.len() - 1 }],
+        }));
+        nodes.push(json!({
+            "name": export.name,
+            "mesh": gltf_meshes.len() - 1,
+            "translation": export.transform.translation,
+            "rotation": export.transform.rotation,
+            "scale": export.transform.scale,
+        }));
+    }
+    while !bin.len().is_multiple_of(4) {
+        bin.push(0);
+    }
+
+    let mut document = json!({
+        "asset": { "version": "2.0", "generator": "theta_engine" },
+        "scene": 0,
+        "scenes": [{ "nodes": (0..nodes.len()).collect::<Vec<_>>() }],
+        "nodes": nodes,
+        "meshes": gltf_meshes,
+        "accessors": accessors,
+        "bufferViews": buffer_views,
+    });
+    if !bin.is_empty() {
+        document["buffers"] = json!([{ "byteLength": bin.len() }]);
+    }
+    let mut json_chunk = serde_json::to_vec(&document)
+        .map_err(|err| MeshIoError::parse(MeshFormat::Glb, err.to_string()))?;
+    while !json_chunk.len().is_multiple_of(4) {
+        json_chunk.push(b' ');
+    }
+
+    let bin_chunk_len = if bin.is_empty() { 0 } else { 8 + bin.len() };
+    let total = 12 + 8 + json_chunk.len() + bin_chunk_len;
+    let mut out = Vec::with_capacity(total);
+    out.extend(GLB_MAGIC.to_le_bytes());
+    out.extend(2u32.to_le_bytes());
+    out.extend((total as u32).to_le_bytes());
+    out.extend((json_chunk.len() as u32).to_le_bytes());
+    out.extend(CHUNK_JSON.to_le_bytes());
+    out.extend(json_chunk);
+    if !bin.is_empty() {
+        out.extend((bin.len() as u32).to_le_bytes());
+        out.extend(CHUNK_BIN.to_le_bytes());
+        out.extend(bin);
+    }
+    Ok(out)
+}
+
+fn floats<const N: usize>(values: &[[f32; N]]) -> Vec<u8> {
+    values
+        .iter()
+        .flatten()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+fn bounds(positions: &[[f32; 3]]) -> ([f32; 3], [f32; 3]) {
+    let mut min = [f32::INFINITY; 3];
+    let mut max = [f32::NEG_INFINITY; 3];
+    for position in positions {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(position[axis]);
+            max[axis] = max[axis].max(position[axis]);
+        }
+    }
+    (min, max)
+}
+
+/// Column-major 4x4 product `a * b`, matching glTF's matrix layout.
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut out = [[0.0; 4]; 4];
+    for (column, out_column) in out.iter_mut().enumerate() {
+        for (row, value) in out_column.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b[column][k]).sum();
+        }
+    }
+    out
+}
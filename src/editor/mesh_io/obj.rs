@@ -0,0 +1,168 @@
+use super::{
+    ExportMesh, ImportedMesh, MeshDataVertex, MeshFormat, MeshIoError, NodeTransform, build_mesh,
+    dense_indices,
+};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Reads `o`/`g` groups as separate meshes. Vertices are welded by position index so
+/// faces sharing a corner share topology; the first `vt`/`vn` seen for a corner wins.
+pub(super) fn import(bytes: &[u8]) -> Result<Vec<ImportedMesh>, MeshIoError> {
+    let text = std::str::from_utf8(bytes)
+        .map_err(|err| MeshIoError::parse(MeshFormat::Obj, err.to_string()))?;
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+    let mut groups = vec![Group::default()];
+
+    for (number, line) in text.lines().enumerate() {
+        let error = |message: &str| {
+            MeshIoError::parse(MeshFormat::Obj, format!("line {}: {message}", number + 1))
+        };
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => positions.push(floats::<3>(tokens).ok_or_else(|| error("bad vertex"))?),
+            Some("vt") => uvs.push(floats::<2>(tokens).ok_or_else(|| error("bad uv"))?),
+            Some("vn") => normals.push(floats::<3>(tokens).ok_or_else(|| error("bad normal"))?),
+            Some("o" | "g") => {
+                let name = tokens.collect::<Vec<_>>().join(" ");
+                let current = groups.last_mut().expect("group");
+                if current.faces.is_empty() {
+                    current.name = Some(name);
+                } else {
+                    groups.push(Group {
+                        name: Some(name),
+                        ..Group::default()
+                    });
+                }
+            }
+            Some("f") => {
+                let mut face = Vec::new();
+                for corner in tokens {
+                    let mut parts = corner.split('/');
+                    let mut index = |count: usize| -> Result<Option<usize>, MeshIoError> {
+                        match parts.next() {
+                            None | Some("") => Ok(None),
+                            Some(raw) => resolve(raw, count)
+                                .map(Some)
+                                .ok_or_else(|| error("bad face index")),
+                        }
+                    };
+                    let position = index(positions.len())?.ok_or_else(|| error("bad face"))?;
+                    let uv = index(uvs.len())?;
+                    let normal = index(normals.len())?;
+                    face.push(Corner {
+                        position,
+                        uv,
+                        normal,
+                    });
+                }
+                groups.last_mut().expect("group").faces.push(face);
+            }
+            _ => {}
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter(|group| !group.faces.is_empty())
+        .map(|group| {
+            let mut local: BTreeMap<usize, u32> = BTreeMap::new();
+            let mut vertices = Vec::new();
+            let mut polygons = Vec::with_capacity(group.faces.len());
+            for face in &group.faces {
+                let polygon = face
+                    .iter()
+                    .map(|corner| {
+                        *local.entry(corner.position).or_insert_with(|| {
+                            vertices.push(MeshDataVertex {
+                                position: positions[corner.position],
+                                normal: corner.normal.map(|index| normals[index]),
+                                uv: corner.uv.map(|index| uvs[index]),
+                            });
+                            vertices.len() as u32 - 1
+                        })
+                    })
+                    .collect();
+                polygons.push(polygon);
+            }
+            let (mesh, skipped_faces) = build_mesh(vertices, polygons)?;
+            Ok(ImportedMesh {
+                name: group.name,
+                mesh,
+                transform: NodeTransform::default(),
+                skipped_faces,
+            })
+        })
+        .collect()
+}
+
+pub(super) fn export(meshes: &[ExportMesh<'_>]) -> Vec<u8> {
+    let mut out = String::from("# theta_engine mesh export\n");
+    let (mut position_base, mut uv_base) = (1usize, 1usize);
+    for export in meshes {
+        let (indices, ids) = dense_indices(export.mesh);
+        let _ = writeln!(out, "o {}", export.name);
+        let mut uv_slots = BTreeMap::new();
+        for id in &ids {
+            let vertex = export.mesh.vertex(*id).expect("listed vertex");
+            let [x, y, z] = export.transform.apply(vertex.position);
+            let _ = writeln!(out, "v {x} {y} {z}");
+            if let Some([u, v]) = vertex.uv {
+                let _ = writeln!(out, "vt {u} {v}");
+                uv_slots.insert(*id, uv_base + uv_slots.len());
+            }
+        }
+        for face in export.mesh.face_ids() {
+            out.push('f');
+            for vertex in export.mesh.face_vertices(face).expect("listed face") {
+                let position = position_base + indices[&vertex] as usize;
+                match uv_slots.get(&vertex) {
+                    Some(uv) => {
+                        let _ = write!(out, " {position}/{uv}");
+                    }
+                    None => {
+                        let _ = write!(out, " {position}");
+                    }
+                }
+            }
+            out.push('\n');
+        }
+        position_base += ids.len();
+        uv_base += uv_slots.len();
+    }
+    out.into_bytes()
+}
+
+#[derive(Default)]
+struct Group {
+    name: Option<String>,
+    faces: Vec<Vec<Corner>>,
+}
+
+struct Corner {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+fn floats<'a, const N: usize>(mut tokens: impl Iterator<Item = &'a str>) -> Option<[f32; N]> {
+    let mut values = [0.0; N];
+    for value in &mut values {
+        *value = tokens.next()?.parse().ok()?;
+    }
+    Some(values)
+}
+
+/// Resolves a 1-based (or negative, relative) OBJ index against `count` entries.
+fn resolve(raw: &str, count: usize) -> Option<usize> {
+    let index: i64 = raw.parse().ok()?;
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    (0..count as i64)
+        .contains(&resolved)
+        .then_some(resolved as usize)
+}
@@ -0,0 +1,329 @@
+use super::{
+    ExportMesh, ImportedMesh, MeshDataVertex, MeshFormat, MeshIoError, NodeTransform, build_mesh,
+    dense_indices,
+};
+use std::fmt::Write;
+
+/// Reads the `vertex` and `face` elements of an ASCII or binary PLY file. Other
+/// elements are skipped.
+pub(super) fn import(bytes: &[u8]) -> Result<Vec<ImportedMesh>, MeshIoError> {
+    let (header, body) = parse_header(bytes)?;
+    let mut reader = match header.encoding {
+        Encoding::Ascii => {
+            let text = std::str::from_utf8(body)
+                .map_err(|err| MeshIoError::parse(MeshFormat::Ply, err.to_string()))?;
+            Reader::Ascii(text.split_whitespace())
+        }
+        Encoding::Binary { big_endian } => Reader::Binary {
+            bytes: body,
+            big_endian,
+        },
+    };
+
+    let mut vertices = Vec::new();
+    let mut polygons = Vec::new();
+    for element in &header.elements {
+        for _ in 0..element.count {
+            let mut vertex = VertexFields::default();
+            for property in &element.properties {
+                match property {
+                    Property::Scalar { name, kind } => {
+                        let value = reader.scalar(*kind)?;
+                        if element.name == "vertex" {
+                            vertex.set(name, value as f32);
+                        }
+                    }
+                    Property::List { name, count, item } => {
+                        // The length comes from the file; the values it promises must
+                        // be read before anything is allocated for them.
+                        let length = reader.scalar(*count)? as usize;
+                        let mut values = Vec::new();
+                        for _ in 0..length {
+                            values.push(reader.scalar(*item)? as u32);
+                        }
+                        if element.name == "face"
+                            && matches!(name.as_str(), "vertex_indices" | "vertex_index")
+                        {
+                            polygons.push(values);
+                        }
+                    }
+                }
+            }
+            if element.name == "vertex" {
+                vertices.push(vertex.into_vertex());
+            }
+        }
+    }
+
+    let (mesh, skipped_faces) = build_mesh(vertices, polygons)?;
+    Ok(vec![ImportedMesh {
+        name: None,
+        mesh,
+        transform: NodeTransform::default(),
+        skipped_faces,
+    }])
+}
+
+/// Writes one ASCII PLY holding every mesh with transforms baked in. Texture
+/// coordinates are written only when every vertex has one.
+pub(super) fn export(meshes: &[ExportMesh<'_>]) -> Vec<u8> {
+    let vertex_count: usize = meshes.iter().map(|export| export.mesh.vertex_count()).sum();
+    let face_count: usize = meshes.iter().map(|export| export.mesh.face_count()).sum();
+    let with_uv = meshes.iter().all(|export| {
+        export.mesh.vertex_ids().all(|id| {
+            export
+                .mesh
+                .vertex(id)
+                .is_some_and(|vertex| vertex.uv.is_some())
+        })
+    });
+
+    let mut out = String::from("ply\nformat ascii 1.0\ncomment theta_engine mesh export\n");
+    let _ = writeln!(out, "element vertex {vertex_count}");
+    out.push_str("property float x\nproperty float y\nproperty float z\n");
+    if with_uv {
+        out.push_str("property float s\nproperty float t\n");
+    }
+    let _ = writeln!(out, "element face {face_count}");
+    out.push_str("property list uchar uint vertex_indices\nend_header\n");
+
+    for export in meshes {
+        for id in export.mesh.vertex_ids() {
+            let vertex = export.mesh.vertex(id).expect("listed vertex");
+            let [x, y, z] = export.transform.apply(vertex.position);
+            let _ = write!(out, "{x} {y} {z}");
+            if let (true, Some([s, t])) = (with_uv, vertex.uv) {
+                let _ = write!(out, " {s} {t}");
+            }
+            out.push('\n');
+        }
+    }
+    let mut base = 0u32;
+    for export in meshes {
+        let (indices, ids) = dense_indices(export.mesh);
+        for face in export.mesh.face_ids() {
+            let ring = export.mesh.face_vertices(face).expect("listed face");
+            let _ = write!(out, "{}", ring.len());
+            for vertex in ring {
+                let _ = write!(out, " {}", base + indices[&vertex]);
+            }
+            out.push('\n');
+        }
+        base += ids.len() as u32;
+    }
+    out.into_bytes()
+}
+
+enum Encoding {
+    Ascii,
+    Binary { big_endian: bool },
+}
+
+struct Header {
+    encoding: Encoding,
+    elements: Vec<Element>,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+enum Property {
+    Scalar {
+        name: String,
+        kind: Scalar,
+    },
+    List {
+        name: String,
+        count: Scalar,
+        item: Scalar,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+}
+
+fn parse_header(bytes: &[u8]) -> Result<(Header, &[u8]), MeshIoError> {
+    const END: &[u8] = b"end_header";
+    let error = |message: &str| MeshIoError::parse(MeshFormat::Ply, message);
+    if !bytes.starts_with(b"ply") {
+        return Err(error("missing ply magic"));
+    }
+    let end = bytes
+        .windows(END.len())
+        .position(|window| window == END)
+        .ok_or_else(|| error("missing end_header"))?;
+    let body_start = bytes[end..]
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .map_or(bytes.len(), |offset| end + offset + 1);
+    let text = std::str::from_utf8(&bytes[..end]).map_err(|_| error("header is not utf-8"))?;
+
+    let mut encoding = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in text.lines().skip(1) {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["format", "ascii", _] => encoding = Some(Encoding::Ascii),
+            ["format", "binary_little_endian", _] => {
+                encoding = Some(Encoding::Binary { big_endian: false })
+            }
+            ["format", "binary_big_endian", _] => {
+                encoding = Some(Encoding::Binary { big_endian: true })
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| error("bad element count"))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| error("orphan property"))?;
+                element.properties.push(Property::List {
+                    name: name.to_string(),
+                    count: Scalar::parse(count).ok_or_else(|| error("bad list count type"))?,
+                    item: Scalar::parse(item).ok_or_else(|| error("bad list item type"))?,
+                });
+            }
+            ["property", kind, name] => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| error("orphan property"))?;
+                element.properties.push(Property::Scalar {
+                    name: name.to_string(),
+                    kind: Scalar::parse(kind).ok_or_else(|| error("bad property type"))?,
+                });
+            }
+            ["format", ..] => {
+                return Err(MeshIoError::unsupported(MeshFormat::Ply, line.to_string()));
+            }
+            _ => {}
+        }
+    }
+    let encoding = encoding.ok_or_else(|| error("missing format line"))?;
+    Ok((Header { encoding, elements }, &bytes[body_start..]))
+}
+
+enum Reader<'a> {
+    Ascii(std::str::SplitWhitespace<'a>),
+    Binary { bytes: &'a [u8], big_endian: bool },
+}
+
+impl Reader<'_> {
+    fn scalar(&mut self, kind: Scalar) -> Result<f64, MeshIoError> {
+        let truncated = || MeshIoError::parse(MeshFormat::Ply, "unexpected end of data");
+        match self {
+            Reader::Ascii(tokens) => tokens
+                .next()
+                .ok_or_else(truncated)?
+                .parse()
+                .map_err(|_| MeshIoError::parse(MeshFormat::Ply, "bad ascii value")),
+            Reader::Binary { bytes, big_endian } => {
+                let size = kind.size();
+                if bytes.len() < size {
+                    return Err(truncated());
+                }
+                let (raw, rest) = bytes.split_at(size);
+                *bytes = rest;
+                let mut buffer = [0u8; 8];
+                buffer[..size].copy_from_slice(raw);
+                if *big_endian {
+                    buffer[..size].reverse();
+                }
+                let value = match kind {
+                    Scalar::I8 => buffer[0] as i8 as f64,
+                    Scalar::U8 => buffer[0] as f64,
+                    Scalar::I16 => i16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+                    Scalar::U16 => u16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+                    Scalar::I32 => {
+                        i32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64
+                    }
+                    Scalar::U32 => {
+                        u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64
+                    }
+                    Scalar::F32 => {
+                        f32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64
+                    }
+                    Scalar::F64 => f64::from_le_bytes(buffer),
+                };
+                Ok(value)
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct VertexFields {
+    position: [f32; 3],
+    normal: [Option<f32>; 3],
+    uv: [Option<f32>; 2],
+}
+
+impl VertexFields {
+    fn set(&mut self, name: &str, value: f32) {
+        match name {
+            "x" => self.position[0] = value,
+            "y" => self.position[1] = value,
+            "z" => self.position[2] = value,
+            "nx" => self.normal[0] = Some(value),
+            "ny" => self.normal[1] = Some(value),
+            "nz" => self.normal[2] = Some(value),
+            "s" | "u" | "texture_u" => self.uv[0] = Some(value),
+            "t" | "v" | "texture_v" => self.uv[1] = Some(value),
+            _ => {}
+        }
+    }
+
+    fn into_vertex(self) -> MeshDataVertex {
+        let normal = match self.normal {
+            [Some(x), Some(y), Some(z)] => Some([x, y, z]),
+            _ => None,
+        };
+        let uv = match self.uv {
+            [Some(u), Some(v)] => Some([u, v]),
+            _ => None,
+        };
+        MeshDataVertex {
+            position: self.position,
+            normal,
+            uv,
+        }
+    }
+}
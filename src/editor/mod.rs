@@ -4,9 +4,20 @@ pub use telemetry::{
     FrameTelemetry, StageSample, TelemetryOverlay, TelemetryReplicator, TelemetrySurface,
 };
 pub mod commands;
+pub mod gizmo;
+pub mod locks;
+pub mod mesh;
+pub mod mesh_io;
+pub mod primitives;
+pub mod selection;
 pub use commands::{
     CMD_SELECTION_HIGHLIGHT, CommandOutbox, CommandTransportQueue, SelectionHighlightCommand,
 };
+pub use gizmo::{GizmoMode, TransformGizmo};
+pub use locks::EntityLock;
+pub use mesh::EditableMesh;
+pub use primitives::PrimitiveShape;
+pub use selection::{PivotMode, SelectionMode, SelectionSet};
 
 pub struct MeshEditor {
     telemetry_overlay: TelemetryOverlay,
@@ -19,8 +30,8 @@ impl MeshEditor {
         }
     }
 
-    pub fn create_primitive(&mut self) {
-        println!("Mesh creation placeholder");
+    pub fn create_primitive(&mut self, shape: &PrimitiveShape) -> EditableMesh {
+        shape.build()
     }
 
     pub fn telemetry_overlay(&self) -> &TelemetryOverlay {
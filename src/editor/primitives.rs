@@ -0,0 +1,495 @@
+use crate::editor::mesh::EditableMesh;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+
+pub const MIN_PRIMITIVE_SEGMENTS: u32 = 3;
+pub const MAX_PRIMITIVE_SEGMENTS: u32 = 256;
+pub const MAX_ICOSPHERE_SUBDIVISIONS: u32 = 5;
+
+/// Parametric primitive. Every generator is centred on the origin with +Y up and
+/// produces a closed (or, for planes and grids, single-sided) mesh with outward winding.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PrimitiveShape {
+    Cube {
+        size: f32,
+    },
+    UvSphere {
+        radius: f32,
+        segments: u32,
+        rings: u32,
+    },
+    Icosphere {
+        radius: f32,
+        subdivisions: u32,
+    },
+    Cylinder {
+        radius: f32,
+        depth: f32,
+        segments: u32,
+    },
+    Cone {
+        radius: f32,
+        depth: f32,
+        segments: u32,
+    },
+    Torus {
+        major_radius: f32,
+        minor_radius: f32,
+        major_segments: u32,
+        minor_segments: u32,
+    },
+    Plane {
+        size: f32,
+    },
+    Grid {
+        size: f32,
+        x_segments: u32,
+        z_segments: u32,
+    },
+}
+
+impl Default for PrimitiveShape {
+    fn default() -> Self {
+        PrimitiveShape::Cube { size: 1.0 }
+    }
+}
+
+impl PrimitiveShape {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PrimitiveShape::Cube { .. } => "cube",
+            PrimitiveShape::UvSphere { .. } => "uv_sphere",
+            PrimitiveShape::Icosphere { .. } => "icosphere",
+            PrimitiveShape::Cylinder { .. } => "cylinder",
+            PrimitiveShape::Cone { .. } => "cone",
+            PrimitiveShape::Torus { .. } => "torus",
+            PrimitiveShape::Plane { .. } => "plane",
+            PrimitiveShape::Grid { .. } => "grid",
+        }
+    }
+
+    /// Generates the mesh. Segment counts are clamped to sane bounds, so any payload a
+    /// peer sends produces a valid mesh of bounded size.
+    pub fn build(&self) -> EditableMesh {
+        let (positions, polygons) = match *self {
+            PrimitiveShape::Cube { size } => cube(size),
+            PrimitiveShape::UvSphere {
+                radius,
+                segments,
+                rings,
+            } => uv_sphere(radius, clamp_segments(segments), clamp(rings, 2)),
+            PrimitiveShape::Icosphere {
+                radius,
+                subdivisions,
+            } => icosphere(radius, subdivisions.min(MAX_ICOSPHERE_SUBDIVISIONS)),
+            PrimitiveShape::Cylinder {
+                radius,
+                depth,
+                segments,
+            } => cylinder(radius, depth, clamp_segments(segments)),
+            PrimitiveShape::Cone {
+                radius,
+                depth,
+                segments,
+            } => cone(radius, depth, clamp_segments(segments)),
+            PrimitiveShape::Torus {
+                major_radius,
+                minor_radius,
+                major_segments,
+                minor_segments,
+            } => torus(
+                major_radius,
+                minor_radius,
+                clamp_segments(major_segments),
+                clamp_segments(minor_segments),
+            ),
+            PrimitiveShape::Plane { size } => grid(size, 1, 1),
+            PrimitiveShape::Grid {
+                size,
+                x_segments,
+                z_segments,
+            } => grid(size, clamp(x_segments, 1), clamp(z_segments, 1)),
+        };
+
+        EditableMesh::from_polygons(&positions, &polygons)
+            .expect("primitive generators emit manifold topology")
+    }
+}
+
+type Polygons = (Vec<[f32; 3]>, Vec<Vec<u32>>);
+
+fn clamp(value: u32, min: u32) -> u32 {
+    value.clamp(min, MAX_PRIMITIVE_SEGMENTS)
+}
+
+fn clamp_segments(value: u32) -> u32 {
+    clamp(value, MIN_PRIMITIVE_SEGMENTS)
+}
+
+fn cube(size: f32) -> Polygons {
+    let half = size * 0.5;
+    let positions = (0..8u32)
+        .map(|bits| {
+            let axis = |bit: u32| if bits & bit == 0 { -half } else { half };
+            [axis(1), axis(2), axis(4)]
+        })
+        .collect();
+    let polygons = vec![
+        vec![0, 4, 6, 2],
+        vec![1, 3, 7, 5],
+        vec![0, 1, 5, 4],
+        vec![2, 6, 7, 3],
+        vec![0, 2, 3, 1],
+        vec![4, 5, 7, 6],
+    ];
+    (positions, polygons)
+}
+
+fn grid(size: f32, x_segments: u32, z_segments: u32) -> Polygons {
+    let half = size * 0.5;
+    let stride = x_segments + 1;
+    let mut positions = Vec::with_capacity((stride * (z_segments + 1)) as usize);
+    for z in 0..=z_segments {
+        for x in 0..=x_segments {
+            positions.push([
+                -half + size * x as f32 / x_segments as f32,
+                0.0,
+                -half + size * z as f32 / z_segments as f32,
+            ]);
+        }
+    }
+    let mut polygons = Vec::with_capacity((x_segments * z_segments) as usize);
+    for z in 0..z_segments {
+        for x in 0..x_segments {
+            let base = z * stride + x;
+            polygons.push(vec![base, base + stride, base + stride + 1, base + 1]);
+        }
+    }
+    (positions, polygons)
+}
+
+/// Point on a horizontal circle; `segment` runs counter-clockwise about +Y.
+fn ring_point(radius: f32, y: f32, segment: u32, segments: u32) -> [f32; 3] {
+    let angle = TAU * segment as f32 / segments as f32;
+    [radius * angle.cos(), y, -radius * angle.sin()]
+}
+
+fn uv_sphere(radius: f32, segments: u32, rings: u32) -> Polygons {
+    let mut positions = vec![[0.0, radius, 0.0]];
+    for ring in 1..rings {
+        let polar = PI * ring as f32 / rings as f32;
+        for segment in 0..segments {
+            positions.push(ring_point(
+                radius * polar.sin(),
+                radius * polar.cos(),
+                segment,
+                segments,
+            ));
+        }
+    }
+    positions.push([0.0, -radius, 0.0]);
+    let bottom = positions.len() as u32 - 1;
+    let at = |ring: u32, segment: u32| 1 + (ring - 1) * segments + segment % segments;
+
+    let mut polygons = Vec::new();
+    for segment in 0..segments {
+        polygons.push(vec![0, at(1, segment), at(1, segment + 1)]);
+    }
+    for ring in 1..rings - 1 {
+        for segment in 0..segments {
+            polygons.push(vec![
+                at(ring, segment),
+                at(ring + 1, segment),
+                at(ring + 1, segment + 1),
+                at(ring, segment + 1),
+            ]);
+        }
+    }
+    for segment in 0..segments {
+        polygons.push(vec![
+            bottom,
+            at(rings - 1, segment + 1),
+            at(rings - 1, segment),
+        ]);
+    }
+    (positions, polygons)
+}
+
+fn icosphere(radius: f32, subdivisions: u32) -> Polygons {
+    let t = (1.0 + 5.0f32.sqrt()) * 0.5;
+    let mut positions: Vec<[f32; 3]> = [
+        [-1.0, t, 0.0],
+        [1.0, t, 0.0],
+        [-1.0, -t, 0.0],
+        [1.0, -t, 0.0],
+        [0.0, -1.0, t],
+        [0.0, 1.0, t],
+        [0.0, -1.0, -t],
+        [0.0, 1.0, -t],
+        [t, 0.0, -1.0],
+        [t, 0.0, 1.0],
+        [-t, 0.0, -1.0],
+        [-t, 0.0, 1.0],
+    ]
+    .into_iter()
+    .map(|point| project(point, radius))
+    .collect();
+    let mut triangles: Vec<[u32; 3]> = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        let mut midpoints: HashMap<(u32, u32), u32> = HashMap::new();
+        let mut midpoint = |a: u32, b: u32, positions: &mut Vec<[f32; 3]>| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let (pa, pb) = (positions[a as usize], positions[b as usize]);
+                positions.push(project(
+                    [pa[0] + pb[0], pa[1] + pb[1], pa[2] + pb[2]],
+                    radius,
+                ));
+                positions.len() as u32 - 1
+            })
+        };
+        let mut refined = Vec::with_capacity(triangles.len() * 4);
+        for [a, b, c] in triangles {
+            let ab = midpoint(a, b, &mut positions);
+            let bc = midpoint(b, c, &mut positions);
+            let ca = midpoint(c, a, &mut positions);
+            refined.extend([[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]);
+        }
+        triangles = refined;
+    }
+
+    (positions, triangles.into_iter().map(Vec::from).collect())
+}
+
+fn project(point: [f32; 3], radius: f32) -> [f32; 3] {
+    let length = (point[0] * point[0] + point[1] * point[1] + point[2] * point[2]).sqrt();
+    let scale = radius / length;
+    [point[0] * scale, point[1] * scale, point[2] * scale]
+}
+
+fn cylinder(radius: f32, depth: f32, segments: u32) -> Polygons {
+    let half = depth * 0.5;
+    let mut positions = Vec::with_capacity(segments as usize * 2);
+    for y in [half, -half] {
+        for segment in 0..segments {
+            positions.push(ring_point(radius, y, segment, segments));
+        }
+    }
+    let top = |segment: u32| segment % segments;
+    let bottom = |segment: u32| segments + segment % segments;
+
+    let mut polygons: Vec<Vec<u32>> = (0..segments)
+        .map(|segment| {
+            vec![
+                top(segment),
+                bottom(segment),
+                bottom(segment + 1),
+                top(segment + 1),
+            ]
+        })
+        .collect();
+    polygons.push((0..segments).map(top).collect());
+    polygons.push((0..segments).rev().map(bottom).collect());
+    (positions, polygons)
+}
+
+fn cone(radius: f32, depth: f32, segments: u32) -> Polygons {
+    let half = depth * 0.5;
+    let mut positions: Vec<[f32; 3]> = (0..segments)
+        .map(|segment| ring_point(radius, -half, segment, segments))
+        .collect();
+    positions.push([0.0, half, 0.0]);
+    let apex = segments;
+
+    let mut polygons: Vec<Vec<u32>> = (0..segments)
+        .map(|segment| vec![apex, segment, (segment + 1) % segments])
+        .collect();
+    polygons.push((0..segments).rev().collect());
+    (positions, polygons)
+}
+
+fn torus(
+    major_radius: f32,
+    minor_radius: f32,
+    major_segments: u32,
+    minor_segments: u32,
+) -> Polygons {
+    let mut positions = Vec::with_capacity((major_segments * minor_segments) as usize);
+    for major in 0..major_segments {
+        let [x, _, z] = ring_point(1.0, 0.0, major, major_segments);
+        for minor in 0..minor_segments {
+            let angle = TAU * minor as f32 / minor_segments as f32;
+            let distance = major_radius + minor_radius * angle.cos();
+            positions.push([x * distance, minor_radius * angle.sin(), z * distance]);
+        }
+    }
+    let at =
+        |major: u32, minor: u32| (major % major_segments) * minor_segments + minor % minor_segments;
+
+    let mut polygons = Vec::with_capacity((major_segments * minor_segments) as usize);
+    for major in 0..major_segments {
+        for minor in 0..minor_segments {
+            polygons.push(vec![
+                at(major, minor),
+                at(major + 1, minor),
+                at(major + 1, minor + 1),
+                at(major, minor + 1),
+            ]);
+        }
+    }
+    (positions, polygons)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed_volume(mesh: &EditableMesh) -> f32 {
+        let position = |id| mesh.vertex(id).unwrap().position;
+        let mut volume = 0.0;
+        for face in mesh.face_ids() {
+            let ring = mesh.face_vertices(face).unwrap();
+            let origin = position(ring[0]);
+            for pair in ring[1..].windows(2) {
+                let (b, c) = (position(pair[0]), position(pair[1]));
+                let cross = [
+                    b[1] * c[2] - b[2] * c[1],
+                    b[2] * c[0] - b[0] * c[2],
+                    b[0] * c[1] - b[1] * c[0],
+                ];
+                volume +=
+                    (origin[0] * cross[0] + origin[1] * cross[1] + origin[2] * cross[2]) / 6.0;
+            }
+        }
+        volume
+    }
+
+    fn is_closed(mesh: &EditableMesh) -> bool {
+        mesh.edge_ids()
+            .all(|edge| !mesh.is_boundary_edge(edge).unwrap())
+    }
+
+    #[test]
+    fn solid_primitives_are_closed_with_outward_winding() {
+        let solids = [
+            (PrimitiveShape::Cube { size: 2.0 }, 8.0, 0.0),
+            (
+                PrimitiveShape::UvSphere {
+                    radius: 1.0,
+                    segments: 32,
+                    rings: 16,
+                },
+                4.0 / 3.0 * PI,
+                0.05,
+            ),
+            (
+                PrimitiveShape::Icosphere {
+                    radius: 1.0,
+                    subdivisions: 3,
+                },
+                4.0 / 3.0 * PI,
+                0.05,
+            ),
+            (
+                PrimitiveShape::Cylinder {
+                    radius: 1.0,
+                    depth: 2.0,
+                    segments: 64,
+                },
+                2.0 * PI,
+                0.02,
+            ),
+            (
+                PrimitiveShape::Cone {
+                    radius: 1.0,
+                    depth: 3.0,
+                    segments: 64,
+                },
+                PI,
+                0.02,
+            ),
+            (
+                PrimitiveShape::Torus {
+                    major_radius: 2.0,
+                    minor_radius: 0.5,
+                    major_segments: 48,
+                    minor_segments: 24,
+                },
+                2.0 * PI * PI * 2.0 * 0.25,
+                0.02,
+            ),
+        ];
+        for (shape, expected, tolerance) in solids {
+            let mesh = shape.build();
+            mesh.validate().expect("valid primitive");
+            assert!(is_closed(&mesh), "{} has boundary edges", shape.name());
+            let volume = signed_volume(&mesh);
+            assert!(
+                volume > 0.0 && (volume - expected).abs() <= expected * tolerance + 1e-4,
+                "{} volume {volume} expected {expected}",
+                shape.name()
+            );
+        }
+    }
+
+    #[test]
+    fn planes_and_grids_face_up() {
+        let grid = PrimitiveShape::Grid {
+            size: 4.0,
+            x_segments: 4,
+            z_segments: 2,
+        }
+        .build();
+        grid.validate().expect("valid grid");
+        assert_eq!(grid.face_count(), 8);
+        assert_eq!(grid.vertex_count(), 15);
+
+        let plane = PrimitiveShape::Plane { size: 1.0 }.build();
+        plane.validate().expect("valid plane");
+        assert_eq!((plane.face_count(), plane.vertex_count()), (1, 4));
+    }
+
+    #[test]
+    fn segment_counts_are_clamped() {
+        let tiny = PrimitiveShape::Cylinder {
+            radius: 1.0,
+            depth: 1.0,
+            segments: 0,
+        }
+        .build();
+        assert_eq!(tiny.vertex_count(), 6);
+
+        let huge = PrimitiveShape::Icosphere {
+            radius: 1.0,
+            subdivisions: 40,
+        }
+        .build();
+        assert_eq!(
+            huge.face_count(),
+            20 * 4usize.pow(MAX_ICOSPHERE_SUBDIVISIONS)
+        );
+    }
+}
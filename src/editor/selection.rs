@@ -0,0 +1,357 @@
+use crate::editor::commands::Quaternion;
+use crate::network::EntityHandle;
+use crate::vr::TrackedPose;
+use serde::{Deserialize, Serialize};
+
+/// Ordered set of selected entities. The most recently added member is the active one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SelectionSet {
+    members: Vec<EntityHandle>,
+    active: Option<EntityHandle>,
+}
+
+impl SelectionSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, entity: EntityHandle) {
+        if !self.contains(entity) {
+            self.members.push(entity);
+        }
+        self.active = Some(entity);
+    }
+
+    pub fn remove(&mut self, entity: EntityHandle) -> bool {
+        let Some(index) = self.members.iter().position(|member| *member == entity) else {
+            return false;
+        };
+        self.members.remove(index);
+        if self.active == Some(entity) {
+            self.active = self.members.last().copied();
+        }
+        true
+    }
+
+    /// Removes `entity` if selected, otherwise adds it. Returns whether it is now selected.
+    pub fn toggle(&mut self, entity: EntityHandle) -> bool {
+        if self.remove(entity) {
+            false
+        } else {
+            self.add(entity);
+            true
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.members.clear();
+        self.active = None;
+    }
+
+    pub fn contains(&self, entity: EntityHandle) -> bool {
+        self.members.contains(&entity)
+    }
+
+    pub fn active(&self) -> Option<EntityHandle> {
+        self.active
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = EntityHandle> + '_ {
+        self.members.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+}
+
+/// How a box or ray pick combines with the current selection.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionMode {
+    #[default]
+    Replace,
+    Add,
+    Remove,
+    Toggle,
+}
+
+/// Point that group rotations and scales pivot around.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PivotMode {
+    /// Mean position of the selected entities.
+    #[default]
+    Median,
+    /// Position of the active entity, falling back to the median.
+    Active,
+    WorldOrigin,
+}
+
+impl PivotMode {
+    pub fn resolve(
+        self,
+        positions: &[(EntityHandle, [f32; 3])],
+        active: Option<EntityHandle>,
+    ) -> [f32; 3] {
+        let median = || {
+            if positions.is_empty() {
+                return [0.0; 3];
+            }
+            let mut sum = [0.0f32; 3];
+            for (_, position) in positions {
+                for axis in 0..3 {
+                    sum[axis] += position[axis];
+                }
+            }
+            sum.map(|value| value / positions.len() as f32)
+        };
+        match self {
+            PivotMode::Median => median(),
+            PivotMode::Active => active
+                .and_then(|active| positions.iter().find(|(entity, _)| *entity == active))
+                .map_or_else(median, |(_, position)| *position),
+            PivotMode::WorldOrigin => [0.0; 3],
+        }
+    }
+}
+
+/// Axis-aligned selection volume, typically spanned by the two controllers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelectionBox {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl SelectionBox {
+    pub fn from_corners(a: [f32; 3], b: [f32; 3]) -> Self {
+        Self {
+            min: [a[0].min(b[0]), a[1].min(b[1]), a[2].min(b[2])],
+            max: [a[0].max(b[0]), a[1].max(b[1]), a[2].max(b[2])],
+        }
+    }
+
+    pub fn between_poses(left: &TrackedPose, right: &TrackedPose) -> Self {
+        Self::from_corners(left.position, right.position)
+    }
+
+    pub fn contains(&self, point: [f32; 3]) -> bool {
+        (0..3).all(|axis| (self.min[axis]..=self.max[axis]).contains(&point[axis]))
+    }
+
+    pub fn pick(
+        &self,
+        candidates: impl IntoIterator<Item = (EntityHandle, [f32; 3])>,
+    ) -> Vec<EntityHandle> {
+        candidates
+            .into_iter()
+            .filter(|(_, position)| self.contains(*position))
+            .map(|(entity, _)| entity)
+            .collect()
+    }
+}
+
+/// Pointing ray cast from a controller along its local -Z axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelectionRay {
+    pub origin: [f32; 3],
+    pub direction: [f32; 3],
+}
+
+impl SelectionRay {
+    /// Entities within this distance of the ray count as hit.
+    pub const DEFAULT_RADIUS: f32 = 0.1;
+
+    pub fn from_pose(pose: &TrackedPose) -> Self {
+        let [x, y, z, w] = pose.orientation;
+        let direction = Quaternion::new(x, y, z, w).rotate([0.0, 0.0, -1.0]);
+        Self {
+            origin: pose.position,
+            direction: normalize(direction),
+        }
+    }
+
+    /// Returns the candidate nearest the origin whose position lies within `radius` of the ray.
+    pub fn pick(
+        &self,
+        candidates: impl IntoIterator<Item = (EntityHandle, [f32; 3])>,
+        radius: f32,
+    ) -> Option<EntityHandle> {
+        candidates
+            .into_iter()
+            .filter_map(|(entity, position)| {
+                let offset = [
+                    position[0] - self.origin[0],
+                    position[1] - self.origin[1],
+                    position[2] - self.origin[2],
+                ];
+                let along: f32 = (0..3).map(|axis| offset[axis] * self.direction[axis]).sum();
+                if along < 0.0 {
+                    return None;
+                }
+                let squared: f32 = (0..3)
+                    .map(|axis| (offset[axis] - self.direction[axis] * along).powi(2))
+                    .sum();
+                (squared <= radius * radius).then_some((entity, along))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(entity, _)| entity)
+    }
+}
+
+/// Placement of one selected entity, as read from or written to its transform.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MemberPose {
+    pub position: [f32; 3],
+    pub rotation: Quaternion,
+    pub scale: [f32; 3],
+}
+
+/// Transform applied to a whole selection around a shared pivot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupTransform {
+    Translate([f32; 3]),
+    Rotate(Quaternion),
+    /// Per-axis factor; offsets from the pivot scale in world space.
+    Scale([f32; 3]),
+}
+
+impl GroupTransform {
+    pub fn apply(&self, pivot: [f32; 3], pose: MemberPose) -> MemberPose {
+        let offset = [
+            pose.position[0] - pivot[0],
+            pose.position[1] - pivot[1],
+            pose.position[2] - pivot[2],
+        ];
+        match *self {
+            GroupTransform::Translate(delta) => MemberPose {
+                position: [
+                    pose.position[0] + delta[0],
+                    pose.position[1] + delta[1],
+                    pose.position[2] + delta[2],
+                ],
+                ..pose
+            },
+            GroupTransform::Rotate(rotation) => {
+                let rotated = rotation.rotate(offset);
+                MemberPose {
+                    position: [
+                        pivot[0] + rotated[0],
+                        pivot[1] + rotated[1],
+                        pivot[2] + rotated[2],
+                    ],
+                    rotation: rotation.compose(pose.rotation),
+                    ..pose
+                }
+            }
+            GroupTransform::Scale(factor) => MemberPose {
+                position: [
+                    pivot[0] + offset[0] * factor[0],
+                    pivot[1] + offset[1] * factor[1],
+                    pivot[2] + offset[2] * factor[2],
+                ],
+                scale: [
+                    pose.scale[0] * factor[0],
+                    pose.scale[1] * factor[1],
+                    pose.scale[2] * factor[2],
+                ],
+                ..pose
+            },
+        }
+    }
+}
+
+fn normalize(vector: [f32; 3]) -> [f32; 3] {
+    let length = vector.iter().map(|value| value * value).sum::<f32>().sqrt();
+    if length <= f32::EPSILON {
+        [0.0, 0.0, -1.0]
+    } else {
+        vector.map(|value| value / length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handle(index: u32) -> EntityHandle {
+        EntityHandle {
+            index,
+            generation: 0,
+        }
+    }
+
+    #[test]
+    fn set_tracks_membership_and_active_entity() {
+        let mut set = SelectionSet::new();
+        set.add(handle(1));
+        set.add(handle(2));
+        set.add(handle(1));
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.active(), Some(handle(1)));
+
+        assert!(!set.toggle(handle(1)));
+        assert_eq!(set.active(), Some(handle(2)));
+        assert!(set.toggle(handle(3)));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![handle(2), handle(3)]);
+
+        set.clear();
+        assert!(set.is_empty());
+        assert_eq!(set.active(), None);
+    }
+
+    #[test]
+    fn box_and_ray_pick_entities() {
+        let candidates = [
+            (handle(1), [0.0, 1.0, -2.0]),
+            (handle(2), [0.0, 1.0, -4.0]),
+            (handle(3), [3.0, 1.0, -2.0]),
+        ];
+        let volume = SelectionBox::from_corners([1.0, 0.0, -5.0], [-1.0, 2.0, 0.0]);
+        assert_eq!(volume.pick(candidates), vec![handle(1), handle(2)]);
+
+        let pose = TrackedPose {
+            position: [0.0, 1.0, 0.0],
+            orientation: [0.0, 0.0, 0.0, 1.0],
+        };
+        let ray = SelectionRay::from_pose(&pose);
+        assert_eq!(ray.pick(candidates, 0.1), Some(handle(1)));
+
+        // Yaw 90 degrees left so the ray points down -X.
+        let half = std::f32::consts::FRAC_PI_4;
+        let pose = TrackedPose {
+            position: [5.0, 1.0, -2.0],
+            orientation: [0.0, half.sin(), 0.0, half.cos()],
+        };
+        let ray = SelectionRay::from_pose(&pose);
+        assert_eq!(ray.pick(candidates, 0.1), Some(handle(3)));
+    }
+
+    #[test]
+    fn group_transforms_pivot_around_the_resolved_point() {
+        let positions = [(handle(1), [1.0, 0.0, 0.0]), (handle(2), [3.0, 0.0, 0.0])];
+        let median = PivotMode::Median.resolve(&positions, Some(handle(1)));
+        assert_eq!(median, [2.0, 0.0, 0.0]);
+        let active = PivotMode::Active.resolve(&positions, Some(handle(1)));
+        assert_eq!(active, [1.0, 0.0, 0.0]);
+
+        let pose = MemberPose {
+            position: [3.0, 0.0, 0.0],
+            rotation: Quaternion::default(),
+            scale: [1.0; 3],
+        };
+        let half = std::f32::consts::FRAC_PI_4;
+        let turn = GroupTransform::Rotate(Quaternion::new(0.0, half.sin(), 0.0, half.cos()));
+        let turned = turn.apply(median, pose);
+        assert!((turned.position[0] - 2.0).abs() < 1e-5);
+        assert!((turned.position[2] + 1.0).abs() < 1e-5);
+
+        let grown = GroupTransform::Scale([2.0; 3]).apply(active, pose);
+        assert_eq!(grown.position, [5.0, 0.0, 0.0]);
+        assert_eq!(grown.scale, [2.0; 3]);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::ecs::Entity;
 use crate::engine::CommandMetricsSnapshot;
 use crate::engine::schedule::Stage;
+use crate::network::interpolation::InterpolationHealth;
 use crate::network::voice::VoiceDiagnostics;
 use crate::network::{
     ChangeSet, ComponentDescriptor, ComponentDiff, ComponentKey, DiffPayload, NetworkSession,
@@ -76,6 +77,8 @@ pub struct FrameTelemetry {
     pub transport: Option<crate::network::TransportDiagnostics>,
     pub command_metrics: Option<CommandMetricsSnapshot>,
     pub webrtc: Option<WebRtcTelemetry>,
+    #[serde(default)]
+    pub interpolation: Option<InterpolationHealth>,
 }
 
 impl FrameTelemetry {
@@ -113,6 +116,7 @@ impl FrameTelemetry {
             transport: None,
             command_metrics: None,
             webrtc: None,
+            interpolation: None,
         }
     }
 
@@ -131,6 +135,10 @@ impl FrameTelemetry {
     pub fn set_webrtc_metrics(&mut self, metrics: Option<WebRtcTelemetry>) {
         self.webrtc = metrics;
     }
+
+    pub fn set_interpolation_health(&mut self, health: Option<InterpolationHealth>) {
+        self.interpolation = health;
+    }
 }
 
 #[derive(Default)]
@@ -194,6 +202,7 @@ impl TelemetryReplicator {
                         DiffPayload::Insert { bytes } | DiffPayload::Update { bytes } => {
                             bytes.len()
                         }
+                        DiffPayload::Delta { patch, .. } => patch.len(),
                         DiffPayload::Remove => 0,
                     })
                     .unwrap_or_default();
@@ -323,6 +332,22 @@ impl TelemetryOverlay {
             }
         }
 
+        if let Some(health) = &latest.interpolation {
+            lines.push(format!(
+                "  Interp   delay {}ms entities {} samples {} lead {:>6.1}ms interp {} rest {} extrap {} starved {} late {} underruns {}",
+                health.delay_ms,
+                health.entities,
+                health.buffered_samples,
+                health.min_lead_ms,
+                health.interpolating,
+                health.resting,
+                health.extrapolating,
+                health.starved,
+                health.late_samples,
+                health.underruns
+            ));
+        }
+
         if let Some(webrtc) = &latest.webrtc {
             let active = webrtc.active_transport.as_deref().unwrap_or("none");
             lines.push(format!(
@@ -676,6 +701,29 @@ mod tests {
         assert!(panel.contains("speakers local, remote"));
     }
 
+    #[test]
+    fn telemetry_overlay_reports_interpolation_health() {
+        let mut overlay = TelemetryOverlay::default();
+        let mut sample = static_sample(7);
+        sample.set_interpolation_health(Some(InterpolationHealth {
+            delay_ms: 100,
+            entities: 3,
+            buffered_samples: 11,
+            min_lead_ms: -12.5,
+            interpolating: 2,
+            resting: 0,
+            extrapolating: 1,
+            starved: 0,
+            late_samples: 4,
+            underruns: 9,
+        }));
+
+        overlay.ingest(sample);
+        let panel = overlay.text_panel().expect("panel text");
+        assert!(panel.contains("Interp   delay 100ms entities 3 samples 11 lead  -12.5ms"));
+        assert!(panel.contains("extrap 1 starved 0 late 4 underruns 9"));
+    }
+
     proptest::prop_compose! {
         fn stage_arrays()(values in proptest::array::uniform4(-2000i16..2000i16)) -> [f32; Stage::count()] {
             values.map(|v| v as f32 / 10.0)
@@ -0,0 +1,853 @@
+use super::typed_commands::{
+    Checkpoint, CoalescingCommand, CommandApplyError, CommandContext, CommandHandlerRegistry,
+    TypedCommand,
+};
+use super::{EditorSelection, EditorToolState, Transform, sanitize_scale};
+use crate::ecs::World;
+use crate::editor::commands::{
+    BridgeEdgeLoopsCommand, CMD_ENTITY_ROTATE, CMD_ENTITY_SCALE, CMD_ENTITY_TRANSLATE,
+    CMD_MESH_BRIDGE_LOOPS, CMD_MESH_CREATE_PRIMITIVE, CMD_MESH_DELETE, CMD_MESH_EDGE_BEVEL,
+    CMD_MESH_EDGE_EXTRUDE, CMD_MESH_FACE_EXTRUDE, CMD_MESH_FACE_INSET, CMD_MESH_FACE_SUBDIVIDE,
+    CMD_MESH_IMPORT, CMD_MESH_LOOP_CUT, CMD_MESH_MERGE_BY_DISTANCE, CMD_MESH_VERTEX_CREATE,
+    CMD_SELECTION_ADD, CMD_SELECTION_CLEAR, CMD_SELECTION_HIGHLIGHT, CMD_SELECTION_REMOVE,
+    CMD_SELECTION_TOGGLE, CMD_TOOL_ACTIVATE, CMD_TOOL_DEACTIVATE, CreatePrimitiveCommand,
+    EdgeBevelCommand, EdgeExtrudeCommand, EntityRotateCommand, EntityScaleCommand,
+    EntityTranslateCommand, FaceExtrudeCommand, FaceInsetCommand, FaceSubdivideCommand,
+    ImportMeshCommand, LoopCutCommand, MergeByDistanceCommand, MeshDeleteCommand, MeshElement,
+    SelectionAddCommand, SelectionClearCommand, SelectionHighlightCommand, SelectionRemoveCommand,
+    SelectionToggleCommand, ToolActivateCommand, ToolDeactivateCommand, VertexCreateCommand,
+};
+use crate::editor::mesh::{EdgeId, EditableMesh, FaceId, MeshError, VertexId};
+use crate::network::EntityHandle;
+use crate::network::command_log::{
+    CommandDefinition, CommandRegistry, CommandRole, CommandScope, ConflictStrategy,
+};
+use crate::network::network_id::{NetworkId, lock_entity_map};
+
+/// Registers the editor's own commands with both the log registry and the handlers.
+pub(super) fn register_builtin_commands(
+    handlers: &mut CommandHandlerRegistry,
+    registry: &mut CommandRegistry,
+) {
+    handlers.register_with::<SelectionHighlightCommand>(registry);
+    handlers.register_with::<SelectionAddCommand>(registry);
+    handlers.register_with::<SelectionRemoveCommand>(registry);
+    handlers.register_with::<SelectionToggleCommand>(registry);
+    handlers.register_with::<SelectionClearCommand>(registry);
+    handlers.register_with::<EntityTranslateCommand>(registry);
+    handlers.register_with::<EntityRotateCommand>(registry);
+    handlers.register_with::<EntityScaleCommand>(registry);
+    handlers.register_with::<ToolActivateCommand>(registry);
+    handlers.register_with::<ToolDeactivateCommand>(registry);
+    handlers.register_with::<VertexCreateCommand>(registry);
+    handlers.register_with::<EdgeExtrudeCommand>(registry);
+    handlers.register_with::<FaceSubdivideCommand>(registry);
+    handlers.register_with::<CreatePrimitiveCommand>(registry);
+    handlers.register_with::<FaceExtrudeCommand>(registry);
+    handlers.register_with::<FaceInsetCommand>(registry);
+    handlers.register_with::<EdgeBevelCommand>(registry);
+    handlers.register_with::<LoopCutCommand>(registry);
+    handlers.register_with::<BridgeEdgeLoopsCommand>(registry);
+    handlers.register_with::<MergeByDistanceCommand>(registry);
+    handlers.register_with::<MeshDeleteCommand>(registry);
+    handlers.register_with::<ImportMeshCommand>(registry);
+}
+
+fn transform_definition(strategy: ConflictStrategy) -> CommandDefinition {
+    CommandDefinition::builder()
+        .required_role(CommandRole::Editor)
+        .default_strategy(strategy)
+        .require_signature(false)
+        .lock_guarded(true)
+        .build()
+}
+
+/// Mesh edits only replicate with a signature the session's verifier accepts.
+fn mesh_definition(strategy: ConflictStrategy) -> CommandDefinition {
+    CommandDefinition::builder()
+        .required_role(CommandRole::Editor)
+        .default_strategy(strategy)
+        .require_signature(true)
+        .build()
+}
+
+fn transform_mut<'w>(
+    world: &'w mut World,
+    context: &CommandContext<'_>,
+    entity: EntityHandle,
+) -> Result<&'w mut Transform, CommandApplyError> {
+    world
+        .get_mut::<Transform>(context.resolve(entity))
+        .ok_or(CommandApplyError::MissingEntity(entity))
+}
+
+fn mesh_scope(mesh: Option<EntityHandle>) -> CommandScope {
+    mesh.map_or(CommandScope::Global, CommandScope::Entity)
+}
+
+/// Resolves the mesh a command edits, falling back to the editor's scratch mesh.
+fn target_mesh<'w>(
+    world: &'w mut World,
+    context: &CommandContext<'_>,
+    mesh: Option<EntityHandle>,
+) -> Result<&'w mut EditableMesh, CommandApplyError> {
+    let entity = match mesh {
+        Some(handle) => context.resolve(handle),
+        None => context.require_editor_entity()?,
+    };
+    world
+        .get_mut::<EditableMesh>(entity)
+        .ok_or(CommandApplyError::MissingComponent("EditableMesh"))
+}
+
+/// Keeps a copy of the mesh a command edits, so concurrent edits of one mesh are
+/// replayed in command id order wherever they arrive first.
+fn mesh_checkpoint(
+    world: &World,
+    context: &CommandContext<'_>,
+    mesh: Option<EntityHandle>,
+) -> Option<Checkpoint> {
+    let entity = match mesh {
+        Some(handle) => context.resolve(handle),
+        None => context.editor_entity?,
+    };
+    let before = world.get::<EditableMesh>(entity)?.clone();
+    Some(Box::new(move |world: &mut World| {
+        let _ = world.insert(entity, before);
+    }))
+}
+
+/// Applies `edit` to the mesh a command targets. New element ids are seeded from the
+/// command id, so peers applying concurrent edits in any order agree on them.
+fn edit_mesh<T>(
+    world: &mut World,
+    context: &CommandContext<'_>,
+    mesh: Option<EntityHandle>,
+    edit: impl FnOnce(&mut EditableMesh) -> Result<T, MeshError>,
+) -> Result<T, CommandApplyError> {
+    let target = target_mesh(world, context, mesh)?;
+    Ok(target.with_id_seed(context.id_seed(), edit)?)
+}
+
+impl TypedCommand for SelectionHighlightCommand {
+    const TYPE_ID: &'static str = CMD_SELECTION_HIGHLIGHT;
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::Entity(self.entity)
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        if !matches!(context.entry.payload.scope, CommandScope::Entity(_)) {
+            return Err(CommandApplyError::InvalidScope(
+                context.entry.payload.scope.clone(),
+            ));
+        }
+
+        let editor_entity = context.require_editor_entity()?;
+        let target_entity = context.resolve(self.entity);
+        let exists = world.contains(target_entity);
+        let selection = world
+            .get_mut::<EditorSelection>(editor_entity)
+            .ok_or(CommandApplyError::MissingComponent("EditorSelection"))?;
+        if exists {
+            selection.set.add(self.entity);
+        } else {
+            log::warn!(
+                "[commands] remote highlight target {entity:?} missing locally",
+                entity = self.entity
+            );
+        }
+        selection.highlight_active = self.active;
+        selection.frames_since_change = 0;
+        Ok(())
+    }
+}
+
+fn selection_mut<'w>(
+    world: &'w mut World,
+    context: &CommandContext<'_>,
+) -> Result<&'w mut EditorSelection, CommandApplyError> {
+    let editor_entity = context.require_editor_entity()?;
+    let selection = world
+        .get_mut::<EditorSelection>(editor_entity)
+        .ok_or(CommandApplyError::MissingComponent("EditorSelection"))?;
+    selection.frames_since_change = 0;
+    Ok(selection)
+}
+
+/// Drops entities that do not exist locally, so a late spawn cannot leave a dangling member.
+fn live_entities(
+    world: &World,
+    context: &CommandContext<'_>,
+    entities: &[EntityHandle],
+) -> Vec<EntityHandle> {
+    entities
+        .iter()
+        .copied()
+        .filter(|entity| {
+            let exists = world.contains(context.resolve(*entity));
+            if !exists {
+                log::warn!("[commands] selection target {entity:?} missing locally");
+            }
+            exists
+        })
+        .collect()
+}
+
+impl TypedCommand for SelectionAddCommand {
+    const TYPE_ID: &'static str = CMD_SELECTION_ADD;
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::Global
+    }
+
+    fn strategy() -> ConflictStrategy {
+        ConflictStrategy::Merge
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        let entities = live_entities(world, context, &self.entities);
+        let selection = selection_mut(world, context)?;
+        for entity in entities {
+            selection.set.add(entity);
+        }
+        Ok(())
+    }
+}
+
+impl TypedCommand for SelectionRemoveCommand {
+    const TYPE_ID: &'static str = CMD_SELECTION_REMOVE;
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::Global
+    }
+
+    fn strategy() -> ConflictStrategy {
+        ConflictStrategy::Merge
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        let selection = selection_mut(world, context)?;
+        for entity in &self.entities {
+            selection.set.remove(*entity);
+        }
+        Ok(())
+    }
+}
+
+impl TypedCommand for SelectionToggleCommand {
+    const TYPE_ID: &'static str = CMD_SELECTION_TOGGLE;
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::Global
+    }
+
+    fn strategy() -> ConflictStrategy {
+        ConflictStrategy::Merge
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        let entities = live_entities(world, context, &self.entities);
+        let selection = selection_mut(world, context)?;
+        for entity in entities {
+            selection.set.toggle(entity);
+        }
+        Ok(())
+    }
+}
+
+impl TypedCommand for SelectionClearCommand {
+    const TYPE_ID: &'static str = CMD_SELECTION_CLEAR;
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::Global
+    }
+
+    fn strategy() -> ConflictStrategy {
+        ConflictStrategy::Merge
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        selection_mut(world, context)?.set.clear();
+        Ok(())
+    }
+}
+
+impl TypedCommand for EntityTranslateCommand {
+    const TYPE_ID: &'static str = CMD_ENTITY_TRANSLATE;
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::Entity(self.entity)
+    }
+
+    fn strategy() -> ConflictStrategy {
+        ConflictStrategy::Merge
+    }
+
+    fn definition() -> CommandDefinition {
+        transform_definition(Self::strategy())
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        let transform = transform_mut(world, context, self.entity)?;
+        for (axis, delta) in transform.position.iter_mut().zip(self.delta.iter()) {
+            *axis += *delta;
+        }
+        Ok(())
+    }
+}
+
+impl CoalescingCommand for EntityTranslateCommand {
+    fn coalesce(&mut self, next: &Self) {
+        for (axis, delta) in self.delta.iter_mut().zip(next.delta) {
+            *axis += delta;
+        }
+    }
+}
+
+impl TypedCommand for EntityRotateCommand {
+    const TYPE_ID: &'static str = CMD_ENTITY_ROTATE;
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::Entity(self.entity)
+    }
+
+    fn definition() -> CommandDefinition {
+        transform_definition(Self::strategy())
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        let transform = transform_mut(world, context, self.entity)?;
+        transform.rotation = [
+            self.rotation.x,
+            self.rotation.y,
+            self.rotation.z,
+            self.rotation.w,
+        ];
+        Ok(())
+    }
+}
+
+impl CoalescingCommand for EntityRotateCommand {
+    fn coalesce(&mut self, next: &Self) {
+        self.rotation = next.rotation;
+    }
+}
+
+impl TypedCommand for EntityScaleCommand {
+    const TYPE_ID: &'static str = CMD_ENTITY_SCALE;
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::Entity(self.entity)
+    }
+
+    fn definition() -> CommandDefinition {
+        transform_definition(Self::strategy())
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        transform_mut(world, context, self.entity)?.scale = sanitize_scale(self.scale);
+        Ok(())
+    }
+}
+
+impl CoalescingCommand for EntityScaleCommand {
+    fn coalesce(&mut self, next: &Self) {
+        self.scale = next.scale;
+    }
+}
+
+impl TypedCommand for ToolActivateCommand {
+    const TYPE_ID: &'static str = CMD_TOOL_ACTIVATE;
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::Tool(self.tool_id.clone())
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        let editor_entity = context.require_editor_entity()?;
+        world
+            .get_mut::<EditorToolState>(editor_entity)
+            .ok_or(CommandApplyError::MissingComponent("EditorToolState"))?
+            .activate(self.tool_id.clone(), context.lamport());
+        Ok(())
+    }
+}
+
+impl TypedCommand for ToolDeactivateCommand {
+    const TYPE_ID: &'static str = CMD_TOOL_DEACTIVATE;
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::Tool(self.tool_id.clone())
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        let editor_entity = context.require_editor_entity()?;
+        let tool_state = world
+            .get_mut::<EditorToolState>(editor_entity)
+            .ok_or(CommandApplyError::MissingComponent("EditorToolState"))?;
+        if tool_state.matches_active(&self.tool_id) {
+            tool_state.deactivate(context.lamport());
+        }
+        Ok(())
+    }
+}
+
+impl TypedCommand for VertexCreateCommand {
+    const TYPE_ID: &'static str = CMD_MESH_VERTEX_CREATE;
+
+    fn scope(&self) -> CommandScope {
+        mesh_scope(self.mesh)
+    }
+
+    fn strategy() -> ConflictStrategy {
+        ConflictStrategy::Merge
+    }
+
+    fn definition() -> CommandDefinition {
+        mesh_definition(Self::strategy())
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        edit_mesh(world, context, self.mesh, |mesh| {
+            Ok(mesh.add_vertex(self.position, self.metadata.clone()))
+        })?;
+        Ok(())
+    }
+
+    fn checkpoint(&self, world: &World, context: &CommandContext<'_>) -> Option<Checkpoint> {
+        mesh_checkpoint(world, context, self.mesh)
+    }
+}
+
+impl TypedCommand for EdgeExtrudeCommand {
+    const TYPE_ID: &'static str = CMD_MESH_EDGE_EXTRUDE;
+
+    fn scope(&self) -> CommandScope {
+        mesh_scope(self.mesh)
+    }
+
+    fn strategy() -> ConflictStrategy {
+        ConflictStrategy::Merge
+    }
+
+    fn definition() -> CommandDefinition {
+        mesh_definition(Self::strategy())
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        edit_mesh(world, context, self.mesh, |mesh| {
+            mesh.extrude_edge(EdgeId(self.edge_id), self.direction)
+        })?;
+        Ok(())
+    }
+
+    fn checkpoint(&self, world: &World, context: &CommandContext<'_>) -> Option<Checkpoint> {
+        mesh_checkpoint(world, context, self.mesh)
+    }
+}
+
+impl TypedCommand for FaceSubdivideCommand {
+    const TYPE_ID: &'static str = CMD_MESH_FACE_SUBDIVIDE;
+
+    fn scope(&self) -> CommandScope {
+        mesh_scope(self.mesh)
+    }
+
+    fn strategy() -> ConflictStrategy {
+        ConflictStrategy::Merge
+    }
+
+    fn definition() -> CommandDefinition {
+        mesh_definition(Self::strategy())
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        edit_mesh(world, context, self.mesh, |mesh| {
+            mesh.subdivide_face(
+                FaceId(self.face_id),
+                self.params.levels,
+                self.params.smoothness,
+            )
+        })?;
+        Ok(())
+    }
+
+    fn checkpoint(&self, world: &World, context: &CommandContext<'_>) -> Option<Checkpoint> {
+        mesh_checkpoint(world, context, self.mesh)
+    }
+}
+
+/// Attaches `mesh` and `transform` to the entity `handle` names, spawning and binding a
+/// local entity when the id is new to this peer. Creates only name allocated ids and
+/// never replace an existing mesh, so a peer cannot overwrite an unrelated entity.
+fn spawn_mesh_entity(
+    world: &mut World,
+    context: &CommandContext<'_>,
+    handle: EntityHandle,
+    mesh: EditableMesh,
+    transform: Transform,
+) -> Result<(), CommandApplyError> {
+    let id = NetworkId::from(handle);
+    if !id.is_allocated() {
+        return Err(CommandApplyError::UnallocatedEntity(handle));
+    }
+    let mut entities = lock_entity_map(context.entities);
+    let entity = match entities.entity(id).filter(|entity| world.contains(*entity)) {
+        Some(entity) if world.get::<EditableMesh>(entity).is_some() => {
+            return Err(CommandApplyError::EntityExists(handle));
+        }
+        Some(entity) => entity,
+        None => {
+            let entity = world.spawn();
+            world.insert(entity, id)?;
+            entities.insert(id, entity);
+            entity
+        }
+    };
+    drop(entities);
+    world.insert(entity, mesh)?;
+    world.insert(entity, transform)?;
+    Ok(())
+}
+
+impl TypedCommand for CreatePrimitiveCommand {
+    const TYPE_ID: &'static str = CMD_MESH_CREATE_PRIMITIVE;
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::Entity(self.entity)
+    }
+
+    fn strategy() -> ConflictStrategy {
+        ConflictStrategy::Merge
+    }
+
+    fn definition() -> CommandDefinition {
+        mesh_definition(Self::strategy())
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        let transform = Transform {
+            position: self.position,
+            rotation: [
+                self.rotation.x,
+                self.rotation.y,
+                self.rotation.z,
+                self.rotation.w,
+            ],
+            ..Transform::default()
+        };
+        spawn_mesh_entity(world, context, self.entity, self.shape.build(), transform)
+    }
+}
+
+impl TypedCommand for FaceExtrudeCommand {
+    const TYPE_ID: &'static str = CMD_MESH_FACE_EXTRUDE;
+
+    fn scope(&self) -> CommandScope {
+        mesh_scope(self.mesh)
+    }
+
+    fn strategy() -> ConflictStrategy {
+        ConflictStrategy::Merge
+    }
+
+    fn definition() -> CommandDefinition {
+        mesh_definition(Self::strategy())
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        edit_mesh(world, context, self.mesh, |mesh| {
+            mesh.extrude_face(FaceId(self.face_id), self.distance)
+        })?;
+        Ok(())
+    }
+
+    fn checkpoint(&self, world: &World, context: &CommandContext<'_>) -> Option<Checkpoint> {
+        mesh_checkpoint(world, context, self.mesh)
+    }
+}
+
+impl TypedCommand for FaceInsetCommand {
+    const TYPE_ID: &'static str = CMD_MESH_FACE_INSET;
+
+    fn scope(&self) -> CommandScope {
+        mesh_scope(self.mesh)
+    }
+
+    fn strategy() -> ConflictStrategy {
+        ConflictStrategy::Merge
+    }
+
+    fn definition() -> CommandDefinition {
+        mesh_definition(Self::strategy())
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        edit_mesh(world, context, self.mesh, |mesh| {
+            mesh.inset_face(FaceId(self.face_id), self.amount)
+        })?;
+        Ok(())
+    }
+
+    fn checkpoint(&self, world: &World, context: &CommandContext<'_>) -> Option<Checkpoint> {
+        mesh_checkpoint(world, context, self.mesh)
+    }
+}
+
+impl TypedCommand for EdgeBevelCommand {
+    const TYPE_ID: &'static str = CMD_MESH_EDGE_BEVEL;
+
+    fn scope(&self) -> CommandScope {
+        mesh_scope(self.mesh)
+    }
+
+    fn strategy() -> ConflictStrategy {
+        ConflictStrategy::Merge
+    }
+
+    fn definition() -> CommandDefinition {
+        mesh_definition(Self::strategy())
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        edit_mesh(world, context, self.mesh, |mesh| {
+            mesh.bevel_edge(EdgeId(self.edge_id), self.width)
+        })?;
+        Ok(())
+    }
+
+    fn checkpoint(&self, world: &World, context: &CommandContext<'_>) -> Option<Checkpoint> {
+        mesh_checkpoint(world, context, self.mesh)
+    }
+}
+
+impl TypedCommand for LoopCutCommand {
+    const TYPE_ID: &'static str = CMD_MESH_LOOP_CUT;
+
+    fn scope(&self) -> CommandScope {
+        mesh_scope(self.mesh)
+    }
+
+    fn strategy() -> ConflictStrategy {
+        ConflictStrategy::Merge
+    }
+
+    fn definition() -> CommandDefinition {
+        mesh_definition(Self::strategy())
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        edit_mesh(world, context, self.mesh, |mesh| {
+            mesh.loop_cut(EdgeId(self.edge_id), self.factor)
+        })?;
+        Ok(())
+    }
+
+    fn checkpoint(&self, world: &World, context: &CommandContext<'_>) -> Option<Checkpoint> {
+        mesh_checkpoint(world, context, self.mesh)
+    }
+}
+
+impl TypedCommand for BridgeEdgeLoopsCommand {
+    const TYPE_ID: &'static str = CMD_MESH_BRIDGE_LOOPS;
+
+    fn scope(&self) -> CommandScope {
+        mesh_scope(self.mesh)
+    }
+
+    fn strategy() -> ConflictStrategy {
+        ConflictStrategy::Merge
+    }
+
+    fn definition() -> CommandDefinition {
+        mesh_definition(Self::strategy())
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        let first: Vec<EdgeId> = self.first.iter().copied().map(EdgeId).collect();
+        let second: Vec<EdgeId> = self.second.iter().copied().map(EdgeId).collect();
+        edit_mesh(world, context, self.mesh, |mesh| {
+            mesh.bridge_edge_loops(&first, &second)
+        })?;
+        Ok(())
+    }
+
+    fn checkpoint(&self, world: &World, context: &CommandContext<'_>) -> Option<Checkpoint> {
+        mesh_checkpoint(world, context, self.mesh)
+    }
+}
+
+impl TypedCommand for MergeByDistanceCommand {
+    const TYPE_ID: &'static str = CMD_MESH_MERGE_BY_DISTANCE;
+
+    fn scope(&self) -> CommandScope {
+        mesh_scope(self.mesh)
+    }
+
+    fn strategy() -> ConflictStrategy {
+        ConflictStrategy::Merge
+    }
+
+    fn definition() -> CommandDefinition {
+        mesh_definition(Self::strategy())
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        edit_mesh(world, context, self.mesh, |mesh| {
+            mesh.merge_by_distance(self.distance)
+        })?;
+        Ok(())
+    }
+
+    fn checkpoint(&self, world: &World, context: &CommandContext<'_>) -> Option<Checkpoint> {
+        mesh_checkpoint(world, context, self.mesh)
+    }
+}
+
+impl TypedCommand for MeshDeleteCommand {
+    const TYPE_ID: &'static str = CMD_MESH_DELETE;
+
+    fn scope(&self) -> CommandScope {
+        mesh_scope(self.mesh)
+    }
+
+    fn strategy() -> ConflictStrategy {
+        ConflictStrategy::Merge
+    }
+
+    fn definition() -> CommandDefinition {
+        mesh_definition(Self::strategy())
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        let mesh = target_mesh(world, context, self.mesh)?;
+        match self.element {
+            MeshElement::Vertex(id) => {
+                mesh.delete_vertex(VertexId(id))?;
+            }
+            MeshElement::Edge(id) => {
+                mesh.delete_edge(EdgeId(id))?;
+            }
+            MeshElement::Face(id) => mesh.delete_face(FaceId(id))?,
+        }
+        Ok(())
+    }
+
+    fn checkpoint(&self, world: &World, context: &CommandContext<'_>) -> Option<Checkpoint> {
+        mesh_checkpoint(world, context, self.mesh)
+    }
+}
+
+impl TypedCommand for ImportMeshCommand {
+    const TYPE_ID: &'static str = CMD_MESH_IMPORT;
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::Entity(self.entity)
+    }
+
+    fn strategy() -> ConflictStrategy {
+        ConflictStrategy::Merge
+    }
+
+    fn definition() -> CommandDefinition {
+        mesh_definition(Self::strategy())
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError> {
+        let transform = Transform {
+            position: self.transform.translation,
+            rotation: self.transform.rotation,
+            scale: sanitize_scale(self.transform.scale),
+        };
+        spawn_mesh_entity(world, context, self.entity, self.data.build()?, transform)
+    }
+}
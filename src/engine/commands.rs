@@ -1,23 +1,51 @@
+use super::Transform;
+use super::builtin_commands::register_builtin_commands;
+use super::ordering::{Handlers, OrderedScopes};
+use super::typed_commands::{
+    CoalescingCommand, CommandApplyError, CommandContext, CommandHandlerRegistry, TypedCommand,
+};
+use crate::ecs::{Entity, World};
+use crate::editor::PrimitiveShape;
 use crate::editor::commands::{
-    CMD_ENTITY_ROTATE, CMD_ENTITY_SCALE, CMD_ENTITY_TRANSLATE, CMD_MESH_EDGE_EXTRUDE,
-    CMD_MESH_FACE_SUBDIVIDE, CMD_MESH_VERTEX_CREATE, CMD_SELECTION_HIGHLIGHT, CMD_TOOL_ACTIVATE,
-    CMD_TOOL_DEACTIVATE, EdgeExtrudeCommand, EntityRotateCommand, EntityScaleCommand,
-    EntityTranslateCommand, FaceSubdivideCommand, Quaternion, SelectionHighlightCommand,
-    SubdivideParams, ToolActivateCommand, ToolDeactivateCommand, VertexCreateCommand,
+    BridgeEdgeLoopsCommand, CMD_ENTITY_ROTATE, CMD_ENTITY_SCALE, CMD_ENTITY_TRANSLATE,
+    CreatePrimitiveCommand, EdgeBevelCommand, EdgeExtrudeCommand, EntityRotateCommand,
+    EntityScaleCommand, EntityTranslateCommand, FaceExtrudeCommand, FaceInsetCommand,
+    FaceSubdivideCommand, ImportMeshCommand, LoopCutCommand, MergeByDistanceCommand,
+    MeshDeleteCommand, MeshElement, Quaternion, SelectionAddCommand, SelectionClearCommand,
+    SelectionHighlightCommand, SelectionRemoveCommand, SelectionToggleCommand, SubdivideParams,
+    ToolActivateCommand, ToolDeactivateCommand, VertexCreateCommand,
+};
+use crate::editor::mesh_io::{MeshData, NodeTransform};
+use crate::network::access::{
+    CMD_ACCESS_ENTITY_OWNER, CMD_ACCESS_SET_ROLE, CMD_ACCESS_TOOL_GRANT, EntityOwnershipCommand,
+    SetRoleCommand, ToolGrantCommand, register_access_commands,
+};
+use crate::network::authority::{
+    AuthorityGrantCommand, AuthorityRequestCommand, AuthorityRevokeCommand, AuthorityTable,
+    CMD_AUTHORITY_GRANT, CMD_AUTHORITY_REQUEST, CMD_AUTHORITY_REVOKE, register_authority_commands,
 };
 use crate::network::command_log::{
-    AuthorId, CommandAuthor, CommandDefinition, CommandEntry, CommandId, CommandLog,
-    CommandLogError, CommandPacket, CommandPayload, CommandRegistry, CommandRole, CommandScope,
-    CommandSigner, ConflictStrategy, MAX_COMMAND_PACKET_BYTES, NoopCommandSigner,
-    NoopSignatureVerifier, SignatureVerifier,
+    AuthorId, CommandAuthor, CommandEntry, CommandId, CommandLog, CommandLogError, CommandPacket,
+    CommandPayload, CommandRegistry, CommandRole, CommandScope, CommandSigner, ConflictStrategy,
+    FragmentReassembler, MAX_COMMAND_PACKET_BYTES, NoopSignatureVerifier, PacketEncoding,
+    SharedKeyCommandSigner, SignatureVerifier, VersionVector,
 };
+use crate::network::inspector::CommandCapture;
+use crate::network::locks::{
+    CMD_ENTITY_LOCK, CMD_ENTITY_UNLOCK, EntityLease, EntityLockCommand, EntityUnlockCommand,
+    register_lock_commands,
+};
+use crate::network::network_id::{SharedEntityMap, lock_entity_map};
+use crate::network::prediction::ClientPrediction;
+use crate::network::preview::PreviewPacket;
 use crate::network::transport::TransportMetricsHandle;
-use crate::network::{EntityHandle, NetworkSession};
+use crate::network::{EntityHandle, NetworkSession, current_time_millis};
 use serde::{Deserialize, Serialize};
 use serde_json::to_vec;
+use std::any::Any;
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 pub struct CommandPipeline {
     log: CommandLog,
@@ -25,89 +53,94 @@ pub struct CommandPipeline {
     session: NetworkSession,
     last_published: Option<CommandId>,
     pending_packets: Vec<CommandPacket>,
+    packet_encoding: PacketEncoding,
+    reassembler: FragmentReassembler,
+    handlers: CommandHandlerRegistry,
+    ordered: OrderedScopes,
+    entities: SharedEntityMap,
     metrics: CommandMetricsInternal,
+    coalesce_config: CoalesceConfig,
+    coalescing: Option<PendingCoalesce>,
+    rejected_drags: Vec<(Entity, Transform)>,
+    /// Why drags committed ahead of another command were refused.
+    drag_errors: Vec<CommandLogError>,
+    /// Transforms moved by local commands ahead of the host, while mirroring one.
+    prediction: Option<ClientPrediction<Transform>>,
+    previews: Vec<PreviewPacket>,
+    preview_sequence: u64,
+    capture: Option<CommandCapture>,
+}
+
+/// When a coalesced drag is committed to the log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoalesceConfig {
+    /// Commit once no command has joined the drag for this long.
+    pub idle_window: Duration,
+    /// Commit a drag that has been open this long even if it is still moving, so a long
+    /// drag is never held back indefinitely.
+    pub max_hold: Duration,
+}
+
+impl Default for CoalesceConfig {
+    fn default() -> Self {
+        Self {
+            idle_window: Duration::from_millis(150),
+            max_hold: Duration::from_secs(1),
+        }
+    }
+}
+
+/// The drag being coalesced; `command` holds the concrete [`CoalescingCommand`] and
+/// `before` the pose the drag started from, restored if the log rejects it.
+struct PendingCoalesce {
+    type_id: &'static str,
+    scope: CommandScope,
+    command: Box<dyn Any + Send>,
+    commit: fn(&mut CommandPipeline, &dyn Any) -> Result<(), CommandLogError>,
+    before: Option<(Entity, Transform)>,
+    started: Instant,
+    updated: Instant,
+}
+
+impl PendingCoalesce {
+    fn new<C: CoalescingCommand>(
+        command: C,
+        before: Option<(Entity, Transform)>,
+        now: Instant,
+    ) -> Self {
+        Self {
+            type_id: C::TYPE_ID,
+            scope: command.scope(),
+            command: Box::new(command),
+            before,
+            commit: |pipeline, command| {
+                let command = command
+                    .downcast_ref::<C>()
+                    .expect("pending command matches its commit fn");
+                pipeline.record(command)
+            },
+            started: now,
+            updated: now,
+        }
+    }
 }
 
 impl CommandPipeline {
     pub fn new() -> Self {
         let mut registry = CommandRegistry::new();
-        registry.register(
-            CMD_SELECTION_HIGHLIGHT,
-            CommandDefinition::builder()
-                .required_role(CommandRole::Editor)
-                .default_strategy(ConflictStrategy::LastWriteWins)
-                .require_signature(false)
-                .build(),
-        );
-        registry.register(
-            CMD_ENTITY_TRANSLATE,
-            CommandDefinition::builder()
-                .required_role(CommandRole::Editor)
-                .default_strategy(ConflictStrategy::Merge)
-                .require_signature(false)
-                .build(),
-        );
-        registry.register(
-            CMD_ENTITY_ROTATE,
-            CommandDefinition::builder()
-                .required_role(CommandRole::Editor)
-                .default_strategy(ConflictStrategy::LastWriteWins)
-                .require_signature(false)
-                .build(),
-        );
-        registry.register(
-            CMD_ENTITY_SCALE,
-            CommandDefinition::builder()
-                .required_role(CommandRole::Editor)
-                .default_strategy(ConflictStrategy::LastWriteWins)
-                .require_signature(false)
-                .build(),
-        );
-        registry.register(
-            CMD_TOOL_ACTIVATE,
-            CommandDefinition::builder()
-                .required_role(CommandRole::Editor)
-                .default_strategy(ConflictStrategy::LastWriteWins)
-                .require_signature(false)
-                .build(),
-        );
-        registry.register(
-            CMD_TOOL_DEACTIVATE,
-            CommandDefinition::builder()
-                .required_role(CommandRole::Editor)
-                .default_strategy(ConflictStrategy::LastWriteWins)
-                .require_signature(false)
-                .build(),
-        );
-        registry.register(
-            CMD_MESH_VERTEX_CREATE,
-            CommandDefinition::builder()
-                .required_role(CommandRole::Editor)
-                .default_strategy(ConflictStrategy::Merge)
-                .require_signature(false)
-                .build(),
-        );
-        registry.register(
-            CMD_MESH_EDGE_EXTRUDE,
-            CommandDefinition::builder()
-                .required_role(CommandRole::Editor)
-                .default_strategy(ConflictStrategy::Merge)
-                .require_signature(false)
-                .build(),
-        );
-        registry.register(
-            CMD_MESH_FACE_SUBDIVIDE,
-            CommandDefinition::builder()
-                .required_role(CommandRole::Editor)
-                .default_strategy(ConflictStrategy::Merge)
-                .require_signature(false)
-                .build(),
-        );
+        let mut handlers = CommandHandlerRegistry::new();
+        register_builtin_commands(&mut handlers, &mut registry);
+        register_access_commands(&mut registry);
+        register_lock_commands(&mut registry);
+        register_authority_commands(&mut registry);
         let registry = Arc::new(registry);
         let verifier = Arc::new(NoopSignatureVerifier) as Arc<dyn SignatureVerifier>;
-        let log = CommandLog::new(Arc::clone(&registry), verifier);
+        let mut log = CommandLog::new(Arc::clone(&registry), verifier);
         let author = CommandAuthor::new(AuthorId(0), CommandRole::Editor);
-        let signer: Box<dyn CommandSigner> = Box::new(NoopCommandSigner::new(author));
+        // A standalone pipeline hosts its own session until one is attached.
+        log.authority_mut().set_host(author.id.clone());
+        let signer: Box<dyn CommandSigner> =
+            Box::new(SharedKeyCommandSigner::with_random_key(author));
 
         Self {
             log,
@@ -115,7 +148,20 @@ impl CommandPipeline {
             session: NetworkSession::connect(),
             last_published: None,
             pending_packets: Vec::new(),
+            packet_encoding: PacketEncoding::default(),
+            reassembler: FragmentReassembler::new(),
+            handlers,
+            ordered: OrderedScopes::default(),
+            entities: SharedEntityMap::default(),
             metrics: CommandMetricsInternal::default(),
+            coalesce_config: CoalesceConfig::default(),
+            coalescing: None,
+            rejected_drags: Vec::new(),
+            drag_errors: Vec::new(),
+            prediction: None,
+            previews: Vec::new(),
+            preview_sequence: 0,
+            capture: None,
         }
     }
 
@@ -133,7 +179,10 @@ impl CommandPipeline {
             match err {
                 CommandLogError::ConflictRejected
                 | CommandLogError::Duplicate
-                | CommandLogError::InsufficientPermissions { .. } => {
+                | CommandLogError::InsufficientPermissions { .. }
+                | CommandLogError::ScopeAccessDenied { .. }
+                | CommandLogError::EntityLocked { .. }
+                | CommandLogError::AuthorityDenied { .. } => {
                     self.metrics.record_conflict(strategy_hint);
                 }
                 CommandLogError::RateLimited(_) => {
@@ -148,39 +197,446 @@ impl CommandPipeline {
         }
 
         self.metrics.record_local_append();
+        if let Some(capture) = self.capture.as_mut()
+            && let Some(entry) = self.log.latest_id().and_then(|id| self.log.entry(&id))
+        {
+            capture.push(entry.clone());
+        }
 
         let new_entries = self.log.entries_since(self.last_published.as_ref());
         if !new_entries.is_empty() {
             self.last_published = self.log.latest_id();
-            let batch = self.session.craft_command_batch(new_entries);
-            match CommandPacket::from_batch(&batch) {
-                Ok(packet) => {
-                    self.log.record_packet_nonce(&batch.author, batch.nonce);
-                    self.pending_packets.push(packet);
+            let author = new_entries[0].author.id.clone();
+            match self
+                .session
+                .craft_command_packets(new_entries, self.packet_encoding)
+            {
+                Ok(packets) => {
+                    for packet in &packets {
+                        self.log.record_packet_nonce(&author, packet.nonce);
+                    }
+                    self.pending_packets.extend(packets);
                 }
                 Err(err) => {
                     self.metrics.record_payload_guard_drop();
-                    log::error!(
-                        "[commands] failed to serialize command batch {}: {}",
-                        batch.sequence,
-                        err
-                    );
+                    log::error!("[commands] failed to serialize command batch: {err}");
                 }
             }
         }
         Ok(())
     }
 
+    /// Registers a game or editor command defined outside the engine.
+    pub fn register_command<C: TypedCommand>(&mut self) {
+        self.log.register_command(C::TYPE_ID, C::definition());
+        self.handlers.register::<C>();
+    }
+
+    pub fn handlers(&self) -> &CommandHandlerRegistry {
+        &self.handlers
+    }
+
+    /// Binds the network ids in command scopes to local entities; share it with the
+    /// replication mirror so both name entities alike.
+    pub fn entity_map(&self) -> &SharedEntityMap {
+        &self.entities
+    }
+
+    /// Applies an integrated `entry` to `world` with the registered handler, in command
+    /// id order among the entries of its scope when its command captures checkpoints.
+    /// Entries the log dropped since the last call are undone first.
+    pub fn apply_entry(
+        &mut self,
+        world: &mut World,
+        entry: &CommandEntry,
+        editor_entity: Option<Entity>,
+    ) -> Result<bool, CommandApplyError> {
+        let handlers = Handlers {
+            registry: &self.handlers,
+            editor_entity,
+            entities: &self.entities,
+        };
+        for superseded in self.log.drain_superseded() {
+            if !self.ordered.revoke(&handlers, world, &superseded) {
+                log::warn!(
+                    "[commands] cannot undo superseded {} command {:?}",
+                    superseded.payload.command_type,
+                    superseded.id
+                );
+            }
+        }
+        self.ordered.apply(&handlers, world, entry)
+    }
+
+    /// Starts recording every appended or integrated entry, in application order. The
+    /// log itself compacts superseded entries, so it cannot be replayed on its own.
+    pub fn start_capture(&mut self) {
+        self.capture.get_or_insert_with(CommandCapture::default);
+    }
+
+    /// Stops recording and returns what was captured.
+    pub fn take_capture(&mut self) -> Option<CommandCapture> {
+        self.capture.take()
+    }
+
+    /// Newest lamport clock seen from each author.
+    pub fn version_vector(&self) -> &VersionVector {
+        self.log.version_vector()
+    }
+
+    pub fn latest_entry(&self) -> Option<CommandEntry> {
+        self.log
+            .latest_id()
+            .and_then(|id| self.log.entry(&id).cloned())
+    }
+
+    /// Appends `command` to the log. An open drag is committed first so the log keeps
+    /// the order commands were issued in; if the log refuses it, `command` is still
+    /// recorded and the refusal waits in [`Self::drain_drag_errors`].
+    pub fn record<C: TypedCommand>(&mut self, command: &C) -> Result<(), CommandLogError> {
+        self.commit_before_next();
+        let payload = command
+            .to_payload()
+            .map_err(|err| CommandLogError::PayloadEncodeFailed(err.to_string()))?;
+        self.append_payload(payload, Some(C::strategy()))
+    }
+
+    /// Records `command` and applies the new entry to `world`. Apply failures are logged;
+    /// the entry still replicates.
+    pub fn submit<C: TypedCommand>(
+        &mut self,
+        world: &mut World,
+        command: &C,
+        editor_entity: Option<Entity>,
+    ) -> Result<(), CommandLogError> {
+        let recorded = self.record(command);
+        self.undo_rejected_drags(world);
+        recorded?;
+        let Some(entry) = self.latest_entry() else {
+            return Ok(());
+        };
+        let predicted = self.predicted_entity(C::TYPE_ID, &command.scope());
+        if let (Some(prediction), Some(entity)) = (self.prediction.as_mut(), predicted) {
+            prediction.begin(world, entity);
+        }
+        if let Err(err) = self.apply_entry(world, &entry, editor_entity) {
+            log::warn!(
+                "[commands] failed to apply local {} command: {err}",
+                C::TYPE_ID
+            );
+        }
+        if let (Some(prediction), Some(entity)) = (self.prediction.as_mut(), predicted) {
+            prediction.record(entity, entry);
+            prediction.release(entity);
+        }
+        Ok(())
+    }
+
+    /// Runs the role, scope and lock checks `command` would face without recording it.
+    pub fn authorize<C: TypedCommand>(&self, command: &C) -> Result<(), CommandLogError> {
+        self.log
+            .authorize_local(self.signer.author(), C::TYPE_ID, &command.scope())
+    }
+
+    /// How many more commands this peer may record before the rate limiter refuses them.
+    pub fn rate_capacity(&mut self) -> u32 {
+        let author = self.signer.author().id.clone();
+        self.log.rate_capacity(&author)
+    }
+
+    /// Fails unless `commands` more entries, plus the open drag, fit within the rate
+    /// limit, so a group of commands is either recorded whole or not at all.
+    pub fn ensure_capacity(&mut self, commands: usize) -> Result<(), CommandLogError> {
+        let needed = commands + usize::from(self.coalescing.is_some());
+        if (self.rate_capacity() as usize) < needed {
+            return Err(CommandLogError::RateLimited(
+                self.signer.author().id.clone(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Applies `command` locally at once but folds it into the open drag instead of
+    /// appending it, so a drag spends one rate-limit token. Each call queues a
+    /// [`PreviewPacket`] with the entity's resulting pose for peers.
+    pub fn submit_coalesced<C: CoalescingCommand>(
+        &mut self,
+        world: &mut World,
+        command: &C,
+        editor_entity: Option<Entity>,
+    ) -> Result<(), CommandLogError> {
+        let scope = command.scope();
+        if self
+            .coalescing
+            .as_ref()
+            .is_some_and(|pending| pending.type_id != C::TYPE_ID || pending.scope != scope)
+        {
+            self.commit_before_next();
+            self.undo_rejected_drags(world);
+        }
+
+        self.authorize(command)?;
+        let author = self.signer.author().clone();
+        let payload = command
+            .to_payload()
+            .map_err(|err| CommandLogError::PayloadEncodeFailed(err.to_string()))?;
+        // The provisional entry only gives the handler its context; it never enters the log.
+        let provisional = CommandEntry::new(
+            CommandId::new(0, author.id.clone()),
+            current_time_millis(),
+            payload,
+            C::strategy(),
+            author.clone(),
+            None,
+        );
+        let context = CommandContext {
+            entry: &provisional,
+            editor_entity,
+            entities: &self.entities,
+        };
+        let before = match scope {
+            CommandScope::Entity(handle) if self.coalescing.is_none() => {
+                let entity = context.resolve(handle);
+                world
+                    .get::<Transform>(entity)
+                    .map(|transform| (entity, *transform))
+            }
+            _ => None,
+        };
+        let predicted = self.predicted_entity(C::TYPE_ID, &scope);
+        if let (Some(prediction), Some(entity)) = (self.prediction.as_mut(), predicted) {
+            prediction.begin(world, entity);
+        }
+        if let Err(err) = command.apply(world, &context) {
+            log::warn!(
+                "[commands] failed to apply local {} command: {err}",
+                C::TYPE_ID
+            );
+        }
+
+        let now = Instant::now();
+        match self.coalescing.as_mut() {
+            Some(pending) => {
+                pending
+                    .command
+                    .downcast_mut::<C>()
+                    .expect("pending command matches its type id")
+                    .coalesce(command);
+                pending.updated = now;
+            }
+            None => self.coalescing = Some(PendingCoalesce::new(command.clone(), before, now)),
+        }
+        // Replays of the open drag apply its running total, not just this step.
+        if let (Some(entity), Some(pending)) = (predicted, self.coalescing.as_ref()) {
+            let total = pending
+                .command
+                .downcast_ref::<C>()
+                .expect("pending command matches its type id")
+                .to_payload()
+                .map_err(|err| CommandLogError::PayloadEncodeFailed(err.to_string()))?;
+            let entry = CommandEntry::new(
+                CommandId::new(u64::MAX, author.id.clone()),
+                provisional.timestamp_ms,
+                total,
+                C::strategy(),
+                author.clone(),
+                None,
+            );
+            if let Some(prediction) = self.prediction.as_mut() {
+                prediction.set_provisional(Some((entity, entry)));
+            }
+        }
+
+        if let CommandScope::Entity(handle) = scope
+            && let Some(transform) = world.get::<Transform>(context.resolve(handle))
+        {
+            self.preview_sequence += 1;
+            self.previews.push(PreviewPacket {
+                author: author.id,
+                sequence: self.preview_sequence,
+                entity: handle,
+                position: transform.position,
+                rotation: transform.rotation,
+                scale: transform.scale,
+                timestamp_ms: current_time_millis(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Commits the open drag, if any, as one log entry. Call on release. A drag the log
+    /// rejects is undone: the entity returns to the pose it had before the drag.
+    pub fn flush_coalesced(&mut self, world: &mut World) -> Result<bool, CommandLogError> {
+        let committed = self.commit_coalesced();
+        self.undo_rejected_drags(world);
+        committed
+    }
+
+    /// Commits the open drag once it has idled or been held past the configured limits.
+    pub fn poll_coalesced(
+        &mut self,
+        world: &mut World,
+        now: Instant,
+    ) -> Result<bool, CommandLogError> {
+        let due = self.coalescing.as_ref().is_some_and(|pending| {
+            now.duration_since(pending.updated) >= self.coalesce_config.idle_window
+                || now.duration_since(pending.started) >= self.coalesce_config.max_hold
+        });
+        if due {
+            self.flush_coalesced(world)
+        } else {
+            self.undo_rejected_drags(world);
+            Ok(false)
+        }
+    }
+
+    /// Commits the open drag ahead of another command, keeping a refusal for
+    /// [`Self::drain_drag_errors`] instead of failing the command that follows.
+    fn commit_before_next(&mut self) {
+        if let Err(err) = self.commit_coalesced() {
+            self.drag_errors.push(err);
+        }
+    }
+
+    /// Why drags committed ahead of other commands were refused since the last call.
+    /// Those drags are undone like one refused by [`Self::flush_coalesced`].
+    pub fn drain_drag_errors(&mut self) -> Vec<CommandLogError> {
+        std::mem::take(&mut self.drag_errors)
+    }
+
+    /// Commits the open drag without a world to undo it in; a rejected drag waits in
+    /// `rejected_drags` for the next [`Self::flush_coalesced`].
+    fn commit_coalesced(&mut self) -> Result<bool, CommandLogError> {
+        let Some(pending) = self.coalescing.take() else {
+            return Ok(false);
+        };
+        let predicted = self.predicted_entity(pending.type_id, &pending.scope);
+        if let Some(prediction) = self.prediction.as_mut() {
+            prediction.set_provisional(None);
+        }
+        let committed = (pending.commit)(self, pending.command.as_ref());
+        let entry = committed.as_ref().ok().and_then(|_| self.latest_entry());
+        if let (Some(prediction), Some(entity)) = (self.prediction.as_mut(), predicted) {
+            if let Some(entry) = entry {
+                prediction.record(entity, entry);
+            }
+            prediction.release(entity);
+        }
+        if let Err(err) = committed {
+            self.rejected_drags.extend(pending.before);
+            return Err(err);
+        }
+        Ok(true)
+    }
+
+    /// The entity a local transform command is predicted on, while predicting.
+    fn predicted_entity(&self, type_id: &str, scope: &CommandScope) -> Option<Entity> {
+        self.prediction.as_ref()?;
+        match scope {
+            CommandScope::Entity(handle)
+                if [CMD_ENTITY_TRANSLATE, CMD_ENTITY_ROTATE, CMD_ENTITY_SCALE]
+                    .contains(&type_id) =>
+            {
+                Some(lock_entity_map(&self.entities).resolve(*handle))
+            }
+            _ => None,
+        }
+    }
+
+    /// Predicts local transform commands until the host acknowledges them; used while
+    /// mirroring a host's world.
+    pub fn set_prediction(&mut self, enabled: bool) {
+        if !enabled {
+            self.prediction = None;
+        } else if self.prediction.is_none() {
+            self.prediction = Some(ClientPrediction::new(self.signer.author().id.clone()));
+        }
+    }
+
+    pub fn is_predicting(&self, entity: Entity) -> bool {
+        self.prediction
+            .as_ref()
+            .is_some_and(|prediction| prediction.is_predicting(entity))
+    }
+
+    /// Restores predicted transforms to the host's last state before new state from it
+    /// is applied.
+    pub fn rewind_predictions(&self, world: &mut World) {
+        if let Some(prediction) = &self.prediction {
+            prediction.rewind(world);
+        }
+    }
+
+    /// Takes the world's transforms as the host's and replays the local commands it had
+    /// not applied as of `command_ack`.
+    pub fn reconcile_predictions(
+        &mut self,
+        world: &mut World,
+        command_ack: u64,
+        editor_entity: Option<Entity>,
+    ) {
+        let Some(prediction) = self.prediction.as_mut() else {
+            return;
+        };
+        let handlers = &self.handlers;
+        let entities = &self.entities;
+        prediction.reconcile(world, command_ack, &mut |world, _, entry| {
+            if let Err(err) = handlers.apply(world, entry, editor_entity, entities) {
+                log::warn!(
+                    "[commands] failed to replay predicted {} command: {err}",
+                    entry.payload.command_type
+                );
+            }
+        });
+    }
+
+    fn undo_rejected_drags(&mut self, world: &mut World) {
+        for (entity, before) in self.rejected_drags.drain(..) {
+            if let Some(transform) = world.get_mut::<Transform>(entity) {
+                *transform = before;
+            }
+        }
+    }
+
+    pub fn set_coalesce_config(&mut self, config: CoalesceConfig) {
+        self.coalesce_config = config;
+    }
+
+    pub fn drain_previews(&mut self) -> Vec<PreviewPacket> {
+        std::mem::take(&mut self.previews)
+    }
+
     pub fn record_selection_highlight(
         &mut self,
         entity: EntityHandle,
         active: bool,
     ) -> Result<(), CommandLogError> {
-        let command = SelectionHighlightCommand::new(entity, active);
-        let data = to_vec(&command).expect("serialize highlight command");
-        let payload =
-            CommandPayload::new(CMD_SELECTION_HIGHLIGHT, CommandScope::Entity(entity), data);
-        self.append_payload(payload, Some(ConflictStrategy::LastWriteWins))
+        self.record(&SelectionHighlightCommand::new(entity, active))
+    }
+
+    pub fn record_selection_add(
+        &mut self,
+        entities: Vec<EntityHandle>,
+    ) -> Result<(), CommandLogError> {
+        self.record(&SelectionAddCommand::new(entities))
+    }
+
+    pub fn record_selection_remove(
+        &mut self,
+        entities: Vec<EntityHandle>,
+    ) -> Result<(), CommandLogError> {
+        self.record(&SelectionRemoveCommand::new(entities))
+    }
+
+    pub fn record_selection_toggle(
+        &mut self,
+        entities: Vec<EntityHandle>,
+    ) -> Result<(), CommandLogError> {
+        self.record(&SelectionToggleCommand::new(entities))
+    }
+
+    pub fn record_selection_clear(&mut self) -> Result<(), CommandLogError> {
+        self.record(&SelectionClearCommand::new())
     }
 
     pub fn record_entity_translate(
@@ -188,10 +644,7 @@ impl CommandPipeline {
         entity: EntityHandle,
         delta: [f32; 3],
     ) -> Result<(), CommandLogError> {
-        let command = EntityTranslateCommand::new(entity, delta);
-        let data = to_vec(&command).expect("serialize translate command");
-        let payload = CommandPayload::new(CMD_ENTITY_TRANSLATE, CommandScope::Entity(entity), data);
-        self.append_payload(payload, Some(ConflictStrategy::Merge))
+        self.record(&EntityTranslateCommand::new(entity, delta))
     }
 
     pub fn record_entity_rotate(
@@ -200,10 +653,7 @@ impl CommandPipeline {
         rotation: Quaternion,
     ) -> Result<(), CommandLogError> {
         let normalized = normalize_quaternion(rotation);
-        let command = EntityRotateCommand::new(entity, normalized);
-        let data = to_vec(&command).expect("serialize rotate command");
-        let payload = CommandPayload::new(CMD_ENTITY_ROTATE, CommandScope::Entity(entity), data);
-        self.append_payload(payload, Some(ConflictStrategy::LastWriteWins))
+        self.record(&EntityRotateCommand::new(entity, normalized))
     }
 
     pub fn record_entity_scale(
@@ -211,67 +661,265 @@ impl CommandPipeline {
         entity: EntityHandle,
         scale: [f32; 3],
     ) -> Result<(), CommandLogError> {
-        let command = EntityScaleCommand::new(entity, scale);
-        let data = to_vec(&command).expect("serialize scale command");
-        let payload = CommandPayload::new(CMD_ENTITY_SCALE, CommandScope::Entity(entity), data);
-        self.append_payload(payload, Some(ConflictStrategy::LastWriteWins))
+        self.record(&EntityScaleCommand::new(entity, scale))
     }
 
     pub fn record_tool_activate(
         &mut self,
         tool_id: impl Into<String>,
     ) -> Result<(), CommandLogError> {
-        let tool_id = tool_id.into();
-        let command = ToolActivateCommand::new(tool_id.clone());
-        let data = to_vec(&command).expect("serialize tool activate command");
-        let payload = CommandPayload::new(CMD_TOOL_ACTIVATE, CommandScope::Tool(tool_id), data);
-        self.append_payload(payload, Some(ConflictStrategy::LastWriteWins))
+        self.record(&ToolActivateCommand::new(tool_id))
     }
 
     pub fn record_tool_deactivate(
         &mut self,
         tool_id: impl Into<String>,
     ) -> Result<(), CommandLogError> {
-        let tool_id = tool_id.into();
-        let command = ToolDeactivateCommand::new(tool_id.clone());
-        let data = to_vec(&command).expect("serialize tool deactivate command");
-        let payload = CommandPayload::new(CMD_TOOL_DEACTIVATE, CommandScope::Tool(tool_id), data);
-        self.append_payload(payload, Some(ConflictStrategy::LastWriteWins))
+        self.record(&ToolDeactivateCommand::new(tool_id))
     }
 
+    /// `mesh` is the entity whose [`crate::editor::mesh::EditableMesh`] the
+    /// `record_mesh_*` commands edit; `None` edits the editor's scratch mesh.
     pub fn record_mesh_vertex_create(
         &mut self,
+        mesh: Option<EntityHandle>,
         position: [f32; 3],
         metadata: HashMap<String, String>,
     ) -> Result<(), CommandLogError> {
-        let command = VertexCreateCommand::new(position, metadata);
-        let data = to_vec(&command).expect("serialize vertex create command");
-        let payload = CommandPayload::new(CMD_MESH_VERTEX_CREATE, CommandScope::Global, data);
-        self.append_payload(payload, Some(ConflictStrategy::Merge))
+        self.record(&VertexCreateCommand {
+            mesh,
+            ..VertexCreateCommand::new(position, metadata)
+        })
     }
 
     pub fn record_mesh_edge_extrude(
         &mut self,
-        edge_id: u32,
+        mesh: Option<EntityHandle>,
+        edge_id: u64,
         direction: [f32; 3],
     ) -> Result<(), CommandLogError> {
-        let command = EdgeExtrudeCommand::new(edge_id, direction);
-        let data = to_vec(&command).expect("serialize edge extrude command");
-        let payload = CommandPayload::new(CMD_MESH_EDGE_EXTRUDE, CommandScope::Global, data);
-        self.append_payload(payload, Some(ConflictStrategy::Merge))
+        self.record(&EdgeExtrudeCommand {
+            mesh,
+            ..EdgeExtrudeCommand::new(edge_id, direction)
+        })
     }
 
     pub fn record_mesh_face_subdivide(
         &mut self,
-        face_id: u32,
+        mesh: Option<EntityHandle>,
+        face_id: u64,
         params: SubdivideParams,
     ) -> Result<(), CommandLogError> {
-        let command = FaceSubdivideCommand::new(face_id, params);
-        let data = to_vec(&command).expect("serialize face subdivide command");
-        let payload = CommandPayload::new(CMD_MESH_FACE_SUBDIVIDE, CommandScope::Global, data);
+        self.record(&FaceSubdivideCommand {
+            mesh,
+            ..FaceSubdivideCommand::new(face_id, params)
+        })
+    }
+
+    pub fn record_mesh_face_extrude(
+        &mut self,
+        mesh: Option<EntityHandle>,
+        face_id: u64,
+        distance: f32,
+    ) -> Result<(), CommandLogError> {
+        self.record(&FaceExtrudeCommand {
+            mesh,
+            ..FaceExtrudeCommand::new(face_id, distance)
+        })
+    }
+
+    pub fn record_mesh_face_inset(
+        &mut self,
+        mesh: Option<EntityHandle>,
+        face_id: u64,
+        amount: f32,
+    ) -> Result<(), CommandLogError> {
+        self.record(&FaceInsetCommand {
+            mesh,
+            ..FaceInsetCommand::new(face_id, amount)
+        })
+    }
+
+    pub fn record_mesh_edge_bevel(
+        &mut self,
+        mesh: Option<EntityHandle>,
+        edge_id: u64,
+        width: f32,
+    ) -> Result<(), CommandLogError> {
+        self.record(&EdgeBevelCommand {
+            mesh,
+            ..EdgeBevelCommand::new(edge_id, width)
+        })
+    }
+
+    pub fn record_mesh_loop_cut(
+        &mut self,
+        mesh: Option<EntityHandle>,
+        edge_id: u64,
+        factor: f32,
+    ) -> Result<(), CommandLogError> {
+        self.record(&LoopCutCommand {
+            mesh,
+            ..LoopCutCommand::new(edge_id, factor)
+        })
+    }
+
+    pub fn record_mesh_bridge_loops(
+        &mut self,
+        mesh: Option<EntityHandle>,
+        first: Vec<u64>,
+        second: Vec<u64>,
+    ) -> Result<(), CommandLogError> {
+        self.record(&BridgeEdgeLoopsCommand {
+            mesh,
+            ..BridgeEdgeLoopsCommand::new(first, second)
+        })
+    }
+
+    pub fn record_mesh_merge_by_distance(
+        &mut self,
+        mesh: Option<EntityHandle>,
+        distance: f32,
+    ) -> Result<(), CommandLogError> {
+        self.record(&MergeByDistanceCommand {
+            mesh,
+            ..MergeByDistanceCommand::new(distance)
+        })
+    }
+
+    pub fn record_mesh_delete(
+        &mut self,
+        mesh: Option<EntityHandle>,
+        element: MeshElement,
+    ) -> Result<(), CommandLogError> {
+        self.record(&MeshDeleteCommand {
+            mesh,
+            ..MeshDeleteCommand::new(element)
+        })
+    }
+
+    pub fn record_create_primitive(
+        &mut self,
+        entity: EntityHandle,
+        shape: PrimitiveShape,
+        position: [f32; 3],
+    ) -> Result<(), CommandLogError> {
+        self.record(&CreatePrimitiveCommand::new(entity, shape, position))
+    }
+
+    pub fn record_import_mesh(
+        &mut self,
+        entity: EntityHandle,
+        data: MeshData,
+        transform: NodeTransform,
+    ) -> Result<(), CommandLogError> {
+        self.record(&ImportMeshCommand::new(entity, data, transform))
+    }
+
+    pub fn record_access_role(
+        &mut self,
+        author: AuthorId,
+        role: CommandRole,
+    ) -> Result<(), CommandLogError> {
+        let command = SetRoleCommand::new(author, role);
+        let data = to_vec(&command).expect("serialize role change command");
+        let payload = CommandPayload::new(CMD_ACCESS_SET_ROLE, CommandScope::Global, data);
+        self.append_payload(payload, Some(ConflictStrategy::Merge))
+    }
+
+    pub fn record_entity_owner(
+        &mut self,
+        entity: EntityHandle,
+        owner: Option<AuthorId>,
+    ) -> Result<(), CommandLogError> {
+        let command = EntityOwnershipCommand::new(entity, owner);
+        let data = to_vec(&command).expect("serialize entity ownership command");
+        let payload = CommandPayload::new(CMD_ACCESS_ENTITY_OWNER, CommandScope::Global, data);
+        self.append_payload(payload, Some(ConflictStrategy::Merge))
+    }
+
+    pub fn record_tool_grant(
+        &mut self,
+        tool_id: &str,
+        author: AuthorId,
+        granted: bool,
+    ) -> Result<(), CommandLogError> {
+        let command = ToolGrantCommand::new(tool_id, author, granted);
+        let data = to_vec(&command).expect("serialize tool grant command");
+        let payload = CommandPayload::new(CMD_ACCESS_TOOL_GRANT, CommandScope::Global, data);
+        self.append_payload(payload, Some(ConflictStrategy::Merge))
+    }
+
+    pub fn record_entity_lock(
+        &mut self,
+        entity: EntityHandle,
+        lease_ms: u64,
+    ) -> Result<(), CommandLogError> {
+        let command = EntityLockCommand::new(entity, lease_ms);
+        let data = to_vec(&command).expect("serialize entity lock command");
+        let payload = CommandPayload::new(CMD_ENTITY_LOCK, CommandScope::Entity(entity), data);
+        self.append_payload(payload, Some(ConflictStrategy::Merge))
+    }
+
+    pub fn record_entity_unlock(&mut self, entity: EntityHandle) -> Result<(), CommandLogError> {
+        let command = EntityUnlockCommand::new(entity);
+        let data = to_vec(&command).expect("serialize entity unlock command");
+        let payload = CommandPayload::new(CMD_ENTITY_UNLOCK, CommandScope::Entity(entity), data);
+        self.append_payload(payload, Some(ConflictStrategy::Merge))
+    }
+
+    /// Returns the active leases after dropping the ones whose timeout elapsed.
+    pub fn refresh_entity_locks(&mut self) -> Vec<(EntityHandle, EntityLease)> {
+        self.log.expire_locks();
+        self.log
+            .locks()
+            .leases()
+            .map(|(entity, lease)| (*entity, lease.clone()))
+            .collect()
+    }
+
+    pub fn release_entity_locks(&mut self, author: &AuthorId) -> Vec<EntityHandle> {
+        self.log.release_locks_held_by(author)
+    }
+
+    pub fn request_authority(&mut self, entity: EntityHandle) -> Result<(), CommandLogError> {
+        let command = AuthorityRequestCommand::new(entity);
+        let data = to_vec(&command).expect("serialize authority request command");
+        let payload =
+            CommandPayload::new(CMD_AUTHORITY_REQUEST, CommandScope::Entity(entity), data);
+        self.append_payload(payload, Some(ConflictStrategy::Merge))
+    }
+
+    pub fn grant_authority(
+        &mut self,
+        entity: EntityHandle,
+        owner: AuthorId,
+    ) -> Result<(), CommandLogError> {
+        let command = AuthorityGrantCommand::new(entity, owner);
+        let data = to_vec(&command).expect("serialize authority grant command");
+        let payload = CommandPayload::new(CMD_AUTHORITY_GRANT, CommandScope::Entity(entity), data);
         self.append_payload(payload, Some(ConflictStrategy::Merge))
     }
 
+    pub fn revoke_authority(&mut self, entity: EntityHandle) -> Result<(), CommandLogError> {
+        let command = AuthorityRevokeCommand::new(entity);
+        let data = to_vec(&command).expect("serialize authority revoke command");
+        let payload = CommandPayload::new(CMD_AUTHORITY_REVOKE, CommandScope::Entity(entity), data);
+        self.append_payload(payload, Some(ConflictStrategy::Merge))
+    }
+
+    pub fn local_author(&self) -> &AuthorId {
+        &self.signer.author().id
+    }
+
+    pub fn authority(&self) -> &AuthorityTable {
+        self.log.authority()
+    }
+
+    pub fn authority_mut(&mut self) -> &mut AuthorityTable {
+        self.log.authority_mut()
+    }
+
     pub fn drain_packets(&mut self) -> Vec<CommandPacket> {
         self.pending_packets.drain(..).collect()
     }
@@ -291,9 +939,13 @@ impl CommandPipeline {
             return Ok(Vec::new());
         }
 
-        let batch = packet
-            .decode()
-            .map_err(|err| CommandLogError::PacketDecodeFailed(err.to_string()))?;
+        let Some(batch) = self
+            .reassembler
+            .accept(packet)
+            .map_err(|err| CommandLogError::PacketDecodeFailed(err.to_string()))?
+        else {
+            return Ok(Vec::new());
+        };
 
         if let Err(err) = self.log.verify_packet_nonce(&batch.author, batch.nonce) {
             match err {
@@ -318,7 +970,18 @@ impl CommandPipeline {
             self.metrics.record_signature_latency(latency_ms);
 
             match result {
-                Ok(true) => applied.push(entry),
+                Ok(true) => {
+                    if let Some(capture) = self.capture.as_mut() {
+                        capture.push(entry.clone());
+                    }
+                    applied.push(entry);
+                }
+                Ok(false) if self.log.is_deferred(&entry.id) => {
+                    log::debug!(
+                        "[commands] remote command {:?} waits for its causal dependencies",
+                        entry.id
+                    );
+                }
                 Ok(false) => {
                     self.metrics.record_conflict(entry.strategy);
                 }
@@ -340,6 +1003,33 @@ impl CommandPipeline {
                         entry.id
                     );
                 }
+                Err(CommandLogError::ScopeAccessDenied { author, scope }) => {
+                    self.metrics.record_conflict(entry.strategy);
+                    log::warn!(
+                        "[commands] remote command {:?} denied for author {:?} in scope {:?}",
+                        entry.id,
+                        author,
+                        scope
+                    );
+                }
+                Err(CommandLogError::EntityLocked { entity, holder }) => {
+                    self.metrics.record_conflict(entry.strategy);
+                    log::warn!(
+                        "[commands] remote command {:?} rejected; entity {:?} locked by {:?}",
+                        entry.id,
+                        entity,
+                        holder
+                    );
+                }
+                Err(CommandLogError::AuthorityDenied { entity, author }) => {
+                    self.metrics.record_conflict(entry.strategy);
+                    log::warn!(
+                        "[commands] remote command {:?} rejected; {:?} has no authority over {:?}",
+                        entry.id,
+                        author,
+                        entity
+                    );
+                }
                 Err(CommandLogError::ReplayDetected(author)) => {
                     self.metrics.record_replay_rejection();
                     log::warn!(
@@ -358,6 +1048,12 @@ impl CommandPipeline {
                 }
                 Err(err) => return Err(err),
             }
+            for released in self.log.drain_released() {
+                if let Some(capture) = self.capture.as_mut() {
+                    capture.push(released.clone());
+                }
+                applied.push(released);
+            }
         }
 
         if let Some(latest) = self.log.latest_id() {
@@ -375,9 +1071,21 @@ impl CommandPipeline {
         self.metrics.snapshot()
     }
 
+    #[allow(dead_code)]
+    pub fn set_packet_encoding(&mut self, encoding: PacketEncoding) {
+        self.packet_encoding = encoding;
+    }
+
+    pub fn packet_encoding(&self) -> PacketEncoding {
+        self.packet_encoding
+    }
+
     #[allow(dead_code)]
     pub fn set_signer(&mut self, signer: Box<dyn CommandSigner>) {
         self.signer = signer;
+        if self.prediction.is_some() {
+            self.prediction = Some(ClientPrediction::new(self.signer.author().id.clone()));
+        }
     }
 
     #[allow(dead_code)]
@@ -401,7 +1109,7 @@ impl Default for CommandPipeline {
     }
 }
 
-fn normalize_quaternion(mut rotation: Quaternion) -> Quaternion {
+pub(super) fn normalize_quaternion(mut rotation: Quaternion) -> Quaternion {
     let magnitude = (rotation.x * rotation.x
         + rotation.y * rotation.y
         + rotation.z * rotation.z
@@ -515,7 +1223,11 @@ impl CommandMetricsInternal {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::network::command_log::CommandBatch;
+    use crate::editor::commands::{
+        CMD_ENTITY_TRANSLATE, CMD_MESH_VERTEX_CREATE, CMD_SELECTION_ADD, CMD_SELECTION_HIGHLIGHT,
+        SelectionAddCommand,
+    };
+    use crate::network::command_log::{CommandBatch, CommandSignature};
 
     #[test]
     fn pipeline_emits_batches_for_highlight() {
@@ -547,6 +1259,225 @@ mod tests {
         assert!(none.is_empty());
     }
 
+    #[test]
+    fn entity_lock_rejects_remote_transforms_until_released() {
+        let mut pipeline = CommandPipeline::new();
+        let entity = EntityHandle {
+            index: 4,
+            generation: 0,
+        };
+
+        pipeline
+            .record_entity_lock(entity, 60_000)
+            .expect("acquire lock");
+        let leases = pipeline.refresh_entity_locks();
+        assert_eq!(leases.len(), 1);
+        assert_eq!(leases[0].1.holder, AuthorId(0));
+
+        let translate = |lamport: u64, nonce: u64| {
+            let command = EntityTranslateCommand::new(entity, [1.0, 0.0, 0.0]);
+            let entry = CommandEntry::new(
+                CommandId::new(lamport, AuthorId(12)),
+                crate::network::current_time_millis(),
+                CommandPayload::new(
+                    CMD_ENTITY_TRANSLATE,
+                    CommandScope::Entity(entity),
+                    serde_json::to_vec(&command).unwrap(),
+                ),
+                ConflictStrategy::Merge,
+                CommandAuthor::new(AuthorId(12), CommandRole::Editor),
+                None,
+            );
+            let batch = CommandBatch {
+                sequence: nonce,
+                nonce,
+                timestamp_ms: 0,
+                author: AuthorId(12),
+                entries: vec![entry],
+            };
+            CommandPacket::from_batch(&batch).expect("packet serialize")
+        };
+
+        let applied = pipeline
+            .integrate_remote_packet(&translate(20, 1))
+            .expect("integrate remote");
+        assert!(applied.is_empty());
+        assert_eq!(
+            pipeline
+                .metrics_snapshot()
+                .conflict_rejections
+                .get(&ConflictStrategy::Merge),
+            Some(&1)
+        );
+
+        assert_eq!(pipeline.release_entity_locks(&AuthorId(0)), vec![entity]);
+        let applied = pipeline
+            .integrate_remote_packet(&translate(21, 2))
+            .expect("integrate remote");
+        assert_eq!(applied.len(), 1);
+    }
+
+    #[test]
+    fn locked_entities_cannot_be_reached_through_another_scope() {
+        let mut world = World::new();
+        world.register_component::<Transform>();
+        let entity = world.spawn();
+        world
+            .insert(entity, Transform::default())
+            .expect("insert transform");
+        let handle = EntityHandle::from(entity);
+        let position = Transform::default().position;
+
+        let mut pipeline = CommandPipeline::new();
+        pipeline
+            .record_entity_lock(handle, 60_000)
+            .expect("acquire lock");
+
+        // The payload moves the locked entity, but the entry claims the global scope.
+        let command = EntityTranslateCommand::new(handle, [1.0, 0.0, 0.0]);
+        let entry = CommandEntry::new(
+            CommandId::new(20, AuthorId(12)),
+            crate::network::current_time_millis(),
+            CommandPayload::new(
+                CMD_ENTITY_TRANSLATE,
+                CommandScope::Global,
+                serde_json::to_vec(&command).unwrap(),
+            ),
+            ConflictStrategy::Merge,
+            CommandAuthor::new(AuthorId(12), CommandRole::Editor),
+            None,
+        );
+        let batch = CommandBatch {
+            sequence: 1,
+            nonce: 1,
+            timestamp_ms: 0,
+            author: AuthorId(12),
+            entries: vec![entry],
+        };
+        let packet = CommandPacket::from_batch(&batch).expect("packet serialize");
+
+        for entry in pipeline
+            .integrate_remote_packet(&packet)
+            .expect("integrate remote")
+        {
+            assert!(matches!(
+                pipeline.apply_entry(&mut world, &entry, None),
+                Err(CommandApplyError::InvalidScope(CommandScope::Global))
+            ));
+        }
+        assert_eq!(
+            world
+                .get::<Transform>(entity)
+                .map(|transform| transform.position),
+            Some(position)
+        );
+    }
+
+    #[test]
+    fn concurrent_selection_edits_both_apply() {
+        let entity = |index: u32| EntityHandle {
+            index,
+            generation: 0,
+        };
+        let mut pipeline = CommandPipeline::new();
+        pipeline
+            .record(&SelectionAddCommand::new(vec![entity(1)]))
+            .expect("local select");
+        pipeline
+            .record(&SelectionAddCommand::new(vec![entity(2)]))
+            .expect("local select");
+
+        let command = SelectionAddCommand::new(vec![entity(3)]);
+        let batch = CommandBatch {
+            sequence: 1,
+            nonce: 1,
+            timestamp_ms: 0,
+            author: AuthorId(12),
+            entries: vec![CommandEntry::new(
+                CommandId::new(1, AuthorId(12)),
+                crate::network::current_time_millis(),
+                CommandPayload::new(
+                    CMD_SELECTION_ADD,
+                    CommandScope::Global,
+                    serde_json::to_vec(&command).unwrap(),
+                ),
+                SelectionAddCommand::strategy(),
+                CommandAuthor::new(AuthorId(12), CommandRole::Editor),
+                None,
+            )],
+        };
+        let applied = pipeline
+            .integrate_remote_packet(&CommandPacket::from_batch(&batch).unwrap())
+            .expect("integrate remote");
+        assert_eq!(applied.len(), 1);
+    }
+
+    #[test]
+    fn oversized_commands_travel_as_fragments() {
+        let metadata = HashMap::from([("blob".to_string(), "x".repeat(200 * 1024))]);
+        let mut pipeline = CommandPipeline::new();
+        pipeline
+            .record_mesh_vertex_create(None, [0.0, 1.0, 0.0], metadata.clone())
+            .expect("append large command");
+        let packets = pipeline.drain_packets();
+        assert!(packets.len() > 1);
+        assert!(packets.iter().all(|packet| packet.fragment.is_some()));
+        assert!(packets[0].decode().is_err());
+
+        let command = VertexCreateCommand::new([0.0, 1.0, 0.0], metadata.clone());
+        let batch = |nonce: u64, signature: Option<CommandSignature>| CommandBatch {
+            sequence: nonce,
+            nonce,
+            timestamp_ms: 0,
+            author: AuthorId(12),
+            entries: vec![CommandEntry::new(
+                CommandId::new(nonce + 2, AuthorId(12)),
+                crate::network::current_time_millis(),
+                CommandPayload::new(
+                    CMD_MESH_VERTEX_CREATE,
+                    CommandScope::Global,
+                    serde_json::to_vec(&command).unwrap(),
+                ),
+                ConflictStrategy::Merge,
+                CommandAuthor::new(AuthorId(12), CommandRole::Editor),
+                signature,
+            )],
+        };
+
+        // Mesh edits must be signed.
+        let mut receiver = CommandPipeline::new();
+        let unsigned =
+            CommandPacket::encode_fragmented(&batch(1, None), PacketEncoding::Json).unwrap();
+        let (last, rest) = unsigned.split_last().unwrap();
+        for fragment in rest {
+            receiver.integrate_remote_packet(fragment).unwrap();
+        }
+        assert!(matches!(
+            receiver.integrate_remote_packet(last),
+            Err(CommandLogError::SignatureMissing(_))
+        ));
+
+        let signed = batch(2, Some(CommandSignature(vec![0; 64])));
+        let mut fragments =
+            CommandPacket::encode_fragmented(&signed, PacketEncoding::Json).expect("fragment");
+        fragments.rotate_left(1);
+
+        let (last, rest) = fragments.split_last().unwrap();
+        for fragment in rest {
+            assert!(
+                receiver
+                    .integrate_remote_packet(fragment)
+                    .unwrap()
+                    .is_empty()
+            );
+        }
+        let applied = receiver.integrate_remote_packet(last).expect("integrate");
+        assert_eq!(applied.len(), 1);
+        let decoded: VertexCreateCommand =
+            serde_json::from_slice(&applied[0].payload.data).expect("decode vertex");
+        assert_eq!(decoded.metadata, metadata);
+    }
+
     #[test]
     fn integrates_remote_packet_and_updates_lamport() {
         let mut pipeline = CommandPipeline::new();
@@ -654,4 +1585,176 @@ mod tests {
         assert_eq!(updated.total_appended, 1);
         assert_eq!(updated.queue_depth, 5);
     }
+
+    #[test]
+    fn coalesced_drag_commits_one_entry_and_streams_previews() {
+        let mut world = World::new();
+        world.register_component::<Transform>();
+        let entity = world.spawn();
+        world
+            .insert(entity, Transform::default())
+            .expect("insert transform");
+        let handle = EntityHandle::from(entity);
+
+        // More increments than the default rate-limit burst allows as separate entries.
+        let mut pipeline = CommandPipeline::new();
+        for _ in 0..200 {
+            pipeline
+                .submit_coalesced(
+                    &mut world,
+                    &EntityTranslateCommand::new(handle, [0.01, 0.0, 0.0]),
+                    None,
+                )
+                .expect("coalesce translate");
+        }
+        assert!(pipeline.drain_packets().is_empty());
+        let previews = pipeline.drain_previews();
+        assert_eq!(previews.len(), 200);
+        assert!(
+            previews
+                .windows(2)
+                .all(|pair| pair[0].sequence < pair[1].sequence)
+        );
+        assert!((previews[199].position[0] - 2.0).abs() < 1e-3);
+
+        assert!(pipeline.flush_coalesced(&mut world).expect("flush drag"));
+        assert!(
+            !pipeline
+                .flush_coalesced(&mut world)
+                .expect("nothing pending")
+        );
+        let entries: Vec<_> = pipeline
+            .drain_packets()
+            .iter()
+            .flat_map(|packet| packet.decode().expect("decode packet").entries)
+            .collect();
+        assert_eq!(entries.len(), 1);
+        let committed: EntityTranslateCommand =
+            serde_json::from_slice(&entries[0].payload.data).expect("decode translate");
+        assert!((committed.delta[0] - 2.0).abs() < 1e-3);
+        assert!((world.get::<Transform>(entity).unwrap().position[0] - 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn records_commit_the_open_drag_first_and_rejected_drags_roll_back() {
+        let mut world = World::new();
+        world.register_component::<Transform>();
+        let entity = world.spawn();
+        world
+            .insert(entity, Transform::default())
+            .expect("insert transform");
+        let handle = EntityHandle::from(entity);
+        let start = Transform::default().position;
+        let drag = EntityTranslateCommand::new(handle, [0.5, 0.0, 0.0]);
+
+        let mut pipeline = CommandPipeline::new();
+        pipeline
+            .submit_coalesced(&mut world, &drag, None)
+            .expect("start drag");
+        pipeline
+            .record(&SelectionAddCommand::new(vec![handle]))
+            .expect("record selection");
+        let types: Vec<String> = pipeline
+            .drain_packets()
+            .iter()
+            .flat_map(|packet| packet.decode().expect("decode packet").entries)
+            .map(|entry| entry.payload.command_type)
+            .collect();
+        assert_eq!(types, [CMD_ENTITY_TRANSLATE, CMD_SELECTION_ADD]);
+
+        // Someone else locks the entity mid-drag; the release is refused and undone.
+        pipeline
+            .submit_coalesced(&mut world, &drag, None)
+            .expect("second drag");
+        let lock = EntityLockCommand::new(handle, 60_000);
+        let batch = CommandBatch {
+            sequence: 1,
+            nonce: 1,
+            timestamp_ms: 0,
+            author: AuthorId(12),
+            entries: vec![CommandEntry::new(
+                CommandId::new(40, AuthorId(12)),
+                crate::network::current_time_millis(),
+                CommandPayload::new(
+                    CMD_ENTITY_LOCK,
+                    CommandScope::Entity(handle),
+                    serde_json::to_vec(&lock).unwrap(),
+                ),
+                ConflictStrategy::Merge,
+                CommandAuthor::new(AuthorId(12), CommandRole::Editor),
+                None,
+            )],
+        };
+        pipeline
+            .integrate_remote_packet(&CommandPacket::from_batch(&batch).unwrap())
+            .expect("remote lock");
+        assert!(matches!(
+            pipeline.flush_coalesced(&mut world),
+            Err(CommandLogError::EntityLocked { .. })
+        ));
+        let position = world.get::<Transform>(entity).unwrap().position;
+        assert_eq!(position, [start[0] + 0.5, start[1], start[2]]);
+        assert!(pipeline.drain_packets().is_empty());
+    }
+
+    #[test]
+    fn refused_drags_do_not_hold_back_the_next_command() {
+        let mut world = World::new();
+        world.register_component::<Transform>();
+        let entity = world.spawn();
+        world
+            .insert(entity, Transform::default())
+            .expect("insert transform");
+        let handle = EntityHandle::from(entity);
+        let start = Transform::default().position;
+
+        let mut pipeline = CommandPipeline::new();
+        pipeline
+            .submit_coalesced(
+                &mut world,
+                &EntityTranslateCommand::new(handle, [0.5, 0.0, 0.0]),
+                None,
+            )
+            .expect("start drag");
+        let lock = EntityLockCommand::new(handle, 60_000);
+        let batch = CommandBatch {
+            sequence: 1,
+            nonce: 1,
+            timestamp_ms: 0,
+            author: AuthorId(12),
+            entries: vec![CommandEntry::new(
+                CommandId::new(40, AuthorId(12)),
+                crate::network::current_time_millis(),
+                CommandPayload::new(
+                    CMD_ENTITY_LOCK,
+                    CommandScope::Entity(handle),
+                    serde_json::to_vec(&lock).unwrap(),
+                ),
+                ConflictStrategy::Merge,
+                CommandAuthor::new(AuthorId(12), CommandRole::Editor),
+                None,
+            )],
+        };
+        pipeline
+            .integrate_remote_packet(&CommandPacket::from_batch(&batch).unwrap())
+            .expect("remote lock");
+
+        // The drag is refused and undone; the selection still goes ahead.
+        pipeline
+            .submit(&mut world, &SelectionAddCommand::new(vec![handle]), None)
+            .expect("selection goes ahead");
+        assert!(matches!(
+            pipeline.drain_drag_errors().as_slice(),
+            [CommandLogError::EntityLocked { .. }]
+        ));
+        assert!(pipeline.drain_drag_errors().is_empty());
+        assert_eq!(world.get::<Transform>(entity).unwrap().position, start);
+        let types: Vec<String> = pipeline
+            .drain_packets()
+            .iter()
+            .flat_map(|packet| packet.decode().expect("decode packet").entries)
+            .map(|entry| entry.payload.command_type)
+            .collect();
+        assert_eq!(types, [CMD_SELECTION_ADD]);
+    }
 }
@@ -1,25 +1,46 @@
+mod builtin_commands;
 mod commands;
+mod ordering;
+mod replay;
+mod replication;
 pub use self::commands::CommandMetricsSnapshot;
-pub use self::commands::CommandPipeline;
+pub use self::commands::{CoalesceConfig, CommandPipeline};
+pub use self::replay::{EntityState, MeshSummary, WorldState};
+pub use self::replication::ReplicationMode;
 pub mod schedule;
-use crate::ecs::World;
+pub mod typed_commands;
+pub use self::typed_commands::{
+    Checkpoint, CoalescingCommand, CommandApplyError, CommandContext, CommandHandlerRegistry,
+    TypedCommand,
+};
+use crate::ecs::{Entity, World};
 use crate::editor::commands::{
-    CMD_ENTITY_ROTATE, CMD_ENTITY_SCALE, CMD_ENTITY_TRANSLATE, CMD_MESH_EDGE_EXTRUDE,
-    CMD_MESH_FACE_SUBDIVIDE, CMD_MESH_VERTEX_CREATE, CMD_SELECTION_HIGHLIGHT, CMD_TOOL_ACTIVATE,
-    CMD_TOOL_DEACTIVATE, EdgeExtrudeCommand, EntityRotateCommand, EntityScaleCommand,
-    EntityTranslateCommand, FaceSubdivideCommand, SelectionHighlightCommand, ToolActivateCommand,
-    ToolDeactivateCommand, VertexCreateCommand,
+    CMD_ENTITY_ROTATE, CMD_ENTITY_SCALE, CMD_ENTITY_TRANSLATE, CreatePrimitiveCommand,
+    EntityRotateCommand, EntityScaleCommand, EntityTranslateCommand, ImportMeshCommand, Quaternion,
+    SelectionAddCommand, SelectionClearCommand, SelectionRemoveCommand, SelectionToggleCommand,
 };
+use crate::editor::gizmo::{GizmoCommand, GizmoTarget};
+use crate::editor::mesh_io::{self, ExportMesh, MeshData, MeshFormat, MeshIoError, NodeTransform};
+use crate::editor::selection::{GroupTransform, MemberPose, SelectionBox, SelectionRay};
 use crate::editor::telemetry::{
     FrameTelemetry, TelemetryReplicator, TelemetrySurface, WebRtcTelemetry,
 };
 #[cfg(feature = "network-quic")]
 use crate::editor::telemetry::{WebRtcIceMetrics, WebRtcLinkMetrics, WebRtcPeerSample};
-use crate::editor::{CommandOutbox, CommandTransportQueue};
+use crate::editor::{
+    CommandOutbox, CommandTransportQueue, EditableMesh, EntityLock, PivotMode, PrimitiveShape,
+    SelectionMode, SelectionSet, TransformGizmo,
+};
 use crate::network::EntityHandle;
+use crate::network::command_log::AuthorId;
 use crate::network::command_log::{CommandBatch, CommandEntry, CommandPacket, CommandScope};
-#[cfg(feature = "network-quic")]
 use crate::network::current_time_millis;
+use crate::network::interpolation::{InterpolationConfig, TransformSample};
+use crate::network::network_id::{
+    NetworkIdAllocator, SharedEntityMap, lock_entity_map, network_handle,
+};
+use crate::network::preview::{PREVIEW_TIMEOUT_MS, PreviewPacket};
+use crate::network::replication::ReplicationRegistry;
 #[cfg(feature = "network-quic")]
 use crate::network::signaling::{
     IceCandidate, PeerId, RoomId, SessionDescription, SignalingClient, SignalingError,
@@ -27,7 +48,7 @@ use crate::network::signaling::{
 };
 #[cfg(feature = "network-quic")]
 use crate::network::transport::{
-    CommandTransport, TransportError, TransportSession, WebRtcTransport,
+    CommandTransport, ReplicationFrame, TransportError, TransportSession, WebRtcTransport,
 };
 #[cfg(feature = "network-quic")]
 use crate::network::voice::{
@@ -45,13 +66,15 @@ use crate::vr::{
 use schedule::{Scheduler, Stage, System};
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "network-quic")]
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
+use std::collections::HashSet;
 #[cfg(feature = "network-quic")]
 use std::env;
 #[cfg(feature = "network-quic")]
 use std::f32::consts::TAU;
 #[cfg(feature = "network-quic")]
 use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
 #[cfg(feature = "network-quic")]
 use std::process;
 use std::sync::{Arc, Mutex};
@@ -140,6 +163,13 @@ pub struct Engine {
     command_entity: Option<crate::ecs::Entity>,
     input_provider: Arc<Mutex<Box<dyn VrInputProvider>>>,
     command_pipeline: Arc<Mutex<CommandPipeline>>,
+    /// The pipeline's entity map, also mirrored into by replication.
+    entity_map: SharedEntityMap,
+    network_ids: NetworkIdAllocator,
+    replication_registry: ReplicationRegistry,
+    replication: replication::ReplicationState,
+    replication_peer: Option<AuthorId>,
+    interpolation_config: InterpolationConfig,
     #[cfg(feature = "network-quic")]
     command_transport: Option<CommandTransport>,
     #[cfg(feature = "network-quic")]
@@ -169,6 +199,8 @@ pub struct Engine {
     #[cfg(feature = "network-quic")]
     active_webrtc_peer: Option<PeerId>,
     #[cfg(feature = "network-quic")]
+    peer_authors: HashMap<PeerId, HashSet<AuthorId>>,
+    #[cfg(feature = "network-quic")]
     webrtc_ice_servers: Vec<IceServerConfig>,
     #[cfg(feature = "network-quic")]
     voice_encoder: Option<OpusCodec>,
@@ -248,7 +280,9 @@ impl Engine {
         let scheduler = Scheduler::default();
         let renderer = Self::build_renderer(config);
         let input_provider = build_input_provider();
-        let command_pipeline = Arc::new(Mutex::new(CommandPipeline::new()));
+        let command_pipeline = CommandPipeline::new();
+        let entity_map = Arc::clone(command_pipeline.entity_map());
+        let command_pipeline = Arc::new(Mutex::new(command_pipeline));
         #[cfg(feature = "network-quic")]
         let (webrtc_event_tx, webrtc_event_rx) = unbounded_channel();
         #[cfg(feature = "network-quic")]
@@ -266,6 +300,12 @@ impl Engine {
             command_entity: None,
             input_provider,
             command_pipeline,
+            entity_map,
+            network_ids: NetworkIdAllocator::new(0),
+            replication_registry: replication::default_replication_registry(),
+            replication: replication::ReplicationState::Off,
+            replication_peer: None,
+            interpolation_config: InterpolationConfig::default(),
             #[cfg(feature = "network-quic")]
             command_transport: None,
             #[cfg(feature = "network-quic")]
@@ -295,6 +335,8 @@ impl Engine {
             #[cfg(feature = "network-quic")]
             active_webrtc_peer: None,
             #[cfg(feature = "network-quic")]
+            peer_authors: HashMap::new(),
+            #[cfg(feature = "network-quic")]
             webrtc_ice_servers: load_webrtc_ice_servers(),
             #[cfg(feature = "network-quic")]
             voice_encoder: None,
@@ -335,9 +377,26 @@ impl Engine {
         self.ensure_network_runtime();
         if let Ok(mut pipeline) = self.command_pipeline.lock() {
             pipeline.attach_transport_metrics(transport.metrics_handle());
+            pipeline.set_packet_encoding(transport.packet_encoding());
+        }
+        self.forget_replication_peer();
+        if let CommandTransport::Quic(session) = &transport {
+            let host = AuthorId(session.handshake().host_author);
+            self.set_network_prefix(session.handshake().network_prefix);
+            self.set_session_host(host.clone());
+            let local = self
+                .command_pipeline
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .local_author()
+                .clone();
+            if host != local {
+                self.set_replication_peer(host);
+            }
         }
 
         self.command_transport = Some(transport);
+        self.restart_replication();
     }
 
     #[cfg(feature = "network-quic")]
@@ -574,6 +633,310 @@ impl Engine {
         self.scheduler.world_mut()
     }
 
+    /// Makes a [`TypedCommand`] replicable: remote entries of this type are decoded and
+    /// applied to the world, and local ones can be issued with [`Engine::submit_command`].
+    pub fn register_command<C: TypedCommand>(&mut self) {
+        self.command_pipeline
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .register_command::<C>();
+    }
+
+    /// Records `command` in the command log and applies it to the local world.
+    pub fn submit_command<C: TypedCommand>(
+        &mut self,
+        command: C,
+    ) -> Result<(), crate::network::command_log::CommandLogError> {
+        let mut pipeline = self
+            .command_pipeline
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        pipeline.submit(self.scheduler.world_mut(), &command, self.command_entity)
+    }
+
+    /// Spawns a primitive mesh entity through the command log so peers create the same
+    /// entity. Returns its network handle.
+    pub fn create_primitive(
+        &mut self,
+        shape: PrimitiveShape,
+        position: [f32; 3],
+    ) -> Result<EntityHandle, crate::network::command_log::CommandLogError> {
+        let (entity, handle) = self.allocate_network_entity();
+        self.submit_command(CreatePrimitiveCommand::new(handle, shape, position))
+            .inspect_err(|_| self.release_network_entity(entity))?;
+        Ok(handle)
+    }
+
+    /// The prefix of the ids this peer allocates; the host assigns it at session start.
+    pub fn set_network_prefix(&mut self, prefix: u32) {
+        if self.network_ids.prefix() != prefix {
+            self.network_ids = NetworkIdAllocator::new(prefix);
+        }
+    }
+
+    /// The peer whose word is final for host-authoritative entities; set at session start.
+    pub fn set_session_host(&mut self, host: AuthorId) {
+        self.command_pipeline
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .authority_mut()
+            .set_host(host);
+    }
+
+    /// Spawns an entity under a fresh network id, so commands can name it before it
+    /// first replicates.
+    fn allocate_network_entity(&mut self) -> (Entity, EntityHandle) {
+        let (entity, id) = self.network_ids.spawn(
+            self.scheduler.world_mut(),
+            &mut lock_entity_map(&self.entity_map),
+        );
+        (entity, id.into())
+    }
+
+    fn release_network_entity(&mut self, entity: Entity) {
+        lock_entity_map(&self.entity_map).remove_entity(entity);
+        let _ = self.scheduler.world_mut().despawn(entity);
+    }
+
+    /// The local entity a command or selection handle names.
+    fn resolve_entity(&self, handle: EntityHandle) -> Entity {
+        lock_entity_map(&self.entity_map).resolve(handle)
+    }
+
+    /// Replaces the VR input source, e.g. with a scripted provider in tests or tools.
+    pub fn set_input_provider(&mut self, provider: Box<dyn VrInputProvider>) {
+        *self
+            .input_provider
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = provider;
+    }
+
+    /// The controller gizmo, for switching modes or adjusting snapping.
+    pub fn transform_gizmo_mut(&mut self) -> Option<&mut TransformGizmo> {
+        let editor_entity = self.command_entity?;
+        self.scheduler
+            .world_mut()
+            .get_mut::<TransformGizmo>(editor_entity)
+    }
+
+    /// The editor's current selection set.
+    pub fn selection(&self) -> Option<&SelectionSet> {
+        let editor_entity = self.command_entity?;
+        self.scheduler
+            .world()
+            .get::<EditorSelection>(editor_entity)
+            .map(|selection| &selection.set)
+    }
+
+    /// The pose to draw `handle` at: a peer's in-flight drag while one is showing,
+    /// otherwise the committed transform.
+    pub fn displayed_transform(&self, handle: EntityHandle) -> Option<TransformSample> {
+        displayed_transform(self.scheduler.world(), self.resolve_entity(handle))
+    }
+
+    /// Changes the selection through the command log. `Replace` clears first.
+    pub fn select(
+        &mut self,
+        entities: Vec<EntityHandle>,
+        mode: SelectionMode,
+    ) -> Result<(), crate::network::command_log::CommandLogError> {
+        match mode {
+            SelectionMode::Replace => {
+                self.submit_command(SelectionClearCommand::new())?;
+                if entities.is_empty() {
+                    return Ok(());
+                }
+                self.submit_command(SelectionAddCommand::new(entities))
+            }
+            SelectionMode::Add => self.submit_command(SelectionAddCommand::new(entities)),
+            SelectionMode::Remove => self.submit_command(SelectionRemoveCommand::new(entities)),
+            SelectionMode::Toggle => self.submit_command(SelectionToggleCommand::new(entities)),
+        }
+    }
+
+    /// Selects every transformed entity inside `volume`. The picked list is replicated
+    /// rather than the box, so peers agree even if their transforms lag.
+    pub fn select_in_box(
+        &mut self,
+        volume: SelectionBox,
+        mode: SelectionMode,
+    ) -> Result<Vec<EntityHandle>, crate::network::command_log::CommandLogError> {
+        let picked = volume.pick(self.selectable_positions());
+        self.select(picked.clone(), mode)?;
+        Ok(picked)
+    }
+
+    /// Selects the nearest transformed entity along the controller's pointing ray.
+    pub fn select_with_ray(
+        &mut self,
+        pose: &TrackedPose,
+        mode: SelectionMode,
+    ) -> Result<Option<EntityHandle>, crate::network::command_log::CommandLogError> {
+        let ray = SelectionRay::from_pose(pose);
+        let picked = ray.pick(self.selectable_positions(), SelectionRay::DEFAULT_RADIUS);
+        if let Some(entity) = picked {
+            self.select(vec![entity], mode)?;
+        }
+        Ok(picked)
+    }
+
+    /// Applies `transform` to every selected entity around the `pivot`. Each member is
+    /// moved with the per-entity transform commands; if any member is locked by another
+    /// author nothing moves.
+    pub fn transform_selection(
+        &mut self,
+        transform: GroupTransform,
+        pivot: PivotMode,
+    ) -> Result<(), crate::network::command_log::CommandLogError> {
+        let Some(selection) = self.selection() else {
+            return Ok(());
+        };
+        let active = selection.active();
+        let world = self.scheduler.world();
+        let members: Vec<(EntityHandle, MemberPose)> = selection
+            .iter()
+            .filter_map(|handle| {
+                let transform = world.get::<Transform>(self.resolve_entity(handle))?;
+                let [x, y, z, w] = transform.rotation;
+                let pose = MemberPose {
+                    position: transform.position,
+                    rotation: Quaternion::new(x, y, z, w),
+                    scale: transform.scale,
+                };
+                Some((handle, pose))
+            })
+            .collect();
+        let positions: Vec<(EntityHandle, [f32; 3])> = members
+            .iter()
+            .map(|(handle, pose)| (*handle, pose.position))
+            .collect();
+        let pivot = pivot.resolve(&positions, active);
+
+        let mut translates = Vec::new();
+        let mut rotates = Vec::new();
+        let mut scales = Vec::new();
+        for (handle, before) in members {
+            let after = transform.apply(pivot, before);
+            let delta = [
+                after.position[0] - before.position[0],
+                after.position[1] - before.position[1],
+                after.position[2] - before.position[2],
+            ];
+            if delta != [0.0; 3] {
+                translates.push(EntityTranslateCommand::new(handle, delta));
+            }
+            if after.rotation != before.rotation {
+                let rotation = commands::normalize_quaternion(after.rotation);
+                rotates.push(EntityRotateCommand::new(handle, rotation));
+            }
+            if after.scale != before.scale {
+                scales.push(EntityScaleCommand::new(handle, after.scale));
+            }
+        }
+
+        {
+            let mut pipeline = self
+                .command_pipeline
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            translates
+                .iter()
+                .try_for_each(|command| pipeline.authorize(command))?;
+            rotates
+                .iter()
+                .try_for_each(|command| pipeline.authorize(command))?;
+            scales
+                .iter()
+                .try_for_each(|command| pipeline.authorize(command))?;
+            pipeline.ensure_capacity(translates.len() + rotates.len() + scales.len())?;
+        }
+        for command in translates {
+            self.submit_command(command)?;
+        }
+        for command in rotates {
+            self.submit_command(command)?;
+        }
+        for command in scales {
+            self.submit_command(command)?;
+        }
+        Ok(())
+    }
+
+    /// Transformed entities in index order, so picks are stable across runs.
+    fn selectable_positions(&self) -> Vec<(EntityHandle, [f32; 3])> {
+        let world = self.scheduler.world();
+        let mut positions: Vec<(EntityHandle, [f32; 3])> = world
+            .component_entries::<Transform>()
+            .into_iter()
+            .map(|(entity, transform)| (network_handle(world, entity), transform.position))
+            .collect();
+        positions.sort_by_key(|(handle, _)| (handle.index, handle.generation));
+        positions
+    }
+
+    /// Imports every mesh in `path` (OBJ, GLB or PLY) as a new entity through the
+    /// command log, so peers receive the geometry rather than the file.
+    pub fn import_mesh(&mut self, path: &Path) -> Result<Vec<EntityHandle>, MeshIoError> {
+        let format = MeshFormat::from_path(path)
+            .ok_or_else(|| MeshIoError::UnknownFormat(path.display().to_string()))?;
+        self.import_mesh_bytes(&std::fs::read(path)?, format)
+    }
+
+    pub fn import_mesh_bytes(
+        &mut self,
+        bytes: &[u8],
+        format: MeshFormat,
+    ) -> Result<Vec<EntityHandle>, MeshIoError> {
+        let mut handles = Vec::new();
+        for imported in mesh_io::import_bytes(bytes, format)? {
+            if imported.skipped_faces > 0 {
+                log::warn!(
+                    "[mesh_io] skipped {} unsupported faces in {}",
+                    imported.skipped_faces,
+                    imported.name.as_deref().unwrap_or("unnamed mesh")
+                );
+            }
+            let (entity, handle) = self.allocate_network_entity();
+            let data = MeshData::from_mesh(&imported.mesh);
+            self.submit_command(ImportMeshCommand::new(handle, data, imported.transform))
+                .inspect_err(|_| self.release_network_entity(entity))?;
+            handles.push(handle);
+        }
+        Ok(handles)
+    }
+
+    /// Writes the meshes of `entities` to `path`, using each entity's transform.
+    /// Entities without an [`EditableMesh`] are skipped.
+    pub fn export_meshes(&self, path: &Path, entities: &[EntityHandle]) -> Result<(), MeshIoError> {
+        let world = self.scheduler.world();
+        let names: Vec<String> = entities
+            .iter()
+            .map(|handle| format!("entity_{}", self.resolve_entity(*handle).index()))
+            .collect();
+        let meshes: Vec<ExportMesh<'_>> = entities
+            .iter()
+            .zip(&names)
+            .filter_map(|(handle, name)| {
+                let entity = self.resolve_entity(*handle);
+                let mesh = world.get::<EditableMesh>(entity)?;
+                let transform = world
+                    .get::<Transform>(entity)
+                    .map(|transform| NodeTransform {
+                        translation: transform.position,
+                        rotation: transform.rotation,
+                        scale: transform.scale,
+                    })
+                    .unwrap_or_default();
+                Some(ExportMesh {
+                    name,
+                    mesh,
+                    transform,
+                })
+            })
+            .collect();
+        mesh_io::export_file(path, &meshes)
+    }
+
     fn register_core_systems(&mut self) {
         {
             let world = self.scheduler.world_mut();
@@ -588,6 +951,9 @@ impl Engine {
             world.register_component::<CommandOutbox>();
             world.register_component::<CommandTransportQueue>();
             world.register_component::<EditorToolState>();
+            world.register_component::<EditableMesh>();
+            world.register_component::<TransformGizmo>();
+            world.register_component::<TransformPreview>();
         }
 
         let stats_entity = {
@@ -638,6 +1004,12 @@ impl Engine {
             world
                 .insert(editor_entity, EditorToolState::default())
                 .expect("editor tool state component should insert");
+            world
+                .insert(editor_entity, EditableMesh::default())
+                .expect("editor scratch mesh should insert");
+            world
+                .insert(editor_entity, TransformGizmo::default())
+                .expect("transform gizmo should insert");
         }
 
         let input_source = Arc::clone(&self.input_provider);
@@ -737,23 +1109,98 @@ impl Engine {
                     selection.frames_since_change = 0;
                     selection.highlight_active = !selection.highlight_active;
 
-                    if let Some(primary) = selection.primary {
-                        let handle = EntityHandle::from(primary);
-                        if let Ok(mut pipeline) = pipeline_handle.lock()
-                            && let Err(err) = pipeline
-                                .record_selection_highlight(handle, selection.highlight_active)
-                        {
-                            eprintln!("[commands] failed to record highlight command: {err}");
-                        }
+                    if let Some(handle) = selection.set.active()
+                        && let Ok(mut pipeline) = pipeline_handle.lock()
+                        && let Err(err) =
+                            pipeline.record_selection_highlight(handle, selection.highlight_active)
+                    {
+                        eprintln!("[commands] failed to record highlight command: {err}");
+                    }
+                }
+            }
+        });
+
+        let gizmo_pipeline = Arc::clone(&self.command_pipeline);
+        let gizmo_entities = Arc::clone(&self.entity_map);
+        self.add_system_fn(Stage::Editor, "transform_gizmo", move |world, delta| {
+            let Some(controller) = world
+                .get::<ControllerState>(right_controller_entity)
+                .copied()
+            else {
+                return;
+            };
+            let target = world
+                .get::<EditorSelection>(editor_entity)
+                .and_then(|selection| selection.set.active())
+                .and_then(|handle| {
+                    let entity = lock_entity_map(&gizmo_entities).resolve(handle);
+                    let transform = world.get::<Transform>(entity)?;
+                    let [x, y, z, w] = transform.rotation;
+                    Some(GizmoTarget {
+                        entity: handle,
+                        position: transform.position,
+                        rotation: Quaternion::new(x, y, z, w),
+                        scale: transform.scale,
+                    })
+                });
+            let Some(gizmo) = world.get_mut::<TransformGizmo>(editor_entity) else {
+                return;
+            };
+            let was_dragging = gizmo.is_dragging();
+            let commands = gizmo.update(&controller, target, delta);
+            let released = was_dragging && !gizmo.is_dragging();
+            if commands.is_empty() && !released {
+                return;
+            }
+
+            let mut pipeline = gizmo_pipeline
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let editor = Some(editor_entity);
+            for command in commands {
+                let result = match &command {
+                    GizmoCommand::Translate(command) => {
+                        pipeline.submit_coalesced(world, command, editor)
+                    }
+                    GizmoCommand::Rotate(command) => {
+                        pipeline.submit_coalesced(world, command, editor)
+                    }
+                    GizmoCommand::Scale(command) => {
+                        pipeline.submit_coalesced(world, command, editor)
                     }
+                };
+                if let Err(err) = result {
+                    log::warn!("[editor] gizmo command rejected: {err}");
                 }
             }
+            if released && let Err(err) = pipeline.flush_coalesced(world) {
+                log::warn!("[editor] failed to commit gizmo drag: {err}");
+            }
+            for err in pipeline.drain_drag_errors() {
+                log::warn!("[editor] failed to commit gizmo drag: {err}");
+            }
         });
 
+        self.add_system_fn(Stage::Editor, "expire_transform_previews", |world, _| {
+            let now = current_time_millis();
+            let expired: Vec<crate::ecs::Entity> = world
+                .component_entries::<TransformPreview>()
+                .into_iter()
+                .filter(|(_, preview)| {
+                    now.saturating_sub(preview.received_ms) >= PREVIEW_TIMEOUT_MS
+                })
+                .map(|(entity, _)| entity)
+                .collect();
+            for entity in expired {
+                world.remove::<TransformPreview>(entity);
+            }
+        });
+
+        let debug_entities = Arc::clone(&self.entity_map);
         self.add_parallel_system_fn(Stage::Editor, "editor_debug_view", move |world, _| {
             if let Some(selection) = world.get::<EditorSelection>(editor_entity)
-                && let Some(entity) = selection.primary
-                && let Some(transform) = world.get::<Transform>(entity)
+                && let Some(entity) = selection.primary(&debug_entities)
+                && let Some(transform) = displayed_transform(world, entity)
             {
                 println!(
                     "[editor] selection {:?} transform {:?} highlight {}",
@@ -900,8 +1347,10 @@ impl Engine {
         let kind = transport.kind();
         let metrics = transport.metrics_handle();
 
+        let encoding = transport.packet_encoding();
         if let Ok(mut pipeline) = self.command_pipeline.lock() {
             pipeline.attach_transport_metrics(metrics);
+            pipeline.set_packet_encoding(encoding);
         }
         self.command_transport = Some(transport);
         kind
@@ -923,6 +1372,9 @@ impl Engine {
     }
 
     fn close_transport(&mut self, transport: CommandTransport) {
+        if let Some(source) = self.transport_source(&transport) {
+            self.release_peer_locks(&source);
+        }
         let runtime = self.ensure_network_runtime();
         runtime.block_on(async move {
             transport.close().await;
@@ -2391,6 +2843,29 @@ impl Transform {
     }
 }
 
+/// Provisional pose shown while a peer drags an entity; the committed [`Transform`]
+/// is left untouched until the coalesced command arrives.
+#[derive(Debug, Clone, PartialEq)]
+struct TransformPreview {
+    author: AuthorId,
+    sequence: u64,
+    position: [f32; 3],
+    rotation: [f32; 4],
+    scale: [f32; 3],
+    received_ms: u64,
+}
+
+fn displayed_transform(world: &World, entity: Entity) -> Option<TransformSample> {
+    match world.get::<TransformPreview>(entity) {
+        Some(preview) => Some(TransformSample {
+            position: preview.position,
+            rotation: preview.rotation,
+            scale: preview.scale,
+        }),
+        None => world.get::<Transform>(entity).map(TransformSample::from),
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 struct Velocity {
     linear: [f32; 3],
@@ -2404,18 +2879,26 @@ impl Default for Velocity {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 struct EditorSelection {
-    primary: Option<crate::ecs::Entity>,
+    set: SelectionSet,
     frames_since_change: u32,
     highlight_interval: u32,
     highlight_active: bool,
 }
 
+impl EditorSelection {
+    /// The local entity of the active member of the selection set.
+    fn primary(&self, entities: &SharedEntityMap) -> Option<crate::ecs::Entity> {
+        let handle = self.set.active()?;
+        Some(lock_entity_map(entities).resolve(handle))
+    }
+}
+
 impl Default for EditorSelection {
     fn default() -> Self {
         Self {
-            primary: None,
+            set: SelectionSet::default(),
             frames_since_change: 0,
             highlight_interval: 120,
             highlight_active: true,
@@ -2498,10 +2981,8 @@ fn initialize_actor(world: &mut World) -> crate::ecs::Entity {
 }
 
 fn initialize_editor_state(world: &mut World, primary: crate::ecs::Entity) -> crate::ecs::Entity {
-    let selection = EditorSelection {
-        primary: Some(primary),
-        ..EditorSelection::default()
-    };
+    let mut selection = EditorSelection::default();
+    selection.set.add(network_handle(world, primary));
     let entity = world.spawn();
     world
         .insert(entity, selection)
@@ -2509,6 +2990,26 @@ fn initialize_editor_state(world: &mut World, primary: crate::ecs::Entity) -> cr
     entity
 }
 
+/// Drops the preview a committed transform command from the same author supersedes.
+fn clear_committed_preview(world: &mut World, entities: &SharedEntityMap, entry: &CommandEntry) {
+    let CommandScope::Entity(handle) = entry.payload.scope else {
+        return;
+    };
+    if !matches!(
+        entry.payload.command_type.as_str(),
+        CMD_ENTITY_TRANSLATE | CMD_ENTITY_ROTATE | CMD_ENTITY_SCALE
+    ) {
+        return;
+    }
+    let entity = lock_entity_map(entities).resolve(handle);
+    if world
+        .get::<TransformPreview>(entity)
+        .is_some_and(|preview| preview.author == entry.author.id)
+    {
+        world.remove::<TransformPreview>(entity);
+    }
+}
+
 fn sanitize_scale(mut scale: [f32; 3]) -> [f32; 3] {
     for axis in scale.iter_mut() {
         if !axis.is_finite() {
@@ -2557,6 +3058,14 @@ impl Engine {
         #[cfg(feature = "network-quic")]
         self.poll_remote_commands();
 
+        #[cfg(feature = "network-quic")]
+        self.poll_remote_previews();
+
+        #[cfg(feature = "network-quic")]
+        self.pump_replication();
+
+        let interpolation_health = self.interpolate_remote_transforms(current_time_millis());
+
         #[cfg(feature = "network-quic")]
         self.poll_signaling_events();
 
@@ -2566,6 +3075,8 @@ impl Engine {
         #[cfg(feature = "network-quic")]
         self.tick_voice_channels();
 
+        self.sync_entity_locks();
+
         #[cfg(feature = "network-quic")]
         let webrtc_metrics = {
             self.tick_webrtc_negotiation();
@@ -2627,11 +3138,25 @@ impl Engine {
             crate::ecs::Entity,
         )> = None;
 
+        #[cfg(feature = "network-quic")]
+        let mut previews_for_transport: Vec<PreviewPacket> = Vec::new();
+
         if let Ok(mut pipeline) = self.command_pipeline.lock() {
+            if let Err(err) = pipeline.poll_coalesced(self.scheduler.world_mut(), Instant::now()) {
+                log::warn!("[commands] failed to commit coalesced drag: {err}");
+            }
+            let previews = pipeline.drain_previews();
+            #[cfg(feature = "network-quic")]
+            {
+                previews_for_transport = previews;
+            }
+            #[cfg(not(feature = "network-quic"))]
+            drop(previews);
+
             let packets = pipeline.drain_packets();
             if !packets.is_empty() {
                 let mut decoded_batches: Vec<CommandBatch> = Vec::with_capacity(packets.len());
-                for packet in &packets {
+                for packet in packets.iter().filter(|packet| packet.fragment.is_none()) {
                     match packet.decode() {
                         Ok(batch) => decoded_batches.push(batch),
                         Err(err) => {
@@ -2654,21 +3179,19 @@ impl Engine {
                 if let Some(entity) = self.command_entity {
                     let mut packets_to_queue: Vec<CommandPacket> = Vec::new();
 
-                    if !decoded_batches.is_empty() {
-                        let mut outbox_packets = None;
-                        {
-                            let world = self.scheduler.world_mut();
-                            if let Some(outbox) = world.get_mut::<CommandOutbox>(entity) {
-                                outbox.ingest(decoded_batches);
-                                outbox_packets = Some(outbox.drain_packets());
-                            }
+                    let mut outbox_packets = None;
+                    {
+                        let world = self.scheduler.world_mut();
+                        if let Some(outbox) = world.get_mut::<CommandOutbox>(entity) {
+                            outbox.ingest_packets(packets.iter().cloned());
+                            outbox_packets = Some(outbox.drain_packets());
                         }
+                    }
 
-                        if let Some(mut drained) = outbox_packets
-                            && !drained.is_empty()
-                        {
-                            packets_to_queue.append(&mut drained);
-                        }
+                    if let Some(mut drained) = outbox_packets
+                        && !drained.is_empty()
+                    {
+                        packets_to_queue.append(&mut drained);
                     }
 
                     // If no packets were drained from the outbox, fall back to the original packets.
@@ -2727,6 +3250,22 @@ impl Engine {
             command_metrics_snapshot = Some(pipeline.metrics_snapshot());
         }
 
+        #[cfg(feature = "network-quic")]
+        if !previews_for_transport.is_empty()
+            && let Some(transport) = self.command_transport.as_ref()
+        {
+            let runtime = self.network_runtime.as_ref();
+            if let Some(runtime) = runtime
+                && let Err(err) =
+                    runtime.block_on(transport.send_preview_packets(&previews_for_transport))
+            {
+                log::debug!(
+                    "[commands] dropped {} previews: {err}",
+                    previews_for_transport.len()
+                );
+            }
+        }
+
         #[cfg(feature = "network-quic")]
         if let Some((packets_to_send, entity)) = packets_ready_for_transport {
             if self.command_transport.is_some() {
@@ -2763,6 +3302,7 @@ impl Engine {
         if let Some(sample) = telemetry_sample.as_mut() {
             sample.set_command_metrics(command_metrics_snapshot.clone());
             sample.set_webrtc_metrics(webrtc_metrics.clone());
+            sample.set_interpolation_health(interpolation_health.clone());
         }
 
         if let (Some(entity), Some(sample)) = (self.telemetry_entity, telemetry_sample) {
@@ -2780,6 +3320,7 @@ impl Engine {
                     latest.set_command_metrics(command_metrics_snapshot.clone());
                 }
                 latest.set_webrtc_metrics(webrtc_metrics.clone());
+                latest.set_interpolation_health(interpolation_health.clone());
                 if let Some(replicator) = world.get_mut::<TelemetryReplicator>(entity) {
                     replicator.publish(entity, &latest);
                 }
@@ -2822,10 +3363,169 @@ impl Engine {
                 continue;
             }
 
+            if let Some(source) = self
+                .command_transport
+                .as_ref()
+                .and_then(|transport| self.transport_source(transport))
+            {
+                let authors = self.peer_authors.entry(source).or_default();
+                authors.extend(applied_entries.iter().map(|entry| entry.author.id.clone()));
+            }
+
             self.apply_remote_entries(&applied_entries);
         }
     }
 
+    #[cfg(feature = "network-quic")]
+    fn poll_remote_previews(&mut self) {
+        let runtime = self.ensure_network_runtime();
+        loop {
+            let Some(transport) = self.command_transport.as_ref() else {
+                return;
+            };
+            match runtime.block_on(transport.receive_preview_packet(Duration::from_millis(0))) {
+                Ok(Some(packet)) => self.apply_remote_preview(&packet),
+                Ok(None) => return,
+                Err(err) => {
+                    log::debug!("[transport] failed to receive preview: {err}");
+                    return;
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "network-quic")]
+    fn pump_replication(&mut self) {
+        if self.replication_mode() == ReplicationMode::Off {
+            return;
+        }
+        let runtime = self.ensure_network_runtime();
+        // One transport reaches one peer; other peers' streams wait for their own.
+        let peer = self.replication_peer.clone();
+        let outgoing: Vec<_> = self
+            .outgoing_replication(Instant::now())
+            .into_iter()
+            .filter(|(to, _)| Some(to) == peer.as_ref())
+            .flat_map(|(_, messages)| messages)
+            .collect();
+        let Some(transport) = self.command_transport.as_ref() else {
+            return;
+        };
+        if !outgoing.is_empty()
+            && let Err(err) = runtime.block_on(transport.send_replication_messages(&outgoing))
+        {
+            log::error!(
+                "[replication] failed to send {} messages: {err}",
+                outgoing.len()
+            );
+        }
+
+        let mut ack = None;
+        loop {
+            let Some(transport) = self.command_transport.as_ref() else {
+                return;
+            };
+            let frame = match runtime
+                .block_on(transport.receive_replication_frame(Duration::from_millis(0)))
+            {
+                Ok(Some(frame)) => frame,
+                Ok(None) => break,
+                Err(err) => {
+                    log::error!("[replication] failed to receive frame: {err}");
+                    break;
+                }
+            };
+            match frame {
+                ReplicationFrame::Ack { sequence, resync } => {
+                    let Some(peer) = peer.as_ref() else {
+                        continue;
+                    };
+                    self.acknowledge_replication(peer, sequence);
+                    if resync {
+                        self.resync_replication(peer);
+                    }
+                }
+                ReplicationFrame::Message(message) => {
+                    if let Some((applied, _)) = self.receive_replication(message) {
+                        ack = Some(applied);
+                    }
+                }
+            }
+        }
+
+        let resync = ack.is_some() && self.take_replication_resync();
+        if let (Some(sequence), Some(transport)) = (ack, self.command_transport.as_ref())
+            && let Err(err) = runtime.block_on(transport.send_replication_ack(sequence, resync))
+        {
+            log::error!("[replication] failed to acknowledge {sequence}: {err}");
+        }
+    }
+
+    /// Key under which the authors heard over `transport` are remembered, so their locks
+    /// are released when the peer leaves or the session closes.
+    #[cfg(feature = "network-quic")]
+    fn transport_source(&self, transport: &CommandTransport) -> Option<PeerId> {
+        match transport {
+            CommandTransport::Quic(session) => Some(PeerId(format!(
+                "quic-session-{}",
+                session.handshake().session_id
+            ))),
+            CommandTransport::WebRtc(_) => self.active_webrtc_peer.clone(),
+        }
+    }
+
+    #[cfg(feature = "network-quic")]
+    fn release_peer_locks(&mut self, peer_id: &PeerId) {
+        let Some(authors) = self.peer_authors.remove(peer_id) else {
+            return;
+        };
+        if let Ok(mut pipeline) = self.command_pipeline.lock() {
+            for author in &authors {
+                let released = pipeline.release_entity_locks(author);
+                if !released.is_empty() {
+                    log::info!(
+                        "[commands] released {} entity locks held by {author:?} after {peer_id} left",
+                        released.len()
+                    );
+                }
+            }
+        }
+    }
+
+    fn sync_entity_locks(&mut self) {
+        let leases = match self.command_pipeline.lock() {
+            Ok(mut pipeline) => pipeline.refresh_entity_locks(),
+            Err(err) => {
+                log::error!(
+                    "[commands] command pipeline mutex poisoned while syncing locks: {err}"
+                );
+                return;
+            }
+        };
+
+        let world = self.scheduler.world_mut();
+        let entities = lock_entity_map(&self.entity_map);
+        let locked: HashSet<crate::ecs::Entity> = leases
+            .iter()
+            .map(|(handle, _)| entities.resolve(*handle))
+            .collect();
+        let released: Vec<crate::ecs::Entity> = world
+            .component_entries::<EntityLock>()
+            .into_iter()
+            .map(|(entity, _)| entity)
+            .filter(|entity| !locked.contains(entity))
+            .collect();
+        for entity in released {
+            world.remove::<EntityLock>(entity);
+        }
+        for (handle, lease) in &leases {
+            let entity = entities.resolve(*handle);
+            if world.contains(entity) {
+                let _ = world.insert(entity, EntityLock::from(lease));
+            }
+        }
+    }
+
     #[cfg(feature = "network-quic")]
     fn receive_next_command_packet(&mut self) -> Result<Option<CommandPacket>, TransportError> {
         let runtime = self.ensure_network_runtime();
@@ -2889,6 +3589,7 @@ impl Engine {
                     log::debug!("[signaling] local peer left event ignored");
                     return;
                 }
+                self.release_peer_locks(&peer_id);
                 self.cleanup_peer_connection(peer_id);
             }
             SignalingResponse::Error { message } => {
@@ -3213,223 +3914,62 @@ impl Engine {
         }
     }
 
+    /// Shows a peer's in-flight drag. Older previews from the same author are ignored.
     #[cfg_attr(not(any(feature = "network-quic", test)), allow(dead_code))]
+    fn apply_remote_preview(&mut self, packet: &PreviewPacket) {
+        let entity = self.resolve_entity(packet.entity);
+        let world = self.scheduler.world_mut();
+        if !world.contains(entity) {
+            return;
+        }
+        if let Some(current) = world.get::<TransformPreview>(entity)
+            && current.author == packet.author
+            && current.sequence >= packet.sequence
+        {
+            return;
+        }
+        let preview = TransformPreview {
+            author: packet.author.clone(),
+            sequence: packet.sequence,
+            position: packet.position,
+            rotation: packet.rotation,
+            scale: sanitize_scale(packet.scale),
+            received_ms: current_time_millis(),
+        };
+        if let Err(err) = world.insert(entity, preview) {
+            log::warn!("[commands] failed to show preview for {entity:?}: {err}");
+        }
+    }
+
     fn apply_remote_entries(&mut self, entries: &[CommandEntry]) {
         if entries.is_empty() {
             return;
         }
 
-        let Some(editor_entity) = self.command_entity else {
-            log::warn!("[commands] editor entity unavailable; skipping remote command apply");
-            return;
+        let mut pipeline = match self.command_pipeline.lock() {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                log::error!(
+                    "[commands] command pipeline mutex poisoned while applying remote entries: {err}"
+                );
+                return;
+            }
         };
-
         let world = self.scheduler.world_mut();
         for entry in entries {
-            match entry.payload.command_type.as_str() {
-                CMD_SELECTION_HIGHLIGHT => {
-                    if !matches!(entry.payload.scope, CommandScope::Entity(_)) {
-                        log::warn!(
-                            "[commands] selection highlight command missing entity scope (id {id:?})",
-                            id = entry.id
-                        );
-                        continue;
-                    }
-
-                    match serde_json::from_slice::<SelectionHighlightCommand>(&entry.payload.data) {
-                        Ok(command) => {
-                            let target_entity = crate::ecs::Entity::from(command.entity);
-                            let exists = world.contains(target_entity);
-
-                            match world.get_mut::<EditorSelection>(editor_entity) {
-                                Some(selection) => {
-                                    if exists {
-                                        selection.primary = Some(target_entity);
-                                    } else {
-                                        log::warn!(
-                                            "[commands] remote highlight target {entity:?} missing locally",
-                                            entity = command.entity
-                                        );
-                                    }
-                                    selection.highlight_active = command.active;
-                                    selection.frames_since_change = 0;
-                                }
-                                None => {
-                                    log::warn!(
-                                        "[commands] editor selection component missing on {editor_entity:?}"
-                                    );
-                                }
-                            }
-                        }
-                        Err(err) => {
-                            log::error!(
-                                "[commands] failed to decode SelectionHighlightCommand payload: {err}"
-                            );
-                        }
-                    }
-                }
-                CMD_ENTITY_TRANSLATE => {
-                    match serde_json::from_slice::<EntityTranslateCommand>(&entry.payload.data) {
-                        Ok(command) => {
-                            let target_entity = crate::ecs::Entity::from(command.entity);
-                            if let Some(transform) = world.get_mut::<Transform>(target_entity) {
-                                for (axis, delta) in
-                                    transform.position.iter_mut().zip(command.delta.iter())
-                                {
-                                    *axis += *delta;
-                                }
-                            } else {
-                                log::warn!(
-                                    "[commands] translate target {entity:?} missing transform",
-                                    entity = command.entity
-                                );
-                            }
-                        }
-                        Err(err) => {
-                            log::error!(
-                                "[commands] failed to decode EntityTranslateCommand payload: {err}"
-                            );
-                        }
-                    }
-                }
-                CMD_ENTITY_ROTATE => {
-                    match serde_json::from_slice::<EntityRotateCommand>(&entry.payload.data) {
-                        Ok(command) => {
-                            let target_entity = crate::ecs::Entity::from(command.entity);
-                            if let Some(transform) = world.get_mut::<Transform>(target_entity) {
-                                transform.rotation = [
-                                    command.rotation.x,
-                                    command.rotation.y,
-                                    command.rotation.z,
-                                    command.rotation.w,
-                                ];
-                            } else {
-                                log::warn!(
-                                    "[commands] rotate target {entity:?} missing transform",
-                                    entity = command.entity
-                                );
-                            }
-                        }
-                        Err(err) => {
-                            log::error!(
-                                "[commands] failed to decode EntityRotateCommand payload: {err}"
-                            );
-                        }
-                    }
-                }
-                CMD_ENTITY_SCALE => {
-                    match serde_json::from_slice::<EntityScaleCommand>(&entry.payload.data) {
-                        Ok(command) => {
-                            let target_entity = crate::ecs::Entity::from(command.entity);
-                            if let Some(transform) = world.get_mut::<Transform>(target_entity) {
-                                transform.scale = sanitize_scale(command.scale);
-                            } else {
-                                log::warn!(
-                                    "[commands] scale target {entity:?} missing transform",
-                                    entity = command.entity
-                                );
-                            }
-                        }
-                        Err(err) => {
-                            log::error!(
-                                "[commands] failed to decode EntityScaleCommand payload: {err}"
-                            );
-                        }
-                    }
-                }
-                CMD_TOOL_ACTIVATE => {
-                    match serde_json::from_slice::<ToolActivateCommand>(&entry.payload.data) {
-                        Ok(command) => {
-                            if let Some(tool_state) =
-                                world.get_mut::<EditorToolState>(editor_entity)
-                            {
-                                tool_state.activate(command.tool_id.clone(), entry.id.lamport());
-                            } else {
-                                log::warn!(
-                                    "[commands] editor tool state component missing on {editor_entity:?}"
-                                );
-                            }
-                        }
-                        Err(err) => {
-                            log::error!(
-                                "[commands] failed to decode ToolActivateCommand payload: {err}"
-                            );
-                        }
-                    }
-                }
-                CMD_TOOL_DEACTIVATE => {
-                    match serde_json::from_slice::<ToolDeactivateCommand>(&entry.payload.data) {
-                        Ok(command) => {
-                            if let Some(tool_state) =
-                                world.get_mut::<EditorToolState>(editor_entity)
-                            {
-                                if tool_state.matches_active(&command.tool_id) {
-                                    tool_state.deactivate(entry.id.lamport());
-                                }
-                            } else {
-                                log::warn!(
-                                    "[commands] editor tool state component missing on {editor_entity:?}"
-                                );
-                            }
-                        }
-                        Err(err) => {
-                            log::error!(
-                                "[commands] failed to decode ToolDeactivateCommand payload: {err}"
-                            );
-                        }
-                    }
-                }
-                CMD_MESH_VERTEX_CREATE => {
-                    match serde_json::from_slice::<VertexCreateCommand>(&entry.payload.data) {
-                        Ok(command) => {
-                            log::debug!(
-                                "[commands] mesh vertex create queued at position {:?} metadata {:?}",
-                                command.position,
-                                command.metadata
-                            );
-                        }
-                        Err(err) => {
-                            log::error!(
-                                "[commands] failed to decode VertexCreateCommand payload: {err}"
-                            );
-                        }
-                    }
-                }
-                CMD_MESH_EDGE_EXTRUDE => {
-                    match serde_json::from_slice::<EdgeExtrudeCommand>(&entry.payload.data) {
-                        Ok(command) => {
-                            log::debug!(
-                                "[commands] mesh edge {} extrude direction {:?}",
-                                command.edge_id,
-                                command.direction
-                            );
-                        }
-                        Err(err) => {
-                            log::error!(
-                                "[commands] failed to decode EdgeExtrudeCommand payload: {err}"
-                            );
-                        }
-                    }
-                }
-                CMD_MESH_FACE_SUBDIVIDE => {
-                    match serde_json::from_slice::<FaceSubdivideCommand>(&entry.payload.data) {
-                        Ok(command) => {
-                            log::debug!(
-                                "[commands] mesh face {} subdivide levels {} smoothness {:.3}",
-                                command.face_id,
-                                command.params.levels,
-                                command.params.smoothness
-                            );
-                        }
-                        Err(err) => {
-                            log::error!(
-                                "[commands] failed to decode FaceSubdivideCommand payload: {err}"
-                            );
-                        }
-                    }
+            match pipeline.apply_entry(world, entry, self.command_entity) {
+                Ok(true) => clear_committed_preview(world, &self.entity_map, entry),
+                Ok(false) => {
+                    log::debug!(
+                        "[commands] ignoring unhandled remote command type {}",
+                        entry.payload.command_type
+                    );
                 }
-                other => {
-                    log::debug!("[commands] ignoring unhandled remote command type {other}");
+                Err(err) => {
+                    log::warn!(
+                        "[commands] failed to apply remote command (id {id:?}): {err}",
+                        id = entry.id
+                    );
                 }
             }
         }
@@ -3450,8 +3990,10 @@ crate::register_component_types!(
 mod tests {
     use super::*;
     use crate::editor::commands::{
-        EntityRotateCommand, EntityScaleCommand, EntityTranslateCommand, Quaternion,
-        ToolActivateCommand, ToolDeactivateCommand,
+        CMD_ENTITY_ROTATE, CMD_ENTITY_SCALE, CMD_ENTITY_TRANSLATE, CMD_SELECTION_HIGHLIGHT,
+        CMD_TOOL_ACTIVATE, CMD_TOOL_DEACTIVATE, EntityRotateCommand, EntityScaleCommand,
+        EntityTranslateCommand, Quaternion, SelectionHighlightCommand, ToolActivateCommand,
+        ToolDeactivateCommand,
     };
     use crate::network::command_log::{
         AuthorId, CommandAuthor, CommandEntry, CommandId, CommandPayload, CommandRole,
@@ -3474,7 +4016,9 @@ mod tests {
             let (entity, selection) = entries.remove(0);
             (
                 entity,
-                selection.primary.expect("selection should have primary"),
+                selection
+                    .primary(&engine.entity_map)
+                    .expect("selection should have primary"),
             )
         };
 
@@ -3500,11 +4044,45 @@ mod tests {
         let selection = world
             .get::<EditorSelection>(editor_entity)
             .expect("editor selection present");
-        assert_eq!(selection.primary, Some(primary_entity));
+        assert_eq!(selection.primary(&engine.entity_map), Some(primary_entity));
         assert!(!selection.highlight_active);
         assert_eq!(selection.frames_since_change, 0);
     }
 
+    #[test]
+    fn entity_lock_component_tracks_lock_table() {
+        let mut engine = Engine::new();
+        let primary = engine
+            .world()
+            .component_entries::<EditorSelection>()
+            .into_iter()
+            .find_map(|(_, selection)| selection.primary(&engine.entity_map))
+            .expect("selection should have primary");
+        let handle = EntityHandle::from(primary);
+
+        engine
+            .command_pipeline
+            .lock()
+            .unwrap()
+            .record_entity_lock(handle, 60_000)
+            .expect("acquire lock");
+        engine.sync_entity_locks();
+        let lock = engine
+            .world()
+            .get::<EntityLock>(primary)
+            .expect("lock component attached");
+        assert_eq!(lock.holder, AuthorId(0));
+
+        engine
+            .command_pipeline
+            .lock()
+            .unwrap()
+            .record_entity_unlock(handle)
+            .expect("release lock");
+        engine.sync_entity_locks();
+        assert!(engine.world().get::<EntityLock>(primary).is_none());
+    }
+
     #[test]
     fn transform_commands_mutate_entities() {
         let mut engine = Engine::new();
@@ -3518,7 +4096,7 @@ mod tests {
                 .expect("selection component present");
             let primary = selection_entry
                 .1
-                .primary
+                .primary(&engine.entity_map)
                 .expect("selection should have primary");
             let handle = EntityHandle::from(primary);
             (primary, handle)
@@ -3659,6 +4237,607 @@ mod tests {
         assert_eq!(tool_state.last_lamport, Some(21));
     }
 
+    #[derive(Debug, Clone, PartialEq)]
+    struct Nameplate(String);
+
+    #[derive(Serialize, Deserialize)]
+    struct RenameCommand {
+        entity: EntityHandle,
+        name: String,
+    }
+
+    impl TypedCommand for RenameCommand {
+        const TYPE_ID: &'static str = "game.nameplate.rename";
+
+        fn scope(&self) -> CommandScope {
+            CommandScope::Entity(self.entity)
+        }
+
+        fn apply(
+            &self,
+            world: &mut crate::ecs::World,
+            _context: &CommandContext<'_>,
+        ) -> Result<(), CommandApplyError> {
+            world
+                .insert(
+                    crate::ecs::Entity::from(self.entity),
+                    Nameplate(self.name.clone()),
+                )
+                .map_err(|_| CommandApplyError::MissingEntity(self.entity))?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn registered_typed_commands_apply_locally_and_remotely() {
+        let mut engine = Engine::new();
+        let entity = engine.world_mut().spawn();
+        let handle = EntityHandle::from(entity);
+
+        let unregistered = engine.submit_command(RenameCommand {
+            entity: handle,
+            name: "early".into(),
+        });
+        assert!(matches!(
+            unregistered,
+            Err(crate::network::command_log::CommandLogError::UnregisteredCommand(_))
+        ));
+
+        engine.register_command::<RenameCommand>();
+        engine
+            .submit_command(RenameCommand {
+                entity: handle,
+                name: "local".into(),
+            })
+            .expect("submit typed command");
+        assert_eq!(
+            engine.world().get::<Nameplate>(entity),
+            Some(&Nameplate("local".into()))
+        );
+
+        let remote = RenameCommand {
+            entity: handle,
+            name: "remote".into(),
+        };
+        let entry = CommandEntry::new(
+            CommandId::new(40, AuthorId(6)),
+            0,
+            remote.to_payload().unwrap(),
+            ConflictStrategy::LastWriteWins,
+            CommandAuthor::new(AuthorId(6), CommandRole::Editor),
+            None,
+        );
+        engine.apply_remote_entries(&[entry]);
+        assert_eq!(
+            engine.world().get::<Nameplate>(entity),
+            Some(&Nameplate("remote".into()))
+        );
+    }
+
+    #[test]
+    fn replicated_mesh_edits_produce_identical_topology() {
+        use crate::editor::commands::{
+            EdgeExtrudeCommand, FaceSubdivideCommand, SubdivideParams, VertexCreateCommand,
+        };
+
+        let mut author = Engine::new();
+        let mut peer = Engine::new();
+        let spawn_quad = |engine: &mut Engine| {
+            let world = engine.world_mut();
+            let entity = world.spawn();
+            world.insert(entity, EditableMesh::quad(1.0)).unwrap();
+            entity
+        };
+        let mesh_entity = spawn_quad(&mut author);
+        assert_eq!(spawn_quad(&mut peer), mesh_entity);
+        let mesh = EntityHandle::from(mesh_entity);
+
+        author
+            .submit_command(EdgeExtrudeCommand::new(2, [0.0, 0.5, 0.0]).on_mesh(mesh))
+            .expect("extrude");
+        author
+            .submit_command(
+                FaceSubdivideCommand::new(
+                    0,
+                    SubdivideParams {
+                        levels: 2,
+                        smoothness: 0.0,
+                    },
+                )
+                .on_mesh(mesh),
+            )
+            .expect("subdivide");
+        author
+            .submit_command(VertexCreateCommand::new(
+                [0.0, 2.0, 0.0],
+                Default::default(),
+            ))
+            .expect("scratch vertex");
+
+        let entries: Vec<CommandEntry> = author
+            .command_pipeline
+            .lock()
+            .unwrap()
+            .drain_packets()
+            .iter()
+            .flat_map(|packet| packet.decode().expect("decode packet").entries)
+            .collect();
+        assert_eq!(entries.len(), 3);
+        peer.apply_remote_entries(&entries);
+
+        let authored = author.world().get::<EditableMesh>(mesh_entity).unwrap();
+        let replicated = peer.world().get::<EditableMesh>(mesh_entity).unwrap();
+        authored.validate().expect("valid authored mesh");
+        assert_eq!(authored.face_count(), 17);
+        assert_eq!(authored, replicated);
+
+        let scratch = |engine: &Engine| {
+            let editor = engine.command_entity.unwrap();
+            engine.world().get::<EditableMesh>(editor).unwrap().clone()
+        };
+        assert_eq!(scratch(&author).vertex_count(), 1);
+        assert_eq!(scratch(&author), scratch(&peer));
+    }
+
+    #[test]
+    fn created_primitives_replicate_into_the_same_entity() {
+        let mut author = Engine::new();
+        let mut peer = Engine::new();
+        let shape = PrimitiveShape::Cylinder {
+            radius: 0.5,
+            depth: 2.0,
+            segments: 12,
+        };
+        let handle = author
+            .create_primitive(shape.clone(), [1.0, 0.0, -2.0])
+            .expect("create primitive");
+        let entity = author.resolve_entity(handle);
+        assert_eq!(network_handle(author.world(), entity), handle);
+
+        let entries: Vec<CommandEntry> = author
+            .command_pipeline
+            .lock()
+            .unwrap()
+            .drain_packets()
+            .iter()
+            .flat_map(|packet| packet.decode().expect("decode packet").entries)
+            .collect();
+        peer.apply_remote_entries(&entries);
+
+        let mirrored = peer.resolve_entity(handle);
+        assert_eq!(network_handle(peer.world(), mirrored), handle);
+        let mesh = peer
+            .world()
+            .get::<EditableMesh>(mirrored)
+            .expect("replicated mesh");
+        assert_eq!(mesh, &shape.build());
+        assert_eq!(author.world().get::<EditableMesh>(entity), Some(mesh));
+        let transform = peer.world().get::<Transform>(mirrored).unwrap();
+        assert_eq!(transform.position, [1.0, 0.0, -2.0]);
+    }
+
+    #[test]
+    fn remote_creates_cannot_claim_existing_entities() {
+        let mut engine = Engine::new();
+        let cube = PrimitiveShape::Cube { size: 1.0 };
+        let existing = engine
+            .create_primitive(cube.clone(), [0.0; 3])
+            .expect("create cube");
+        let actor = engine.selection().unwrap().active().unwrap();
+        let create = |lamport: u64, target: EntityHandle| {
+            let shape = PrimitiveShape::Cube { size: 4.0 };
+            let command = CreatePrimitiveCommand::new(target, shape, [9.0; 3]);
+            CommandEntry::new(
+                CommandId::new(lamport, AuthorId(7)),
+                0,
+                command.to_payload().expect("payload"),
+                CreatePrimitiveCommand::strategy(),
+                CommandAuthor::new(AuthorId(7), CommandRole::Editor),
+                None,
+            )
+        };
+        engine.apply_remote_entries(&[create(1, actor), create(2, existing)]);
+
+        let world = engine.world();
+        let actor = engine.resolve_entity(actor);
+        assert!(world.get::<EditableMesh>(actor).is_none());
+        let existing = engine.resolve_entity(existing);
+        assert_eq!(world.get::<EditableMesh>(existing), Some(&cube.build()));
+        assert_eq!(world.get::<Transform>(existing).unwrap().position, [0.0; 3]);
+    }
+
+    #[test]
+    fn imported_meshes_replicate_and_export() {
+        use crate::editor::mesh_io::{self, MeshFormat};
+
+        let obj = b"o left\nv 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n\
+                    o right\nv 2 0 0\nv 3 0 0\nv 2 1 0\nf 5 6 7\n";
+        let mut author = Engine::new();
+        let mut peer = Engine::new();
+        let handles = author
+            .import_mesh_bytes(obj, MeshFormat::Obj)
+            .expect("import obj");
+        assert_eq!(handles.len(), 2);
+
+        let entries: Vec<CommandEntry> = author
+            .command_pipeline
+            .lock()
+            .unwrap()
+            .drain_packets()
+            .iter()
+            .flat_map(|packet| packet.decode().expect("decode packet").entries)
+            .collect();
+        assert_eq!(entries.len(), 2);
+        peer.apply_remote_entries(&entries);
+        for handle in &handles {
+            let mesh = peer
+                .world()
+                .get::<EditableMesh>(peer.resolve_entity(*handle))
+                .expect("mesh");
+            let authored = author.resolve_entity(*handle);
+            assert_eq!(author.world().get::<EditableMesh>(authored), Some(mesh));
+        }
+
+        let path = std::env::temp_dir().join(format!("theta_export_{}.ply", std::process::id()));
+        peer.export_meshes(&path, &handles).expect("export ply");
+        let reimported = mesh_io::import_file(&path).expect("reimport");
+        std::fs::remove_file(&path).ok();
+        assert_eq!(reimported[0].mesh.vertex_count(), 7);
+        assert_eq!(reimported[0].mesh.face_count(), 2);
+
+        // Imports only ever create: a replayed import and one aimed at the peer's own
+        // actor leave the existing entities alone.
+        let before = peer.world_state();
+        let mut hijack = entries[0].clone();
+        let mut command: ImportMeshCommand =
+            serde_json::from_slice(&hijack.payload.data).expect("decode import");
+        command.entity = peer.selection().unwrap().active().unwrap();
+        hijack.id = CommandId::new(99, AuthorId(7));
+        hijack.payload = command.to_payload().expect("payload");
+        peer.apply_remote_entries(&entries);
+        peer.apply_remote_entries(&[hijack]);
+        assert_eq!(peer.world_state(), before);
+    }
+
+    #[test]
+    fn selection_sets_and_group_transforms_replicate() {
+        use crate::editor::selection::{GroupTransform, SelectionBox};
+
+        let mut author = Engine::new();
+        let mut peer = Engine::new();
+        let cube = PrimitiveShape::Cube { size: 1.0 };
+        let left = author
+            .create_primitive(cube.clone(), [-1.0, 0.0, -3.0])
+            .expect("left cube");
+        let right = author
+            .create_primitive(cube, [1.0, 0.0, -3.0])
+            .expect("right cube");
+
+        let volume = SelectionBox::from_corners([-2.0, -1.0, -4.0], [2.0, 1.0, -2.0]);
+        let picked = author
+            .select_in_box(volume, SelectionMode::Replace)
+            .expect("box select");
+        assert_eq!(picked, vec![left, right]);
+        assert_eq!(author.selection().unwrap().active(), Some(right));
+
+        let half = std::f32::consts::FRAC_PI_4;
+        let quarter_turn = Quaternion::new(0.0, half.sin(), 0.0, half.cos());
+        author
+            .transform_selection(GroupTransform::Rotate(quarter_turn), PivotMode::Median)
+            .expect("group rotate");
+
+        let entries: Vec<CommandEntry> = author
+            .command_pipeline
+            .lock()
+            .unwrap()
+            .drain_packets()
+            .iter()
+            .flat_map(|packet| packet.decode().expect("decode packet").entries)
+            .collect();
+        peer.apply_remote_entries(&entries);
+
+        assert_eq!(peer.selection(), author.selection());
+        for (handle, expected_x, expected_z) in [(left, 0.0, -2.0), (right, 0.0, -4.0)] {
+            let mirrored = peer.resolve_entity(handle);
+            let transform = peer.world().get::<Transform>(mirrored).unwrap();
+            assert!((transform.position[0] - expected_x).abs() < 1e-5);
+            assert!((transform.position[2] - expected_z).abs() < 1e-5);
+            assert!((transform.rotation[1] - half.sin()).abs() < 1e-5);
+            let local = author
+                .world()
+                .get::<Transform>(author.resolve_entity(handle))
+                .unwrap();
+            assert_eq!(local.position, transform.position);
+        }
+
+        // One member locked by someone else holds back the whole group.
+        let lock = crate::network::locks::EntityLockCommand::new(right, 60_000);
+        let entry = CommandEntry::new(
+            CommandId::new(40, AuthorId(7)),
+            crate::network::current_time_millis(),
+            CommandPayload::new(
+                crate::network::locks::CMD_ENTITY_LOCK,
+                CommandScope::Entity(right),
+                serde_json::to_vec(&lock).unwrap(),
+            ),
+            ConflictStrategy::Merge,
+            CommandAuthor::new(AuthorId(7), CommandRole::Editor),
+            None,
+        );
+        let batch = CommandBatch {
+            sequence: 1,
+            nonce: 1,
+            timestamp_ms: 0,
+            author: AuthorId(7),
+            entries: vec![entry],
+        };
+        author
+            .command_pipeline
+            .lock()
+            .unwrap()
+            .integrate_remote_packet(&CommandPacket::from_batch(&batch).unwrap())
+            .expect("remote lock");
+        let before = author.world_state();
+        let moved =
+            author.transform_selection(GroupTransform::Translate([1.0; 3]), PivotMode::Median);
+        assert!(matches!(
+            moved,
+            Err(crate::network::command_log::CommandLogError::EntityLocked { .. })
+        ));
+        assert_eq!(author.world_state(), before);
+
+        author
+            .select(vec![left], SelectionMode::Toggle)
+            .expect("toggle");
+        assert!(!author.selection().unwrap().contains(left));
+    }
+
+    #[test]
+    fn group_transforms_beyond_the_rate_limit_record_nothing() {
+        use crate::editor::selection::GroupTransform;
+
+        let mut engine = Engine::new();
+        let cube = PrimitiveShape::Cube { size: 1.0 };
+        let left = engine
+            .create_primitive(cube.clone(), [-1.0, 0.0, -3.0])
+            .expect("left cube");
+        let right = engine
+            .create_primitive(cube, [1.0, 0.0, -3.0])
+            .expect("right cube");
+        engine
+            .select(vec![left, right], SelectionMode::Replace)
+            .expect("select");
+
+        // Leave two tokens; rotating both cubes about their median moves and turns each.
+        {
+            let mut pipeline = engine.command_pipeline.lock().unwrap();
+            let spare = pipeline.rate_capacity().saturating_sub(2);
+            for _ in 0..spare {
+                pipeline
+                    .record_selection_highlight(left, true)
+                    .expect("spend token");
+            }
+        }
+        let before = engine.world_state();
+        let half = std::f32::consts::FRAC_PI_4;
+        let rotated = engine.transform_selection(
+            GroupTransform::Rotate(Quaternion::new(0.0, half.sin(), 0.0, half.cos())),
+            PivotMode::Median,
+        );
+        assert!(matches!(
+            rotated,
+            Err(crate::network::command_log::CommandLogError::RateLimited(_))
+        ));
+        assert_eq!(engine.world_state(), before);
+    }
+
+    #[test]
+    fn controller_gizmo_drags_record_transform_commands() {
+        use crate::vr::{VrInputProvider, VrInputSample};
+
+        struct Scripted(std::collections::VecDeque<VrInputSample>);
+
+        impl VrInputProvider for Scripted {
+            fn label(&self) -> &'static str {
+                "scripted"
+            }
+
+            fn sample(&mut self, _delta_seconds: f32) -> VrInputSample {
+                self.0.pop_front().unwrap_or_default()
+            }
+        }
+
+        let mut engine = Engine::new();
+        let actor = engine.selection().unwrap().active().unwrap();
+        engine
+            .world_mut()
+            .remove::<Velocity>(crate::ecs::Entity::from(actor));
+        let start = engine
+            .world()
+            .get::<Transform>(crate::ecs::Entity::from(actor))
+            .unwrap()
+            .position;
+
+        // Point at the X handle from in front, squeeze, pull 0.2 m along +X, release.
+        let frame = |offset: f32, trigger: f32| {
+            let mut sample = VrInputSample::default();
+            sample.right.pose.position = [start[0] + 0.15 + offset, start[1], start[2] + 0.5];
+            sample.right.trigger = trigger;
+            sample
+        };
+        let mut script = vec![frame(0.0, 0.0), frame(0.0, 1.0)];
+        script.extend((1..=20).map(|step| frame(step as f32 * 0.01, 1.0)));
+        script.push(frame(0.2, 0.0));
+        let frames = script.len();
+        engine.set_input_provider(Box::new(Scripted(script.into())));
+        for _ in 0..frames {
+            engine.scheduler.tick(1.0 / 60.0);
+        }
+
+        let moved = engine
+            .world()
+            .get::<Transform>(crate::ecs::Entity::from(actor))
+            .unwrap()
+            .position;
+        assert!((moved[0] - start[0] - 0.2).abs() < 1e-4, "moved {moved:?}");
+        let translates = engine
+            .command_pipeline
+            .lock()
+            .unwrap()
+            .drain_packets()
+            .iter()
+            .flat_map(|packet| packet.decode().expect("decode packet").entries)
+            .filter(|entry| entry.payload.command_type == CMD_ENTITY_TRANSLATE)
+            .count();
+        assert_eq!(translates, 1, "drag coalesces into one translate entry");
+    }
+
+    #[test]
+    fn captured_commands_rebuild_state_at_any_point() {
+        let mut author = Engine::new();
+        author.start_command_capture();
+        let cube = author
+            .create_primitive(PrimitiveShape::Cube { size: 1.0 }, [1.0, 0.0, 0.0])
+            .expect("create cube");
+        author
+            .submit_command(EntityTranslateCommand::new(cube, [0.0, 2.0, 0.0]))
+            .expect("translate cube");
+        author
+            .select(vec![cube], SelectionMode::Replace)
+            .expect("select cube");
+
+        let mut bytes = Vec::new();
+        author
+            .take_command_capture()
+            .expect("capture started")
+            .write_to(&mut bytes)
+            .expect("write capture");
+        let capture = crate::network::inspector::CommandCapture::from_reader(bytes.as_slice())
+            .expect("read capture");
+        assert_eq!(
+            Engine::replay(capture.entries()).world_state(),
+            author.world_state()
+        );
+
+        let created = capture.entries()[0].id.clone();
+        let early = Engine::replay(capture.entries_until(&created).unwrap()).world_state();
+        let cube_state = early
+            .entities
+            .iter()
+            .find(|state| state.entity == cube)
+            .expect("cube exists after its create command");
+        assert_eq!(cube_state.position, [1.0, 0.0, 0.0]);
+        assert!(cube_state.mesh.is_some_and(|mesh| mesh.faces == 6));
+        assert_ne!(early.selection, vec![cube]);
+    }
+
+    #[test]
+    fn remote_previews_show_until_the_drag_commits() {
+        let mut engine = Engine::new();
+        let actor = engine.selection().unwrap().active().unwrap();
+        let entity = crate::ecs::Entity::from(actor);
+        let start = engine.world().get::<Transform>(entity).unwrap().position;
+
+        let preview = |sequence: u64, x: f32| PreviewPacket {
+            author: AuthorId(7),
+            sequence,
+            entity: actor,
+            position: [x, start[1], start[2]],
+            rotation: [0.0, 0.0, 0.0, 1.0],
+            scale: [1.0; 3],
+            timestamp_ms: current_time_millis(),
+        };
+        engine.apply_remote_preview(&preview(2, 1.0));
+        // A late, older preview must not rewind the one shown.
+        engine.apply_remote_preview(&preview(1, 0.5));
+        let shown = engine.world().get::<TransformPreview>(entity).unwrap();
+        assert_eq!(shown.sequence, 2);
+        assert_eq!(engine.displayed_transform(actor).unwrap().position[0], 1.0);
+        assert_eq!(
+            engine.world().get::<Transform>(entity).unwrap().position,
+            start
+        );
+
+        let translate = EntityTranslateCommand::new(actor, [1.0 - start[0], 0.0, 0.0]);
+        engine.apply_remote_entries(&[CommandEntry::new(
+            CommandId::new(30, AuthorId(7)),
+            current_time_millis(),
+            CommandPayload::new(
+                CMD_ENTITY_TRANSLATE,
+                CommandScope::Entity(actor),
+                serde_json::to_vec(&translate).unwrap(),
+            ),
+            ConflictStrategy::Merge,
+            CommandAuthor::new(AuthorId(7), CommandRole::Editor),
+            None,
+        )]);
+        assert!(engine.world().get::<TransformPreview>(entity).is_none());
+        let committed = engine.world().get::<Transform>(entity).unwrap().position;
+        assert!((committed[0] - 1.0).abs() < 1e-5);
+        assert_eq!(
+            engine.displayed_transform(actor).unwrap().position,
+            committed
+        );
+    }
+
+    #[test]
+    fn modelling_operations_replicate_identically() {
+        use crate::editor::commands::{
+            EdgeBevelCommand, FaceExtrudeCommand, FaceInsetCommand, LoopCutCommand,
+            MergeByDistanceCommand, MeshDeleteCommand, MeshElement,
+        };
+
+        let mut author = Engine::new();
+        let mut peer = Engine::new();
+        let mesh = author
+            .create_primitive(PrimitiveShape::Cube { size: 2.0 }, [0.0; 3])
+            .expect("create cube");
+
+        author
+            .submit_command(FaceExtrudeCommand::new(3, 1.0).on_mesh(mesh))
+            .expect("extrude");
+        author
+            .submit_command(FaceInsetCommand::new(3, 0.25).on_mesh(mesh))
+            .expect("inset");
+        author
+            .submit_command(EdgeBevelCommand::new(0, 0.1).on_mesh(mesh))
+            .expect("bevel");
+        author
+            .submit_command(LoopCutCommand::new(6, 0.5).on_mesh(mesh))
+            .expect("loop cut");
+        author
+            .submit_command(MergeByDistanceCommand::new(0.01).on_mesh(mesh))
+            .expect("merge");
+        author
+            .submit_command(MeshDeleteCommand::new(MeshElement::Face(3)).on_mesh(mesh))
+            .expect("delete");
+
+        let entries: Vec<CommandEntry> = author
+            .command_pipeline
+            .lock()
+            .unwrap()
+            .drain_packets()
+            .iter()
+            .flat_map(|packet| packet.decode().expect("decode packet").entries)
+            .collect();
+        assert_eq!(entries.len(), 7);
+        peer.apply_remote_entries(&entries);
+
+        let authored = author
+            .world()
+            .get::<EditableMesh>(author.resolve_entity(mesh))
+            .unwrap();
+        authored.validate().expect("valid authored mesh");
+        assert_eq!(authored.face_count(), 15);
+        assert!(
+            authored
+                .face_ids()
+                .all(|face| face != crate::editor::mesh::FaceId(3))
+        );
+        let mirrored = peer.resolve_entity(mesh);
+        assert_eq!(peer.world().get::<EditableMesh>(mirrored), Some(authored));
+    }
+
     #[cfg(feature = "network-quic")]
     #[test]
     fn webrtc_offer_timeout_reactivates_fallback_transport() {
@@ -0,0 +1,315 @@
+//! Applies entries of commands that capture a [`Checkpoint`] in command id order, the
+//! same on every peer however they arrive. A scope keeps its recent entries with the
+//! state from before each; an entry that sorts before some of them rolls the scope back,
+//! applies and replays the rest after it.
+
+use super::typed_commands::{Checkpoint, CommandApplyError, CommandHandlerRegistry};
+use crate::ecs::{Entity, World};
+use crate::network::command_log::{CommandEntry, CommandId, CommandScope};
+use crate::network::network_id::SharedEntityMap;
+use std::collections::{HashMap, VecDeque};
+
+/// Entries kept per scope; one arriving behind all of them can no longer be ordered.
+pub const MAX_ORDERED_ENTRIES: usize = 32;
+
+struct Applied {
+    entry: CommandEntry,
+    checkpoint: Checkpoint,
+}
+
+#[derive(Default)]
+struct ScopeHistory {
+    /// Ascending by command id.
+    applied: VecDeque<Applied>,
+    /// Whether entries were dropped from the front to respect the limit.
+    truncated: bool,
+}
+
+/// Everything applying an entry needs besides the world.
+pub(super) struct Handlers<'a> {
+    pub registry: &'a CommandHandlerRegistry,
+    pub editor_entity: Option<Entity>,
+    pub entities: &'a SharedEntityMap,
+}
+
+impl Handlers<'_> {
+    fn apply(&self, world: &mut World, entry: &CommandEntry) -> Result<bool, CommandApplyError> {
+        self.registry
+            .apply(world, entry, self.editor_entity, self.entities)
+    }
+
+    fn checkpoint(&self, world: &World, entry: &CommandEntry) -> Option<Checkpoint> {
+        self.registry
+            .checkpoint(world, entry, self.editor_entity, self.entities)
+    }
+}
+
+#[derive(Default)]
+pub(super) struct OrderedScopes {
+    scopes: HashMap<CommandScope, ScopeHistory>,
+}
+
+impl OrderedScopes {
+    /// Applies `entry`. If its command captures a checkpoint and entries of its scope
+    /// with higher ids were applied already, those are rolled back and replayed after it.
+    pub fn apply(
+        &mut self,
+        handlers: &Handlers<'_>,
+        world: &mut World,
+        entry: &CommandEntry,
+    ) -> Result<bool, CommandApplyError> {
+        let Some(checkpoint) = handlers.checkpoint(world, entry) else {
+            return handlers.apply(world, entry);
+        };
+        let history = self.scopes.entry(entry.payload.scope.clone()).or_default();
+        let position = history
+            .applied
+            .partition_point(|applied| applied.entry.id < entry.id);
+        if position == 0 && history.truncated {
+            log::warn!(
+                "[commands] {:?} arrived behind its scope's history; applying it out of order",
+                entry.id
+            );
+        }
+        let later = rewind(world, history.applied.split_off(position));
+        let checkpoint = if later.is_empty() {
+            Some(checkpoint)
+        } else {
+            handlers.checkpoint(world, entry)
+        };
+        let result = handlers.apply(world, entry);
+        if let Some(checkpoint) = checkpoint {
+            history.applied.push_back(Applied {
+                entry: entry.clone(),
+                checkpoint,
+            });
+        }
+        replay(handlers, world, history, later);
+        while history.applied.len() > MAX_ORDERED_ENTRIES {
+            history.applied.pop_front();
+            history.truncated = true;
+        }
+        result
+    }
+
+    /// Undoes `superseded`, an entry the log dropped after it applied, and replays the
+    /// entries of its scope that came after it. Returns `false` when it is no longer
+    /// kept, e.g. because its command captures no checkpoint.
+    pub fn revoke(
+        &mut self,
+        handlers: &Handlers<'_>,
+        world: &mut World,
+        superseded: &CommandEntry,
+    ) -> bool {
+        let Some(history) = self.scopes.get_mut(&superseded.payload.scope) else {
+            return false;
+        };
+        let Some(position) = position_of(&history.applied, &superseded.id) else {
+            return false;
+        };
+        let later = rewind(world, history.applied.split_off(position));
+        replay(handlers, world, history, later.into_iter().skip(1));
+        true
+    }
+}
+
+fn position_of(applied: &VecDeque<Applied>, id: &CommandId) -> Option<usize> {
+    applied.iter().position(|applied| &applied.entry.id == id)
+}
+
+/// Restores the state from before the earliest of `rolled_back` and returns their
+/// entries for replay. Replay captures fresh checkpoints, so the others are dropped.
+fn rewind(world: &mut World, rolled_back: VecDeque<Applied>) -> Vec<CommandEntry> {
+    let mut rolled_back = rolled_back.into_iter();
+    let Some(first) = rolled_back.next() else {
+        return Vec::new();
+    };
+    (first.checkpoint)(world);
+    std::iter::once(first.entry)
+        .chain(rolled_back.map(|applied| applied.entry))
+        .collect()
+}
+
+fn replay(
+    handlers: &Handlers<'_>,
+    world: &mut World,
+    history: &mut ScopeHistory,
+    later: impl IntoIterator<Item = CommandEntry>,
+) {
+    for entry in later {
+        let checkpoint = handlers.checkpoint(world, &entry);
+        if let Err(err) = handlers.apply(world, &entry) {
+            log::warn!(
+                "[commands] failed to replay {:?} after an earlier entry: {err}",
+                entry.id
+            );
+        }
+        if let Some(checkpoint) = checkpoint {
+            history.applied.push_back(Applied { entry, checkpoint });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ecs::{Entity, World};
+    use crate::editor::commands::{FaceExtrudeCommand, MeshDeleteCommand, MeshElement};
+    use crate::editor::mesh::EditableMesh;
+    use crate::editor::primitives::PrimitiveShape;
+    use crate::engine::TypedCommand;
+    use crate::engine::{Checkpoint, CommandApplyError, CommandContext, CommandPipeline};
+    use crate::network::EntityHandle;
+    use crate::network::command_log::{
+        AuthorId, CommandAuthor, CommandRole, CommandScope, ConflictStrategy,
+        SharedKeyCommandSigner,
+    };
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Score(u32);
+
+    /// Doubles the score and adds a bonus, so applying it twice or out of order shows.
+    #[derive(Serialize, Deserialize)]
+    struct DoubleScore {
+        entity: EntityHandle,
+        bonus: u32,
+    }
+
+    impl TypedCommand for DoubleScore {
+        const TYPE_ID: &'static str = "test.score.double";
+
+        fn scope(&self) -> CommandScope {
+            CommandScope::Entity(self.entity)
+        }
+
+        fn strategy() -> ConflictStrategy {
+            ConflictStrategy::Reject
+        }
+
+        fn apply(
+            &self,
+            world: &mut World,
+            context: &CommandContext<'_>,
+        ) -> Result<(), CommandApplyError> {
+            let score = world
+                .get_mut::<Score>(context.resolve(self.entity))
+                .ok_or(CommandApplyError::MissingEntity(self.entity))?;
+            score.0 = score.0 * 2 + self.bonus;
+            Ok(())
+        }
+
+        fn checkpoint(&self, world: &World, context: &CommandContext<'_>) -> Option<Checkpoint> {
+            let entity = context.resolve(self.entity);
+            let before = *world.get::<Score>(entity)?;
+            Some(Box::new(move |world: &mut World| {
+                let _ = world.insert(entity, before);
+            }))
+        }
+    }
+
+    fn peer(author: u64) -> (CommandPipeline, World, Entity) {
+        let mut pipeline = CommandPipeline::new();
+        pipeline.register_command::<DoubleScore>();
+        pipeline.set_signer(Box::new(SharedKeyCommandSigner::new(
+            CommandAuthor::new(AuthorId(author), CommandRole::Editor),
+            [7; 16],
+        )));
+        let mut world = World::new();
+        let entity = world.spawn();
+        world.insert(entity, Score(1)).unwrap();
+        world
+            .insert(entity, PrimitiveShape::Cube { size: 1.0 }.build())
+            .unwrap();
+        (pipeline, world, entity)
+    }
+
+    fn deliver(from: &mut CommandPipeline, to: &mut CommandPipeline, world: &mut World) {
+        for packet in from.drain_packets() {
+            for entry in to.integrate_remote_packet(&packet).expect("integrate") {
+                to.apply_entry(world, &entry, None).expect("apply");
+            }
+        }
+    }
+
+    #[test]
+    fn superseded_rejects_are_undone_so_peers_converge() {
+        let (mut first, mut first_world, entity) = peer(1);
+        let (mut second, mut second_world, _) = peer(2);
+        let handle = EntityHandle::from(entity);
+
+        // Concurrent writes: the first peer's has the lower id and wins everywhere.
+        first
+            .submit(
+                &mut first_world,
+                &DoubleScore {
+                    entity: handle,
+                    bonus: 1,
+                },
+                None,
+            )
+            .expect("first write");
+        second
+            .submit(
+                &mut second_world,
+                &DoubleScore {
+                    entity: handle,
+                    bonus: 2,
+                },
+                None,
+            )
+            .expect("second write");
+        // Each peer sees its own write first, so only the second has to undo one.
+        let first_packets = first.drain_packets();
+        deliver(&mut second, &mut first, &mut first_world);
+        for packet in first_packets {
+            for entry in second.integrate_remote_packet(&packet).expect("integrate") {
+                second
+                    .apply_entry(&mut second_world, &entry, None)
+                    .expect("apply");
+            }
+        }
+
+        assert_eq!(first_world.get::<Score>(entity), Some(&Score(3)));
+        assert_eq!(second_world.get::<Score>(entity), Some(&Score(3)));
+    }
+
+    #[test]
+    fn concurrent_mesh_edits_converge_in_either_delivery_order() {
+        let (mut first, mut first_world, entity) = peer(1);
+        let (mut second, mut second_world, _) = peer(2);
+        let handle = EntityHandle::from(entity);
+        let face = first_world
+            .get::<EditableMesh>(entity)
+            .and_then(|mesh| mesh.face_ids().next())
+            .expect("cube face");
+
+        // One peer extrudes a face while the other deletes it.
+        first
+            .submit(
+                &mut first_world,
+                &FaceExtrudeCommand::new(face.0, 0.5).on_mesh(handle),
+                None,
+            )
+            .expect("extrude");
+        second
+            .submit(
+                &mut second_world,
+                &MeshDeleteCommand::new(MeshElement::Face(face.0)).on_mesh(handle),
+                None,
+            )
+            .expect("delete");
+        let first_packets = first.drain_packets();
+        deliver(&mut second, &mut first, &mut first_world);
+        for packet in first_packets {
+            for entry in second.integrate_remote_packet(&packet).expect("integrate") {
+                second
+                    .apply_entry(&mut second_world, &entry, None)
+                    .expect("apply");
+            }
+        }
+
+        let mesh = first_world.get::<EditableMesh>(entity).expect("mesh");
+        assert_ne!(mesh, &PrimitiveShape::Cube { size: 1.0 }.build());
+        assert_eq!(second_world.get::<EditableMesh>(entity), Some(mesh));
+    }
+}
@@ -0,0 +1,83 @@
+use super::{EditorSelection, Engine, Transform};
+use crate::editor::EditableMesh;
+use crate::network::EntityHandle;
+use crate::network::command_log::CommandEntry;
+use crate::network::inspector::CommandCapture;
+use crate::network::network_id::network_handle;
+use crate::render::RendererConfig;
+use serde::Serialize;
+
+/// Command-visible state of a world, comparable across peers to locate desyncs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorldState {
+    pub entities: Vec<EntityState>,
+    pub selection: Vec<EntityHandle>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EntityState {
+    pub entity: EntityHandle,
+    pub position: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<MeshSummary>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MeshSummary {
+    pub vertices: usize,
+    pub faces: usize,
+}
+
+impl Engine {
+    /// Builds a headless engine and applies `entries` in order, the way a peer joining
+    /// with that history would.
+    pub fn replay(entries: &[CommandEntry]) -> Self {
+        let mut engine = Self::with_renderer_config(RendererConfig::default());
+        engine.apply_remote_entries(entries);
+        engine
+    }
+
+    /// Records every command this engine appends or integrates from now on.
+    pub fn start_command_capture(&mut self) {
+        if let Ok(mut pipeline) = self.command_pipeline.lock() {
+            pipeline.start_capture();
+        }
+    }
+
+    pub fn take_command_capture(&mut self) -> Option<CommandCapture> {
+        self.command_pipeline
+            .lock()
+            .ok()
+            .and_then(|mut pipeline| pipeline.take_capture())
+    }
+
+    pub fn world_state(&self) -> WorldState {
+        let world = self.scheduler.world();
+        let mut entities: Vec<EntityState> = world
+            .component_entries::<Transform>()
+            .into_iter()
+            .map(|(entity, transform)| EntityState {
+                entity: network_handle(world, entity),
+                position: transform.position,
+                rotation: transform.rotation,
+                scale: transform.scale,
+                mesh: world.get::<EditableMesh>(entity).map(|mesh| MeshSummary {
+                    vertices: mesh.vertex_count(),
+                    faces: mesh.face_count(),
+                }),
+            })
+            .collect();
+        entities.sort_by_key(|state| (state.entity.index, state.entity.generation));
+        let selection = self
+            .command_entity
+            .and_then(|entity| world.get::<EditorSelection>(entity))
+            .map(|selection| selection.set.iter().collect())
+            .unwrap_or_default();
+        WorldState {
+            entities,
+            selection,
+        }
+    }
+}
@@ -0,0 +1,508 @@
+use super::{Engine, Transform};
+use crate::ecs::World;
+use crate::network::command_log::AuthorId;
+use crate::network::current_time_millis;
+use crate::network::interest::InterestManager;
+use crate::network::interpolation::{
+    InterpolationBuffer, InterpolationConfig, InterpolationHealth, TransformSample,
+};
+use crate::network::network_id::lock_entity_map;
+use crate::network::replication::{ApplyStats, ReplicationApplier, ReplicationRegistry};
+use crate::network::replication_stream::{
+    ReplicationHost, ReplicationMessage, ReplicationReceiver, ReplicationStreamConfig,
+};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Whether this engine streams its world to peers, mirrors a peer's world, or leaves
+/// replication to the command log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplicationMode {
+    #[default]
+    Off,
+    Publish,
+    Mirror,
+}
+
+pub(super) enum ReplicationState {
+    Off,
+    Publish {
+        host: Box<ReplicationHost>,
+    },
+    Mirror {
+        receiver: Box<ReplicationReceiver>,
+        interpolation: Box<InterpolationBuffer>,
+    },
+}
+
+pub(super) fn default_replication_registry() -> ReplicationRegistry {
+    let mut registry = ReplicationRegistry::new();
+    registry.register::<Transform>();
+    registry
+}
+
+impl From<&Transform> for TransformSample {
+    fn from(transform: &Transform) -> Self {
+        Self {
+            position: transform.position,
+            rotation: transform.rotation,
+            scale: transform.scale,
+        }
+    }
+}
+
+impl From<TransformSample> for Transform {
+    fn from(sample: TransformSample) -> Self {
+        Self {
+            position: sample.position,
+            rotation: sample.rotation,
+            scale: sample.scale,
+        }
+    }
+}
+
+/// Writes each buffered entity's newest received transform back, replacing the
+/// delayed one rendered last frame.
+fn restore_latest(world: &mut World, applier: &ReplicationApplier, buffer: &InterpolationBuffer) {
+    for remote in buffer.entities() {
+        if let (Some(local), Some(latest)) = (applier.local_entity(remote), buffer.latest(remote))
+            && world.contains(local)
+        {
+            let _ = world.insert(local, Transform::from(*latest));
+        }
+    }
+}
+
+impl Engine {
+    pub fn replication_mode(&self) -> ReplicationMode {
+        match self.replication {
+            ReplicationState::Off => ReplicationMode::Off,
+            ReplicationState::Publish { .. } => ReplicationMode::Publish,
+            ReplicationState::Mirror { .. } => ReplicationMode::Mirror,
+        }
+    }
+
+    /// Switching modes restarts the stream: a publisher sends a fresh snapshot and a
+    /// mirror resynchronizes the entities bound in the engine's entity map. A publisher
+    /// filters each peer's stream by its interest, locating entities by their transform.
+    /// A mirror predicts the transform commands it submits until the publisher has
+    /// applied them.
+    pub fn set_replication_mode(&mut self, mode: ReplicationMode) {
+        self.command_pipeline
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .set_prediction(mode == ReplicationMode::Mirror);
+        self.replication = match mode {
+            ReplicationMode::Off => ReplicationState::Off,
+            ReplicationMode::Publish => {
+                let interest = InterestManager::new()
+                    .with_position_source::<Transform>(|transform| transform.position);
+                let mut host = ReplicationHost::new(ReplicationStreamConfig::default(), interest);
+                if let Some(peer) = self.replication_peer.clone() {
+                    host.add_peer(peer);
+                }
+                ReplicationState::Publish {
+                    host: Box::new(host),
+                }
+            }
+            ReplicationMode::Mirror => ReplicationState::Mirror {
+                receiver: Box::new(
+                    ReplicationReceiver::new().with_entity_map(Arc::clone(&self.entity_map)),
+                ),
+                interpolation: Box::new(InterpolationBuffer::new(self.interpolation_config)),
+            },
+        };
+    }
+
+    /// Components streamed in replication modes; entity transforms are registered by default.
+    pub fn replication_registry_mut(&mut self) -> &mut ReplicationRegistry {
+        &mut self.replication_registry
+    }
+
+    /// The peer at the other end of the command transport, which a publisher streams
+    /// to. A client learns the host from the handshake; a host sets each peer here, since
+    /// the authors on relayed commands say nothing about who sits on the connection.
+    /// Setting it starts the peer's stream over with a snapshot.
+    pub fn set_replication_peer(&mut self, peer: AuthorId) {
+        self.forget_replication_peer();
+        self.replication_peer = Some(peer.clone());
+        if let ReplicationState::Publish { host } = &mut self.replication {
+            host.add_peer(peer);
+        }
+    }
+
+    pub fn replication_peer(&self) -> Option<&AuthorId> {
+        self.replication_peer.as_ref()
+    }
+
+    pub(super) fn forget_replication_peer(&mut self) {
+        if let Some(peer) = self.replication_peer.take()
+            && let ReplicationState::Publish { host } = &mut self.replication
+        {
+            host.remove_peer(&peer);
+        }
+    }
+
+    /// Head positions, subscriptions and groups that decide what each peer receives.
+    /// `None` unless publishing.
+    pub fn replication_interest_mut(&mut self) -> Option<&mut InterestManager> {
+        match &mut self.replication {
+            ReplicationState::Publish { host } => Some(host.interest_mut()),
+            _ => None,
+        }
+    }
+
+    /// Messages to send this tick per peer: the snapshot when a stream (re)starts, then
+    /// deltas and resends of what is relevant to the peer. Empty unless publishing.
+    pub fn outgoing_replication(
+        &mut self,
+        now: Instant,
+    ) -> Vec<(AuthorId, Vec<ReplicationMessage>)> {
+        let ReplicationState::Publish { host } = &mut self.replication else {
+            return Vec::new();
+        };
+        let versions = self
+            .command_pipeline
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .version_vector()
+            .clone();
+        host.acknowledge_commands(&versions);
+        host.tick(&self.replication_registry, self.scheduler.world(), now)
+    }
+
+    pub fn acknowledge_replication(&mut self, peer: &AuthorId, sequence: u64) {
+        if let ReplicationState::Publish { host } = &mut self.replication {
+            let confirmed = host.acknowledge(peer, sequence);
+            lock_entity_map(&self.entity_map).confirm(confirmed);
+        }
+    }
+
+    /// Sends `peer` a fresh snapshot on the next tick when publishing, for a mirror that
+    /// asked through [`Self::take_replication_resync`].
+    pub fn resync_replication(&mut self, peer: &AuthorId) {
+        if let ReplicationState::Publish { host } = &mut self.replication {
+            host.request_resync(peer);
+        }
+    }
+
+    /// Whether the mirror lost a patch baseline since the last call and needs a snapshot.
+    pub fn take_replication_resync(&mut self) -> bool {
+        match &mut self.replication {
+            ReplicationState::Mirror { receiver, .. } => receiver.take_resync_request(),
+            _ => false,
+        }
+    }
+
+    /// Applies a publisher's message when mirroring and returns the cumulative
+    /// acknowledgement to send back. The publisher is the session host, so changes to
+    /// entities another peer owns are dropped. Mirrored transforms are buffered and
+    /// rendered through [`InterpolationConfig::delay_ms`] on the next frame, except
+    /// predicted ones, which replay the local commands the publisher has not applied.
+    pub fn receive_replication(
+        &mut self,
+        message: ReplicationMessage,
+    ) -> Option<(u64, ApplyStats)> {
+        let ReplicationState::Mirror {
+            receiver,
+            interpolation,
+        } = &mut self.replication
+        else {
+            return None;
+        };
+        let mut pipeline = self
+            .command_pipeline
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let authority = pipeline.authority().clone();
+        if let Some(host) = authority.host().cloned() {
+            receiver.set_authority(authority, host);
+        }
+        let world = self.scheduler.world_mut();
+        restore_latest(world, receiver.applier(), interpolation);
+        pipeline.rewind_predictions(world);
+        let previous = receiver.timestamp_ms();
+        let stats = receiver.receive(&self.replication_registry, world, message);
+        pipeline.reconcile_predictions(world, receiver.command_ack(), self.command_entity);
+        if receiver.timestamp_ms() > previous {
+            let received_ms = current_time_millis();
+            let applier = receiver.applier();
+            interpolation.retain(|remote| {
+                applier
+                    .local_entity(remote)
+                    .is_some_and(|local| !pipeline.is_predicting(local))
+            });
+            for (remote, local) in applier.entities() {
+                if pipeline.is_predicting(local) {
+                    continue;
+                }
+                if let Some(transform) = world.get::<Transform>(local) {
+                    interpolation.push(
+                        remote,
+                        receiver.acknowledged(),
+                        receiver.timestamp_ms(),
+                        TransformSample::from(transform),
+                        received_ms,
+                    );
+                }
+            }
+        }
+        Some((receiver.acknowledged(), stats))
+    }
+
+    pub fn replication_applier(&self) -> Option<&ReplicationApplier> {
+        match &self.replication {
+            ReplicationState::Mirror { receiver, .. } => Some(receiver.applier()),
+            _ => None,
+        }
+    }
+
+    /// Applies to mirrors created afterwards as well as the current one.
+    pub fn set_interpolation_config(&mut self, config: InterpolationConfig) {
+        self.interpolation_config = config;
+        if let ReplicationState::Mirror { interpolation, .. } = &mut self.replication {
+            interpolation.set_config(config);
+        }
+    }
+
+    /// Moves mirrored transforms to their interpolated pose at `now_ms`.
+    pub(super) fn interpolate_remote_transforms(
+        &mut self,
+        now_ms: u64,
+    ) -> Option<InterpolationHealth> {
+        let ReplicationState::Mirror {
+            receiver,
+            interpolation,
+        } = &mut self.replication
+        else {
+            return None;
+        };
+        let pipeline = self
+            .command_pipeline
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let world = self.scheduler.world_mut();
+        for (remote, sample) in interpolation.advance(now_ms) {
+            if let Some(local) = receiver.applier().local_entity(remote)
+                && world.contains(local)
+                && !pipeline.is_predicting(local)
+            {
+                let _ = world.insert(local, Transform::from(sample));
+            }
+        }
+        Some(interpolation.health().clone())
+    }
+
+    /// Restarts mirroring for a newly attached transport; publishers restart per peer
+    /// through [`Self::set_replication_peer`].
+    #[cfg_attr(not(feature = "network-quic"), allow(dead_code))]
+    pub(super) fn restart_replication(&mut self) {
+        if self.replication_mode() == ReplicationMode::Mirror {
+            self.set_replication_mode(ReplicationMode::Mirror);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::PrimitiveShape;
+    use crate::editor::commands::EntityTranslateCommand;
+    use crate::network::command_log::{
+        CommandAuthor, CommandPacket, CommandRole, NoopCommandSigner,
+    };
+    use crate::render::RendererConfig;
+    use std::collections::VecDeque;
+    use std::time::Duration;
+
+    #[test]
+    fn mirror_engine_follows_publisher_through_lost_messages() {
+        let mut host = Engine::with_renderer_config(RendererConfig::default());
+        let mut mirror = Engine::with_renderer_config(RendererConfig::default());
+        let peer = AuthorId(1);
+        host.set_replication_mode(ReplicationMode::Publish);
+        host.set_replication_peer(peer.clone());
+        mirror.set_replication_mode(ReplicationMode::Mirror);
+        let outgoing = |host: &mut Engine, now| -> Vec<ReplicationMessage> {
+            host.outgoing_replication(now)
+                .into_iter()
+                .flat_map(|(_, messages)| messages)
+                .collect()
+        };
+
+        let world = host.scheduler.world_mut();
+        let crate_entity = world.spawn();
+        world.insert(crate_entity, Transform::default()).unwrap();
+        let start = Instant::now();
+        let snapshot = outgoing(&mut host, start);
+        assert_eq!(snapshot.len(), 1);
+
+        host.scheduler
+            .world_mut()
+            .get_mut::<Transform>(crate_entity)
+            .unwrap()
+            .position = [1.0, 2.0, 3.0];
+        let delta = outgoing(&mut host, start);
+        // The snapshot is lost; the delta waits until the resend fills the gap.
+        let (ack, _) = mirror.receive_replication(delta[0].clone()).unwrap();
+        assert_eq!(ack, 0);
+
+        for message in outgoing(&mut host, start + Duration::from_secs(1)) {
+            let (ack, _) = mirror.receive_replication(message).unwrap();
+            host.acknowledge_replication(&peer, ack);
+        }
+        let mirrored = mirror
+            .replication_applier()
+            .and_then(|applier| applier.local_entity(crate_entity.into()))
+            .expect("entity mirrored");
+        let transform = mirror.scheduler.world().get::<Transform>(mirrored).unwrap();
+        assert_eq!(transform.position, [1.0, 2.0, 3.0]);
+        assert!(outgoing(&mut host, start + Duration::from_secs(2)).is_empty());
+
+        // Far outside the peer's interest the crate stops being replicated to it.
+        host.replication_interest_mut()
+            .unwrap()
+            .peer_mut(peer.clone())
+            .set_head_position([100.0, 0.0, 0.0]);
+        host.replication_interest_mut()
+            .unwrap()
+            .peer_mut(peer.clone())
+            .radius = Some(10.0);
+        let leaving = outgoing(&mut host, start + Duration::from_secs(3));
+        for message in leaving {
+            let (ack, _) = mirror.receive_replication(message).unwrap();
+            host.acknowledge_replication(&peer, ack);
+        }
+        assert!(
+            mirror
+                .scheduler
+                .world()
+                .get::<Transform>(mirrored)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn reentering_mirror_updates_the_entities_already_mirrored() {
+        let mut host = Engine::with_renderer_config(RendererConfig::default());
+        let mut mirror = Engine::with_renderer_config(RendererConfig::default());
+        let world = host.scheduler.world_mut();
+        let crate_entity = world.spawn();
+        world.insert(crate_entity, Transform::default()).unwrap();
+
+        let sync = |host: &mut Engine, mirror: &mut Engine| {
+            host.set_replication_mode(ReplicationMode::Publish);
+            host.set_replication_peer(AuthorId(1));
+            mirror.set_replication_mode(ReplicationMode::Mirror);
+            for (_, messages) in host.outgoing_replication(Instant::now()) {
+                for message in messages {
+                    mirror.receive_replication(message);
+                }
+            }
+            mirror
+                .replication_applier()
+                .and_then(|applier| applier.local_entity(crate_entity.into()))
+                .expect("entity mirrored")
+        };
+        let first = sync(&mut host, &mut mirror);
+        let transforms = mirror.world().component_entries::<Transform>().len();
+        assert_eq!(sync(&mut host, &mut mirror), first);
+        assert_eq!(
+            mirror.world().component_entries::<Transform>().len(),
+            transforms
+        );
+    }
+
+    #[test]
+    fn mirror_predicts_its_transform_commands_under_150ms_latency() {
+        const FRAME: Duration = Duration::from_millis(10);
+        const LATENCY_FRAMES: u32 = 15;
+        const STEP: f32 = 0.01;
+
+        let mut host = Engine::with_renderer_config(RendererConfig::default());
+        let mut client = Engine::with_renderer_config(RendererConfig::default());
+        let peer = AuthorId(1);
+        client
+            .command_pipeline
+            .lock()
+            .unwrap()
+            .set_signer(Box::new(NoopCommandSigner::new(CommandAuthor::new(
+                peer.clone(),
+                CommandRole::Editor,
+            ))));
+        let handle = host
+            .create_primitive(PrimitiveShape::Cube { size: 1.0 }, [0.0; 3])
+            .expect("create crate");
+        host.set_replication_mode(ReplicationMode::Publish);
+        host.set_replication_peer(peer.clone());
+        client.set_replication_mode(ReplicationMode::Mirror);
+
+        let mut to_host: VecDeque<(u32, CommandPacket)> = VecDeque::new();
+        let mut acks: VecDeque<(u32, u64)> = VecDeque::new();
+        let mut to_client: VecDeque<(u32, ReplicationMessage)> = VecDeque::new();
+        let mut observed = Vec::new();
+        let start = Instant::now();
+        for frame in 0..160u32 {
+            let mirrored = client.resolve_entity(handle);
+            let position = |engine: &Engine| {
+                engine
+                    .world()
+                    .get::<Transform>(mirrored)
+                    .map(|transform| transform.position[0])
+            };
+            // The client drags the crate for 80 frames once it is mirrored.
+            if (20..100).contains(&frame) {
+                assert!(position(&client).is_some(), "crate mirrored by frame 20");
+                client
+                    .submit_command(EntityTranslateCommand::new(handle, [STEP, 0.0, 0.0]))
+                    .expect("translate");
+            }
+            let packets = client.command_pipeline.lock().unwrap().drain_packets();
+            to_host.extend(
+                packets
+                    .into_iter()
+                    .map(|packet| (frame + LATENCY_FRAMES, packet)),
+            );
+
+            while to_host.front().is_some_and(|(due, _)| *due <= frame) {
+                let (_, packet) = to_host.pop_front().unwrap();
+                let entries = host
+                    .command_pipeline
+                    .lock()
+                    .unwrap()
+                    .integrate_remote_packet(&packet)
+                    .expect("integrate");
+                host.apply_remote_entries(&entries);
+            }
+            while acks.front().is_some_and(|(due, _)| *due <= frame) {
+                let (_, ack) = acks.pop_front().unwrap();
+                host.acknowledge_replication(&peer, ack);
+            }
+            for (_, messages) in host.outgoing_replication(start + FRAME * frame) {
+                to_client.extend(
+                    messages
+                        .into_iter()
+                        .map(|message| (frame + LATENCY_FRAMES, message)),
+                );
+            }
+            while to_client.front().is_some_and(|(due, _)| *due <= frame) {
+                let (_, message) = to_client.pop_front().unwrap();
+                let (ack, _) = client.receive_replication(message).unwrap();
+                acks.push_back((frame + LATENCY_FRAMES, ack));
+            }
+            observed.extend(position(&client));
+        }
+
+        // Local moves show at once and replies from the host never pull the crate back.
+        for pair in observed.windows(2) {
+            let step = pair[1] - pair[0];
+            assert!((-1e-5..=STEP + 1e-5).contains(&step), "jumped by {step}");
+        }
+        let hosted = host.resolve_entity(handle);
+        let expected = host.world().get::<Transform>(hosted).unwrap().position[0];
+        assert!((expected - 80.0 * STEP).abs() < 1e-4);
+        assert!((observed.last().unwrap() - expected).abs() < 1e-5);
+        let pipeline = client.command_pipeline.lock().unwrap();
+        assert!(!pipeline.is_predicting(client.resolve_entity(handle)));
+    }
+}
@@ -0,0 +1,329 @@
+use crate::ecs::{EcsError, Entity, World};
+use crate::editor::mesh::MeshError;
+use crate::network::EntityHandle;
+use crate::network::command_log::{
+    CommandDefinition, CommandEntry, CommandPayload, CommandRegistry, CommandRole, CommandScope,
+    ConflictStrategy,
+};
+use crate::network::network_id::{SharedEntityMap, lock_entity_map};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use siphasher::sip::SipHasher24;
+use std::collections::HashMap;
+use std::hash::Hasher;
+use thiserror::Error;
+
+/// A replicated command that knows its wire identity, registry definition and how to
+/// apply itself to the world. Implement it and register the type with
+/// [`crate::engine::Engine::register_command`] to replicate it without touching the engine.
+pub trait TypedCommand: Serialize + DeserializeOwned + Send + Sync + 'static {
+    const TYPE_ID: &'static str;
+
+    fn scope(&self) -> CommandScope;
+
+    fn required_role() -> CommandRole {
+        CommandRole::Editor
+    }
+
+    fn strategy() -> ConflictStrategy {
+        ConflictStrategy::LastWriteWins
+    }
+
+    fn definition() -> CommandDefinition {
+        CommandDefinition::builder()
+            .required_role(Self::required_role())
+            .default_strategy(Self::strategy())
+            .require_signature(false)
+            .build()
+    }
+
+    fn apply(
+        &self,
+        world: &mut World,
+        context: &CommandContext<'_>,
+    ) -> Result<(), CommandApplyError>;
+
+    /// Captures the state `apply` is about to change. Commands that return one are kept
+    /// so an entry arriving late can be applied in command id order, and an entry the
+    /// log drops after applying it can be undone. The default opts out.
+    fn checkpoint(&self, _world: &World, _context: &CommandContext<'_>) -> Option<Checkpoint> {
+        None
+    }
+
+    fn to_payload(&self) -> Result<CommandPayload, serde_json::Error> {
+        Ok(CommandPayload::new(
+            Self::TYPE_ID,
+            self.scope(),
+            serde_json::to_vec(self)?,
+        ))
+    }
+}
+
+/// Puts back the state a [`TypedCommand::checkpoint`] captured.
+pub type Checkpoint = Box<dyn FnOnce(&mut World) + Send>;
+
+/// A command a continuous drag emits every frame. The pipeline folds consecutive
+/// commands of one type and scope into a single log entry; see
+/// [`crate::engine::CommandPipeline::submit_coalesced`].
+pub trait CoalescingCommand: TypedCommand + Clone {
+    /// Folds `next`, issued after `self`, into `self`.
+    fn coalesce(&mut self, next: &Self);
+}
+
+/// Everything a handler may need besides the decoded command itself.
+#[derive(Debug, Clone, Copy)]
+pub struct CommandContext<'a> {
+    pub entry: &'a CommandEntry,
+    pub editor_entity: Option<Entity>,
+    /// Binds the network ids commands name entities by to local entities.
+    pub entities: &'a SharedEntityMap,
+}
+
+impl CommandContext<'_> {
+    pub fn lamport(&self) -> u64 {
+        self.entry.id.lamport()
+    }
+
+    /// Derived from the command id, so every peer applying the command gets the same
+    /// seed for the ids it creates.
+    pub fn id_seed(&self) -> u64 {
+        let mut hasher = SipHasher24::new();
+        hasher.write(&self.lamport().to_le_bytes());
+        hasher.write(&self.entry.id.author().0.to_le_bytes());
+        hasher.finish()
+    }
+
+    /// The local entity `handle` names on this peer.
+    pub fn resolve(&self, handle: EntityHandle) -> Entity {
+        lock_entity_map(self.entities).resolve(handle)
+    }
+
+    pub fn require_editor_entity(&self) -> Result<Entity, CommandApplyError> {
+        self.editor_entity
+            .ok_or(CommandApplyError::MissingComponent("editor entity"))
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CommandApplyError {
+    #[error("failed to decode {command_type} payload: {source}")]
+    Decode {
+        command_type: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("command target {0:?} is missing locally")]
+    MissingEntity(EntityHandle),
+    #[error("command target {0:?} already exists")]
+    EntityExists(EntityHandle),
+    #[error("command target {0:?} is not an allocated network id")]
+    UnallocatedEntity(EntityHandle),
+    #[error("required component {0} is missing")]
+    MissingComponent(&'static str),
+    #[error("command carries unexpected scope {0:?}")]
+    InvalidScope(CommandScope),
+    #[error(transparent)]
+    Mesh(#[from] MeshError),
+    #[error(transparent)]
+    Ecs(#[from] EcsError),
+}
+
+type ApplyFn = fn(&mut World, &CommandContext<'_>) -> Result<(), CommandApplyError>;
+type CheckpointFn = fn(&World, &CommandContext<'_>) -> Option<Checkpoint>;
+
+/// Maps command type ids to the decode-and-apply routine of their [`TypedCommand`].
+#[derive(Default, Clone)]
+pub struct CommandHandlerRegistry {
+    handlers: HashMap<&'static str, (ApplyFn, CheckpointFn)>,
+}
+
+impl CommandHandlerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<C: TypedCommand>(&mut self) {
+        self.handlers
+            .insert(C::TYPE_ID, (apply_typed::<C>, checkpoint_typed::<C>));
+    }
+
+    /// Registers the handler and the matching definition in `registry`.
+    pub fn register_with<C: TypedCommand>(&mut self, registry: &mut CommandRegistry) {
+        registry.register(C::TYPE_ID, C::definition());
+        self.register::<C>();
+    }
+
+    pub fn contains(&self, command_type: &str) -> bool {
+        self.handlers.contains_key(command_type)
+    }
+
+    pub fn command_types(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.handlers.keys().copied()
+    }
+
+    /// Applies `entry` to the world, resolving entity handles through `entities`.
+    /// Returns `Ok(false)` when no handler is registered.
+    pub fn apply(
+        &self,
+        world: &mut World,
+        entry: &CommandEntry,
+        editor_entity: Option<Entity>,
+        entities: &SharedEntityMap,
+    ) -> Result<bool, CommandApplyError> {
+        let Some((apply, _)) = self.handlers.get(entry.payload.command_type.as_str()) else {
+            return Ok(false);
+        };
+        let context = CommandContext {
+            entry,
+            editor_entity,
+            entities,
+        };
+        apply(world, &context)?;
+        Ok(true)
+    }
+
+    /// The state `entry` would change, if its command captures one.
+    pub fn checkpoint(
+        &self,
+        world: &World,
+        entry: &CommandEntry,
+        editor_entity: Option<Entity>,
+        entities: &SharedEntityMap,
+    ) -> Option<Checkpoint> {
+        let (_, checkpoint) = self.handlers.get(entry.payload.command_type.as_str())?;
+        let context = CommandContext {
+            entry,
+            editor_entity,
+            entities,
+        };
+        checkpoint(world, &context)
+    }
+}
+
+fn apply_typed<C: TypedCommand>(
+    world: &mut World,
+    context: &CommandContext<'_>,
+) -> Result<(), CommandApplyError> {
+    let command: C = serde_json::from_slice(&context.entry.payload.data).map_err(|source| {
+        CommandApplyError::Decode {
+            command_type: C::TYPE_ID.to_string(),
+            source,
+        }
+    })?;
+    // The log checks locks and access against the entry's scope, so a command may only
+    // touch what that scope names.
+    if command.scope() != context.entry.payload.scope {
+        return Err(CommandApplyError::InvalidScope(
+            context.entry.payload.scope.clone(),
+        ));
+    }
+    command.apply(world, context)
+}
+
+fn checkpoint_typed<C: TypedCommand>(
+    world: &World,
+    context: &CommandContext<'_>,
+) -> Option<Checkpoint> {
+    let command = serde_json::from_slice::<C>(&context.entry.payload.data).ok()?;
+    if command.scope() != context.entry.payload.scope {
+        return None;
+    }
+    command.checkpoint(world, context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::command_log::{AuthorId, CommandAuthor, CommandId};
+    use crate::network::network_id::NetworkIdAllocator;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, Default, PartialEq)]
+    struct Score(u32);
+
+    #[derive(Serialize, Deserialize)]
+    struct AddScore {
+        entity: EntityHandle,
+        amount: u32,
+    }
+
+    impl TypedCommand for AddScore {
+        const TYPE_ID: &'static str = "game.score.add";
+
+        fn scope(&self) -> CommandScope {
+            CommandScope::Entity(self.entity)
+        }
+
+        fn strategy() -> ConflictStrategy {
+            ConflictStrategy::Merge
+        }
+
+        fn apply(
+            &self,
+            world: &mut World,
+            context: &CommandContext<'_>,
+        ) -> Result<(), CommandApplyError> {
+            let score = world
+                .get_mut::<Score>(context.resolve(self.entity))
+                .ok_or(CommandApplyError::MissingEntity(self.entity))?;
+            score.0 += self.amount;
+            Ok(())
+        }
+    }
+
+    fn entry_for(command: &AddScore) -> CommandEntry {
+        CommandEntry::new(
+            CommandId::new(1, AuthorId(5)),
+            0,
+            command.to_payload().expect("payload"),
+            AddScore::strategy(),
+            CommandAuthor::new(AuthorId(5), CommandRole::Editor),
+            None,
+        )
+    }
+
+    #[test]
+    fn registered_commands_decode_and_apply() {
+        let mut world = World::new();
+        let entities = SharedEntityMap::default();
+        let (entity, id) =
+            NetworkIdAllocator::new(1).spawn(&mut world, &mut lock_entity_map(&entities));
+        world.insert(entity, Score(1)).unwrap();
+
+        let mut registry = CommandRegistry::new();
+        let mut handlers = CommandHandlerRegistry::new();
+        handlers.register_with::<AddScore>(&mut registry);
+        assert!(handlers.contains(AddScore::TYPE_ID));
+
+        let command = AddScore {
+            entity: id.into(),
+            amount: 4,
+        };
+        assert!(
+            handlers
+                .apply(&mut world, &entry_for(&command), None, &entities)
+                .unwrap()
+        );
+        assert_eq!(world.get::<Score>(entity), Some(&Score(5)));
+
+        let missing = AddScore {
+            entity: EntityHandle {
+                index: 99,
+                generation: 0,
+            },
+            amount: 1,
+        };
+        assert!(matches!(
+            handlers.apply(&mut world, &entry_for(&missing), None, &entities),
+            Err(CommandApplyError::MissingEntity(_))
+        ));
+
+        let mut unknown = entry_for(&command);
+        unknown.payload.command_type = "game.unknown".to_string();
+        assert!(
+            !handlers
+                .apply(&mut world, &unknown, None, &entities)
+                .unwrap()
+        );
+    }
+}
@@ -0,0 +1,343 @@
+use crate::network::EntityHandle;
+use crate::network::command_log::{
+    AuthorId, CommandAuthor, CommandDefinition, CommandId, CommandPayload, CommandRegistry,
+    CommandRole, CommandScope, ConflictStrategy,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Error as JsonError;
+use std::collections::{BTreeSet, HashMap};
+
+pub const CMD_ACCESS_SET_ROLE: &str = "session.access.set_role";
+pub const CMD_ACCESS_ENTITY_OWNER: &str = "session.access.entity_owner";
+pub const CMD_ACCESS_TOOL_GRANT: &str = "session.access.tool_grant";
+
+/// Promotes or demotes an author at runtime. Overrides the role the author declares.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SetRoleCommand {
+    pub author: AuthorId,
+    pub role: CommandRole,
+}
+
+impl SetRoleCommand {
+    pub fn new(author: AuthorId, role: CommandRole) -> Self {
+        Self { author, role }
+    }
+}
+
+/// Assigns (or clears) the single author allowed to edit an entity scope.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EntityOwnershipCommand {
+    pub entity: EntityHandle,
+    pub owner: Option<AuthorId>,
+}
+
+impl EntityOwnershipCommand {
+    pub fn new(entity: EntityHandle, owner: Option<AuthorId>) -> Self {
+        Self { entity, owner }
+    }
+}
+
+/// Grants or revokes tool-specific rights. Once a tool has at least one grant,
+/// only granted authors (and admins) may issue commands in its scope.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ToolGrantCommand {
+    pub tool_id: String,
+    pub author: AuthorId,
+    pub granted: bool,
+}
+
+impl ToolGrantCommand {
+    pub fn new(tool_id: impl Into<String>, author: AuthorId, granted: bool) -> Self {
+        Self {
+            tool_id: tool_id.into(),
+            author,
+            granted,
+        }
+    }
+}
+
+pub fn is_access_command(command_type: &str) -> bool {
+    matches!(
+        command_type,
+        CMD_ACCESS_SET_ROLE | CMD_ACCESS_ENTITY_OWNER | CMD_ACCESS_TOOL_GRANT
+    )
+}
+
+/// Registers the admin-only access commands with the provided registry.
+pub fn register_access_commands(registry: &mut CommandRegistry) {
+    for command_type in [
+        CMD_ACCESS_SET_ROLE,
+        CMD_ACCESS_ENTITY_OWNER,
+        CMD_ACCESS_TOOL_GRANT,
+    ] {
+        registry.register(
+            command_type,
+            CommandDefinition::builder()
+                .required_role(CommandRole::Admin)
+                .default_strategy(ConflictStrategy::Merge)
+                .require_signature(true)
+                .build(),
+        );
+    }
+}
+
+/// The setting an access command changes; concurrent changes to one key resolve to the
+/// higher command id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum AccessKey {
+    Role(AuthorId),
+    EntityOwner(EntityHandle),
+    ToolGrant(String, AuthorId),
+}
+
+/// Replicated per-scope permissions layered on top of the role hierarchy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessControlList {
+    roles: HashMap<AuthorId, CommandRole>,
+    /// Role of remote authors nobody granted one; what they declare is not trusted.
+    remote_role: CommandRole,
+    entity_owners: HashMap<EntityHandle, AuthorId>,
+    tool_grants: HashMap<String, BTreeSet<AuthorId>>,
+    changed_by: HashMap<AccessKey, CommandId>,
+}
+
+impl Default for AccessControlList {
+    fn default() -> Self {
+        Self {
+            roles: HashMap::new(),
+            remote_role: CommandRole::Editor,
+            entity_owners: HashMap::new(),
+            tool_grants: HashMap::new(),
+            changed_by: HashMap::new(),
+        }
+    }
+}
+
+impl AccessControlList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Role granted at runtime. Otherwise the role the author declares if it is
+    /// `trusted`, i.e. this peer or the session host, and [`Self::remote_role`] for
+    /// anyone else, since a remote peer can declare any role it likes.
+    pub fn effective_role(&self, author: &CommandAuthor, trusted: bool) -> CommandRole {
+        match self.roles.get(&author.id) {
+            Some(role) => *role,
+            None if trusted => author.role,
+            None => self.remote_role,
+        }
+    }
+
+    pub fn remote_role(&self) -> CommandRole {
+        self.remote_role
+    }
+
+    /// Sets the role remote authors get until one is granted to them; editors by default.
+    pub fn set_remote_role(&mut self, role: CommandRole) {
+        self.remote_role = role;
+    }
+
+    pub fn role_override(&self, author: &AuthorId) -> Option<CommandRole> {
+        self.roles.get(author).copied()
+    }
+
+    pub fn entity_owner(&self, entity: &EntityHandle) -> Option<&AuthorId> {
+        self.entity_owners.get(entity)
+    }
+
+    pub fn tool_grants(&self, tool_id: &str) -> impl Iterator<Item = &AuthorId> {
+        self.tool_grants.get(tool_id).into_iter().flatten()
+    }
+
+    pub fn set_role(&mut self, author: AuthorId, role: CommandRole) {
+        self.roles.insert(author, role);
+    }
+
+    pub fn set_entity_owner(&mut self, entity: EntityHandle, owner: Option<AuthorId>) {
+        match owner {
+            Some(owner) => {
+                self.entity_owners.insert(entity, owner);
+            }
+            None => {
+                self.entity_owners.remove(&entity);
+            }
+        }
+    }
+
+    pub fn set_tool_grant(&mut self, tool_id: &str, author: AuthorId, granted: bool) {
+        if granted {
+            self.tool_grants
+                .entry(tool_id.to_string())
+                .or_default()
+                .insert(author);
+        } else if let Some(grants) = self.tool_grants.get_mut(tool_id) {
+            grants.remove(&author);
+            if grants.is_empty() {
+                self.tool_grants.remove(tool_id);
+            }
+        }
+    }
+
+    /// Returns true when `author` (holding `role`) may issue commands in `scope`.
+    pub fn permits(&self, author: &AuthorId, role: CommandRole, scope: &CommandScope) -> bool {
+        if role == CommandRole::Admin {
+            return true;
+        }
+
+        match scope {
+            CommandScope::Global => true,
+            CommandScope::Entity(handle) => self
+                .entity_owners
+                .get(handle)
+                .is_none_or(|owner| owner == author),
+            CommandScope::Tool(tool_id) => self
+                .tool_grants
+                .get(tool_id)
+                .is_none_or(|grants| grants.contains(author)),
+        }
+    }
+
+    /// Applies the access command `id` carries. Returns `Ok(false)` for unrelated
+    /// commands. A change older than the last one applied to the same setting is kept
+    /// out, so peers agree whatever order concurrent changes arrive in.
+    pub fn apply(&mut self, id: &CommandId, payload: &CommandPayload) -> Result<bool, JsonError> {
+        match payload.command_type.as_str() {
+            CMD_ACCESS_SET_ROLE => {
+                let command: SetRoleCommand = serde_json::from_slice(&payload.data)?;
+                if self.supersede(AccessKey::Role(command.author.clone()), id) {
+                    self.set_role(command.author, command.role);
+                }
+                Ok(true)
+            }
+            CMD_ACCESS_ENTITY_OWNER => {
+                let command: EntityOwnershipCommand = serde_json::from_slice(&payload.data)?;
+                if self.supersede(AccessKey::EntityOwner(command.entity), id) {
+                    self.set_entity_owner(command.entity, command.owner);
+                }
+                Ok(true)
+            }
+            CMD_ACCESS_TOOL_GRANT => {
+                let command: ToolGrantCommand = serde_json::from_slice(&payload.data)?;
+                let key = AccessKey::ToolGrant(command.tool_id.clone(), command.author.clone());
+                if self.supersede(key, id) {
+                    self.set_tool_grant(&command.tool_id, command.author, command.granted);
+                }
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn supersede(&mut self, key: AccessKey, id: &CommandId) -> bool {
+        if self.changed_by.get(&key).is_some_and(|last| last > id) {
+            return false;
+        }
+        self.changed_by.insert(key, id.clone());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handle(index: u32) -> EntityHandle {
+        EntityHandle {
+            index,
+            generation: 0,
+        }
+    }
+
+    #[test]
+    fn entity_owner_restricts_non_admins() {
+        let mut acl = AccessControlList::new();
+        acl.set_entity_owner(handle(4), Some(AuthorId(1)));
+
+        let scope = CommandScope::Entity(handle(4));
+        assert!(acl.permits(&AuthorId(1), CommandRole::Editor, &scope));
+        assert!(!acl.permits(&AuthorId(2), CommandRole::Editor, &scope));
+        assert!(acl.permits(&AuthorId(2), CommandRole::Admin, &scope));
+        assert!(acl.permits(
+            &AuthorId(2),
+            CommandRole::Editor,
+            &CommandScope::Entity(handle(5))
+        ));
+
+        acl.set_entity_owner(handle(4), None);
+        assert!(acl.permits(&AuthorId(2), CommandRole::Editor, &scope));
+    }
+
+    #[test]
+    fn tool_grants_apply_once_a_tool_is_restricted() {
+        let mut acl = AccessControlList::new();
+        let scope = CommandScope::Tool("sculpt".into());
+        assert!(acl.permits(&AuthorId(7), CommandRole::Editor, &scope));
+
+        acl.set_tool_grant("sculpt", AuthorId(3), true);
+        assert!(acl.permits(&AuthorId(3), CommandRole::Editor, &scope));
+        assert!(!acl.permits(&AuthorId(7), CommandRole::Editor, &scope));
+
+        acl.set_tool_grant("sculpt", AuthorId(3), false);
+        assert!(acl.tool_grants("sculpt").next().is_none());
+        assert!(acl.permits(&AuthorId(7), CommandRole::Editor, &scope));
+    }
+
+    #[test]
+    fn apply_decodes_access_payloads() {
+        let mut acl = AccessControlList::new();
+        let author = CommandAuthor::new(AuthorId(9), CommandRole::Viewer);
+        assert_eq!(acl.effective_role(&author, true), CommandRole::Viewer);
+        let claims_admin = CommandAuthor::new(AuthorId(9), CommandRole::Admin);
+        assert_eq!(
+            acl.effective_role(&claims_admin, false),
+            CommandRole::Editor
+        );
+        acl.set_remote_role(CommandRole::Viewer);
+        assert_eq!(
+            acl.effective_role(&claims_admin, false),
+            CommandRole::Viewer
+        );
+
+        let promote = CommandPayload::new(
+            CMD_ACCESS_SET_ROLE,
+            CommandScope::Global,
+            serde_json::to_vec(&SetRoleCommand::new(AuthorId(9), CommandRole::Editor)).unwrap(),
+        );
+        let id = CommandId::new(1, AuthorId(1));
+        assert!(acl.apply(&id, &promote).expect("decode role change"));
+        assert_eq!(acl.effective_role(&author, true), CommandRole::Editor);
+        assert_eq!(acl.effective_role(&author, false), CommandRole::Editor);
+
+        let unrelated = CommandPayload::new("editor.selection", CommandScope::Global, vec![]);
+        assert!(!acl.apply(&id, &unrelated).expect("unrelated payload"));
+    }
+
+    #[test]
+    fn concurrent_changes_settle_on_the_higher_command_id() {
+        let owner = |owner: u64| {
+            CommandPayload::new(
+                CMD_ACCESS_ENTITY_OWNER,
+                CommandScope::Global,
+                serde_json::to_vec(&EntityOwnershipCommand::new(
+                    handle(1),
+                    Some(AuthorId(owner)),
+                ))
+                .unwrap(),
+            )
+        };
+        let earlier = (CommandId::new(3, AuthorId(1)), owner(5));
+        let later = (CommandId::new(3, AuthorId(2)), owner(6));
+
+        let mut in_order = AccessControlList::new();
+        let mut reversed = AccessControlList::new();
+        for (id, payload) in [&earlier, &later] {
+            in_order.apply(id, payload).unwrap();
+        }
+        for (id, payload) in [&later, &earlier] {
+            reversed.apply(id, payload).unwrap();
+        }
+        assert_eq!(in_order.entity_owner(&handle(1)), Some(&AuthorId(6)));
+        assert_eq!(in_order, reversed);
+    }
+}
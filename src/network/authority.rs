@@ -0,0 +1,377 @@
+//! Which peer may change a replicated entity. Ownership moves through request, grant and
+//! revoke commands; every peer applies them in log order and reaches the same table.
+
+use crate::ecs::{Entity, World};
+use crate::network::EntityHandle;
+use crate::network::command_log::{
+    AuthorId, CommandDefinition, CommandEntry, CommandLogError, CommandRegistry, CommandRole,
+    ConflictStrategy,
+};
+use crate::network::replication::ReplicationOwner;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const CMD_AUTHORITY_REQUEST: &str = "network.authority.request";
+pub const CMD_AUTHORITY_GRANT: &str = "network.authority.grant";
+pub const CMD_AUTHORITY_REVOKE: &str = "network.authority.revoke";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AuthorityModel {
+    /// Only the host changes the entity.
+    #[default]
+    HostAuthoritative,
+    /// The owner changes the entity; the host does while it is unowned.
+    OwnerAuthoritative,
+    /// Any peer changes the entity.
+    Shared,
+}
+
+/// Asks the host for ownership of an entity.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AuthorityRequestCommand {
+    pub entity: EntityHandle,
+}
+
+/// Hands ownership to `owner`; issued by the host or, to pass it on, the current owner.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AuthorityGrantCommand {
+    pub entity: EntityHandle,
+    pub owner: AuthorId,
+}
+
+/// Clears ownership; issued by the host or the owner releasing the entity.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AuthorityRevokeCommand {
+    pub entity: EntityHandle,
+}
+
+impl AuthorityRequestCommand {
+    pub fn new(entity: EntityHandle) -> Self {
+        Self { entity }
+    }
+}
+
+impl AuthorityGrantCommand {
+    pub fn new(entity: EntityHandle, owner: AuthorId) -> Self {
+        Self { entity, owner }
+    }
+}
+
+impl AuthorityRevokeCommand {
+    pub fn new(entity: EntityHandle) -> Self {
+        Self { entity }
+    }
+}
+
+pub fn is_authority_command(command_type: &str) -> bool {
+    matches!(
+        command_type,
+        CMD_AUTHORITY_REQUEST | CMD_AUTHORITY_GRANT | CMD_AUTHORITY_REVOKE
+    )
+}
+
+pub fn register_authority_commands(registry: &mut CommandRegistry) {
+    for command_type in [
+        CMD_AUTHORITY_REQUEST,
+        CMD_AUTHORITY_GRANT,
+        CMD_AUTHORITY_REVOKE,
+    ] {
+        registry.register(
+            command_type,
+            CommandDefinition::builder()
+                .required_role(CommandRole::Editor)
+                .default_strategy(ConflictStrategy::Merge)
+                .require_signature(true)
+                .build(),
+        );
+    }
+}
+
+/// Owners, per-entity models and open requests. Until the session sets a host, only
+/// owners and shared entities may be changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuthorityTable {
+    host: Option<AuthorId>,
+    default_model: AuthorityModel,
+    models: HashMap<EntityHandle, AuthorityModel>,
+    owners: HashMap<EntityHandle, AuthorId>,
+    requests: HashMap<EntityHandle, Vec<AuthorId>>,
+}
+
+impl AuthorityTable {
+    pub fn new(host: AuthorId) -> Self {
+        Self {
+            host: Some(host),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_default_model(mut self, model: AuthorityModel) -> Self {
+        self.default_model = model;
+        self
+    }
+
+    pub fn host(&self) -> Option<&AuthorId> {
+        self.host.as_ref()
+    }
+
+    pub fn set_host(&mut self, host: AuthorId) {
+        self.host = Some(host);
+    }
+
+    pub fn set_model(&mut self, entity: EntityHandle, model: AuthorityModel) {
+        self.models.insert(entity, model);
+    }
+
+    pub fn model(&self, entity: &EntityHandle) -> AuthorityModel {
+        self.models
+            .get(entity)
+            .copied()
+            .unwrap_or(self.default_model)
+    }
+
+    pub fn owner(&self, entity: &EntityHandle) -> Option<&AuthorId> {
+        self.owners.get(entity)
+    }
+
+    pub fn owners(&self) -> impl Iterator<Item = (&EntityHandle, &AuthorId)> {
+        self.owners.iter()
+    }
+
+    /// Peers waiting for ownership of `entity`, oldest first.
+    pub fn requests(&self, entity: &EntityHandle) -> &[AuthorId] {
+        self.requests.get(entity).map_or(&[], Vec::as_slice)
+    }
+
+    fn is_host(&self, author: &AuthorId) -> bool {
+        self.host.as_ref() == Some(author)
+    }
+
+    /// Whether replicated changes to `entity` from `author` should be applied.
+    pub fn may_write(&self, entity: &EntityHandle, author: &AuthorId) -> bool {
+        match self.model(entity) {
+            AuthorityModel::HostAuthoritative => self.is_host(author),
+            AuthorityModel::OwnerAuthoritative => match self.owners.get(entity) {
+                Some(owner) => owner == author,
+                None => self.is_host(author),
+            },
+            AuthorityModel::Shared => true,
+        }
+    }
+
+    fn check_grantor(
+        &self,
+        entity: &EntityHandle,
+        author: &AuthorId,
+    ) -> Result<(), CommandLogError> {
+        if self.is_host(author) || self.owners.get(entity) == Some(author) {
+            return Ok(());
+        }
+        Err(CommandLogError::AuthorityDenied {
+            entity: *entity,
+            author: author.clone(),
+        })
+    }
+
+    /// Applies a request, grant or revoke entry. Returns `Ok(false)` for unrelated
+    /// commands.
+    pub fn apply(&mut self, entry: &CommandEntry) -> Result<bool, CommandLogError> {
+        let decode_err =
+            |err: serde_json::Error| CommandLogError::PacketDecodeFailed(err.to_string());
+        let author = &entry.author.id;
+        match entry.payload.command_type.as_str() {
+            CMD_AUTHORITY_REQUEST => {
+                let command: AuthorityRequestCommand =
+                    serde_json::from_slice(&entry.payload.data).map_err(decode_err)?;
+                let requests = self.requests.entry(command.entity).or_default();
+                if self.owners.get(&command.entity) != Some(author) && !requests.contains(author) {
+                    requests.push(author.clone());
+                }
+                Ok(true)
+            }
+            CMD_AUTHORITY_GRANT => {
+                let command: AuthorityGrantCommand =
+                    serde_json::from_slice(&entry.payload.data).map_err(decode_err)?;
+                self.check_grantor(&command.entity, author)?;
+                if let Some(requests) = self.requests.get_mut(&command.entity) {
+                    requests.retain(|requester| requester != &command.owner);
+                }
+                self.owners.insert(command.entity, command.owner);
+                Ok(true)
+            }
+            CMD_AUTHORITY_REVOKE => {
+                let command: AuthorityRevokeCommand =
+                    serde_json::from_slice(&entry.payload.data).map_err(decode_err)?;
+                self.check_grantor(&command.entity, author)?;
+                self.owners.remove(&command.entity);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Drops ownership and requests of `author`, e.g. when their peer disconnects.
+    pub fn release_held_by(&mut self, author: &AuthorId) -> Vec<EntityHandle> {
+        let released: Vec<EntityHandle> = self
+            .owners
+            .iter()
+            .filter(|(_, owner)| *owner == author)
+            .map(|(entity, _)| *entity)
+            .collect();
+        for entity in &released {
+            self.owners.remove(entity);
+        }
+        for requests in self.requests.values_mut() {
+            requests.retain(|requester| requester != author);
+        }
+        released
+    }
+
+    /// Mirrors ownership into [`ReplicationOwner`] components, resolving handles with
+    /// `local`.
+    pub fn sync_components(
+        &self,
+        world: &mut World,
+        local: impl Fn(EntityHandle) -> Option<Entity>,
+    ) {
+        let owned: HashMap<Entity, &AuthorId> = self
+            .owners
+            .iter()
+            .filter_map(|(handle, owner)| local(*handle).map(|entity| (entity, owner)))
+            .collect();
+        let stale: Vec<Entity> = world
+            .component_entries::<ReplicationOwner>()
+            .into_iter()
+            .filter(|(entity, _)| !owned.contains_key(entity))
+            .map(|(entity, _)| entity)
+            .collect();
+        for entity in stale {
+            world.remove::<ReplicationOwner>(entity);
+        }
+        for (entity, owner) in owned {
+            if world.contains(entity)
+                && world
+                    .get::<ReplicationOwner>(entity)
+                    .map(|current| &current.0)
+                    != Some(owner)
+            {
+                let _ = world.insert(entity, ReplicationOwner(owner.clone()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::command_log::{CommandAuthor, CommandId, CommandPayload, CommandScope};
+    use crate::network::replication::{ReplicationApplier, ReplicationRegistry, encode_component};
+    use crate::network::{ComponentDiff, ComponentKey, DiffPayload};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Position([f32; 3]);
+
+    const HOST: AuthorId = AuthorId(1);
+    const CRATE: EntityHandle = EntityHandle {
+        index: 4,
+        generation: 0,
+    };
+
+    fn entry<T: Serialize>(
+        lamport: u64,
+        author: u64,
+        command_type: &str,
+        command: &T,
+    ) -> CommandEntry {
+        CommandEntry::new(
+            CommandId::new(lamport, AuthorId(author)),
+            0,
+            CommandPayload::new(
+                command_type,
+                CommandScope::Entity(CRATE),
+                serde_json::to_vec(command).unwrap(),
+            ),
+            ConflictStrategy::Merge,
+            CommandAuthor::new(AuthorId(author), CommandRole::Editor),
+            None,
+        )
+    }
+
+    fn moved_to(x: f32) -> Vec<ComponentDiff> {
+        vec![ComponentDiff {
+            entity: CRATE,
+            component: ComponentKey::of::<Position>(),
+            payload: DiffPayload::Update {
+                bytes: encode_component(&Position([x, 0.0, 0.0])).unwrap(),
+            },
+        }]
+    }
+
+    #[test]
+    fn only_the_authoritative_peer_moves_an_entity() {
+        let mut registry = ReplicationRegistry::new();
+        registry.register::<Position>();
+        let mut world = World::new();
+        let mut applier = ReplicationApplier::new();
+        let mut table =
+            AuthorityTable::new(HOST).with_default_model(AuthorityModel::OwnerAuthoritative);
+        let (alice, bob) = (AuthorId(2), AuthorId(3));
+        let mut move_from = |table: &AuthorityTable, source: &AuthorId, x: f32| {
+            applier.set_authority(table.clone(), source.clone());
+            let stats = applier.apply_diffs(&registry, &mut world, &moved_to(x));
+            let local = applier.local_entity(CRATE).unwrap();
+            table.sync_components(&mut world, |handle| applier.local_entity(handle));
+            (
+                stats,
+                world.get::<Position>(local).unwrap().0[0],
+                world.get::<ReplicationOwner>(local).cloned(),
+            )
+        };
+
+        // Unowned: only the host's updates land.
+        assert_eq!(move_from(&table, &HOST, 1.0).0.updated, 1);
+        let (stats, x, _) = move_from(&table, &alice, 2.0);
+        assert_eq!((stats.rejected, x), (1, 1.0));
+
+        table
+            .apply(&entry(
+                1,
+                2,
+                CMD_AUTHORITY_REQUEST,
+                &AuthorityRequestCommand::new(CRATE),
+            ))
+            .unwrap();
+        assert_eq!(table.requests(&CRATE), std::slice::from_ref(&alice));
+        let grant = AuthorityGrantCommand::new(CRATE, alice.clone());
+        assert!(matches!(
+            table.apply(&entry(2, 3, CMD_AUTHORITY_GRANT, &grant)),
+            Err(CommandLogError::AuthorityDenied { .. })
+        ));
+        table
+            .apply(&entry(3, 1, CMD_AUTHORITY_GRANT, &grant))
+            .unwrap();
+        assert!(table.requests(&CRATE).is_empty());
+
+        // Owned by Alice: her update lands, Bob's and the host's are dropped.
+        let (stats, x, owner) = move_from(&table, &alice, 3.0);
+        assert_eq!((stats.updated, x), (1, 3.0));
+        assert_eq!(owner, Some(ReplicationOwner(alice.clone())));
+        assert_eq!(move_from(&table, &bob, 4.0).0.rejected, 1);
+        let (stats, x, _) = move_from(&table, &HOST, 5.0);
+        assert_eq!((stats.rejected, x), (1, 3.0));
+
+        // Alice releases it; a shared entity takes updates from anyone.
+        table
+            .apply(&entry(
+                4,
+                2,
+                CMD_AUTHORITY_REVOKE,
+                &AuthorityRevokeCommand::new(CRATE),
+            ))
+            .unwrap();
+        let (stats, _, owner) = move_from(&table, &alice, 6.0);
+        assert_eq!((stats.rejected, owner), (1, None));
+        table.set_model(CRATE, AuthorityModel::Shared);
+        assert_eq!(move_from(&table, &bob, 7.0).1, 7.0);
+    }
+}
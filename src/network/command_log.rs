@@ -1,9 +1,12 @@
 use crate::network::EntityHandle;
+use crate::network::access::{AccessControlList, is_access_command};
+use crate::network::authority::{AuthorityTable, is_authority_command};
+use crate::network::locks::{EntityLockTable, is_lock_command};
 use serde::de::Error as DeError;
 use serde::ser::Error as SerError;
 use serde::{Deserialize, Serialize};
 use serde_json::Error as JsonError;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 #[cfg(feature = "command-log-persistence")]
 use std::fmt;
 use std::sync::Arc;
@@ -18,7 +21,7 @@ pub enum CommandRole {
 }
 
 impl CommandRole {
-    fn allows(self, required: CommandRole) -> bool {
+    pub fn allows(self, required: CommandRole) -> bool {
         matches!(
             (self, required),
             (CommandRole::Admin, _)
@@ -85,6 +88,8 @@ pub struct CommandDefinition {
     required_role: CommandRole,
     default_strategy: ConflictStrategy,
     require_signature: bool,
+    #[serde(default)]
+    lock_guarded: bool,
 }
 
 impl CommandDefinition {
@@ -103,12 +108,17 @@ impl CommandDefinition {
     pub fn require_signature(&self) -> bool {
         self.require_signature
     }
+
+    pub fn lock_guarded(&self) -> bool {
+        self.lock_guarded
+    }
 }
 
 pub struct CommandDefinitionBuilder {
     required_role: CommandRole,
     default_strategy: ConflictStrategy,
     require_signature: bool,
+    lock_guarded: bool,
 }
 
 impl CommandDefinitionBuilder {
@@ -117,6 +127,7 @@ impl CommandDefinitionBuilder {
             required_role: CommandRole::Editor,
             default_strategy: ConflictStrategy::LastWriteWins,
             require_signature: true,
+            lock_guarded: false,
         }
     }
 
@@ -135,11 +146,18 @@ impl CommandDefinitionBuilder {
         self
     }
 
+    /// Entity-scoped commands of this type are refused while another author holds the entity lock.
+    pub fn lock_guarded(mut self, guarded: bool) -> Self {
+        self.lock_guarded = guarded;
+        self
+    }
+
     pub fn build(self) -> CommandDefinition {
         CommandDefinition {
             required_role: self.required_role,
             default_strategy: self.default_strategy,
             require_signature: self.require_signature,
+            lock_guarded: self.lock_guarded,
         }
     }
 }
@@ -186,24 +204,74 @@ impl CommandId {
     pub fn lamport(&self) -> u64 {
         self.lamport
     }
+
+    pub fn author(&self) -> &AuthorId {
+        &self.author
+    }
+}
+
+/// Causal context attached to a command: the highest Lamport value the author had
+/// observed from every peer when the command was created.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionVector(BTreeMap<AuthorId, u64>);
+
+impl VersionVector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, author: &AuthorId) -> u64 {
+        self.0.get(author).copied().unwrap_or(0)
+    }
+
+    pub fn observe(&mut self, author: &AuthorId, lamport: u64) {
+        let slot = self.0.entry(author.clone()).or_insert(0);
+        *slot = (*slot).max(lamport);
+    }
+
+    pub fn merge(&mut self, other: &VersionVector) {
+        for (author, lamport) in &other.0 {
+            self.observe(author, *lamport);
+        }
+    }
+
+    /// Returns true when the command identified by `id` was already observed.
+    pub fn covers(&self, id: &CommandId) -> bool {
+        self.get(&id.author) >= id.lamport
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&AuthorId, u64)> {
+        self.0.iter().map(|(author, lamport)| (author, *lamport))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CommandSignature(pub Vec<u8>);
 
+/// Signatures cover the lamport time, the payload and the entry's causal context.
 pub trait SignatureVerifier: Send + Sync {
     fn verify(
         &self,
         author: &CommandAuthor,
         lamport: u64,
         payload: &CommandPayload,
+        causal_context: Option<&VersionVector>,
         signature: &CommandSignature,
     ) -> bool;
 }
 
 pub trait CommandSigner: Send + Sync {
     fn author(&self) -> &CommandAuthor;
-    fn sign(&self, lamport: u64, payload: &CommandPayload) -> Option<CommandSignature>;
+    fn sign(
+        &self,
+        lamport: u64,
+        payload: &CommandPayload,
+        causal_context: Option<&VersionVector>,
+    ) -> Option<CommandSignature>;
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -214,6 +282,8 @@ pub struct CommandEntry {
     pub strategy: ConflictStrategy,
     pub author: CommandAuthor,
     pub signature: Option<CommandSignature>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub causal_context: Option<VersionVector>,
 }
 
 impl CommandEntry {
@@ -232,8 +302,28 @@ impl CommandEntry {
             strategy,
             author,
             signature,
+            causal_context: None,
         }
     }
+
+    pub fn with_causal_context(mut self, context: VersionVector) -> Self {
+        self.causal_context = Some(context);
+        self
+    }
+
+    /// Returns true when `self` was observed by the author of `later` before it was
+    /// created. Entries without a causal context never report a causal relationship.
+    pub fn happened_before(&self, later: &CommandEntry) -> bool {
+        later
+            .causal_context
+            .as_ref()
+            .is_some_and(|context| context.covers(&self.id))
+    }
+
+    /// Returns true when neither entry causally precedes the other.
+    pub fn is_concurrent_with(&self, other: &CommandEntry) -> bool {
+        self.id != other.id && !self.happened_before(other) && !other.happened_before(self)
+    }
 }
 
 #[derive(Debug, Error, PartialEq, Eq)]
@@ -245,6 +335,21 @@ pub enum CommandLogError {
         required: CommandRole,
         actual: CommandRole,
     },
+    #[error("author {author:?} may not issue commands in scope {scope:?}")]
+    ScopeAccessDenied {
+        author: AuthorId,
+        scope: CommandScope,
+    },
+    #[error("entity {entity:?} is locked by author {holder:?}")]
+    EntityLocked {
+        entity: EntityHandle,
+        holder: AuthorId,
+    },
+    #[error("author {author:?} has no authority over entity {entity:?}")]
+    AuthorityDenied {
+        entity: EntityHandle,
+        author: AuthorId,
+    },
     #[error("signature missing for command type {0}")]
     SignatureMissing(String),
     #[error("signature rejected for author {0:?}")]
@@ -253,14 +358,32 @@ pub enum CommandLogError {
     ConflictRejected,
     #[error("duplicate command id")]
     Duplicate,
+    #[error("command does not act on its scope {0:?}")]
+    ScopeMismatch(CommandScope),
     #[error("failed to decode command packet: {0}")]
     PacketDecodeFailed(String),
+    #[error("failed to encode command payload: {0}")]
+    PayloadEncodeFailed(String),
     #[error("command replay detected for author {0:?}")]
     ReplayDetected(AuthorId),
     #[error("rate limited command for author {0:?}")]
     RateLimited(AuthorId),
 }
 
+/// Remote entries waiting for commands their author had already seen.
+const MAX_DEFERRED_ENTRIES: usize = 1024;
+/// How long a remote entry waits for missing dependencies before it is checked anyway;
+/// a dependency dropped on receipt, e.g. by the rate limiter, never arrives.
+const MAX_DEFERRAL: Duration = Duration::from_secs(5);
+
+/// Access, lock and authority tables as they stand once an entry is applied.
+#[derive(Default)]
+struct DerivedState {
+    access: Option<AccessControlList>,
+    locks: Option<EntityLockTable>,
+    authority: Option<AuthorityTable>,
+}
+
 #[derive(Debug, Clone)]
 pub struct RateLimitConfig {
     pub burst: u32,
@@ -469,6 +592,16 @@ impl RateLimiterMap {
         bucket.take(now, amount, &self.config)
     }
 
+    fn available(&mut self, author: &AuthorId) -> u32 {
+        let now = Instant::now();
+        let bucket = self
+            .buckets
+            .entry(author.clone())
+            .or_insert_with(|| TokenBucket::new(&self.config, now));
+        bucket.refill(now, &self.config);
+        bucket.tokens as u32
+    }
+
     #[cfg(test)]
     fn tokens_for(&mut self, author: &AuthorId, now: Instant) -> f64 {
         let bucket = self
@@ -549,6 +682,13 @@ pub struct CommandLog {
     lamport_clock: u64,
     entries: BTreeMap<CommandId, CommandEntry>,
     latest_by_scope: HashMap<CommandScopeKey, CommandId>,
+    version_vector: VersionVector,
+    access: AccessControlList,
+    locks: EntityLockTable,
+    authority: AuthorityTable,
+    deferred: BTreeMap<CommandId, (CommandEntry, Instant)>,
+    released: Vec<CommandEntry>,
+    superseded: Vec<CommandEntry>,
     registry: Arc<CommandRegistry>,
     verifier: Arc<dyn SignatureVerifier>,
     #[allow(dead_code)]
@@ -578,6 +718,13 @@ impl CommandLog {
             lamport_clock: 0,
             entries: BTreeMap::new(),
             latest_by_scope: HashMap::new(),
+            version_vector: VersionVector::new(),
+            access: AccessControlList::new(),
+            locks: EntityLockTable::new(),
+            authority: AuthorityTable::default(),
+            deferred: BTreeMap::new(),
+            released: Vec::new(),
+            superseded: Vec::new(),
             registry,
             verifier,
             config,
@@ -591,6 +738,11 @@ impl CommandLog {
         self.verifier = verifier;
     }
 
+    /// Adds or replaces a command definition after the log was created.
+    pub fn register_command(&mut self, command_type: impl Into<String>, def: CommandDefinition) {
+        Arc::make_mut(&mut self.registry).register(command_type, def);
+    }
+
     pub fn config(&self) -> &CommandLogConfig {
         &self.config
     }
@@ -599,6 +751,92 @@ impl CommandLog {
         self.lamport_clock
     }
 
+    pub fn version_vector(&self) -> &VersionVector {
+        &self.version_vector
+    }
+
+    pub fn access(&self) -> &AccessControlList {
+        &self.access
+    }
+
+    pub fn locks(&self) -> &EntityLockTable {
+        &self.locks
+    }
+
+    /// Releases every entity lock held by `author` without logging a command;
+    /// each peer does this independently when it observes the author leave.
+    pub fn release_locks_held_by(&mut self, author: &AuthorId) -> Vec<EntityHandle> {
+        self.locks.release_held_by(author)
+    }
+
+    pub fn expire_locks(&mut self) -> Vec<EntityHandle> {
+        self.locks.expire(current_time_millis())
+    }
+
+    pub fn authority(&self) -> &AuthorityTable {
+        &self.authority
+    }
+
+    /// For setting the host and authority models; ownership changes go through
+    /// authority commands.
+    pub fn authority_mut(&mut self) -> &mut AuthorityTable {
+        &mut self.authority
+    }
+
+    /// Checks the command's required role against the author's effective role,
+    /// then the per-scope ownership, tool grants and entity locks as of local time `now_ms`.
+    /// A `remote` author's declared role only counts if it is the session host.
+    fn authorize(
+        &self,
+        author: &CommandAuthor,
+        definition: &CommandDefinition,
+        scope: &CommandScope,
+        now_ms: u64,
+        remote: bool,
+    ) -> Result<(), CommandLogError> {
+        let required = definition.required_role();
+        let trusted = !remote || self.authority.host() == Some(&author.id);
+        let role = self.access.effective_role(author, trusted);
+        if !role.allows(required) {
+            return Err(CommandLogError::InsufficientPermissions {
+                required,
+                actual: role,
+            });
+        }
+        if !self.access.permits(&author.id, role, scope) {
+            return Err(CommandLogError::ScopeAccessDenied {
+                author: author.id.clone(),
+                scope: scope.clone(),
+            });
+        }
+        if definition.lock_guarded()
+            && let CommandScope::Entity(handle) = scope
+        {
+            self.locks.check_holder(handle, &author.id, now_ms)?;
+        }
+        Ok(())
+    }
+
+    /// How many more entries `author` may append before the rate limiter refuses them.
+    pub fn rate_capacity(&mut self, author: &AuthorId) -> u32 {
+        self.rate_limiter.available(author)
+    }
+
+    /// Runs the role, scope and lock checks of [`CommandLog::append_local`] without
+    /// appending or spending a rate-limit token.
+    pub fn authorize_local(
+        &self,
+        author: &CommandAuthor,
+        command_type: &str,
+        scope: &CommandScope,
+    ) -> Result<(), CommandLogError> {
+        let definition = self
+            .registry
+            .definition(command_type)
+            .ok_or_else(|| CommandLogError::UnregisteredCommand(command_type.to_string()))?;
+        self.authorize(author, definition, scope, current_time_millis(), false)
+    }
+
     pub fn record_packet_nonce(&mut self, author: &AuthorId, nonce: u64) {
         self.packet_tracker.record_local(author, nonce);
     }
@@ -630,29 +868,39 @@ impl CommandLog {
             .registry
             .definition(&payload.command_type)
             .ok_or_else(|| CommandLogError::UnregisteredCommand(payload.command_type.clone()))?;
-        let required_role = definition.required_role();
         let default_strategy = definition.default_strategy();
         let require_signature = definition.require_signature();
 
         let author = signer.author();
-        if !author.role.allows(required_role) {
-            return Err(CommandLogError::InsufficientPermissions {
-                required: required_role,
-                actual: author.role,
-            });
-        }
 
         if matches!(payload.scope, CommandScope::Tool(ref name) if name.trim().is_empty()) {
             payload.scope = CommandScope::Global;
         }
 
+        let timestamp_ms = current_time_millis();
+        self.authorize(author, definition, &payload.scope, timestamp_ms, false)?;
+
+        let context = self.version_vector.clone();
+        // Malformed or refused access, lock and authority commands fail here, before
+        // they spend a lamport tick or a rate-limit token.
+        let provisional = CommandEntry::new(
+            CommandId::new(self.lamport_clock.wrapping_add(1), author.id.clone()),
+            timestamp_ms,
+            payload.clone(),
+            strategy.unwrap_or(default_strategy),
+            author.clone(),
+            None,
+        )
+        .with_causal_context(context.clone());
+        self.derive_state(&provisional)?;
+
         if !self.rate_limiter.take(&author.id, 1) {
             return Err(CommandLogError::RateLimited(author.id.clone()));
         }
 
         let lamport = self.next_lamport();
         let id = CommandId::new(lamport, author.id.clone());
-        let signature = signer.sign(lamport, &payload);
+        let signature = signer.sign(lamport, &payload, Some(&context));
 
         if require_signature && signature.is_none() {
             return Err(CommandLogError::SignatureMissing(
@@ -662,12 +910,13 @@ impl CommandLog {
 
         let entry = CommandEntry::new(
             id.clone(),
-            current_time_millis(),
+            timestamp_ms,
             payload,
             strategy.unwrap_or(default_strategy),
             author.clone(),
             signature,
-        );
+        )
+        .with_causal_context(context);
 
         match self.integrate_entry(entry, true) {
             Ok(true) => {
@@ -689,26 +938,22 @@ impl CommandLog {
                 CommandLogError::UnregisteredCommand(entry.payload.command_type.clone())
             })?;
 
-        if !entry.author.role.allows(definition.required_role()) {
-            return Err(CommandLogError::InsufficientPermissions {
-                required: definition.required_role(),
-                actual: entry.author.role,
-            });
-        }
-
         if definition.require_signature() {
             let signature = entry.signature.as_ref().ok_or_else(|| {
                 CommandLogError::SignatureMissing(entry.payload.command_type.clone())
             })?;
-            if !self
-                .verifier
-                .verify(&entry.author, entry.id.lamport(), &entry.payload, signature)
-            {
+            if !self.verifier.verify(
+                &entry.author,
+                entry.id.lamport(),
+                &entry.payload,
+                entry.causal_context.as_ref(),
+                signature,
+            ) {
                 return Err(CommandLogError::InvalidSignature(entry.author.id.clone()));
             }
         }
 
-        if self.entries.contains_key(&entry.id) {
+        if self.entries.contains_key(&entry.id) || self.deferred.contains_key(&entry.id) {
             return Ok(false);
         }
 
@@ -723,7 +968,98 @@ impl CommandLog {
             return Err(CommandLogError::ReplayDetected(entry.author.id.clone()));
         }
 
-        self.integrate_entry(entry, false)
+        let now = Instant::now();
+        let result = if self.is_causally_ready(&entry) {
+            self.apply_remote(entry)
+        } else {
+            if self.deferred.len() >= MAX_DEFERRED_ENTRIES
+                && let Some((_, (oldest, _))) = self.deferred.pop_first()
+            {
+                self.release(oldest);
+            }
+            self.deferred.insert(entry.id.clone(), (entry, now));
+            Ok(false)
+        };
+        self.release_deferred(now);
+        result
+    }
+
+    /// Whether `id` waits for commands its author had seen but this log has not.
+    pub fn is_deferred(&self, id: &CommandId) -> bool {
+        self.deferred.contains_key(id)
+    }
+
+    /// Deferred entries integrated since the last call, in the order they were applied.
+    pub fn drain_released(&mut self) -> Vec<CommandEntry> {
+        std::mem::take(&mut self.released)
+    }
+
+    /// Entries dropped since the last call because a concurrent `Reject` entry with a
+    /// lower id arrived after them. They were already applied, so their effects must be
+    /// undone for peers to converge.
+    pub fn drain_superseded(&mut self) -> Vec<CommandEntry> {
+        std::mem::take(&mut self.superseded)
+    }
+
+    /// An entry is ready once every command in its causal context was integrated, so
+    /// role, lock and authority checks see what its author saw. The author's own earlier
+    /// commands only hold it back while they are deferred themselves.
+    fn is_causally_ready(&self, entry: &CommandEntry) -> bool {
+        let Some(context) = entry.causal_context.as_ref() else {
+            return true;
+        };
+        context.iter().all(|(author, lamport)| {
+            self.version_vector.get(author) >= lamport
+                || (author == &entry.author.id
+                    && !self
+                        .deferred
+                        .keys()
+                        .any(|id| id.author() == author && id < &entry.id))
+        })
+    }
+
+    fn apply_remote(&mut self, entry: CommandEntry) -> Result<bool, CommandLogError> {
+        let id = entry.id.clone();
+        let registry = Arc::clone(&self.registry);
+        let result = registry
+            .definition(&entry.payload.command_type)
+            .ok_or_else(|| CommandLogError::UnregisteredCommand(entry.payload.command_type.clone()))
+            .and_then(|definition| {
+                self.authorize(
+                    &entry.author,
+                    definition,
+                    &entry.payload.scope,
+                    current_time_millis(),
+                    true,
+                )
+            })
+            .and_then(|()| self.integrate_entry(entry, false));
+        // Refused entries count as seen too, so entries built on them are not held back.
+        self.version_vector.observe(&id.author, id.lamport);
+        result
+    }
+
+    fn release(&mut self, entry: CommandEntry) {
+        if let Ok(true) = self.apply_remote(entry.clone()) {
+            self.released.push(entry);
+        }
+    }
+
+    fn release_deferred(&mut self, now: Instant) {
+        loop {
+            let next = self
+                .deferred
+                .iter()
+                .find(|(_, (entry, deferred_at))| {
+                    self.is_causally_ready(entry)
+                        || now.duration_since(*deferred_at) >= MAX_DEFERRAL
+                })
+                .map(|(id, _)| id.clone());
+            let Some((entry, _)) = next.and_then(|id| self.deferred.remove(&id)) else {
+                break;
+            };
+            self.release(entry);
+        }
     }
 
     fn integrate_entry(
@@ -738,6 +1074,51 @@ impl CommandLog {
             return Ok(false);
         }
 
+        let derived = self.derive_state(&entry)?;
+        let id = entry.id.clone();
+        let result = self.resolve_conflict(entry);
+        if result.is_ok() {
+            self.version_vector.observe(&id.author, id.lamport);
+        }
+        if let Ok(true) = result {
+            if let Some(access) = derived.access {
+                self.access = access;
+            }
+            if let Some(locks) = derived.locks {
+                self.locks = locks;
+            }
+            if let Some(authority) = derived.authority {
+                self.authority = authority;
+            }
+        }
+        result
+    }
+
+    /// Decodes access, lock and authority commands up front so a malformed or refused
+    /// one never lands in the log.
+    fn derive_state(&self, entry: &CommandEntry) -> Result<DerivedState, CommandLogError> {
+        let mut derived = DerivedState::default();
+        if is_access_command(&entry.payload.command_type) {
+            let mut updated = self.access.clone();
+            updated
+                .apply(&entry.id, &entry.payload)
+                .map_err(|err| CommandLogError::PacketDecodeFailed(err.to_string()))?;
+            derived.access = Some(updated);
+        }
+        if is_lock_command(&entry.payload.command_type) {
+            let mut updated = self.locks.clone();
+            updated.apply(entry, current_time_millis())?;
+            derived.locks = Some(updated);
+        }
+        if is_authority_command(&entry.payload.command_type) {
+            let mut updated = self.authority.clone();
+            updated.apply(entry)?;
+            derived.authority = Some(updated);
+        }
+        Ok(derived)
+    }
+
+    fn resolve_conflict(&mut self, entry: CommandEntry) -> Result<bool, CommandLogError> {
         match entry.strategy {
             ConflictStrategy::Merge => {
                 self.entries.insert(entry.id.clone(), entry);
@@ -745,16 +1126,24 @@ impl CommandLog {
             }
             ConflictStrategy::Reject => {
                 let scope_key = entry.payload.scope.key();
-                match self.latest_by_scope.entry(scope_key) {
-                    std::collections::hash_map::Entry::Occupied(_) => {
-                        Err(CommandLogError::ConflictRejected)
-                    }
-                    std::collections::hash_map::Entry::Vacant(slot) => {
-                        slot.insert(entry.id.clone());
-                        self.entries.insert(entry.id.clone(), entry);
-                        Ok(true)
+                // Only writes that are concurrent with the scope's latest entry conflict.
+                // The lower command id wins, so every peer keeps the same write whichever
+                // arrives first.
+                if let Some(previous_id) = self.latest_by_scope.get(&scope_key).cloned() {
+                    let supersedes = entry
+                        .causal_context
+                        .as_ref()
+                        .is_some_and(|context| context.covers(&previous_id));
+                    if !supersedes {
+                        if previous_id < entry.id {
+                            return Err(CommandLogError::ConflictRejected);
+                        }
+                        self.superseded.extend(self.entries.remove(&previous_id));
                     }
                 }
+                self.latest_by_scope.insert(scope_key, entry.id.clone());
+                self.entries.insert(entry.id.clone(), entry);
+                Ok(true)
             }
             ConflictStrategy::LastWriteWins => {
                 let scope_key = entry.payload.scope.key();
@@ -828,6 +1217,39 @@ impl CommandLog {
 
 pub const MAX_COMMAND_PACKET_BYTES: usize = 64 * 1024;
 
+/// Handshake capability advertising FlatBuffers-encoded command packets.
+pub const CAPABILITY_FLATBUFFERS_COMMANDS: u32 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+pub enum PacketEncoding {
+    #[default]
+    Json,
+    FlatBuffers,
+}
+
+impl PacketEncoding {
+    /// Capabilities to advertise in the session handshake for the encodings this build
+    /// can decode.
+    pub fn capabilities() -> Vec<u32> {
+        if cfg!(has_generated_network_schema) {
+            vec![CAPABILITY_FLATBUFFERS_COMMANDS]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Picks the encoding both peers agreed on during the session handshake.
+    pub fn negotiate(capability_mask: &[u32]) -> Self {
+        if cfg!(has_generated_network_schema)
+            && capability_mask.contains(&CAPABILITY_FLATBUFFERS_COMMANDS)
+        {
+            PacketEncoding::FlatBuffers
+        } else {
+            PacketEncoding::Json
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct CommandBatch {
     pub sequence: u64,
@@ -837,17 +1259,85 @@ pub struct CommandBatch {
     pub entries: Vec<CommandEntry>,
 }
 
+/// Bytes reserved for header growth when a split batch is renumbered.
+const PACKET_SPLIT_HEADROOM: usize = 256;
+
+/// Upper bound on fragments per batch; larger entries are rejected outright.
+pub const MAX_COMMAND_FRAGMENTS: u32 = 256;
+
+const MAX_PENDING_FRAGMENT_GROUPS: usize = 8;
+
+/// Payload bytes buffered across all incomplete batches, whoever sent them; enough for
+/// one batch of the largest size.
+pub const MAX_REASSEMBLY_BYTES: usize = MAX_COMMAND_FRAGMENTS as usize * MAX_COMMAND_PACKET_BYTES;
+
+/// First byte of a binary packet frame; frames from peers that still send whole packets
+/// as JSON start with `{`.
+const PACKET_WIRE_VERSION: u8 = 1;
+
+impl CommandBatch {
+    /// Splits the batch into consecutive sub-batches that each fit a packet. Sub-batches
+    /// keep this batch's header; callers must renumber all but the first before sending.
+    /// A single entry that still exceeds the budget is returned alone for fragmenting.
+    pub fn split_for_packets(
+        self,
+        encoding: PacketEncoding,
+    ) -> Result<Vec<CommandBatch>, JsonError> {
+        let mut parts = Vec::new();
+        self.split_into(encoding, &mut parts)?;
+        Ok(parts)
+    }
+
+    fn split_into(
+        self,
+        encoding: PacketEncoding,
+        parts: &mut Vec<CommandBatch>,
+    ) -> Result<(), JsonError> {
+        let budget = MAX_COMMAND_PACKET_BYTES - PACKET_SPLIT_HEADROOM;
+        if self.entries.len() <= 1 || encode_payload(&self, encoding)?.0.len() <= budget {
+            parts.push(self);
+            return Ok(());
+        }
+
+        let mut head = self;
+        let tail_entries = head.entries.split_off(head.entries.len() / 2);
+        let tail = CommandBatch {
+            entries: tail_entries,
+            author: head.author.clone(),
+            ..head
+        };
+        head.split_into(encoding, parts)?;
+        tail.split_into(encoding, parts)
+    }
+}
+
+/// Identifies one slice of a batch too large for a single packet.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PacketFragment {
+    pub author: AuthorId,
+    pub index: u32,
+    pub total: u32,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct CommandPacket {
     pub sequence: u64,
     pub nonce: u64,
     pub timestamp_ms: u64,
     pub payload: Vec<u8>,
+    #[serde(default)]
+    pub encoding: PacketEncoding,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fragment: Option<PacketFragment>,
 }
 
 impl CommandPacket {
     pub fn from_batch(batch: &CommandBatch) -> Result<Self, JsonError> {
-        let payload = serde_json::to_vec(batch)?;
+        Self::encode(batch, PacketEncoding::Json)
+    }
+
+    pub fn encode(batch: &CommandBatch, encoding: PacketEncoding) -> Result<Self, JsonError> {
+        let (payload, encoding) = encode_payload(batch, encoding)?;
         if payload.len() > MAX_COMMAND_PACKET_BYTES {
             return Err(SerError::custom(format!(
                 "command batch payload {} exceeds {} byte limit",
@@ -861,9 +1351,114 @@ impl CommandPacket {
             nonce: batch.nonce,
             timestamp_ms: batch.timestamp_ms,
             payload,
+            encoding,
+            fragment: None,
+        })
+    }
+
+    /// Frames the packet for the transport: a version byte, the little-endian header and
+    /// the payload verbatim, so FlatBuffers payloads travel without a JSON wrapper.
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(43 + self.payload.len());
+        bytes.push(PACKET_WIRE_VERSION);
+        bytes.extend_from_slice(&self.sequence.to_le_bytes());
+        bytes.extend_from_slice(&self.nonce.to_le_bytes());
+        bytes.extend_from_slice(&self.timestamp_ms.to_le_bytes());
+        bytes.push(match self.encoding {
+            PacketEncoding::Json => 0,
+            PacketEncoding::FlatBuffers => 1,
+        });
+        match &self.fragment {
+            None => bytes.push(0),
+            Some(fragment) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&fragment.author.0.to_le_bytes());
+                bytes.extend_from_slice(&fragment.index.to_le_bytes());
+                bytes.extend_from_slice(&fragment.total.to_le_bytes());
+            }
+        }
+        bytes.extend_from_slice(&self.payload);
+        bytes
+    }
+
+    /// Reads a frame written by [`CommandPacket::to_wire`] or a legacy JSON packet.
+    pub fn from_wire(bytes: &[u8]) -> Result<Self, JsonError> {
+        let mut rest = match bytes.split_first() {
+            Some((b'{', _)) => return serde_json::from_slice(bytes),
+            Some((&PACKET_WIRE_VERSION, rest)) => rest,
+            _ => return Err(DeError::custom("unknown command packet frame")),
+        };
+        let sequence = u64::from_le_bytes(take_wire(&mut rest)?);
+        let nonce = u64::from_le_bytes(take_wire(&mut rest)?);
+        let timestamp_ms = u64::from_le_bytes(take_wire(&mut rest)?);
+        let encoding = match take_wire::<1>(&mut rest)? {
+            [0] => PacketEncoding::Json,
+            [1] => PacketEncoding::FlatBuffers,
+            [other] => return Err(DeError::custom(format!("unknown packet encoding {other}"))),
+        };
+        let fragment = match take_wire::<1>(&mut rest)? {
+            [0] => None,
+            [1] => Some(PacketFragment {
+                author: AuthorId(u64::from_le_bytes(take_wire(&mut rest)?)),
+                index: u32::from_le_bytes(take_wire(&mut rest)?),
+                total: u32::from_le_bytes(take_wire(&mut rest)?),
+            }),
+            [other] => return Err(DeError::custom(format!("unknown fragment flag {other}"))),
+        };
+        Ok(Self {
+            sequence,
+            nonce,
+            timestamp_ms,
+            payload: rest.to_vec(),
+            encoding,
+            fragment,
         })
     }
 
+    /// Encodes a batch as one packet when it fits, otherwise as ordered fragments that
+    /// share the batch's sequence and nonce and must go through [`FragmentReassembler`].
+    pub fn encode_fragmented(
+        batch: &CommandBatch,
+        encoding: PacketEncoding,
+    ) -> Result<Vec<Self>, JsonError> {
+        let (payload, encoding) = encode_payload(batch, encoding)?;
+        let total = payload.len().div_ceil(MAX_COMMAND_PACKET_BYTES).max(1);
+        if total > MAX_COMMAND_FRAGMENTS as usize {
+            return Err(SerError::custom(format!(
+                "command batch payload {} exceeds {} fragments",
+                payload.len(),
+                MAX_COMMAND_FRAGMENTS
+            )));
+        }
+
+        let packet = |payload: Vec<u8>, fragment: Option<PacketFragment>| Self {
+            sequence: batch.sequence,
+            nonce: batch.nonce,
+            timestamp_ms: batch.timestamp_ms,
+            payload,
+            encoding,
+            fragment,
+        };
+        if total == 1 {
+            return Ok(vec![packet(payload, None)]);
+        }
+
+        Ok(payload
+            .chunks(MAX_COMMAND_PACKET_BYTES)
+            .enumerate()
+            .map(|(index, chunk)| {
+                packet(
+                    chunk.to_vec(),
+                    Some(PacketFragment {
+                        author: batch.author.clone(),
+                        index: index as u32,
+                        total: total as u32,
+                    }),
+                )
+            })
+            .collect())
+    }
+
     /// Decodes the payload into a CommandBatch.
     /// Note: The sequence and timestamp fields are present both in the packet and the payload.
     /// This method trusts the values from the payload for consistency, but you may wish to validate
@@ -876,8 +1471,30 @@ impl CommandPacket {
                 MAX_COMMAND_PACKET_BYTES
             )));
         }
+        if self.fragment.is_some() {
+            return Err(DeError::custom(
+                "command packet fragment must be reassembled before decoding",
+            ));
+        }
 
-        let mut batch: CommandBatch = serde_json::from_slice(&self.payload)?;
+        self.decode_payload(&self.payload)
+    }
+
+    fn decode_payload(&self, payload: &[u8]) -> Result<CommandBatch, JsonError> {
+        let mut batch: CommandBatch = match self.encoding {
+            PacketEncoding::Json => serde_json::from_slice(payload)?,
+            #[cfg(has_generated_network_schema)]
+            PacketEncoding::FlatBuffers => {
+                crate::network::packet_codec::decode_command_batch(payload)
+                    .map_err(DeError::custom)?
+            }
+            #[cfg(not(has_generated_network_schema))]
+            PacketEncoding::FlatBuffers => {
+                return Err(DeError::custom(
+                    "FlatBuffers command packets require generated network bindings",
+                ));
+            }
+        };
         if batch.sequence != self.sequence {
             batch.sequence = self.sequence;
         }
@@ -892,6 +1509,135 @@ impl CommandPacket {
     }
 }
 
+fn take_wire<const N: usize>(bytes: &mut &[u8]) -> Result<[u8; N], JsonError> {
+    let (head, rest) = bytes
+        .split_first_chunk::<N>()
+        .ok_or_else(|| DeError::custom("truncated command packet frame"))?;
+    *bytes = rest;
+    Ok(*head)
+}
+
+fn encode_payload(
+    batch: &CommandBatch,
+    encoding: PacketEncoding,
+) -> Result<(Vec<u8>, PacketEncoding), JsonError> {
+    match encoding {
+        PacketEncoding::Json => Ok((serde_json::to_vec(batch)?, PacketEncoding::Json)),
+        #[cfg(has_generated_network_schema)]
+        PacketEncoding::FlatBuffers => Ok((
+            crate::network::packet_codec::encode_command_batch(batch),
+            PacketEncoding::FlatBuffers,
+        )),
+        #[cfg(not(has_generated_network_schema))]
+        PacketEncoding::FlatBuffers => Ok((serde_json::to_vec(batch)?, PacketEncoding::Json)),
+    }
+}
+
+struct FragmentGroup {
+    first: CommandPacket,
+    parts: Vec<Option<Vec<u8>>>,
+    received: u32,
+}
+
+/// Buffers packet fragments until every slice of a batch has arrived. Fragments may
+/// arrive in any order; only a bounded number of incomplete batches and
+/// [`MAX_REASSEMBLY_BYTES`] in total are kept, evicting the oldest batch first.
+#[derive(Default)]
+pub struct FragmentReassembler {
+    groups: HashMap<(AuthorId, u64), FragmentGroup>,
+    arrival: VecDeque<(AuthorId, u64)>,
+    buffered: usize,
+}
+
+impl FragmentReassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pending_groups(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn buffered_bytes(&self) -> usize {
+        self.buffered
+    }
+
+    /// Returns the decoded batch once the final fragment of its group is accepted.
+    pub fn accept(&mut self, packet: &CommandPacket) -> Result<Option<CommandBatch>, JsonError> {
+        let Some(fragment) = packet.fragment.as_ref() else {
+            return packet.decode().map(Some);
+        };
+        if packet.payload.len() > MAX_COMMAND_PACKET_BYTES
+            || fragment.total == 0
+            || fragment.total > MAX_COMMAND_FRAGMENTS
+            || fragment.index >= fragment.total
+        {
+            return Err(DeError::custom(format!(
+                "invalid command packet fragment {}/{}",
+                fragment.index, fragment.total
+            )));
+        }
+
+        let key = (fragment.author.clone(), packet.nonce);
+        if !self.groups.contains_key(&key) {
+            if self.groups.len() >= MAX_PENDING_FRAGMENT_GROUPS {
+                self.evict_oldest(&key);
+            }
+            self.arrival.push_back(key.clone());
+            self.groups.insert(
+                key.clone(),
+                FragmentGroup {
+                    first: CommandPacket {
+                        payload: Vec::new(),
+                        ..packet.clone()
+                    },
+                    parts: vec![None; fragment.total as usize],
+                    received: 0,
+                },
+            );
+        }
+        while self.buffered + packet.payload.len() > MAX_REASSEMBLY_BYTES && self.evict_oldest(&key)
+        {
+        }
+
+        let group = self.groups.get_mut(&key).expect("fragment group present");
+        if group.parts.len() != fragment.total as usize || group.first.encoding != packet.encoding {
+            return Err(DeError::custom(
+                "command packet fragment does not match its group",
+            ));
+        }
+        let slot = &mut group.parts[fragment.index as usize];
+        if slot.is_none() {
+            *slot = Some(packet.payload.clone());
+            group.received += 1;
+            self.buffered += packet.payload.len();
+        }
+        if group.received < fragment.total {
+            return Ok(None);
+        }
+
+        let group = self.remove_group(&key).expect("fragment group present");
+        let payload: Vec<u8> = group.parts.into_iter().flatten().flatten().collect();
+        group.first.decode_payload(&payload).map(Some)
+    }
+
+    /// Drops the oldest incomplete batch other than `keep`; false when none is left.
+    fn evict_oldest(&mut self, keep: &(AuthorId, u64)) -> bool {
+        let Some(oldest) = self.arrival.iter().find(|key| *key != keep).cloned() else {
+            return false;
+        };
+        self.remove_group(&oldest);
+        true
+    }
+
+    fn remove_group(&mut self, key: &(AuthorId, u64)) -> Option<FragmentGroup> {
+        let group = self.groups.remove(key)?;
+        self.arrival.retain(|pending| pending != key);
+        self.buffered -= group.parts.iter().flatten().map(Vec::len).sum::<usize>();
+        Some(group)
+    }
+}
+
 #[derive(Default)]
 pub struct NoopSignatureVerifier;
 
@@ -901,6 +1647,7 @@ impl SignatureVerifier for NoopSignatureVerifier {
         _author: &CommandAuthor,
         _lamport: u64,
         _payload: &CommandPayload,
+        _causal_context: Option<&VersionVector>,
         _signature: &CommandSignature,
     ) -> bool {
         true
@@ -922,11 +1669,97 @@ impl CommandSigner for NoopCommandSigner {
         &self.author
     }
 
-    fn sign(&self, _lamport: u64, _payload: &CommandPayload) -> Option<CommandSignature> {
+    fn sign(
+        &self,
+        _lamport: u64,
+        _payload: &CommandPayload,
+        _causal_context: Option<&VersionVector>,
+    ) -> Option<CommandSignature> {
         None
     }
 }
 
+/// Signs with a secret every peer of a session shares, for builds without per-author
+/// keys. Pair it with a [`SharedKeySignatureVerifier`] holding the same key.
+pub struct SharedKeyCommandSigner {
+    author: CommandAuthor,
+    key: [u8; 16],
+}
+
+impl SharedKeyCommandSigner {
+    pub fn new(author: CommandAuthor, key: [u8; 16]) -> Self {
+        Self { author, key }
+    }
+
+    /// A signer with a fresh random key, for a pipeline that has not joined a session.
+    pub fn with_random_key(author: CommandAuthor) -> Self {
+        use std::collections::hash_map::RandomState;
+        use std::hash::BuildHasher;
+
+        let mut key = [0; 16];
+        for half in key.chunks_mut(8) {
+            half.copy_from_slice(
+                &RandomState::new()
+                    .hash_one(current_time_millis())
+                    .to_le_bytes(),
+            );
+        }
+        Self::new(author, key)
+    }
+}
+
+impl CommandSigner for SharedKeyCommandSigner {
+    fn author(&self) -> &CommandAuthor {
+        &self.author
+    }
+
+    fn sign(
+        &self,
+        lamport: u64,
+        payload: &CommandPayload,
+        causal_context: Option<&VersionVector>,
+    ) -> Option<CommandSignature> {
+        Some(shared_key_mac(&self.key, lamport, payload, causal_context))
+    }
+}
+
+pub struct SharedKeySignatureVerifier {
+    key: [u8; 16],
+}
+
+impl SharedKeySignatureVerifier {
+    pub fn new(key: [u8; 16]) -> Self {
+        Self { key }
+    }
+}
+
+impl SignatureVerifier for SharedKeySignatureVerifier {
+    fn verify(
+        &self,
+        _author: &CommandAuthor,
+        lamport: u64,
+        payload: &CommandPayload,
+        causal_context: Option<&VersionVector>,
+        signature: &CommandSignature,
+    ) -> bool {
+        shared_key_mac(&self.key, lamport, payload, causal_context) == *signature
+    }
+}
+
+fn shared_key_mac(
+    key: &[u8; 16],
+    lamport: u64,
+    payload: &CommandPayload,
+    causal_context: Option<&VersionVector>,
+) -> CommandSignature {
+    use siphasher::sip128::{Hasher128, SipHasher24};
+    use std::hash::Hasher;
+
+    let mut hasher = SipHasher24::new_with_key(key);
+    hasher.write(&signing_message(lamport, payload, causal_context));
+    CommandSignature(hasher.finish128().as_bytes().to_vec())
+}
+
 fn current_time_millis() -> u64 {
     use std::time::{SystemTime, UNIX_EPOCH};
     SystemTime::now()
@@ -935,16 +1768,25 @@ fn current_time_millis() -> u64 {
         .as_millis() as u64
 }
 
-#[cfg(feature = "network-quic")]
-fn signing_message(lamport: u64, payload: &CommandPayload) -> Vec<u8> {
+fn signing_message(
+    lamport: u64,
+    payload: &CommandPayload,
+    causal_context: Option<&VersionVector>,
+) -> Vec<u8> {
     #[derive(Serialize)]
     struct SigningPacket<'a> {
         lamport: u64,
         #[serde(borrow)]
         payload: &'a CommandPayload,
+        causal_context: Option<&'a VersionVector>,
     }
 
-    serde_json::to_vec(&SigningPacket { lamport, payload }).unwrap_or_default()
+    serde_json::to_vec(&SigningPacket {
+        lamport,
+        payload,
+        causal_context,
+    })
+    .unwrap_or_default()
 }
 
 #[cfg(feature = "network-quic")]
@@ -957,6 +1799,7 @@ impl SignatureVerifier for Ed25519SignatureVerifier {
         author: &CommandAuthor,
         lamport: u64,
         payload: &CommandPayload,
+        causal_context: Option<&VersionVector>,
         signature: &CommandSignature,
     ) -> bool {
         use ed25519_dalek::{Signature, Verifier, VerifyingKey};
@@ -980,7 +1823,7 @@ impl SignatureVerifier for Ed25519SignatureVerifier {
             None => return false,
         };
 
-        let message = signing_message(lamport, payload);
+        let message = signing_message(lamport, payload, causal_context);
         match Signature::try_from(signature_bytes.as_slice()) {
             Ok(sig) => verifying_key.verify(&message, &sig).is_ok(),
             Err(_) => false,
@@ -1007,10 +1850,15 @@ impl CommandSigner for Ed25519CommandSigner {
         &self.author
     }
 
-    fn sign(&self, lamport: u64, payload: &CommandPayload) -> Option<CommandSignature> {
+    fn sign(
+        &self,
+        lamport: u64,
+        payload: &CommandPayload,
+        causal_context: Option<&VersionVector>,
+    ) -> Option<CommandSignature> {
         use ed25519_dalek::Signer;
 
-        let message = signing_message(lamport, payload);
+        let message = signing_message(lamport, payload, causal_context);
         let signature = self.keypair.sign(&message);
         Some(CommandSignature(signature.to_bytes().to_vec()))
     }
@@ -1040,9 +1888,15 @@ mod tests {
                 .require_signature(true)
                 .build(),
         );
+        crate::network::access::register_access_commands(&mut registry);
         Arc::new(registry)
     }
 
+    /// Access commands are refused unsigned; any signature passes the noop verifier.
+    fn signed(author: u64, role: CommandRole) -> SharedKeyCommandSigner {
+        SharedKeyCommandSigner::new(CommandAuthor::new(AuthorId(author), role), [3; 16])
+    }
+
     struct FakeSignatureSigner {
         author: CommandAuthor,
     }
@@ -1058,7 +1912,12 @@ mod tests {
             &self.author
         }
 
-        fn sign(&self, _lamport: u64, _payload: &CommandPayload) -> Option<CommandSignature> {
+        fn sign(
+            &self,
+            _lamport: u64,
+            _payload: &CommandPayload,
+            _causal_context: Option<&VersionVector>,
+        ) -> Option<CommandSignature> {
             Some(CommandSignature(vec![0u8; 64]))
         }
     }
@@ -1071,6 +1930,7 @@ mod tests {
             _author: &CommandAuthor,
             _lamport: u64,
             _payload: &CommandPayload,
+            _causal_context: Option<&VersionVector>,
             _signature: &CommandSignature,
         ) -> bool {
             false
@@ -1218,6 +2078,328 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn entity_ownership_is_enforced_locally_and_remotely() {
+        use crate::network::access::{CMD_ACCESS_ENTITY_OWNER, EntityOwnershipCommand};
+
+        let registry = setup_registry();
+        let verifier = Arc::new(NoopSignatureVerifier) as Arc<dyn SignatureVerifier>;
+        let mut host = CommandLog::new(registry.clone(), verifier.clone());
+        let mut peer = CommandLog::new(registry, verifier);
+        peer.authority_mut().set_host(AuthorId(1));
+
+        let entity = EntityHandle {
+            index: 3,
+            generation: 0,
+        };
+        let admin = signed(1, CommandRole::Admin);
+        let owner = NoopCommandSigner::new(CommandAuthor::new(AuthorId(2), CommandRole::Editor));
+        let intruder = NoopCommandSigner::new(CommandAuthor::new(AuthorId(3), CommandRole::Editor));
+
+        let lock = EntityOwnershipCommand::new(entity, Some(AuthorId(2)));
+        let payload = CommandPayload::new(
+            CMD_ACCESS_ENTITY_OWNER,
+            CommandScope::Global,
+            serde_json::to_vec(&lock).unwrap(),
+        );
+        let lock_id = host
+            .append_local(&admin, payload, None)
+            .expect("admin assigns owner");
+        assert_eq!(host.access().entity_owner(&entity), Some(&AuthorId(2)));
+
+        let lock_entry = host.entry(&lock_id).cloned().expect("lock entry");
+        assert!(peer.integrate_remote(lock_entry).expect("replicate lock"));
+        assert_eq!(peer.access().entity_owner(&entity), Some(&AuthorId(2)));
+
+        let denied = host.append_local(
+            &intruder,
+            CommandPayload::new("editor.selection", CommandScope::Entity(entity), vec![1]),
+            None,
+        );
+        assert!(matches!(
+            denied,
+            Err(CommandLogError::ScopeAccessDenied {
+                author: AuthorId(3),
+                ..
+            })
+        ));
+
+        let allowed = host
+            .append_local(
+                &owner,
+                CommandPayload::new("editor.selection", CommandScope::Entity(entity), vec![2]),
+                None,
+            )
+            .expect("owner edits entity");
+        let owner_entry = host.entry(&allowed).cloned().expect("owner entry");
+        assert!(
+            peer.integrate_remote(owner_entry)
+                .expect("owner edit replicates")
+        );
+
+        let forged = CommandEntry::new(
+            CommandId::new(10, AuthorId(3)),
+            0,
+            CommandPayload::new("editor.selection", CommandScope::Entity(entity), vec![3]),
+            ConflictStrategy::LastWriteWins,
+            intruder.author().clone(),
+            None,
+        );
+        assert!(matches!(
+            peer.integrate_remote(forged),
+            Err(CommandLogError::ScopeAccessDenied { .. })
+        ));
+    }
+
+    #[test]
+    fn role_changes_replicate_as_commands() {
+        use crate::network::access::{CMD_ACCESS_SET_ROLE, SetRoleCommand};
+
+        let registry = setup_registry();
+        let verifier = Arc::new(NoopSignatureVerifier) as Arc<dyn SignatureVerifier>;
+        let mut log = CommandLog::new(registry, verifier);
+
+        let admin = signed(1, CommandRole::Admin);
+        let viewer = NoopCommandSigner::new(CommandAuthor::new(AuthorId(5), CommandRole::Viewer));
+        let selection = || CommandPayload::new("editor.selection", CommandScope::Global, vec![1]);
+
+        assert!(matches!(
+            log.append_local(&viewer, selection(), None),
+            Err(CommandLogError::InsufficientPermissions { .. })
+        ));
+
+        let promote = CommandPayload::new(
+            CMD_ACCESS_SET_ROLE,
+            CommandScope::Global,
+            serde_json::to_vec(&SetRoleCommand::new(AuthorId(5), CommandRole::Editor)).unwrap(),
+        );
+        assert!(matches!(
+            log.append_local(&viewer, promote.clone(), None),
+            Err(CommandLogError::InsufficientPermissions { .. })
+        ));
+        log.append_local(&admin, promote, None)
+            .expect("admin promotes viewer");
+        log.append_local(&viewer, selection(), None)
+            .expect("promoted author may edit");
+
+        let demote = CommandPayload::new(
+            CMD_ACCESS_SET_ROLE,
+            CommandScope::Global,
+            serde_json::to_vec(&SetRoleCommand::new(AuthorId(5), CommandRole::Viewer)).unwrap(),
+        );
+        log.append_local(&admin, demote, None)
+            .expect("admin demotes author");
+        assert!(matches!(
+            log.append_local(&viewer, selection(), None),
+            Err(CommandLogError::InsufficientPermissions { .. })
+        ));
+    }
+
+    #[test]
+    fn forged_admin_entries_from_guests_are_rejected() {
+        use crate::network::access::{
+            CMD_ACCESS_ENTITY_OWNER, CMD_ACCESS_SET_ROLE, EntityOwnershipCommand, SetRoleCommand,
+        };
+
+        let registry = setup_registry();
+        let verifier = Arc::new(NoopSignatureVerifier) as Arc<dyn SignatureVerifier>;
+        let mut host = CommandLog::new(registry.clone(), Arc::clone(&verifier));
+        host.authority_mut().set_host(AuthorId(1));
+        // The guest declares itself an admin in its own log.
+        let mut guest = CommandLog::new(registry.clone(), Arc::clone(&verifier));
+        let mut forge = |payload: CommandPayload| {
+            let id = guest
+                .append_local(&signed(4, CommandRole::Admin), payload, None)
+                .expect("guest log trusts its own author");
+            guest.entry(&id).unwrap().clone()
+        };
+
+        let promote_self = CommandPayload::new(
+            CMD_ACCESS_SET_ROLE,
+            CommandScope::Global,
+            serde_json::to_vec(&SetRoleCommand::new(AuthorId(4), CommandRole::Admin)).unwrap(),
+        );
+        let mut unsigned = forge(promote_self.clone());
+        unsigned.signature = None;
+        assert!(matches!(
+            host.integrate_remote(unsigned),
+            Err(CommandLogError::SignatureMissing(_))
+        ));
+        assert!(matches!(
+            host.integrate_remote(forge(promote_self)),
+            Err(CommandLogError::InsufficientPermissions {
+                actual: CommandRole::Editor,
+                ..
+            })
+        ));
+        let entity = EntityHandle {
+            index: 3,
+            generation: 0,
+        };
+        let take_entity = CommandPayload::new(
+            CMD_ACCESS_ENTITY_OWNER,
+            CommandScope::Global,
+            serde_json::to_vec(&EntityOwnershipCommand::new(entity, Some(AuthorId(4)))).unwrap(),
+        );
+        assert!(matches!(
+            host.integrate_remote(forge(take_entity)),
+            Err(CommandLogError::InsufficientPermissions { .. })
+        ));
+        assert_eq!(host.access().role_override(&AuthorId(4)), None);
+        assert_eq!(host.access().entity_owner(&entity), None);
+    }
+
+    #[test]
+    fn malformed_access_commands_are_not_logged() {
+        use crate::network::access::CMD_ACCESS_TOOL_GRANT;
+
+        let registry = setup_registry();
+        let verifier = Arc::new(NoopSignatureVerifier) as Arc<dyn SignatureVerifier>;
+        let config =
+            CommandLogConfig::with_rate_limit(RateLimitConfig::new(1, 0, Duration::from_secs(1)));
+        let mut log = CommandLog::with_config(registry, verifier, config);
+        let admin = signed(1, CommandRole::Admin);
+
+        let payload = CommandPayload::new(CMD_ACCESS_TOOL_GRANT, CommandScope::Global, vec![0xff]);
+        assert!(matches!(
+            log.append_local(&admin, payload, None),
+            Err(CommandLogError::PacketDecodeFailed(_))
+        ));
+        assert_eq!(log.entries().count(), 0);
+        assert_eq!(log.lamport(), 0);
+
+        // The rejected command spent neither the lamport tick nor the only token.
+        let selection = CommandPayload::new("editor.selection", CommandScope::Global, vec![1]);
+        let id = log
+            .append_local(&admin, selection, None)
+            .expect("token left");
+        assert_eq!(id.lamport(), 1);
+    }
+
+    #[test]
+    fn entries_wait_for_the_access_changes_their_author_saw() {
+        use crate::network::access::{CMD_ACCESS_SET_ROLE, SetRoleCommand};
+
+        let registry = setup_registry();
+        let verifier = Arc::new(NoopSignatureVerifier) as Arc<dyn SignatureVerifier>;
+        let mut admin_log = CommandLog::new(registry.clone(), Arc::clone(&verifier));
+        let mut viewer_log = CommandLog::new(registry.clone(), Arc::clone(&verifier));
+        let mut observer = CommandLog::new(registry, verifier);
+        viewer_log.authority_mut().set_host(AuthorId(1));
+        observer.authority_mut().set_host(AuthorId(1));
+
+        let admin = signed(1, CommandRole::Admin);
+        let viewer = NoopCommandSigner::new(CommandAuthor::new(AuthorId(5), CommandRole::Viewer));
+        let promote = CommandPayload::new(
+            CMD_ACCESS_SET_ROLE,
+            CommandScope::Global,
+            serde_json::to_vec(&SetRoleCommand::new(AuthorId(5), CommandRole::Editor)).unwrap(),
+        );
+        let promote_id = admin_log
+            .append_local(&admin, promote, None)
+            .expect("admin promotes viewer");
+        let promotion = admin_log.entry(&promote_id).unwrap().clone();
+        viewer_log.integrate_remote(promotion.clone()).unwrap();
+        let edit_id = viewer_log
+            .append_local(
+                &viewer,
+                CommandPayload::new("editor.selection", CommandScope::Global, vec![1]),
+                None,
+            )
+            .expect("promoted viewer edits");
+        let edit = viewer_log.entry(&edit_id).unwrap().clone();
+
+        // The edit overtakes the promotion it depends on.
+        assert_eq!(observer.integrate_remote(edit.clone()), Ok(false));
+        assert!(observer.is_deferred(&edit_id));
+        assert!(observer.integrate_remote(promotion).expect("promotion"));
+        assert_eq!(observer.drain_released(), vec![edit]);
+        assert!(!observer.is_deferred(&edit_id));
+        assert!(observer.entry(&edit_id).is_some());
+    }
+
+    #[test]
+    fn transfers_wait_for_the_grant_they_pass_on() {
+        use crate::network::authority::{
+            AuthorityGrantCommand, CMD_AUTHORITY_GRANT, register_authority_commands,
+        };
+
+        let mut registry = CommandRegistry::new();
+        register_authority_commands(&mut registry);
+        let registry = Arc::new(registry);
+        const SESSION_KEY: [u8; 16] = [7; 16];
+        let verifier =
+            Arc::new(SharedKeySignatureVerifier::new(SESSION_KEY)) as Arc<dyn SignatureVerifier>;
+        let hosted = || {
+            let mut log = CommandLog::new(Arc::clone(&registry), Arc::clone(&verifier));
+            log.authority_mut().set_host(AuthorId(1));
+            log
+        };
+        let (mut host_log, mut alice_log, mut observer) = (hosted(), hosted(), hosted());
+
+        let signer = |author: u64| {
+            SharedKeyCommandSigner::new(
+                CommandAuthor::new(AuthorId(author), CommandRole::Editor),
+                SESSION_KEY,
+            )
+        };
+        let (host, alice) = (signer(1), signer(2));
+        let crate_handle = EntityHandle {
+            index: 4,
+            generation: 0,
+        };
+        let grant_to = |owner: u64| {
+            CommandPayload::new(
+                CMD_AUTHORITY_GRANT,
+                CommandScope::Entity(crate_handle),
+                serde_json::to_vec(&AuthorityGrantCommand::new(crate_handle, AuthorId(owner)))
+                    .unwrap(),
+            )
+        };
+
+        let unkeyed = NoopCommandSigner::new(CommandAuthor::new(AuthorId(1), CommandRole::Editor));
+        assert!(matches!(
+            host_log.append_local(&unkeyed, grant_to(2), None),
+            Err(CommandLogError::SignatureMissing(_))
+        ));
+        let grant_id = host_log
+            .append_local(&host, grant_to(2), None)
+            .expect("host grants alice");
+        let grant = host_log.entry(&grant_id).unwrap().clone();
+        alice_log.integrate_remote(grant.clone()).unwrap();
+        let transfer_id = alice_log
+            .append_local(&alice, grant_to(3), None)
+            .expect("alice passes it on");
+        let transfer = alice_log.entry(&transfer_id).unwrap().clone();
+
+        let mut unsigned = transfer.clone();
+        unsigned.signature = None;
+        assert!(matches!(
+            observer.integrate_remote(unsigned),
+            Err(CommandLogError::SignatureMissing(_))
+        ));
+        let mut forged = transfer.clone();
+        forged.signature = SharedKeyCommandSigner::new(transfer.author.clone(), [8; 16]).sign(
+            transfer.id.lamport(),
+            &transfer.payload,
+            transfer.causal_context.as_ref(),
+        );
+        assert!(matches!(
+            observer.integrate_remote(forged),
+            Err(CommandLogError::InvalidSignature(_))
+        ));
+
+        // The transfer overtakes the grant that made alice the owner.
+        assert_eq!(observer.integrate_remote(transfer.clone()), Ok(false));
+        assert!(observer.is_deferred(&transfer_id));
+        assert!(observer.integrate_remote(grant).expect("grant"));
+        assert_eq!(observer.drain_released(), vec![transfer]);
+        assert_eq!(
+            observer.authority().owner(&crate_handle),
+            Some(&AuthorId(3))
+        );
+    }
+
     #[test]
     fn last_write_wins_keeps_latest_lamport() {
         let registry = setup_registry();
@@ -1258,13 +2440,15 @@ mod tests {
     }
 
     #[test]
-    fn reject_conflict_prevents_duplicates() {
+    fn reject_conflict_prevents_concurrent_writes() {
         let registry = setup_registry();
         let verifier = Arc::new(NoopSignatureVerifier) as Arc<dyn SignatureVerifier>;
-        let mut log = CommandLog::new(registry.clone(), verifier);
+        let mut log = CommandLog::new(registry.clone(), Arc::clone(&verifier));
+        let mut concurrent = CommandLog::new(registry.clone(), verifier);
 
         let editor = CommandAuthor::new(AuthorId(3), CommandRole::Editor);
         let signer = NoopCommandSigner::new(editor);
+        let rival = NoopCommandSigner::new(CommandAuthor::new(AuthorId(4), CommandRole::Editor));
 
         let payload = CommandPayload::new("editor.selection", CommandScope::Global, vec![9]);
         let id = log
@@ -1273,12 +2457,283 @@ mod tests {
 
         assert_eq!(id.lamport(), 1);
 
-        let payload_second =
-            CommandPayload::new("editor.selection", CommandScope::Global, vec![10]);
-        let result = log.append_local(&signer, payload_second, Some(ConflictStrategy::Reject));
+        let payload_rival = CommandPayload::new("editor.selection", CommandScope::Global, vec![11]);
+        concurrent
+            .append_local(&rival, payload_rival, Some(ConflictStrategy::Reject))
+            .expect("rival append succeeds locally");
+        let rival_entry = concurrent.entries().next().expect("rival entry").clone();
+
+        let result = log.integrate_remote(rival_entry);
         assert!(matches!(result, Err(CommandLogError::ConflictRejected)));
     }
 
+    #[test]
+    fn concurrent_rejects_settle_on_the_lower_command_id() {
+        let registry = setup_registry();
+        let verifier = Arc::new(NoopSignatureVerifier) as Arc<dyn SignatureVerifier>;
+        let entry = |author: u64, data: u8| {
+            let mut log = CommandLog::new(registry.clone(), Arc::clone(&verifier));
+            let signer =
+                NoopCommandSigner::new(CommandAuthor::new(AuthorId(author), CommandRole::Editor));
+            log.append_local(
+                &signer,
+                CommandPayload::new("editor.selection", CommandScope::Global, vec![data]),
+                Some(ConflictStrategy::Reject),
+            )
+            .expect("local write");
+            log.entries().next().expect("entry").clone()
+        };
+        let winner = entry(3, 1);
+        let loser = entry(4, 2);
+
+        let mut in_order = CommandLog::new(registry.clone(), Arc::clone(&verifier));
+        assert!(in_order.integrate_remote(winner.clone()).expect("winner"));
+        assert_eq!(
+            in_order.integrate_remote(loser.clone()),
+            Err(CommandLogError::ConflictRejected)
+        );
+
+        let mut reversed = CommandLog::new(registry.clone(), Arc::clone(&verifier));
+        assert!(
+            reversed
+                .integrate_remote(loser.clone())
+                .expect("loser first")
+        );
+        assert!(reversed.drain_superseded().is_empty());
+        assert!(reversed.integrate_remote(winner.clone()).expect("winner"));
+        // The loser was applied before the winner arrived; it is handed back to be undone.
+        assert_eq!(reversed.drain_superseded(), vec![loser]);
+        assert!(in_order.drain_superseded().is_empty());
+
+        let ids = |log: &CommandLog| log.entries().map(|e| e.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&in_order), vec![winner.id.clone()]);
+        assert_eq!(ids(&reversed), ids(&in_order));
+    }
+
+    #[test]
+    fn reject_accepts_causally_ordered_writes() {
+        let registry = setup_registry();
+        let verifier = Arc::new(NoopSignatureVerifier) as Arc<dyn SignatureVerifier>;
+        let mut first = CommandLog::new(registry.clone(), Arc::clone(&verifier));
+        let mut second = CommandLog::new(registry.clone(), Arc::clone(&verifier));
+        let mut observer = CommandLog::new(registry, verifier);
+
+        let alice = NoopCommandSigner::new(CommandAuthor::new(AuthorId(1), CommandRole::Editor));
+        let bob = NoopCommandSigner::new(CommandAuthor::new(AuthorId(2), CommandRole::Editor));
+        let scope = CommandScope::Tool("brush".into());
+
+        first
+            .append_local(
+                &alice,
+                CommandPayload::new("editor.selection", scope.clone(), vec![1]),
+                Some(ConflictStrategy::Reject),
+            )
+            .expect("alice writes first");
+        let alice_entry = first.entries().next().expect("alice entry").clone();
+
+        assert!(
+            second
+                .integrate_remote(alice_entry.clone())
+                .expect("bob sees alice")
+        );
+        second
+            .append_local(
+                &bob,
+                CommandPayload::new("editor.selection", scope.clone(), vec![2]),
+                Some(ConflictStrategy::Reject),
+            )
+            .expect("bob's write follows alice causally");
+        let bob_entry = second
+            .entries()
+            .find(|entry| entry.author.id == AuthorId(2))
+            .expect("bob entry")
+            .clone();
+
+        assert!(alice_entry.happened_before(&bob_entry));
+        assert!(!alice_entry.is_concurrent_with(&bob_entry));
+
+        assert!(observer.integrate_remote(alice_entry).expect("alice ok"));
+        assert!(
+            observer
+                .integrate_remote(bob_entry)
+                .expect("bob supersedes")
+        );
+        assert_eq!(observer.version_vector().get(&AuthorId(1)), 1);
+        assert_eq!(observer.version_vector().get(&AuthorId(2)), 2);
+
+        let sequential = second.append_local(
+            &bob,
+            CommandPayload::new("editor.selection", scope, vec![3]),
+            Some(ConflictStrategy::Reject),
+        );
+        assert!(sequential.is_ok());
+    }
+
+    fn bulky_batch(entries: usize, bytes_per_entry: usize) -> CommandBatch {
+        let author = CommandAuthor::new(AuthorId(4), CommandRole::Editor);
+        CommandBatch {
+            sequence: 3,
+            nonce: 8,
+            timestamp_ms: 100,
+            author: AuthorId(4),
+            entries: (0..entries as u64)
+                .map(|index| {
+                    CommandEntry::new(
+                        CommandId::new(index + 1, AuthorId(4)),
+                        0,
+                        CommandPayload::new(
+                            "editor.create",
+                            CommandScope::Global,
+                            vec![index as u8; bytes_per_entry],
+                        ),
+                        ConflictStrategy::Merge,
+                        author.clone(),
+                        None,
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn split_for_packets_preserves_order_and_fits_budget() {
+        let batch = bulky_batch(40, 4 * 1024);
+        assert!(CommandPacket::from_batch(&batch).is_err());
+
+        let parts = batch
+            .clone()
+            .split_for_packets(PacketEncoding::Json)
+            .unwrap();
+        assert!(parts.len() > 1);
+        for part in &parts {
+            CommandPacket::from_batch(part).expect("part fits a packet");
+        }
+        let rejoined: Vec<CommandEntry> = parts.into_iter().flat_map(|part| part.entries).collect();
+        assert_eq!(rejoined, batch.entries);
+    }
+
+    #[test]
+    fn oversized_entry_fragments_and_reassembles_out_of_order() {
+        let batch = bulky_batch(1, 100 * 1024);
+        let mut fragments = CommandPacket::encode_fragmented(&batch, PacketEncoding::Json).unwrap();
+        assert!(fragments.len() > 1);
+        assert!(fragments.iter().all(|packet| {
+            packet.payload.len() <= MAX_COMMAND_PACKET_BYTES && packet.nonce == batch.nonce
+        }));
+        assert!(fragments[0].decode().is_err());
+
+        fragments.reverse();
+        let mut reassembler = FragmentReassembler::new();
+        let (last, rest) = fragments.split_last().unwrap();
+        for fragment in rest {
+            assert!(reassembler.accept(fragment).unwrap().is_none());
+            // duplicates are ignored rather than double counted
+            assert!(reassembler.accept(fragment).unwrap().is_none());
+        }
+        let decoded = reassembler.accept(last).unwrap().expect("complete batch");
+        assert_eq!(decoded, batch);
+        assert_eq!(reassembler.pending_groups(), 0);
+    }
+
+    #[test]
+    fn reassembler_bounds_incomplete_groups() {
+        let mut reassembler = FragmentReassembler::new();
+        for nonce in 0..(MAX_PENDING_FRAGMENT_GROUPS as u64 + 4) {
+            let mut batch = bulky_batch(1, 80 * 1024);
+            batch.nonce = nonce;
+            let fragments = CommandPacket::encode_fragmented(&batch, PacketEncoding::Json).unwrap();
+            assert!(reassembler.accept(&fragments[0]).unwrap().is_none());
+        }
+        assert_eq!(reassembler.pending_groups(), MAX_PENDING_FRAGMENT_GROUPS);
+
+        let mut bogus = CommandPacket::from_batch(&bulky_batch(1, 8)).unwrap();
+        bogus.fragment = Some(PacketFragment {
+            author: AuthorId(4),
+            index: 3,
+            total: 2,
+        });
+        assert!(reassembler.accept(&bogus).is_err());
+    }
+
+    #[test]
+    fn reassembler_caps_bytes_across_authors() {
+        let fragment = |author: u64, index: u32| {
+            let mut packet = CommandPacket::from_batch(&bulky_batch(1, 8)).unwrap();
+            packet.payload = vec![0; MAX_COMMAND_PACKET_BYTES];
+            packet.fragment = Some(PacketFragment {
+                author: AuthorId(author),
+                index,
+                total: MAX_COMMAND_FRAGMENTS,
+            });
+            packet
+        };
+        let mut reassembler = FragmentReassembler::new();
+        for index in 0..MAX_COMMAND_FRAGMENTS / 2 {
+            for author in [1, 2] {
+                assert!(
+                    reassembler
+                        .accept(&fragment(author, index))
+                        .unwrap()
+                        .is_none()
+                );
+            }
+        }
+        assert_eq!(reassembler.pending_groups(), 2);
+
+        for index in 0..4 {
+            assert!(reassembler.accept(&fragment(3, index)).unwrap().is_none());
+            assert!(reassembler.buffered_bytes() <= MAX_REASSEMBLY_BYTES);
+        }
+        // The first author's batch was the oldest and went first; the second one's stays.
+        assert_eq!(reassembler.pending_groups(), 2);
+        assert_eq!(
+            reassembler.buffered_bytes(),
+            (MAX_COMMAND_FRAGMENTS as usize / 2 + 4) * MAX_COMMAND_PACKET_BYTES
+        );
+    }
+
+    #[test]
+    fn causal_context_round_trips_through_packets() {
+        let mut context = VersionVector::new();
+        context.observe(&AuthorId(5), 3);
+        context.observe(&AuthorId(9), 12);
+        context.observe(&AuthorId(5), 1);
+        assert_eq!(context.get(&AuthorId(5)), 3);
+
+        let entry = CommandEntry::new(
+            CommandId::new(13, AuthorId(9)),
+            0,
+            CommandPayload::new("editor.selection", CommandScope::Global, vec![1]),
+            ConflictStrategy::Reject,
+            CommandAuthor::new(AuthorId(9), CommandRole::Editor),
+            None,
+        )
+        .with_causal_context(context.clone());
+        let batch = CommandBatch {
+            sequence: 1,
+            nonce: 1,
+            timestamp_ms: 1,
+            author: AuthorId(9),
+            entries: vec![entry],
+        };
+
+        let packet = CommandPacket::from_batch(&batch).expect("encode");
+        let decoded = packet.decode().expect("decode");
+        assert_eq!(decoded.entries[0].causal_context, Some(context));
+
+        let legacy = serde_json::to_vec(&CommandEntry::new(
+            CommandId::new(1, AuthorId(1)),
+            0,
+            CommandPayload::new("editor.selection", CommandScope::Global, vec![]),
+            ConflictStrategy::Merge,
+            CommandAuthor::new(AuthorId(1), CommandRole::Editor),
+            None,
+        ))
+        .expect("encode legacy");
+        let restored: CommandEntry = serde_json::from_slice(&legacy).expect("decode legacy");
+        assert!(restored.causal_context.is_none());
+    }
+
     #[test]
     fn merge_allows_multiple_entries() {
         let registry = setup_registry();
@@ -0,0 +1,457 @@
+//! Optional zstd compression of transport frames. Peers opt in through session handshake
+//! capabilities; once negotiated, a frame that compresses well travels as a
+//! `CompressedFrame` envelope whose header says `Zstd`. Other frames travel as-is.
+
+use crate::network::wire::theta::net::{self, Compression, MessageBody, root_as_message_envelope};
+use flatbuffers::FlatBufferBuilder;
+#[cfg(feature = "network-zstd")]
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use thiserror::Error;
+
+/// Handshake capability advertising zstd-compressed frames.
+pub const CAPABILITY_ZSTD_FRAMES: u32 = 32;
+
+/// Dictionary capabilities carry the dictionary id in their low 24 bits, so a dictionary
+/// is only used when both peers advertise the same one.
+const CAPABILITY_ZSTD_DICTIONARY: u32 = 0x5A00_0000;
+const DICTIONARY_ID_MASK: u32 = 0x00FF_FFFF;
+const ZSTD_DICTIONARY_MAGIC: u32 = 0xEC30_A437;
+
+/// Kind byte of a compressed frame, next to the transport's own frame kinds.
+pub(crate) const FRAME_KIND_COMPRESSED: u8 = 4;
+
+/// Frames that would decompress past this are rejected.
+pub const MAX_DECOMPRESSED_FRAME_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum CompressionError {
+    #[error("zstd support is not compiled in")]
+    Unsupported,
+    #[error("empty frame")]
+    EmptyFrame,
+    #[error("malformed compressed frame: {0}")]
+    InvalidFrame(String),
+    #[error("frame would decompress to {0} bytes")]
+    TooLarge(u64),
+    #[error("frame uses a dictionary that was not negotiated")]
+    MissingDictionary,
+    #[error("invalid zstd dictionary")]
+    InvalidDictionary,
+    #[error("zstd: {0}")]
+    Zstd(#[from] std::io::Error),
+}
+
+/// A zstd dictionary shared by both peers, typically trained on command batches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressionDictionary {
+    id: u32,
+    bytes: Vec<u8>,
+}
+
+impl CompressionDictionary {
+    /// Wraps a dictionary produced by `zstd --train` or [`Self::train`].
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, CompressionError> {
+        let header = |range: std::ops::Range<usize>| {
+            bytes
+                .get(range)
+                .map(|slice| u32::from_le_bytes(slice.try_into().unwrap()))
+        };
+        if header(0..4) != Some(ZSTD_DICTIONARY_MAGIC) {
+            return Err(CompressionError::InvalidDictionary);
+        }
+        // Frames name their dictionary by id; zero means none.
+        let id = header(4..8)
+            .filter(|id| *id != 0)
+            .ok_or(CompressionError::InvalidDictionary)?;
+        Ok(Self { id, bytes })
+    }
+
+    /// Trains a dictionary of at most `max_size` bytes from sample frames. Small JSON
+    /// command batches compress poorly on their own but share most of their keys.
+    #[cfg(feature = "network-zstd")]
+    pub fn train<S: AsRef<[u8]>>(samples: &[S], max_size: usize) -> Result<Self, CompressionError> {
+        Self::from_bytes(zstd::dict::from_samples(samples, max_size)?)
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn capability(&self) -> u32 {
+        CAPABILITY_ZSTD_DICTIONARY | (self.id & DICTIONARY_ID_MASK)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressionConfig {
+    /// Frames shorter than this are sent uncompressed.
+    pub threshold: usize,
+    pub level: i32,
+    /// Used for frames up to `dictionary_limit` bytes when both peers hold it.
+    pub dictionary: Option<CompressionDictionary>,
+    pub dictionary_limit: usize,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 256,
+            level: 3,
+            dictionary: None,
+            dictionary_limit: 4 * 1024,
+        }
+    }
+}
+
+impl CompressionConfig {
+    pub fn with_threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn with_dictionary(mut self, dictionary: CompressionDictionary) -> Self {
+        self.dictionary = Some(dictionary);
+        self
+    }
+
+    /// Capabilities to add to the session handshake; empty without the `network-zstd`
+    /// feature.
+    pub fn capabilities(&self) -> Vec<u32> {
+        if !cfg!(feature = "network-zstd") {
+            return Vec::new();
+        }
+        std::iter::once(CAPABILITY_ZSTD_FRAMES)
+            .chain(
+                self.dictionary
+                    .as_ref()
+                    .map(CompressionDictionary::capability),
+            )
+            .collect()
+    }
+}
+
+/// Bytes before and after compression across both directions of a session.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompressionStats {
+    pub frames: u64,
+    pub compressed_frames: u64,
+    pub raw_bytes: u64,
+    pub wire_bytes: u64,
+}
+
+impl CompressionStats {
+    /// Raw over wire bytes; 1.0 before any frame.
+    pub fn ratio(&self) -> f32 {
+        if self.wire_bytes == 0 {
+            1.0
+        } else {
+            self.raw_bytes as f32 / self.wire_bytes as f32
+        }
+    }
+}
+
+#[cfg(feature = "network-zstd")]
+struct DictionaryCodec {
+    id: u32,
+    compressor: Mutex<zstd::bulk::Compressor<'static>>,
+    decompressor: Mutex<zstd::bulk::Decompressor<'static>>,
+}
+
+/// Compresses and decompresses the frames of one negotiated session.
+pub struct FrameCompressor {
+    config: CompressionConfig,
+    #[cfg(feature = "network-zstd")]
+    dictionary: Option<DictionaryCodec>,
+    frames: AtomicU64,
+    compressed_frames: AtomicU64,
+    raw_bytes: AtomicU64,
+    wire_bytes: AtomicU64,
+}
+
+impl FrameCompressor {
+    /// `None` unless both peers agreed on zstd frames; the dictionary is used only when
+    /// its capability was negotiated too.
+    pub fn negotiate(
+        config: CompressionConfig,
+        capability_mask: &[u32],
+    ) -> Result<Option<Self>, CompressionError> {
+        if !cfg!(feature = "network-zstd") || !capability_mask.contains(&CAPABILITY_ZSTD_FRAMES) {
+            return Ok(None);
+        }
+        #[cfg(feature = "network-zstd")]
+        let dictionary = match &config.dictionary {
+            Some(dictionary) if capability_mask.contains(&dictionary.capability()) => {
+                Some(DictionaryCodec {
+                    id: dictionary.id(),
+                    compressor: Mutex::new(zstd::bulk::Compressor::with_dictionary(
+                        config.level,
+                        dictionary.bytes(),
+                    )?),
+                    decompressor: Mutex::new(zstd::bulk::Decompressor::with_dictionary(
+                        dictionary.bytes(),
+                    )?),
+                })
+            }
+            _ => None,
+        };
+        Ok(Some(Self {
+            config,
+            #[cfg(feature = "network-zstd")]
+            dictionary,
+            frames: AtomicU64::new(0),
+            compressed_frames: AtomicU64::new(0),
+            raw_bytes: AtomicU64::new(0),
+            wire_bytes: AtomicU64::new(0),
+        }))
+    }
+
+    /// Compresses `frame` when it is large enough and compression helps; otherwise
+    /// returns it unchanged.
+    pub fn compress(&self, frame: &[u8]) -> Vec<u8> {
+        let compressed = if frame.len() >= self.config.threshold {
+            self.try_compress(frame)
+        } else {
+            None
+        };
+        let wire =
+            match compressed.map(|(dictionary_id, bytes)| encode_frame(dictionary_id, &bytes)) {
+                Some(wire) if wire.len() < frame.len() => {
+                    self.compressed_frames.fetch_add(1, Ordering::Relaxed);
+                    wire
+                }
+                _ => frame.to_vec(),
+            };
+        self.record(frame.len(), wire.len());
+        wire
+    }
+
+    pub fn decompress(&self, frame: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        let (&kind, body) = frame.split_first().ok_or(CompressionError::EmptyFrame)?;
+        if kind != FRAME_KIND_COMPRESSED {
+            self.record(frame.len(), frame.len());
+            return Ok(frame.to_vec());
+        }
+        let (dictionary_id, payload) = decode_frame(body)?;
+        let raw = self.zstd_decompress(dictionary_id, payload)?;
+        self.compressed_frames.fetch_add(1, Ordering::Relaxed);
+        self.record(raw.len(), frame.len());
+        Ok(raw)
+    }
+
+    pub fn stats(&self) -> CompressionStats {
+        CompressionStats {
+            frames: self.frames.load(Ordering::Relaxed),
+            compressed_frames: self.compressed_frames.load(Ordering::Relaxed),
+            raw_bytes: self.raw_bytes.load(Ordering::Relaxed),
+            wire_bytes: self.wire_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    fn record(&self, raw: usize, wire: usize) {
+        self.frames.fetch_add(1, Ordering::Relaxed);
+        self.raw_bytes.fetch_add(raw as u64, Ordering::Relaxed);
+        self.wire_bytes.fetch_add(wire as u64, Ordering::Relaxed);
+    }
+
+    /// The compressed bytes and the id of the dictionary used, or zero.
+    #[cfg(feature = "network-zstd")]
+    fn try_compress(&self, frame: &[u8]) -> Option<(u32, Vec<u8>)> {
+        let result = match &self.dictionary {
+            Some(codec) if frame.len() <= self.config.dictionary_limit => codec
+                .compressor
+                .lock()
+                .unwrap()
+                .compress(frame)
+                .map(|bytes| (codec.id, bytes)),
+            _ => zstd::bulk::compress(frame, self.config.level).map(|bytes| (0, bytes)),
+        };
+        result
+            .map_err(|err| log::warn!("[compression] sending frame uncompressed: {err}"))
+            .ok()
+    }
+
+    #[cfg(not(feature = "network-zstd"))]
+    fn try_compress(&self, _frame: &[u8]) -> Option<(u32, Vec<u8>)> {
+        None
+    }
+
+    /// Reserves only the size the zstd frame declares for its content.
+    #[cfg(feature = "network-zstd")]
+    fn zstd_decompress(
+        &self,
+        dictionary_id: u32,
+        payload: &[u8],
+    ) -> Result<Vec<u8>, CompressionError> {
+        let capacity = match zstd::zstd_safe::get_frame_content_size(payload) {
+            Ok(Some(size)) if size <= MAX_DECOMPRESSED_FRAME_BYTES as u64 => size as usize,
+            Ok(Some(size)) => return Err(CompressionError::TooLarge(size)),
+            _ => {
+                return Err(CompressionError::InvalidFrame(
+                    "zstd frame does not declare its content size".into(),
+                ));
+            }
+        };
+        if dictionary_id == 0 {
+            return Ok(zstd::bulk::decompress(payload, capacity)?);
+        }
+        let codec = self
+            .dictionary
+            .as_ref()
+            .filter(|codec| codec.id == dictionary_id)
+            .ok_or(CompressionError::MissingDictionary)?;
+        Ok(codec
+            .decompressor
+            .lock()
+            .unwrap()
+            .decompress(payload, capacity)?)
+    }
+
+    #[cfg(not(feature = "network-zstd"))]
+    fn zstd_decompress(
+        &self,
+        _dictionary_id: u32,
+        _payload: &[u8],
+    ) -> Result<Vec<u8>, CompressionError> {
+        Err(CompressionError::Unsupported)
+    }
+}
+
+fn encode_frame(dictionary_id: u32, payload: &[u8]) -> Vec<u8> {
+    let mut builder = FlatBufferBuilder::with_capacity(64 + payload.len());
+    let payload = builder.create_vector(payload);
+    let body = net::CompressedFrame::create(
+        &mut builder,
+        &net::CompressedFrameArgs {
+            dictionary_id,
+            payload: Some(payload),
+        },
+    );
+    let header = net::PacketHeader::create(
+        &mut builder,
+        &net::PacketHeaderArgs {
+            sequence_id: 0,
+            timestamp_ms: 0,
+            compression: Compression::Zstd,
+            schema_hash: 0,
+        },
+    );
+    let envelope = net::MessageEnvelope::create(
+        &mut builder,
+        &net::MessageEnvelopeArgs {
+            header: Some(header),
+            body_type: MessageBody::CompressedFrame,
+            body: Some(body.as_union_value()),
+        },
+    );
+    net::finish_message_envelope_buffer(&mut builder, envelope);
+    [&[FRAME_KIND_COMPRESSED][..], builder.finished_data()].concat()
+}
+
+/// The dictionary id and zstd payload of a compressed frame, past its kind byte.
+fn decode_frame(bytes: &[u8]) -> Result<(u32, &[u8]), CompressionError> {
+    let invalid = |message: String| CompressionError::InvalidFrame(message);
+    let envelope = root_as_message_envelope(bytes).map_err(|err| invalid(err.to_string()))?;
+    let compression = envelope.header().map(|header| header.compression());
+    if compression != Some(Compression::Zstd) {
+        return Err(invalid(format!("header compression {compression:?}")));
+    }
+    let body = envelope
+        .body_as_compressed_frame()
+        .ok_or_else(|| invalid(format!("unexpected {:?} body", envelope.body_type())))?;
+    let payload = body
+        .payload()
+        .map(|payload| payload.bytes())
+        .unwrap_or_default();
+    Ok((body.dictionary_id(), payload))
+}
+
+#[cfg(all(test, feature = "network-zstd"))]
+mod tests {
+    use super::*;
+    use crate::network::command_log::{AuthorId, CommandBatch};
+
+    fn command_batch_json(sequence: u64) -> Vec<u8> {
+        let batch = CommandBatch {
+            sequence,
+            nonce: sequence * 7919,
+            timestamp_ms: 1_700_000_000_000 + sequence * 16,
+            author: AuthorId(sequence % 4),
+            entries: Vec::new(),
+        };
+        serde_json::to_vec(&batch).unwrap()
+    }
+
+    #[test]
+    fn negotiated_frames_compress_and_small_batches_use_the_dictionary() {
+        let samples: Vec<_> = (0..500).map(command_batch_json).collect();
+        let dictionary = CompressionDictionary::train(&samples, 2 * 1024).unwrap();
+        let config = CompressionConfig::default()
+            .with_threshold(32)
+            .with_dictionary(dictionary.clone());
+        let capabilities = config.capabilities();
+        assert_eq!(
+            capabilities,
+            vec![CAPABILITY_ZSTD_FRAMES, dictionary.capability()]
+        );
+
+        // A peer without the dictionary still gets plain zstd frames.
+        let plain = FrameCompressor::negotiate(config.clone(), &[CAPABILITY_ZSTD_FRAMES])
+            .unwrap()
+            .unwrap();
+        let both = FrameCompressor::negotiate(config.clone(), &capabilities)
+            .unwrap()
+            .unwrap();
+        assert!(FrameCompressor::negotiate(config, &[]).unwrap().is_none());
+
+        // Small enough for the dictionary, large enough to carry the envelope.
+        let batch: Vec<u8> = (9_001..9_005).flat_map(command_batch_json).collect();
+        let with_dictionary = both.compress(&batch);
+        assert_eq!(with_dictionary[0], FRAME_KIND_COMPRESSED);
+        assert_eq!(
+            decode_frame(&with_dictionary[1..]).unwrap().0,
+            dictionary.id()
+        );
+        assert!(with_dictionary.len() < plain.compress(&batch).len());
+        assert_eq!(both.decompress(&with_dictionary).unwrap(), batch);
+        assert!(matches!(
+            plain.decompress(&with_dictionary),
+            Err(CompressionError::MissingDictionary)
+        ));
+
+        let large = serde_json::to_vec(&samples).unwrap();
+        let frame = both.compress(&large);
+        assert_eq!(decode_frame(&frame[1..]).unwrap().0, 0);
+        assert_eq!(plain.decompress(&frame).unwrap(), large);
+        let tiny = both.compress(b"{}");
+        assert_eq!(tiny, b"{}");
+        assert_eq!(both.decompress(&tiny).unwrap(), b"{}");
+        assert!(both.stats().ratio() > 2.0);
+    }
+
+    #[test]
+    fn frames_declaring_too_much_content_are_rejected() {
+        let compressor =
+            FrameCompressor::negotiate(CompressionConfig::default(), &[CAPABILITY_ZSTD_FRAMES])
+                .unwrap()
+                .unwrap();
+        let oversized = compressor.compress(&vec![0; MAX_DECOMPRESSED_FRAME_BYTES + 1]);
+        assert!(matches!(
+            compressor.decompress(&oversized),
+            Err(CompressionError::TooLarge(size)) if size == MAX_DECOMPRESSED_FRAME_BYTES as u64 + 1
+        ));
+
+        // A frame that hides its size is refused rather than given the maximum buffer.
+        let mut encoder = zstd::stream::Encoder::new(Vec::new(), 3).unwrap();
+        std::io::Write::write_all(&mut encoder, &[7; 1024]).unwrap();
+        let unsized_frame = encode_frame(0, &encoder.finish().unwrap());
+        assert!(matches!(
+            compressor.decompress(&unsized_frame),
+            Err(CompressionError::InvalidFrame(_))
+        ));
+    }
+}
@@ -0,0 +1,124 @@
+//! Byte-run patches between two encodings of the same component. Replicated components
+//! use fixed-width binary fields, so a changed field becomes one short run.
+//!
+//! Layout, all integers LEB128: the new length, then `(skip, len, bytes[len])` runs where
+//! `skip` counts unchanged bytes since the end of the previous run.
+
+use crate::network::compression::MAX_DECOMPRESSED_FRAME_BYTES;
+
+/// Unchanged gaps shorter than this are folded into the surrounding run, since a new
+/// run header costs about as much.
+const MIN_GAP: usize = 4;
+
+/// Largest value a patch may produce; a component never outgrows the frame carrying it.
+pub const MAX_COMPONENT_BYTES: usize = MAX_DECOMPRESSED_FRAME_BYTES;
+
+pub fn encode_patch(base: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut patch = Vec::new();
+    write_varint(&mut patch, target.len() as u64);
+    let differs = |index: usize| base.get(index) != Some(&target[index]);
+
+    let mut cursor = 0;
+    let mut index = 0;
+    while index < target.len() {
+        if !differs(index) {
+            index += 1;
+            continue;
+        }
+        let start = index;
+        let mut end = index + 1;
+        let mut scan = end;
+        while scan < target.len() && scan - end < MIN_GAP {
+            if differs(scan) {
+                end = scan + 1;
+            }
+            scan += 1;
+        }
+        write_varint(&mut patch, (start - cursor) as u64);
+        write_varint(&mut patch, (end - start) as u64);
+        patch.extend_from_slice(&target[start..end]);
+        cursor = end;
+        index = end;
+    }
+    patch
+}
+
+/// `None` when the patch is malformed, reaches past the target length or targets more
+/// than [`MAX_COMPONENT_BYTES`].
+pub fn apply_patch(base: &[u8], patch: &[u8]) -> Option<Vec<u8>> {
+    let mut input = patch;
+    let length = usize::try_from(read_varint(&mut input)?)
+        .ok()
+        .filter(|length| *length <= MAX_COMPONENT_BYTES)?;
+    let mut output = base[..base.len().min(length)].to_vec();
+    output.resize(length, 0);
+
+    let mut cursor = 0usize;
+    while !input.is_empty() {
+        let skip = usize::try_from(read_varint(&mut input)?).ok()?;
+        let len = usize::try_from(read_varint(&mut input)?).ok()?;
+        let start = cursor.checked_add(skip)?;
+        let end = start.checked_add(len)?;
+        if end > length || len > input.len() {
+            return None;
+        }
+        output[start..end].copy_from_slice(&input[..len]);
+        input = &input[len..];
+        cursor = end;
+    }
+    Some(output)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(input: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = input.split_first()?;
+        *input = rest;
+        value |= u64::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patches_reproduce_target_and_stay_small() {
+        let base: Vec<u8> = (0..64).collect();
+        let mut target = base.clone();
+        target[10] = 0xFF;
+        target[12] = 0xEE;
+        target[40..44].copy_from_slice(&[1, 2, 3, 4]);
+
+        let patch = encode_patch(&base, &target);
+        assert_eq!(
+            apply_patch(&base, &patch).as_deref(),
+            Some(target.as_slice())
+        );
+        assert!(patch.len() < 16, "patch is {} bytes", patch.len());
+
+        let grown = [target.as_slice(), &[9, 9]].concat();
+        let shrunk = &target[..20];
+        for next in [grown.as_slice(), shrunk, &[]] {
+            let patch = encode_patch(&base, next);
+            assert_eq!(apply_patch(&base, &patch).as_deref(), Some(next));
+        }
+        assert_eq!(apply_patch(&base, &[2, 0, 5, 1]), None);
+
+        let mut oversized = Vec::new();
+        write_varint(&mut oversized, MAX_COMPONENT_BYTES as u64 + 1);
+        assert_eq!(apply_patch(&base, &oversized), None);
+    }
+}
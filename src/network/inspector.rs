@@ -0,0 +1,342 @@
+use super::EntityHandle;
+use super::command_log::{
+    AuthorId, CommandEntry, CommandId, CommandRole, CommandScope, ConflictStrategy,
+    SignatureVerifier,
+};
+use serde::Serialize;
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CaptureError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("line {line}: {source}")]
+    Parse {
+        line: usize,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("invalid command id `{0}`, expected <lamport>:<author>")]
+    InvalidCommandId(String),
+    #[error("command {0:?} is not in the capture")]
+    UnknownCommand(CommandId),
+}
+
+/// Command stream stored as JSON lines, one [`CommandEntry`] per line, in the order the
+/// capturing peer applied them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandCapture {
+    entries: Vec<CommandEntry>,
+}
+
+impl CommandCapture {
+    pub fn new(entries: Vec<CommandEntry>) -> Self {
+        Self { entries }
+    }
+
+    pub fn push(&mut self, entry: CommandEntry) {
+        self.entries.push(entry);
+    }
+
+    pub fn read(path: &Path) -> Result<Self, CaptureError> {
+        Self::from_reader(BufReader::new(std::fs::File::open(path)?))
+    }
+
+    pub fn from_reader(reader: impl BufRead) -> Result<Self, CaptureError> {
+        let mut entries = Vec::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str(&line).map_err(|source| CaptureError::Parse {
+                line: index + 1,
+                source,
+            })?;
+            entries.push(entry);
+        }
+        Ok(Self { entries })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), CaptureError> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.write_to(&mut file)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<(), CaptureError> {
+        for entry in &self.entries {
+            serde_json::to_writer(&mut *writer, entry).map_err(std::io::Error::from)?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    pub fn entries(&self) -> &[CommandEntry] {
+        &self.entries
+    }
+
+    /// Entries up to and including `id`, in capture order.
+    pub fn entries_until(&self, id: &CommandId) -> Result<&[CommandEntry], CaptureError> {
+        let position = self
+            .entries
+            .iter()
+            .position(|entry| &entry.id == id)
+            .ok_or_else(|| CaptureError::UnknownCommand(id.clone()))?;
+        Ok(&self.entries[..=position])
+    }
+}
+
+/// Parses the `<lamport>:<author>` form printed by the inspector.
+pub fn parse_command_id(text: &str) -> Result<CommandId, CaptureError> {
+    let invalid = || CaptureError::InvalidCommandId(text.to_string());
+    let (lamport, author) = text.split_once(':').ok_or_else(invalid)?;
+    let lamport = lamport.trim().parse().map_err(|_| invalid())?;
+    let author = author.trim().parse().map_err(|_| invalid())?;
+    Ok(CommandId::new(lamport, AuthorId(author)))
+}
+
+/// Selects entries by author, scope and command type. Empty criteria match everything;
+/// a type ending in `*` matches by prefix.
+#[derive(Debug, Clone, Default)]
+pub struct CommandFilter {
+    authors: Vec<AuthorId>,
+    scopes: Vec<CommandScope>,
+    command_types: Vec<String>,
+}
+
+impl CommandFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn author(mut self, author: AuthorId) -> Self {
+        self.authors.push(author);
+        self
+    }
+
+    pub fn scope(mut self, scope: CommandScope) -> Self {
+        self.scopes.push(scope);
+        self
+    }
+
+    pub fn command_type(mut self, command_type: impl Into<String>) -> Self {
+        self.command_types.push(command_type.into());
+        self
+    }
+
+    pub fn matches(&self, entry: &CommandEntry) -> bool {
+        let author = self.authors.is_empty() || self.authors.contains(&entry.author.id);
+        let scope = self.scopes.is_empty() || self.scopes.contains(&entry.payload.scope);
+        let command_type = self.command_types.is_empty()
+            || self
+                .command_types
+                .iter()
+                .any(|pattern| match pattern.strip_suffix('*') {
+                    Some(prefix) => entry.payload.command_type.starts_with(prefix),
+                    None => entry.payload.command_type == *pattern,
+                });
+        author && scope && command_type
+    }
+}
+
+/// Parses `global`, `entity:<index>:<generation>` or `tool:<name>`.
+pub fn parse_scope(text: &str) -> Option<CommandScope> {
+    let mut parts = text.splitn(3, ':');
+    match (parts.next()?, parts.next(), parts.next()) {
+        ("global", None, None) => Some(CommandScope::Global),
+        ("entity", Some(index), Some(generation)) => Some(CommandScope::Entity(EntityHandle {
+            index: index.parse().ok()?,
+            generation: generation.parse().ok()?,
+        })),
+        ("tool", Some(name), None) => Some(CommandScope::Tool(name.to_string())),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureStatus {
+    Unsigned,
+    /// Signed, but the inspector has no verifier to check it with.
+    Unverified,
+    Valid,
+    Invalid,
+}
+
+/// One capture entry with its payload decoded for display.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InspectedEntry {
+    pub lamport: u64,
+    pub author: u64,
+    pub role: CommandRole,
+    pub timestamp_ms: u64,
+    pub command_type: String,
+    pub scope: CommandScope,
+    pub strategy: ConflictStrategy,
+    pub signature: SignatureStatus,
+    /// The payload as JSON, or `null` when it is not JSON.
+    pub payload: serde_json::Value,
+    pub payload_bytes: usize,
+}
+
+impl fmt::Display for InspectedEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scope = match &self.scope {
+            CommandScope::Global => "global".to_string(),
+            CommandScope::Entity(handle) => {
+                format!("entity:{}:{}", handle.index, handle.generation)
+            }
+            CommandScope::Tool(name) => format!("tool:{name}"),
+        };
+        write!(
+            f,
+            "{}:{} {:?} {} [{scope}] {:?} sig={:?} ",
+            self.lamport, self.author, self.role, self.command_type, self.strategy, self.signature
+        )?;
+        if self.payload.is_null() {
+            write!(f, "<{} bytes>", self.payload_bytes)
+        } else {
+            write!(f, "{}", self.payload)
+        }
+    }
+}
+
+pub struct CommandInspector {
+    capture: CommandCapture,
+    verifier: Option<Arc<dyn SignatureVerifier>>,
+}
+
+impl CommandInspector {
+    pub fn new(capture: CommandCapture) -> Self {
+        Self {
+            capture,
+            verifier: None,
+        }
+    }
+
+    pub fn with_verifier(mut self, verifier: Arc<dyn SignatureVerifier>) -> Self {
+        self.verifier = Some(verifier);
+        self
+    }
+
+    pub fn capture(&self) -> &CommandCapture {
+        &self.capture
+    }
+
+    pub fn inspect(&self, filter: &CommandFilter) -> Vec<InspectedEntry> {
+        self.capture
+            .entries()
+            .iter()
+            .filter(|entry| filter.matches(entry))
+            .map(|entry| self.describe(entry))
+            .collect()
+    }
+
+    pub fn describe(&self, entry: &CommandEntry) -> InspectedEntry {
+        let signature = match (&entry.signature, &self.verifier) {
+            (None, _) => SignatureStatus::Unsigned,
+            (Some(_), None) => SignatureStatus::Unverified,
+            (Some(signature), Some(verifier)) => {
+                if verifier.verify(
+                    &entry.author,
+                    entry.id.lamport(),
+                    &entry.payload,
+                    entry.causal_context.as_ref(),
+                    signature,
+                ) {
+                    SignatureStatus::Valid
+                } else {
+                    SignatureStatus::Invalid
+                }
+            }
+        };
+        InspectedEntry {
+            lamport: entry.id.lamport(),
+            author: entry.id.author().0,
+            role: entry.author.role,
+            timestamp_ms: entry.timestamp_ms,
+            command_type: entry.payload.command_type.clone(),
+            scope: entry.payload.scope.clone(),
+            strategy: entry.strategy,
+            signature,
+            payload: serde_json::from_slice(&entry.payload.data).unwrap_or(serde_json::Value::Null),
+            payload_bytes: entry.payload.data.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::command_log::{
+        CommandAuthor, CommandPayload, CommandSignature, NoopSignatureVerifier,
+    };
+
+    fn entry(lamport: u64, author: u64, command_type: &str, scope: CommandScope) -> CommandEntry {
+        CommandEntry::new(
+            CommandId::new(lamport, AuthorId(author)),
+            lamport * 10,
+            CommandPayload::new(command_type, scope, br#"{"value":1}"#.to_vec()),
+            ConflictStrategy::LastWriteWins,
+            CommandAuthor::new(AuthorId(author), CommandRole::Editor),
+            None,
+        )
+    }
+
+    #[test]
+    fn capture_round_trips_and_filters() {
+        let entity = EntityHandle {
+            index: 3,
+            generation: 1,
+        };
+        let mut signed = entry(3, 2, "editor.mesh.import", CommandScope::Entity(entity));
+        signed.signature = Some(CommandSignature(vec![1; 64]));
+        let capture = CommandCapture::new(vec![
+            entry(
+                1,
+                1,
+                "editor.entity.translate",
+                CommandScope::Entity(entity),
+            ),
+            entry(2, 1, "editor.selection.clear", CommandScope::Global),
+            signed,
+        ]);
+
+        let mut bytes = Vec::new();
+        capture.write_to(&mut bytes).expect("write capture");
+        let restored = CommandCapture::from_reader(bytes.as_slice()).expect("read capture");
+        assert_eq!(restored, capture);
+
+        let inspector = CommandInspector::new(restored);
+        let by_author = inspector.inspect(&CommandFilter::new().author(AuthorId(1)));
+        assert_eq!(by_author.len(), 2);
+        let scope = parse_scope("entity:3:1").expect("scope");
+        let by_scope_and_type = inspector.inspect(
+            &CommandFilter::new()
+                .scope(scope)
+                .command_type("editor.mesh.*"),
+        );
+        assert_eq!(by_scope_and_type.len(), 1);
+        assert_eq!(by_scope_and_type[0].signature, SignatureStatus::Unverified);
+        assert_eq!(by_scope_and_type[0].payload["value"], 1);
+
+        let verified =
+            CommandInspector::new(capture.clone()).with_verifier(Arc::new(NoopSignatureVerifier));
+        let all = verified.inspect(&CommandFilter::new());
+        assert_eq!(all[0].signature, SignatureStatus::Unsigned);
+        assert_eq!(all[2].signature, SignatureStatus::Valid);
+
+        let until = parse_command_id("2:1").expect("command id");
+        assert_eq!(capture.entries_until(&until).expect("prefix").len(), 2);
+        assert!(matches!(
+            capture.entries_until(&CommandId::new(9, AuthorId(9))),
+            Err(CaptureError::UnknownCommand(_))
+        ));
+    }
+}
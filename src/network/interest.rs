@@ -0,0 +1,210 @@
+//! Per-peer relevance for world replication. An entity is relevant to a peer when the
+//! peer subscribed to it or owns it, or when it passes the peer's group and distance
+//! checks.
+
+use crate::ecs::{Component, Entity, World};
+use crate::network::EntityHandle;
+use crate::network::command_log::AuthorId;
+use crate::network::network_id::network_handle;
+use crate::network::replication::ReplicationOwner;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Restricts an entity to peers that joined the named team or room.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterestGroup(pub String);
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PeerInterest {
+    pub head_position: Option<[f32; 3]>,
+    /// Entities farther than this from the head are not relevant; `None` disables the
+    /// distance check.
+    pub radius: Option<f32>,
+    pub subscriptions: HashSet<EntityHandle>,
+    pub groups: HashSet<String>,
+}
+
+impl PeerInterest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = Some(radius);
+        self
+    }
+
+    pub fn set_head_position(&mut self, position: [f32; 3]) {
+        self.head_position = Some(position);
+    }
+
+    pub fn subscribe(&mut self, entity: EntityHandle) {
+        self.subscriptions.insert(entity);
+    }
+
+    pub fn unsubscribe(&mut self, entity: &EntityHandle) {
+        self.subscriptions.remove(entity);
+    }
+
+    pub fn join(&mut self, group: impl Into<String>) {
+        self.groups.insert(group.into());
+    }
+
+    pub fn leave(&mut self, group: &str) {
+        self.groups.remove(group);
+    }
+}
+
+type PositionSource = Box<dyn Fn(&World, Entity) -> Option<[f32; 3]> + Send + Sync>;
+
+/// Interest of every peer plus the components that locate entities in space.
+#[derive(Default)]
+pub struct InterestManager {
+    peers: HashMap<AuthorId, PeerInterest>,
+    positions: Vec<PositionSource>,
+}
+
+impl InterestManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Locates entities carrying `T` for distance checks. Sources are tried in order;
+    /// entities no source can locate pass the distance check.
+    pub fn with_position_source<T: Component>(mut self, locate: fn(&T) -> [f32; 3]) -> Self {
+        self.positions.push(Box::new(move |world, entity| {
+            world.get::<T>(entity).map(locate)
+        }));
+        self
+    }
+
+    pub fn peer(&self, peer: &AuthorId) -> Option<&PeerInterest> {
+        self.peers.get(peer)
+    }
+
+    pub fn peer_mut(&mut self, peer: AuthorId) -> &mut PeerInterest {
+        self.peers.entry(peer).or_default()
+    }
+
+    pub fn remove_peer(&mut self, peer: &AuthorId) -> Option<PeerInterest> {
+        self.peers.remove(peer)
+    }
+
+    /// Peers without a registered interest see every entity.
+    pub fn is_relevant(&self, world: &World, peer: &AuthorId, entity: Entity) -> bool {
+        let Some(interest) = self.peers.get(peer) else {
+            return true;
+        };
+        if interest
+            .subscriptions
+            .contains(&network_handle(world, entity))
+            || world
+                .get::<ReplicationOwner>(entity)
+                .is_some_and(|owner| &owner.0 == peer)
+        {
+            return true;
+        }
+        if world
+            .get::<InterestGroup>(entity)
+            .is_some_and(|group| !interest.groups.contains(&group.0))
+        {
+            return false;
+        }
+        let position = self
+            .positions
+            .iter()
+            .find_map(|locate| locate(world, entity));
+        match (interest.head_position, interest.radius, position) {
+            (Some(head), Some(radius), Some(position)) => {
+                let distance_sq: f32 = head
+                    .iter()
+                    .zip(position.iter())
+                    .map(|(a, b)| (a - b) * (a - b))
+                    .sum();
+                distance_sq <= radius * radius
+            }
+            _ => true,
+        }
+    }
+}
+
+crate::register_component_types!(InterestGroup);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::replication::ReplicationRegistry;
+    use crate::network::replication_stream::{
+        ReplicationBody, ReplicationHost, ReplicationMessage, ReplicationStreamConfig,
+    };
+    use std::time::Instant;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Position([f32; 3]);
+
+    fn snapshot_entities(messages: &[ReplicationMessage]) -> HashSet<EntityHandle> {
+        messages
+            .iter()
+            .flat_map(|message| match &message.body {
+                ReplicationBody::Snapshot(chunk) => chunk
+                    .components
+                    .iter()
+                    .map(|component| component.entity)
+                    .collect(),
+                _ => Vec::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn peers_receive_only_relevant_entities() {
+        let mut registry = ReplicationRegistry::new();
+        registry.register::<Position>();
+        let mut world = World::new();
+        let near = world.spawn();
+        world.insert(near, Position([1.0, 0.0, 0.0])).unwrap();
+        let far = world.spawn();
+        world.insert(far, Position([50.0, 0.0, 0.0])).unwrap();
+        let red_base = world.spawn();
+        world.insert(red_base, Position([0.0, 0.0, 1.0])).unwrap();
+        world.insert(red_base, InterestGroup("red".into())).unwrap();
+
+        let interest = InterestManager::new().with_position_source::<Position>(|p| p.0);
+        let mut host = ReplicationHost::new(ReplicationStreamConfig::default(), interest);
+        let (scout, sniper) = (AuthorId(1), AuthorId(2));
+        for peer in [scout.clone(), sniper.clone()] {
+            host.add_peer(peer.clone());
+            host.interest_mut()
+                .peer_mut(peer)
+                .set_head_position([0.0; 3]);
+        }
+        host.interest_mut().peer_mut(scout.clone()).radius = Some(10.0);
+        host.interest_mut().peer_mut(scout.clone()).join("red");
+        host.interest_mut()
+            .peer_mut(sniper.clone())
+            .subscribe(far.into());
+        host.interest_mut().peer_mut(sniper.clone()).radius = Some(0.5);
+
+        let now = Instant::now();
+        let first: HashMap<AuthorId, _> = host.tick(&registry, &world, now).into_iter().collect();
+        assert_eq!(
+            snapshot_entities(&first[&scout]),
+            HashSet::from([near.into(), red_base.into()])
+        );
+        assert_eq!(
+            snapshot_entities(&first[&sniper]),
+            HashSet::from([far.into()])
+        );
+
+        // The far entity walks into the scout's range and the scout leaves the red team.
+        world.get_mut::<Position>(far).unwrap().0 = [2.0, 0.0, 0.0];
+        host.interest_mut().peer_mut(scout.clone()).leave("red");
+        let second: HashMap<AuthorId, _> = host.tick(&registry, &world, now).into_iter().collect();
+        let ReplicationBody::Delta(delta) = &second[&scout][0].body else {
+            panic!("expected delta");
+        };
+        assert_eq!(delta.spawned, vec![far.into()]);
+        assert_eq!(delta.despawned, vec![red_base.into()]);
+        assert_eq!(delta.diffs.len(), 2);
+    }
+}
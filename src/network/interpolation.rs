@@ -0,0 +1,517 @@
+//! Smooths remote transforms by rendering them a fixed delay in the past. Each entity
+//! buffers timestamped samples; positions follow a cubic hermite curve through them,
+//! rotations are slerped, and short gaps after the newest sample are extrapolated.
+//! Entities whose newest samples agree are at rest and simply hold their last state.
+
+use crate::network::replication::decode_component;
+use crate::network::{ChangeSet, ComponentKey, DiffPayload, EntityHandle};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TransformSample {
+    pub position: [f32; 3],
+    /// Unit quaternion as `[x, y, z, w]`.
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterpolationConfig {
+    /// How far behind the sender's clock remote entities are rendered.
+    pub delay_ms: u64,
+    /// Longest gap after the newest sample that is extrapolated; beyond it the
+    /// entity shows the newest sample.
+    pub max_extrapolation_ms: u64,
+    /// Samples kept per entity.
+    pub capacity: usize,
+}
+
+impl Default for InterpolationConfig {
+    fn default() -> Self {
+        Self {
+            delay_ms: 100,
+            max_extrapolation_ms: 200,
+            capacity: 32,
+        }
+    }
+}
+
+impl InterpolationConfig {
+    pub fn with_delay(mut self, delay_ms: u64) -> Self {
+        self.delay_ms = delay_ms;
+        self
+    }
+
+    pub fn with_max_extrapolation(mut self, max_extrapolation_ms: u64) -> Self {
+        self.max_extrapolation_ms = max_extrapolation_ms;
+        self
+    }
+}
+
+/// Buffer state as of the last [`InterpolationBuffer::advance`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InterpolationHealth {
+    pub delay_ms: u64,
+    pub entities: usize,
+    pub buffered_samples: usize,
+    /// Smallest margin between the render time and a moving entity's newest sample;
+    /// negative while extrapolating.
+    pub min_lead_ms: f32,
+    pub interpolating: usize,
+    /// Entities past their newest sample that were not moving, so nothing is missing.
+    pub resting: usize,
+    pub extrapolating: usize,
+    /// Entities whose gap exceeded the extrapolation limit.
+    pub starved: usize,
+    /// Samples dropped for arriving after a newer one.
+    pub late_samples: u64,
+    /// Frames a moving entity ran past its newest sample.
+    pub underruns: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    sequence: u64,
+    timestamp_ms: u64,
+    value: TransformSample,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Interpolating,
+    Resting,
+    Extrapolating,
+    Starved,
+}
+
+#[derive(Debug)]
+pub struct InterpolationBuffer {
+    config: InterpolationConfig,
+    entities: HashMap<EntityHandle, VecDeque<Sample>>,
+    /// Smallest `received - timestamp` seen, mapping local time onto the sender's clock.
+    clock_offset_ms: Option<i64>,
+    health: InterpolationHealth,
+}
+
+impl Default for InterpolationBuffer {
+    fn default() -> Self {
+        Self::new(InterpolationConfig::default())
+    }
+}
+
+impl InterpolationBuffer {
+    pub fn new(config: InterpolationConfig) -> Self {
+        Self {
+            config,
+            entities: HashMap::new(),
+            clock_offset_ms: None,
+            health: InterpolationHealth {
+                delay_ms: config.delay_ms,
+                ..InterpolationHealth::default()
+            },
+        }
+    }
+
+    pub fn config(&self) -> &InterpolationConfig {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: InterpolationConfig) {
+        self.config = config;
+        self.health.delay_ms = config.delay_ms;
+    }
+
+    pub fn health(&self) -> &InterpolationHealth {
+        &self.health
+    }
+
+    /// Records `entity`'s state at the sender's `timestamp_ms`, received locally at
+    /// `received_ms`. Returns `false` for samples older than the entity's newest one; a
+    /// newer sequence with the same timestamp replaces it.
+    pub fn push(
+        &mut self,
+        entity: EntityHandle,
+        sequence: u64,
+        timestamp_ms: u64,
+        value: TransformSample,
+        received_ms: u64,
+    ) -> bool {
+        let samples = self.entities.entry(entity).or_default();
+        if let Some(last) = samples.back_mut() {
+            if (timestamp_ms, sequence) <= (last.timestamp_ms, last.sequence) {
+                self.health.late_samples += 1;
+                return false;
+            }
+            if timestamp_ms == last.timestamp_ms {
+                *last = Sample {
+                    sequence,
+                    timestamp_ms,
+                    value,
+                };
+                return true;
+            }
+        }
+        samples.push_back(Sample {
+            sequence,
+            timestamp_ms,
+            value,
+        });
+        while samples.len() > self.config.capacity.max(2) {
+            samples.pop_front();
+        }
+        let offset = received_ms as i64 - timestamp_ms as i64;
+        self.clock_offset_ms = Some(self.clock_offset_ms.map_or(offset, |min| min.min(offset)));
+        true
+    }
+
+    /// Buffers every insert or update of `T` in `change_set`, returning how many were
+    /// accepted.
+    pub fn ingest_change_set<T: DeserializeOwned + 'static>(
+        &mut self,
+        change_set: &ChangeSet,
+        received_ms: u64,
+        to_sample: fn(&T) -> TransformSample,
+    ) -> usize {
+        let key = ComponentKey::of::<T>();
+        let mut accepted = 0;
+        for diff in change_set.diffs.iter().filter(|diff| diff.component == key) {
+            let (DiffPayload::Insert { bytes } | DiffPayload::Update { bytes }) = &diff.payload
+            else {
+                continue;
+            };
+            let Ok(value) = decode_component::<T>(bytes) else {
+                continue;
+            };
+            accepted += usize::from(self.push(
+                diff.entity,
+                change_set.sequence,
+                change_set.timestamp_ms,
+                to_sample(&value),
+                received_ms,
+            ));
+        }
+        accepted
+    }
+
+    /// Newest received state of `entity`.
+    pub fn latest(&self, entity: EntityHandle) -> Option<&TransformSample> {
+        self.entities
+            .get(&entity)
+            .and_then(|samples| samples.back())
+            .map(|sample| &sample.value)
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = EntityHandle> + '_ {
+        self.entities.keys().copied()
+    }
+
+    pub fn remove(&mut self, entity: EntityHandle) {
+        self.entities.remove(&entity);
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(EntityHandle) -> bool) {
+        self.entities.retain(|entity, _| keep(*entity));
+    }
+
+    pub fn clear(&mut self) {
+        self.entities.clear();
+        self.clock_offset_ms = None;
+    }
+
+    /// Sender time rendered at local time `now_ms`, or `None` before any sample arrived.
+    pub fn render_time_ms(&self, now_ms: u64) -> Option<f64> {
+        let offset = self.clock_offset_ms?;
+        Some(now_ms as f64 - offset as f64 - self.config.delay_ms as f64)
+    }
+
+    /// Samples every buffered entity at `now_ms`, drops samples no longer needed and
+    /// refreshes [`Self::health`].
+    pub fn advance(&mut self, now_ms: u64) -> Vec<(EntityHandle, TransformSample)> {
+        let mut health = InterpolationHealth {
+            delay_ms: self.config.delay_ms,
+            late_samples: self.health.late_samples,
+            underruns: self.health.underruns,
+            ..InterpolationHealth::default()
+        };
+        let Some(render_time) = self.render_time_ms(now_ms) else {
+            self.health = health;
+            return Vec::new();
+        };
+        let max_gap = self.config.max_extrapolation_ms as f64;
+        let mut min_lead = f64::INFINITY;
+        let mut sampled = Vec::with_capacity(self.entities.len());
+        for (entity, samples) in &mut self.entities {
+            // The sample before the current interval is kept for its tangent.
+            while samples.len() >= 3 && samples[2].timestamp_ms as f64 <= render_time {
+                samples.pop_front();
+            }
+            let Some((value, phase)) = sample_at(samples, render_time, max_gap) else {
+                continue;
+            };
+            match phase {
+                Phase::Interpolating => health.interpolating += 1,
+                Phase::Resting => health.resting += 1,
+                Phase::Extrapolating => health.extrapolating += 1,
+                Phase::Starved => health.starved += 1,
+            }
+            if matches!(phase, Phase::Extrapolating | Phase::Starved) {
+                health.underruns += 1;
+            }
+            if phase != Phase::Resting
+                && let Some(last) = samples.back()
+            {
+                min_lead = min_lead.min(last.timestamp_ms as f64 - render_time);
+            }
+            health.buffered_samples += samples.len();
+            sampled.push((*entity, value));
+        }
+        health.entities = sampled.len();
+        health.min_lead_ms = if min_lead.is_finite() {
+            min_lead as f32
+        } else {
+            0.0
+        };
+        self.health = health;
+        sampled
+    }
+}
+
+fn sample_at(
+    samples: &VecDeque<Sample>,
+    render_time: f64,
+    max_gap: f64,
+) -> Option<(TransformSample, Phase)> {
+    let first = samples.front()?;
+    let last = samples.back()?;
+    if render_time <= first.timestamp_ms as f64 {
+        return Some((first.value, Phase::Interpolating));
+    }
+    if render_time >= last.timestamp_ms as f64 {
+        let len = samples.len();
+        if len < 2 || samples[len - 2].value == last.value {
+            return Some((last.value, Phase::Resting));
+        }
+        let gap = render_time - last.timestamp_ms as f64;
+        if gap > max_gap {
+            return Some((last.value, Phase::Starved));
+        }
+        let value = extrapolate(&samples[len - 2], last, gap);
+        return Some((value, Phase::Extrapolating));
+    }
+
+    let index = samples
+        .iter()
+        .rposition(|sample| sample.timestamp_ms as f64 <= render_time)?;
+    let (a, b) = (&samples[index], &samples[index + 1]);
+    let span = (b.timestamp_ms - a.timestamp_ms) as f64;
+    let t = ((render_time - a.timestamp_ms as f64) / span) as f32;
+    let velocity_a = velocity(samples, index);
+    let velocity_b = velocity(samples, index + 1);
+    let span = span as f32;
+    let position = std::array::from_fn(|axis| {
+        hermite(
+            a.value.position[axis],
+            velocity_a[axis] * span,
+            b.value.position[axis],
+            velocity_b[axis] * span,
+            t,
+        )
+    });
+    let value = TransformSample {
+        position,
+        rotation: slerp(a.value.rotation, b.value.rotation, t),
+        scale: lerp3(a.value.scale, b.value.scale, t),
+    };
+    Some((value, Phase::Interpolating))
+}
+
+/// Continues the motion between the two newest samples for `gap` milliseconds.
+fn extrapolate(previous: &Sample, last: &Sample, gap: f64) -> TransformSample {
+    let span = last.timestamp_ms.saturating_sub(previous.timestamp_ms) as f64;
+    if span <= 0.0 {
+        return last.value;
+    }
+    let t = (1.0 + gap / span) as f32;
+    TransformSample {
+        position: lerp3(previous.value.position, last.value.position, t),
+        rotation: slerp(previous.value.rotation, last.value.rotation, t),
+        scale: last.value.scale,
+    }
+}
+
+/// Position change per millisecond at `index`, from its neighbours where available.
+fn velocity(samples: &VecDeque<Sample>, index: usize) -> [f32; 3] {
+    let before = &samples[index.saturating_sub(1)];
+    let after = &samples[(index + 1).min(samples.len() - 1)];
+    let span = after.timestamp_ms.saturating_sub(before.timestamp_ms) as f32;
+    if span <= 0.0 {
+        return [0.0; 3];
+    }
+    std::array::from_fn(|axis| (after.value.position[axis] - before.value.position[axis]) / span)
+}
+
+fn hermite(p0: f32, m0: f32, p1: f32, m1: f32, t: f32) -> f32 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (2.0 * t3 - 3.0 * t2 + 1.0) * p0
+        + (t3 - 2.0 * t2 + t) * m0
+        + (-2.0 * t3 + 3.0 * t2) * p1
+        + (t3 - t2) * m1
+}
+
+fn lerp3(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    [
+        a[0] + (b[0] - a[0]) * t,
+        a[1] + (b[1] - a[1]) * t,
+        a[2] + (b[2] - a[2]) * t,
+    ]
+}
+
+/// Spherical interpolation along the shorter arc; `t` past 1 keeps rotating.
+fn slerp(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    let mut dot: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
+    let mut b = b;
+    if dot < 0.0 {
+        dot = -dot;
+        b = b.map(|component| -component);
+    }
+    let (weight_a, weight_b) = if dot > 0.9995 {
+        (1.0 - t, t)
+    } else {
+        let theta = dot.min(1.0).acos();
+        let sin = theta.sin();
+        (((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin)
+    };
+    let out: [f32; 4] = std::array::from_fn(|index| a[index] * weight_a + b[index] * weight_b);
+    let length = out.iter().map(|c| c * c).sum::<f32>().sqrt();
+    if length > f32::EPSILON {
+        out.map(|component| component / length)
+    } else {
+        a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::World;
+    use crate::network::ComponentDiff;
+    use crate::network::replication::encode_component;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct Pose {
+        position: [f32; 3],
+        yaw: f32,
+    }
+
+    fn to_sample(pose: &Pose) -> TransformSample {
+        let half = pose.yaw / 2.0;
+        TransformSample {
+            position: pose.position,
+            rotation: [0.0, half.sin(), 0.0, half.cos()],
+            scale: [1.0; 3],
+        }
+    }
+
+    fn change_set(entity: EntityHandle, sequence: u64, timestamp_ms: u64) -> ChangeSet {
+        // One unit per second along x while turning a quarter radian per second.
+        let seconds = timestamp_ms as f32 / 1000.0;
+        let pose = Pose {
+            position: [seconds, 0.0, 0.0],
+            yaw: seconds * 0.25,
+        };
+        let diff = ComponentDiff {
+            entity,
+            component: ComponentKey::of::<Pose>(),
+            payload: DiffPayload::Update {
+                bytes: encode_component(&pose).unwrap(),
+            },
+        };
+        ChangeSet::new(sequence, timestamp_ms, vec![diff], Vec::new())
+    }
+
+    #[test]
+    fn remote_transforms_render_behind_the_stream_and_extrapolate_short_gaps() {
+        let mut world = World::new();
+        let entity = EntityHandle::from(world.spawn());
+        let config = InterpolationConfig::default()
+            .with_delay(100)
+            .with_max_extrapolation(150);
+        let mut buffer = InterpolationBuffer::new(config);
+        const LATENCY: u64 = 40;
+
+        // Updates every 50 ms until 1 s; frames every 10 ms until 1.4 s.
+        let mut sequence = 0;
+        let mut rendered = Vec::new();
+        for now in (0..1400u64).step_by(10) {
+            if now % 50 == 0 && now <= 1000 {
+                sequence += 1;
+                let set = change_set(entity, sequence, now);
+                assert_eq!(buffer.ingest_change_set(&set, now + LATENCY, to_sample), 1);
+            }
+            let sampled = buffer.advance(now + LATENCY);
+            assert_eq!(sampled.len(), 1);
+            rendered.push((now, sampled[0].1, buffer.health().clone()));
+        }
+
+        for (now, sample, health) in &rendered {
+            let render_time = *now as f32 - 100.0;
+            if (100.0..1000.0).contains(&render_time) {
+                // Hermite through evenly spaced linear motion reproduces it exactly.
+                let expected = render_time / 1000.0;
+                assert!((sample.position[0] - expected).abs() < 1e-4, "{now}");
+                let yaw = 2.0 * sample.rotation[1].atan2(sample.rotation[3]);
+                assert!((yaw - expected * 0.25).abs() < 1e-4, "{now}");
+                assert_eq!(health.interpolating, 1);
+                assert!(health.buffered_samples <= 5);
+            } else if (1000.0..=1150.0).contains(&render_time) {
+                assert!((sample.position[0] - render_time / 1000.0).abs() < 1e-4);
+                assert_eq!(health.extrapolating + health.interpolating, 1);
+            } else if render_time > 1150.0 {
+                // Past the bound the entity shows the newest state received.
+                assert!((sample.position[0] - 1.0).abs() < 1e-6);
+                assert_eq!(health.starved, 1);
+                assert!(health.min_lead_ms < -150.0);
+            }
+        }
+        assert_eq!(rendered.last().unwrap().2.underruns, 30);
+
+        // A stale change set is rejected without disturbing the buffer.
+        let stale = change_set(entity, 3, 150);
+        assert_eq!(buffer.ingest_change_set(&stale, 1400, to_sample), 0);
+        assert_eq!(buffer.health().late_samples, 1);
+        assert!((buffer.latest(entity).unwrap().position[0] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn entities_at_rest_hold_their_state_without_underruns() {
+        let mut world = World::new();
+        let still = EntityHandle::from(world.spawn());
+        let stopped = EntityHandle::from(world.spawn());
+        let mut buffer = InterpolationBuffer::new(InterpolationConfig::default());
+        let pose = |x: f32| TransformSample {
+            position: [x, 0.0, 0.0],
+            rotation: [0.0, 0.0, 0.0, 1.0],
+            scale: [1.0; 3],
+        };
+        buffer.push(still, 1, 0, pose(5.0), 0);
+        // A stopped entity's final value is sent again once it settles.
+        buffer.push(stopped, 1, 0, pose(0.0), 0);
+        buffer.push(stopped, 2, 50, pose(1.0), 50);
+        buffer.push(stopped, 3, 100, pose(1.0), 100);
+
+        for now in (0..5000u64).step_by(10) {
+            let mut sampled = buffer.advance(now);
+            sampled.sort_by_key(|(entity, _)| *entity == stopped);
+            assert_eq!(sampled[0].1, pose(5.0));
+            if now >= 200 {
+                assert_eq!(sampled[1].1, pose(1.0));
+                assert_eq!(buffer.health().resting, 2);
+            }
+        }
+        assert_eq!(buffer.health().underruns, 0);
+        assert_eq!(buffer.health().min_lead_ms, 0.0);
+    }
+}
@@ -0,0 +1,334 @@
+use crate::network::EntityHandle;
+use crate::network::command_log::{
+    AuthorId, CommandDefinition, CommandEntry, CommandId, CommandLogError, CommandRegistry,
+    CommandRole, CommandScope, ConflictStrategy,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const CMD_ENTITY_LOCK: &str = "editor.entity.lock";
+pub const CMD_ENTITY_UNLOCK: &str = "editor.entity.unlock";
+
+pub const DEFAULT_LOCK_LEASE_MS: u64 = 5_000;
+/// Longest lease a lock command can ask for; renewals are needed to hold a lock longer.
+pub const MAX_LOCK_LEASE_MS: u64 = 30_000;
+
+/// Acquires (or renews) a soft lock on an entity for `lease_ms` milliseconds.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EntityLockCommand {
+    pub entity: EntityHandle,
+    pub lease_ms: u64,
+}
+
+impl EntityLockCommand {
+    pub fn new(entity: EntityHandle, lease_ms: u64) -> Self {
+        Self { entity, lease_ms }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EntityUnlockCommand {
+    pub entity: EntityHandle,
+}
+
+impl EntityUnlockCommand {
+    pub fn new(entity: EntityHandle) -> Self {
+        Self { entity }
+    }
+}
+
+pub fn is_lock_command(command_type: &str) -> bool {
+    matches!(command_type, CMD_ENTITY_LOCK | CMD_ENTITY_UNLOCK)
+}
+
+pub fn register_lock_commands(registry: &mut CommandRegistry) {
+    for command_type in [CMD_ENTITY_LOCK, CMD_ENTITY_UNLOCK] {
+        registry.register(
+            command_type,
+            CommandDefinition::builder()
+                .required_role(CommandRole::Editor)
+                .default_strategy(ConflictStrategy::Merge)
+                .require_signature(false)
+                .build(),
+        );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntityLease {
+    pub holder: AuthorId,
+    pub lock_id: CommandId,
+    pub expires_at_ms: u64,
+}
+
+impl EntityLease {
+    pub fn is_active(&self, now_ms: u64) -> bool {
+        now_ms < self.expires_at_ms
+    }
+}
+
+/// Lease table fed by lock/unlock commands. Leases run on the local clock from the
+/// moment a peer applies the lock; the timestamp an author puts on its entries is
+/// never trusted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntityLockTable {
+    leases: HashMap<EntityHandle, EntityLease>,
+}
+
+impl EntityLockTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn lease(&self, entity: &EntityHandle) -> Option<&EntityLease> {
+        self.leases.get(entity)
+    }
+
+    pub fn active_lease(&self, entity: &EntityHandle, now_ms: u64) -> Option<&EntityLease> {
+        self.leases
+            .get(entity)
+            .filter(|lease| lease.is_active(now_ms))
+    }
+
+    pub fn leases(&self) -> impl Iterator<Item = (&EntityHandle, &EntityLease)> {
+        self.leases.iter()
+    }
+
+    /// Returns an error when `author` may not write to `entity` at `now_ms`.
+    pub fn check_holder(
+        &self,
+        entity: &EntityHandle,
+        author: &AuthorId,
+        now_ms: u64,
+    ) -> Result<(), CommandLogError> {
+        match self.active_lease(entity, now_ms) {
+            Some(lease) if &lease.holder != author => Err(CommandLogError::EntityLocked {
+                entity: *entity,
+                holder: lease.holder.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Applies a lock or unlock entry at local time `now_ms`. Returns `Ok(false)` for
+    /// unrelated commands.
+    ///
+    /// Two concurrent lock requests for the same entity resolve to the lower
+    /// command id, so peers converge regardless of arrival order.
+    pub fn apply(&mut self, entry: &CommandEntry, now_ms: u64) -> Result<bool, CommandLogError> {
+        let decode_err =
+            |err: serde_json::Error| CommandLogError::PacketDecodeFailed(err.to_string());
+        let author = &entry.author.id;
+        match entry.payload.command_type.as_str() {
+            CMD_ENTITY_LOCK => {
+                let command: EntityLockCommand =
+                    serde_json::from_slice(&entry.payload.data).map_err(decode_err)?;
+                check_scope(entry, command.entity)?;
+                if let Some(lease) = self.active_lease(&command.entity, now_ms)
+                    && &lease.holder != author
+                {
+                    let observed = entry
+                        .causal_context
+                        .as_ref()
+                        .is_some_and(|context| context.covers(&lease.lock_id));
+                    if observed || lease.lock_id < entry.id {
+                        return Err(CommandLogError::EntityLocked {
+                            entity: command.entity,
+                            holder: lease.holder.clone(),
+                        });
+                    }
+                }
+                self.leases.insert(
+                    command.entity,
+                    EntityLease {
+                        holder: author.clone(),
+                        lock_id: entry.id.clone(),
+                        expires_at_ms: now_ms
+                            .saturating_add(command.lease_ms.min(MAX_LOCK_LEASE_MS)),
+                    },
+                );
+                Ok(true)
+            }
+            CMD_ENTITY_UNLOCK => {
+                let command: EntityUnlockCommand =
+                    serde_json::from_slice(&entry.payload.data).map_err(decode_err)?;
+                check_scope(entry, command.entity)?;
+                self.check_holder(&command.entity, author, now_ms)?;
+                self.leases.remove(&command.entity);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Drops every lease held by `author`, e.g. when their peer disconnects.
+    pub fn release_held_by(&mut self, author: &AuthorId) -> Vec<EntityHandle> {
+        let released: Vec<EntityHandle> = self
+            .leases
+            .iter()
+            .filter(|(_, lease)| &lease.holder == author)
+            .map(|(entity, _)| *entity)
+            .collect();
+        for entity in &released {
+            self.leases.remove(entity);
+        }
+        released
+    }
+
+    pub fn expire(&mut self, now_ms: u64) -> Vec<EntityHandle> {
+        let expired: Vec<EntityHandle> = self
+            .leases
+            .iter()
+            .filter(|(_, lease)| !lease.is_active(now_ms))
+            .map(|(entity, _)| *entity)
+            .collect();
+        for entity in &expired {
+            self.leases.remove(entity);
+        }
+        expired
+    }
+}
+
+/// Lock conflicts and access checks go by the entry's scope, so it must name the entity.
+fn check_scope(entry: &CommandEntry, entity: EntityHandle) -> Result<(), CommandLogError> {
+    if entry.payload.scope == CommandScope::Entity(entity) {
+        Ok(())
+    } else {
+        Err(CommandLogError::ScopeMismatch(entry.payload.scope.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::command_log::{CommandAuthor, CommandPayload, VersionVector};
+
+    const ENTITY: EntityHandle = EntityHandle {
+        index: 2,
+        generation: 0,
+    };
+
+    fn lock_entry(lamport: u64, author: u64, timestamp_ms: u64, lease_ms: u64) -> CommandEntry {
+        CommandEntry::new(
+            CommandId::new(lamport, AuthorId(author)),
+            timestamp_ms,
+            CommandPayload::new(
+                CMD_ENTITY_LOCK,
+                CommandScope::Entity(ENTITY),
+                serde_json::to_vec(&EntityLockCommand::new(ENTITY, lease_ms)).unwrap(),
+            ),
+            ConflictStrategy::Merge,
+            CommandAuthor::new(AuthorId(author), CommandRole::Editor),
+            None,
+        )
+    }
+
+    #[test]
+    fn concurrent_lock_requests_converge_on_lowest_id() {
+        let first = lock_entry(4, 1, 100, 1_000);
+        let second = lock_entry(4, 2, 100, 1_000);
+
+        let mut a = EntityLockTable::new();
+        a.apply(&first, 100).expect("first lock");
+        assert!(matches!(
+            a.apply(&second, 100),
+            Err(CommandLogError::EntityLocked { .. })
+        ));
+
+        let mut b = EntityLockTable::new();
+        b.apply(&second, 100).expect("second lock");
+        b.apply(&first, 100).expect("lower id wins");
+
+        assert_eq!(a.lease(&ENTITY), b.lease(&ENTITY));
+        assert_eq!(a.lease(&ENTITY).unwrap().holder, AuthorId(1));
+    }
+
+    #[test]
+    fn causally_later_lock_cannot_steal_active_lease() {
+        let mut table = EntityLockTable::new();
+        let held = lock_entry(5, 2, 100, 1_000);
+        table.apply(&held, 100).expect("lock");
+
+        let mut context = VersionVector::new();
+        context.observe(&AuthorId(2), 5);
+        let steal = lock_entry(1, 1, 200, 1_000).with_causal_context(context);
+        assert!(matches!(
+            table.apply(&steal, 200),
+            Err(CommandLogError::EntityLocked { .. })
+        ));
+
+        let after_expiry = lock_entry(9, 1, 1_200, 1_000);
+        table
+            .apply(&after_expiry, 1_200)
+            .expect("expired lease is free");
+        assert_eq!(table.lease(&ENTITY).unwrap().holder, AuthorId(1));
+    }
+
+    #[test]
+    fn leases_release_by_holder_and_expire() {
+        let mut table = EntityLockTable::new();
+        table.apply(&lock_entry(1, 3, 0, 500), 0).expect("lock");
+        assert!(table.check_holder(&ENTITY, &AuthorId(4), 100).is_err());
+        assert!(table.check_holder(&ENTITY, &AuthorId(3), 100).is_ok());
+        assert!(table.check_holder(&ENTITY, &AuthorId(4), 600).is_ok());
+
+        assert_eq!(table.release_held_by(&AuthorId(3)), vec![ENTITY]);
+        assert!(table.lease(&ENTITY).is_none());
+
+        table.apply(&lock_entry(2, 3, 0, 500), 0).expect("relock");
+        assert!(table.expire(499).is_empty());
+        assert_eq!(table.expire(500), vec![ENTITY]);
+    }
+
+    #[test]
+    fn leases_run_on_the_local_clock_and_are_capped() {
+        let mut table = EntityLockTable::new();
+        // A far-future author timestamp and an endless lease buy no extra time.
+        let greedy = lock_entry(1, 3, u64::MAX / 2, u64::MAX);
+        table.apply(&greedy, 1_000).expect("lock");
+        assert_eq!(
+            table.lease(&ENTITY).unwrap().expires_at_ms,
+            1_000 + MAX_LOCK_LEASE_MS
+        );
+        assert!(
+            table
+                .check_holder(&ENTITY, &AuthorId(4), 1_000 + MAX_LOCK_LEASE_MS)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn lock_entries_must_be_scoped_to_their_entity() {
+        let mut table = EntityLockTable::new();
+        let mut global = lock_entry(1, 3, 0, 1_000);
+        global.payload.scope = CommandScope::Global;
+        assert!(matches!(
+            table.apply(&global, 0),
+            Err(CommandLogError::ScopeMismatch(CommandScope::Global))
+        ));
+        assert!(table.lease(&ENTITY).is_none());
+
+        table.apply(&lock_entry(2, 3, 0, 1_000), 0).expect("lock");
+        let other = CommandScope::Entity(EntityHandle {
+            index: 7,
+            generation: 0,
+        });
+        let unlock = CommandEntry::new(
+            CommandId::new(3, AuthorId(3)),
+            0,
+            CommandPayload::new(
+                CMD_ENTITY_UNLOCK,
+                other.clone(),
+                serde_json::to_vec(&EntityUnlockCommand::new(ENTITY)).unwrap(),
+            ),
+            ConflictStrategy::Merge,
+            CommandAuthor::new(AuthorId(3), CommandRole::Editor),
+            None,
+        );
+        assert!(matches!(
+            table.apply(&unlock, 0),
+            Err(CommandLogError::ScopeMismatch(scope)) if scope == other
+        ));
+        assert!(table.lease(&ENTITY).is_some());
+    }
+}
@@ -1,5 +1,19 @@
+pub mod access;
+pub mod authority;
 pub mod command_log;
+pub mod compression;
+pub mod delta_encoding;
+pub mod inspector;
+pub mod interest;
+pub mod interpolation;
+pub mod locks;
+pub mod network_id;
+#[cfg(has_generated_network_schema)]
+pub mod packet_codec;
+pub mod prediction;
+pub mod preview;
 pub mod replication;
+pub mod replication_stream;
 pub mod schema;
 pub mod voice;
 
@@ -48,7 +62,9 @@ pub mod wire {
 }
 
 use crate::ecs::Entity;
-use crate::network::command_log::{AuthorId, CommandBatch, CommandEntry};
+use crate::network::command_log::{
+    AuthorId, CommandBatch, CommandEntry, CommandPacket, PacketEncoding,
+};
 use serde::{Deserialize, Serialize};
 use std::any::TypeId;
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -123,8 +139,18 @@ pub struct ComponentDescriptor {
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum DiffPayload {
-    Insert { bytes: Vec<u8> },
-    Update { bytes: Vec<u8> },
+    Insert {
+        bytes: Vec<u8>,
+    },
+    Update {
+        bytes: Vec<u8>,
+    },
+    /// An update as a [`delta_encoding`] patch against the value sent in the
+    /// replication message numbered `baseline`.
+    Delta {
+        baseline: u64,
+        patch: Vec<u8>,
+    },
     Remove,
 }
 
@@ -268,6 +294,26 @@ impl NetworkSession {
             entries,
         }
     }
+
+    /// Crafts packets for `entries`, splitting into several consecutively numbered
+    /// batches when they exceed the packet budget. Packets must be delivered in order
+    /// since receivers reject nonces below the author's high-water mark.
+    pub fn craft_command_packets(
+        &mut self,
+        entries: Vec<CommandEntry>,
+        encoding: PacketEncoding,
+    ) -> Result<Vec<CommandPacket>, serde_json::Error> {
+        let batch = self.craft_command_batch(entries);
+        let mut packets = Vec::new();
+        for (index, mut part) in batch.split_for_packets(encoding)?.into_iter().enumerate() {
+            if index > 0 {
+                part.sequence = self.next_sequence();
+                part.nonce = self.next_command_nonce();
+            }
+            packets.extend(CommandPacket::encode_fragmented(&part, encoding)?);
+        }
+        Ok(packets)
+    }
 }
 
 impl Default for NetworkSession {
@@ -401,6 +447,50 @@ mod tests {
         assert!(public_key.iter().all(|byte| *byte == 9));
     }
 
+    #[test]
+    fn oversized_command_batches_split_into_ordered_packets() {
+        use crate::network::command_log::{
+            CommandAuthor, CommandId, CommandPayload, CommandRole, CommandScope, ConflictStrategy,
+            MAX_COMMAND_PACKET_BYTES,
+        };
+
+        let author = CommandAuthor::new(AuthorId(2), CommandRole::Editor);
+        let entries: Vec<CommandEntry> = (0..64u64)
+            .map(|index| {
+                CommandEntry::new(
+                    CommandId::new(index + 1, AuthorId(2)),
+                    0,
+                    CommandPayload::new("editor.create", CommandScope::Global, vec![7; 2048]),
+                    ConflictStrategy::Merge,
+                    author.clone(),
+                    None,
+                )
+            })
+            .collect();
+
+        let mut session = NetworkSession::new();
+        let packets = session
+            .craft_command_packets(entries.clone(), PacketEncoding::Json)
+            .expect("craft packets");
+        assert!(packets.len() > 1);
+        assert!(
+            packets
+                .windows(2)
+                .all(|pair| pair[0].nonce < pair[1].nonce && pair[0].sequence < pair[1].sequence)
+        );
+        assert!(
+            packets
+                .iter()
+                .all(|packet| packet.payload.len() <= MAX_COMMAND_PACKET_BYTES)
+        );
+
+        let decoded: Vec<CommandEntry> = packets
+            .iter()
+            .flat_map(|packet| packet.decode().expect("decode part").entries)
+            .collect();
+        assert_eq!(decoded, entries);
+    }
+
     #[test]
     fn advertised_components_deduplicate_between_change_sets() {
         let mut session = NetworkSession::new();
@@ -0,0 +1,428 @@
+//! Network-wide entity identities. Local `Entity` indices are reused and differ between
+//! peers, so entities that replicate carry a [`NetworkId`] made of the allocating peer's
+//! prefix and a per-peer serial. On the wire the id takes the place of the entity handle.
+
+use crate::ecs::{Entity, World};
+use crate::network::EntityHandle;
+use crate::network::command_log::AuthorId;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Set on every prefix so ids never equal a handle derived from a local entity, whose
+/// generation stays far below it.
+const PREFIX_TAG: u32 = 0x8000_0000;
+
+/// Highest prefix [`PeerPrefixes`] hands out; larger ones would collide with the tag bit.
+pub const MAX_PEER_PREFIX: u32 = PREFIX_TAG - 1;
+
+/// Id map shared by the command pipeline and the replication applier of one engine, so
+/// commands and replication agree on which local entity a network id names.
+pub type SharedEntityMap = Arc<Mutex<NetworkEntityMap>>;
+
+/// Locks `map`; a holder that panicked leaves it usable, as every update is a single
+/// insert or removal.
+pub fn lock_entity_map(map: &SharedEntityMap) -> MutexGuard<'_, NetworkEntityMap> {
+    map.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct NetworkId(pub u64);
+
+impl NetworkId {
+    pub fn new(prefix: u32, serial: u32) -> Self {
+        Self((u64::from(prefix | PREFIX_TAG) << 32) | u64::from(serial))
+    }
+
+    pub fn prefix(&self) -> u32 {
+        (self.0 >> 32) as u32 & !PREFIX_TAG
+    }
+
+    pub fn serial(&self) -> u32 {
+        self.0 as u32
+    }
+
+    /// False for ids derived from a local entity handle rather than allocated by a
+    /// [`NetworkIdAllocator`].
+    pub fn is_allocated(&self) -> bool {
+        (self.0 >> 32) as u32 & PREFIX_TAG != 0
+    }
+}
+
+impl From<NetworkId> for EntityHandle {
+    fn from(id: NetworkId) -> Self {
+        Self {
+            index: id.serial(),
+            generation: (id.0 >> 32) as u32,
+        }
+    }
+}
+
+impl From<EntityHandle> for NetworkId {
+    fn from(handle: EntityHandle) -> Self {
+        Self((u64::from(handle.generation) << 32) | u64::from(handle.index))
+    }
+}
+
+/// Handle `entity` replicates under: its [`NetworkId`] when it has one, otherwise one
+/// derived from the local index, which is only unique on this peer.
+pub fn network_handle(world: &World, entity: Entity) -> EntityHandle {
+    world
+        .get::<NetworkId>(entity)
+        .map_or_else(|| EntityHandle::from(entity), |id| EntityHandle::from(*id))
+}
+
+/// Bidirectional map between network ids and the local entities standing for them.
+/// Entities this peer spawned stay pending until the host is known to have them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkEntityMap {
+    entities: HashMap<NetworkId, Entity>,
+    ids: HashMap<Entity, NetworkId>,
+    pending: BTreeSet<NetworkId>,
+}
+
+impl NetworkEntityMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds `id` to `entity`, dropping earlier bindings of either.
+    pub fn insert(&mut self, id: NetworkId, entity: Entity) {
+        self.remove_id(id);
+        self.remove_entity(entity);
+        self.entities.insert(id, entity);
+        self.ids.insert(entity, id);
+    }
+
+    /// Binds a locally spawned entity that has not replicated yet.
+    pub fn insert_pending(&mut self, id: NetworkId, entity: Entity) {
+        self.insert(id, entity);
+        self.pending.insert(id);
+    }
+
+    pub fn is_pending(&self, id: NetworkId) -> bool {
+        self.pending.contains(&id)
+    }
+
+    pub fn pending(&self) -> impl Iterator<Item = NetworkId> + '_ {
+        self.pending.iter().copied()
+    }
+
+    /// Settles pending ids among `replicated`, e.g. the entities
+    /// [`crate::network::replication_stream::ReplicationSender::acknowledge`] reports.
+    pub fn confirm(&mut self, replicated: impl IntoIterator<Item = EntityHandle>) {
+        for handle in replicated {
+            self.pending.remove(&NetworkId::from(handle));
+        }
+    }
+
+    pub fn entity(&self, id: NetworkId) -> Option<Entity> {
+        self.entities.get(&id).copied()
+    }
+
+    /// Local entity a handle from a command or the wire names: the one bound to it as a
+    /// network id, otherwise the local entity it was derived from.
+    pub fn resolve(&self, handle: EntityHandle) -> Entity {
+        self.entity(handle.into())
+            .unwrap_or_else(|| Entity::from(handle))
+    }
+
+    /// Drops bindings, pending ones included, whose entity is no longer alive.
+    pub fn unbind_despawned(&mut self, world: &World) {
+        let despawned: Vec<Entity> = self
+            .ids
+            .keys()
+            .copied()
+            .filter(|entity| !world.contains(*entity))
+            .collect();
+        for entity in despawned {
+            self.remove_entity(entity);
+        }
+    }
+
+    pub fn id(&self, entity: Entity) -> Option<NetworkId> {
+        self.ids.get(&entity).copied()
+    }
+
+    pub fn remove_id(&mut self, id: NetworkId) -> Option<Entity> {
+        let entity = self.entities.remove(&id)?;
+        self.ids.remove(&entity);
+        self.pending.remove(&id);
+        Some(entity)
+    }
+
+    pub fn remove_entity(&mut self, entity: Entity) -> Option<NetworkId> {
+        let id = self.ids.remove(&entity)?;
+        self.entities.remove(&id);
+        self.pending.remove(&id);
+        Some(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (NetworkId, Entity)> + '_ {
+        self.entities.iter().map(|(id, entity)| (*id, *entity))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
+
+/// Hands out ids for entities this peer spawns. They are usable at once, e.g. in command
+/// scopes, before the entity first replicates.
+#[derive(Debug, Clone)]
+pub struct NetworkIdAllocator {
+    prefix: u32,
+    next_serial: u32,
+}
+
+impl NetworkIdAllocator {
+    /// `prefix` must be unique to this peer for the session, e.g. from [`PeerPrefixes`].
+    pub fn new(prefix: u32) -> Self {
+        Self {
+            prefix: prefix & !PREFIX_TAG,
+            next_serial: 0,
+        }
+    }
+
+    pub fn prefix(&self) -> u32 {
+        self.prefix
+    }
+
+    pub fn spawn(&mut self, world: &mut World, map: &mut NetworkEntityMap) -> (Entity, NetworkId) {
+        let entity = world.spawn();
+        (entity, self.assign(world, map, entity))
+    }
+
+    /// Gives `entity` a pending id, or returns the one it is already mapped to.
+    pub fn assign(
+        &mut self,
+        world: &mut World,
+        map: &mut NetworkEntityMap,
+        entity: Entity,
+    ) -> NetworkId {
+        if let Some(id) = map.id(entity) {
+            return id;
+        }
+        let id = NetworkId::new(self.prefix, self.next_serial);
+        self.next_serial = self
+            .next_serial
+            .checked_add(1)
+            .expect("network id serials exhausted");
+        let _ = world.insert(entity, id);
+        map.insert_pending(id, entity);
+        id
+    }
+}
+
+/// Host-side assignment of id prefixes to peers; prefix 0 is the host's own.
+#[derive(Debug, Clone)]
+pub struct PeerPrefixes {
+    assigned: HashMap<AuthorId, u32>,
+    next: u32,
+}
+
+impl Default for PeerPrefixes {
+    fn default() -> Self {
+        Self {
+            assigned: HashMap::new(),
+            next: 1,
+        }
+    }
+}
+
+impl PeerPrefixes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The prefix of `peer`, assigning the next free one on first use. `None` once every
+    /// prefix up to [`MAX_PEER_PREFIX`] is taken.
+    pub fn prefix_for(&mut self, peer: &AuthorId) -> Option<u32> {
+        if let Some(prefix) = self.assigned.get(peer) {
+            return Some(*prefix);
+        }
+        if self.next > MAX_PEER_PREFIX {
+            return None;
+        }
+        let prefix = self.next;
+        self.next += 1;
+        self.assigned.insert(peer.clone(), prefix);
+        Some(prefix)
+    }
+
+    pub fn get(&self, peer: &AuthorId) -> Option<u32> {
+        self.assigned.get(peer).copied()
+    }
+}
+
+crate::register_component_types!(NetworkId);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::replication::ReplicationRegistry;
+    use crate::network::replication_stream::{
+        ReplicationReceiver, ReplicationSender, ReplicationStreamConfig,
+    };
+    use std::time::Instant;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Position([f32; 3]);
+
+    struct Peer {
+        world: World,
+        ids: NetworkIdAllocator,
+        map: SharedEntityMap,
+        /// Mirror of the host's world, sharing `map` with `ids`.
+        from_host: ReplicationReceiver,
+        to_host: ReplicationSender,
+    }
+
+    impl Peer {
+        fn join(prefixes: &mut PeerPrefixes, author: u64) -> Self {
+            let map = SharedEntityMap::default();
+            Self {
+                world: World::new(),
+                ids: NetworkIdAllocator::new(prefixes.prefix_for(&AuthorId(author)).unwrap()),
+                from_host: ReplicationReceiver::new().with_entity_map(map.clone()),
+                map,
+                to_host: ReplicationSender::new(ReplicationStreamConfig::default()),
+            }
+        }
+
+        fn spawn_crate(&mut self, x: f32) -> (Entity, NetworkId) {
+            let (entity, id) = self
+                .ids
+                .spawn(&mut self.world, &mut lock_entity_map(&self.map));
+            self.world.insert(entity, Position([x, 0.0, 0.0])).unwrap();
+            (entity, id)
+        }
+    }
+
+    #[test]
+    fn independently_spawned_entities_keep_distinct_ids_through_the_host() {
+        let mut registry = ReplicationRegistry::new();
+        registry.register::<Position>();
+        let mut prefixes = PeerPrefixes::new();
+        let mut alice = Peer::join(&mut prefixes, 2);
+        let mut bob = Peer::join(&mut prefixes, 3);
+        assert_eq!(prefixes.prefix_for(&AuthorId(2)), Some(alice.ids.prefix()));
+
+        // Both spawn their first entity, so the local `Entity` values are identical.
+        let (alice_crate, alice_id) = alice.spawn_crate(1.0);
+        let (bob_crate, bob_id) = bob.spawn_crate(2.0);
+        assert_eq!(alice_crate, bob_crate);
+        assert_ne!(alice_id, bob_id);
+        let pending = |peer: &mut Peer| lock_entity_map(&peer.map).pending().count();
+        assert_eq!(pending(&mut alice), 1);
+
+        // The host's stream to Alice starts before her crate reaches it; the snapshot
+        // lacks the crate but must not remove it while it is pending.
+        let now = Instant::now();
+        let mut host = World::new();
+        let mut to_alice = ReplicationSender::new(ReplicationStreamConfig::default());
+        for message in to_alice.start(&registry, &host, now) {
+            alice
+                .from_host
+                .receive(&registry, &mut alice.world, message);
+        }
+        assert!(alice.world.get::<Position>(alice_crate).is_some());
+
+        // The host mirrors both peers into one world.
+        let mut from_peers = [&alice, &bob]
+            .map(|peer| ReplicationReceiver::new().with_source_prefix(peer.ids.prefix()));
+        for (peer, receiver) in [&mut alice, &mut bob].into_iter().zip(&mut from_peers) {
+            for message in peer.to_host.start(&registry, &peer.world, now) {
+                receiver.receive(&registry, &mut host, message);
+            }
+            let confirmed = peer.to_host.acknowledge(receiver.acknowledged());
+            lock_entity_map(&peer.map).confirm(confirmed);
+            assert_eq!(pending(peer), 0);
+        }
+        assert_eq!(host.component_entries::<Position>().len(), 2);
+
+        // The host's stream names entities by their network ids: Alice's own crate is
+        // updated in place and Bob's arrives as a new entity.
+        for message in to_alice.tick(&registry, &host, now) {
+            alice
+                .from_host
+                .receive(&registry, &mut alice.world, message);
+        }
+        assert_eq!(alice.world.component_entries::<Position>().len(), 2);
+        let applier = alice.from_host.applier();
+        assert_eq!(applier.local_entity(alice_id.into()), Some(alice_crate));
+        assert_eq!(applier.remote_entity(alice_crate), Some(alice_id.into()));
+        let bob_mirror = applier.local_entity(bob_id.into()).unwrap();
+        assert_ne!(bob_mirror, alice_crate);
+        assert_eq!(
+            alice.world.get::<Position>(bob_mirror),
+            Some(&Position([2.0, 0.0, 0.0]))
+        );
+        assert_eq!(alice.world.get::<NetworkId>(bob_mirror), Some(&bob_id));
+        assert_eq!(NetworkId::from(EntityHandle::from(bob_id)), bob_id);
+        assert_eq!((bob_id.prefix(), bob_id.serial()), (2, 0));
+    }
+
+    #[test]
+    fn peers_cannot_name_entities_outside_their_prefix() {
+        let mut registry = ReplicationRegistry::new();
+        registry.register::<Position>();
+        let mut prefixes = PeerPrefixes::new();
+        let mut alice = Peer::join(&mut prefixes, 2);
+        alice.spawn_crate(1.0);
+        // An entity Alice did not allocate, e.g. one the host spawned.
+        let foreign = alice.world.spawn();
+        alice.world.insert(foreign, NetworkId::new(0, 7)).unwrap();
+        alice
+            .world
+            .insert(foreign, Position([9.0, 0.0, 0.0]))
+            .unwrap();
+
+        let mut host = World::new();
+        let mut from_alice = ReplicationReceiver::new().with_source_prefix(alice.ids.prefix());
+        for message in alice.to_host.start(&registry, &alice.world, Instant::now()) {
+            from_alice.receive(&registry, &mut host, message);
+        }
+        assert_eq!(host.component_entries::<Position>().len(), 1);
+        assert_eq!(from_alice.applier().mapped_entities(), 1);
+        assert!(
+            from_alice
+                .applier()
+                .local_entity(NetworkId::new(0, 7).into())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn despawned_pending_entities_are_unbound() {
+        let mut prefixes = PeerPrefixes::new();
+        let mut alice = Peer::join(&mut prefixes, 2);
+        let (entity, id) = alice.spawn_crate(1.0);
+        alice.world.despawn(entity).unwrap();
+
+        let registry = ReplicationRegistry::new();
+        let host = World::new();
+        let mut to_alice = ReplicationSender::new(ReplicationStreamConfig::default());
+        for message in to_alice.start(&registry, &host, Instant::now()) {
+            alice
+                .from_host
+                .receive(&registry, &mut alice.world, message);
+        }
+        let map = lock_entity_map(&alice.map);
+        assert!(!map.is_pending(id));
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn prefixes_stop_below_the_tag_bit() {
+        let mut prefixes = PeerPrefixes::new();
+        prefixes.next = MAX_PEER_PREFIX;
+        assert_eq!(prefixes.prefix_for(&AuthorId(1)), Some(MAX_PEER_PREFIX));
+        assert_eq!(prefixes.prefix_for(&AuthorId(2)), None);
+        assert_eq!(prefixes.prefix_for(&AuthorId(1)), Some(MAX_PEER_PREFIX));
+    }
+}
@@ -0,0 +1,849 @@
+//! FlatBuffers codecs for command batches, carried as `MessageBody::CommandLogBatch`, and
+//! for the world replication stream, carried as `ComponentDelta` and `ReplicationAck`.
+
+use crate::network::command_log::{
+    AuthorId, AuthorPublicKey, CommandAuthor, CommandBatch, CommandEntry, CommandId,
+    CommandPayload, CommandRole, CommandScope, CommandSignature, ConflictStrategy, VersionVector,
+};
+use crate::network::replication::{ReplicationDelta, SnapshotComponent, WorldSnapshotChunk};
+use crate::network::replication_stream::{ReplicationBody, ReplicationMessage};
+use crate::network::wire::theta::net::{
+    self, ComponentOp, Compression, MessageBody, ResolutionStrategy, root_as_message_envelope,
+};
+use crate::network::{ComponentDescriptor, ComponentDiff, ComponentKey, DiffPayload, EntityHandle};
+use flatbuffers::{FlatBufferBuilder, UnionWIPOffset, WIPOffset};
+use std::collections::HashMap;
+
+const SCOPE_GLOBAL: u8 = 0;
+const SCOPE_ENTITY: u8 = 1;
+const SCOPE_TOOL: u8 = 2;
+
+pub fn encode_command_batch(batch: &CommandBatch) -> Vec<u8> {
+    let mut builder = FlatBufferBuilder::with_capacity(256 + batch.entries.len() * 128);
+
+    let entries: Vec<_> = batch
+        .entries
+        .iter()
+        .map(|entry| encode_entry(&mut builder, entry))
+        .collect();
+    let entries = builder.create_vector(&entries);
+    let author_id = builder.create_vector(&batch.author.0.to_le_bytes());
+
+    let body = net::CommandLogBatch::create(
+        &mut builder,
+        &net::CommandLogBatchArgs {
+            sequence_id: batch.sequence,
+            nonce: batch.nonce,
+            timestamp_ms: batch.timestamp_ms,
+            author_id: Some(author_id),
+            entries: Some(entries),
+        },
+    );
+    let header = net::PacketHeader::create(
+        &mut builder,
+        &net::PacketHeaderArgs {
+            sequence_id: batch.sequence,
+            timestamp_ms: batch.timestamp_ms,
+            compression: Compression::None,
+            schema_hash: 0,
+        },
+    );
+    let envelope = net::MessageEnvelope::create(
+        &mut builder,
+        &net::MessageEnvelopeArgs {
+            header: Some(header),
+            body_type: MessageBody::CommandLogBatch,
+            body: Some(WIPOffset::<UnionWIPOffset>::new(body.value())),
+        },
+    );
+    net::finish_message_envelope_buffer(&mut builder, envelope);
+    builder.finished_data().to_vec()
+}
+
+pub fn decode_command_batch(bytes: &[u8]) -> Result<CommandBatch, String> {
+    let envelope = root_as_message_envelope(bytes).map_err(|err| err.to_string())?;
+    let body = envelope.body_as_command_log_batch().ok_or_else(|| {
+        format!(
+            "expected CommandLogBatch body, got {:?}",
+            envelope.body_type()
+        )
+    })?;
+
+    let entries = body
+        .entries()
+        .map(|entries| entries.iter().map(decode_entry).collect())
+        .transpose()?
+        .unwrap_or_default();
+
+    Ok(CommandBatch {
+        sequence: body.sequence_id(),
+        nonce: body.nonce(),
+        timestamp_ms: body.timestamp_ms(),
+        author: decode_author_id(body.author_id())?,
+        entries,
+    })
+}
+
+pub fn encode_replication_message(message: &ReplicationMessage) -> Vec<u8> {
+    let mut builder = FlatBufferBuilder::with_capacity(256);
+    let mut unreliable_tick = 0;
+    let compression = if replication_patches(&message.body) {
+        Compression::Delta
+    } else {
+        Compression::None
+    };
+    let (entries, descriptors, snapshot_chunk, snapshot_chunks) = match &message.body {
+        ReplicationBody::Snapshot(chunk) => {
+            let entries: Vec<_> = chunk
+                .components
+                .iter()
+                .map(|component| {
+                    encode_component_entry(
+                        &mut builder,
+                        component.entity,
+                        component.component.type_hash,
+                        ComponentOp::Insert,
+                        Some(&component.bytes),
+                        0,
+                    )
+                })
+                .collect();
+            (entries, Vec::new(), chunk.chunk_index, chunk.total_chunks)
+        }
+        ReplicationBody::Delta(delta) => {
+            let mut entries = Vec::new();
+            for entity in &delta.spawned {
+                entries.push(encode_component_entry(
+                    &mut builder,
+                    *entity,
+                    0,
+                    ComponentOp::Spawn,
+                    None,
+                    0,
+                ));
+            }
+            for diff in &delta.diffs {
+                entries.push(encode_diff_entry(&mut builder, diff));
+            }
+            for entity in &delta.despawned {
+                entries.push(encode_component_entry(
+                    &mut builder,
+                    *entity,
+                    0,
+                    ComponentOp::Despawn,
+                    None,
+                    0,
+                ));
+            }
+            let descriptors: Vec<_> = delta
+                .descriptors
+                .iter()
+                .map(|descriptor| {
+                    let type_name = builder.create_string(&descriptor.key.type_name);
+                    net::ComponentDescriptor::create(
+                        &mut builder,
+                        &net::ComponentDescriptorArgs {
+                            component_id: descriptor.key.type_hash,
+                            type_name: Some(type_name),
+                        },
+                    )
+                })
+                .collect();
+            (entries, descriptors, 0, 0)
+        }
+        ReplicationBody::Unreliable { tick, diffs } => {
+            unreliable_tick = *tick;
+            let entries = diffs
+                .iter()
+                .filter(|diff| {
+                    matches!(
+                        diff.payload,
+                        DiffPayload::Update { .. } | DiffPayload::Delta { .. }
+                    )
+                })
+                .map(|diff| encode_diff_entry(&mut builder, diff))
+                .collect();
+            (entries, Vec::new(), 0, 0)
+        }
+    };
+    let entries = builder.create_vector(&entries);
+    let descriptors = builder.create_vector(&descriptors);
+    let body = net::ComponentDelta::create(
+        &mut builder,
+        &net::ComponentDeltaArgs {
+            entries: Some(entries),
+            descriptors: Some(descriptors),
+            snapshot_chunk,
+            snapshot_chunks,
+            unreliable_tick,
+            command_ack: message.command_ack,
+        },
+    );
+    finish_envelope(
+        builder,
+        message.sequence,
+        message.timestamp_ms,
+        compression,
+        MessageBody::ComponentDelta,
+        body.as_union_value(),
+    )
+}
+
+/// Decodes a replication message. Component keys carry a type name only when the
+/// message advertised its descriptor; receivers resolve components by hash.
+pub fn decode_replication_message(bytes: &[u8]) -> Result<ReplicationMessage, String> {
+    let envelope = root_as_message_envelope(bytes).map_err(|err| err.to_string())?;
+    let header = envelope
+        .header()
+        .ok_or("replication message missing header")?;
+    let body = envelope.body_as_component_delta().ok_or_else(|| {
+        format!(
+            "expected ComponentDelta body, got {:?}",
+            envelope.body_type()
+        )
+    })?;
+
+    let descriptors: Vec<ComponentDescriptor> = body
+        .descriptors()
+        .map(|descriptors| {
+            descriptors
+                .iter()
+                .map(|descriptor| ComponentDescriptor {
+                    key: ComponentKey {
+                        type_name: descriptor.type_name().unwrap_or_default().to_string(),
+                        type_hash: descriptor.component_id(),
+                    },
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let names: HashMap<u64, &str> = descriptors
+        .iter()
+        .map(|descriptor| (descriptor.key.type_hash, descriptor.key.type_name.as_str()))
+        .collect();
+    let key = |type_hash: u64| ComponentKey {
+        type_name: names
+            .get(&type_hash)
+            .copied()
+            .unwrap_or_default()
+            .to_string(),
+        type_hash,
+    };
+
+    let mut components = Vec::new();
+    let mut delta = ReplicationDelta::default();
+    let snapshot = body.snapshot_chunks() > 0;
+    for entry in body.entries().into_iter().flatten() {
+        let entity = EntityHandle {
+            index: entry.entity_id() as u32,
+            generation: (entry.entity_id() >> 32) as u32,
+        };
+        let bytes = || {
+            entry
+                .diff_payload()
+                .map(|payload| payload.bytes().to_vec())
+                .unwrap_or_default()
+        };
+        match (snapshot, entry.op()) {
+            (true, ComponentOp::Insert) => components.push(SnapshotComponent {
+                component: key(entry.component_id()),
+                entity,
+                bytes: bytes(),
+            }),
+            (false, ComponentOp::Spawn) => delta.spawned.push(entity),
+            (false, ComponentOp::Despawn) => delta.despawned.push(entity),
+            (
+                false,
+                op @ (ComponentOp::Insert
+                | ComponentOp::Update
+                | ComponentOp::Remove
+                | ComponentOp::Delta),
+            ) => {
+                let payload = match op {
+                    ComponentOp::Insert => DiffPayload::Insert { bytes: bytes() },
+                    ComponentOp::Update => DiffPayload::Update { bytes: bytes() },
+                    ComponentOp::Delta => DiffPayload::Delta {
+                        baseline: entry.baseline(),
+                        patch: bytes(),
+                    },
+                    _ => DiffPayload::Remove,
+                };
+                delta.diffs.push(ComponentDiff {
+                    entity,
+                    component: key(entry.component_id()),
+                    payload,
+                });
+            }
+            (_, op) => return Err(format!("unexpected component op {op:?}")),
+        }
+    }
+
+    let command_ack = body.command_ack();
+    let body = if body.unreliable_tick() > 0 {
+        ReplicationBody::Unreliable {
+            tick: body.unreliable_tick(),
+            diffs: delta.diffs,
+        }
+    } else if snapshot {
+        ReplicationBody::Snapshot(WorldSnapshotChunk {
+            chunk_index: body.snapshot_chunk(),
+            total_chunks: body.snapshot_chunks(),
+            components,
+        })
+    } else {
+        delta.descriptors = descriptors;
+        ReplicationBody::Delta(delta)
+    };
+    Ok(ReplicationMessage {
+        sequence: header.sequence_id(),
+        timestamp_ms: header.timestamp_ms(),
+        command_ack,
+        body,
+    })
+}
+
+/// `resync` asks the sender for a fresh snapshot; see
+/// [`crate::network::replication_stream::ReplicationReceiver::take_resync_request`].
+pub fn encode_replication_ack(sequence: u64, resync: bool, timestamp_ms: u64) -> Vec<u8> {
+    let mut builder = FlatBufferBuilder::with_capacity(64);
+    let body = net::ReplicationAck::create(
+        &mut builder,
+        &net::ReplicationAckArgs {
+            sequence_id: sequence,
+            resync,
+        },
+    );
+    finish_envelope(
+        builder,
+        sequence,
+        timestamp_ms,
+        Compression::None,
+        MessageBody::ReplicationAck,
+        body.as_union_value(),
+    )
+}
+
+/// The acknowledged sequence and whether the peer asks for a resync.
+pub fn decode_replication_ack(bytes: &[u8]) -> Result<(u64, bool), String> {
+    let envelope = root_as_message_envelope(bytes).map_err(|err| err.to_string())?;
+    envelope
+        .body_as_replication_ack()
+        .map(|ack| (ack.sequence_id(), ack.resync()))
+        .ok_or_else(|| {
+            format!(
+                "expected ReplicationAck body, got {:?}",
+                envelope.body_type()
+            )
+        })
+}
+
+fn encode_component_entry<'a>(
+    builder: &mut FlatBufferBuilder<'a>,
+    entity: EntityHandle,
+    component_id: u64,
+    op: ComponentOp,
+    bytes: Option<&Vec<u8>>,
+    baseline: u64,
+) -> WIPOffset<net::ComponentEntry<'a>> {
+    let diff_payload = bytes.map(|bytes| builder.create_vector(bytes));
+    net::ComponentEntry::create(
+        builder,
+        &net::ComponentEntryArgs {
+            entity_id: (u64::from(entity.generation) << 32) | u64::from(entity.index),
+            component_id,
+            revision: 0,
+            diff_payload,
+            op,
+            baseline,
+        },
+    )
+}
+
+fn encode_diff_entry<'a>(
+    builder: &mut FlatBufferBuilder<'a>,
+    diff: &ComponentDiff,
+) -> WIPOffset<net::ComponentEntry<'a>> {
+    let (op, bytes, baseline) = match &diff.payload {
+        DiffPayload::Insert { bytes } => (ComponentOp::Insert, Some(bytes), 0),
+        DiffPayload::Update { bytes } => (ComponentOp::Update, Some(bytes), 0),
+        DiffPayload::Delta { baseline, patch } => (ComponentOp::Delta, Some(patch), *baseline),
+        DiffPayload::Remove => (ComponentOp::Remove, None, 0),
+    };
+    encode_component_entry(
+        builder,
+        diff.entity,
+        diff.component.type_hash,
+        op,
+        bytes,
+        baseline,
+    )
+}
+
+/// Whether any entry of a replication body is a patch against an earlier value.
+fn replication_patches(body: &ReplicationBody) -> bool {
+    let diffs = match body {
+        ReplicationBody::Snapshot(_) => return false,
+        ReplicationBody::Delta(delta) => &delta.diffs,
+        ReplicationBody::Unreliable { diffs, .. } => diffs,
+    };
+    diffs
+        .iter()
+        .any(|diff| matches!(diff.payload, DiffPayload::Delta { .. }))
+}
+
+fn finish_envelope(
+    mut builder: FlatBufferBuilder<'_>,
+    sequence: u64,
+    timestamp_ms: u64,
+    compression: Compression,
+    body_type: MessageBody,
+    body: WIPOffset<UnionWIPOffset>,
+) -> Vec<u8> {
+    let header = net::PacketHeader::create(
+        &mut builder,
+        &net::PacketHeaderArgs {
+            sequence_id: sequence,
+            timestamp_ms,
+            compression,
+            schema_hash: 0,
+        },
+    );
+    let envelope = net::MessageEnvelope::create(
+        &mut builder,
+        &net::MessageEnvelopeArgs {
+            header: Some(header),
+            body_type,
+            body: Some(body),
+        },
+    );
+    net::finish_message_envelope_buffer(&mut builder, envelope);
+    builder.finished_data().to_vec()
+}
+
+fn encode_entry<'a>(
+    builder: &mut FlatBufferBuilder<'a>,
+    entry: &CommandEntry,
+) -> WIPOffset<net::CommandLogEntry<'a>> {
+    let author_id = builder.create_vector(&entry.id.author().0.to_le_bytes());
+    let payload = builder.create_vector(&entry.payload.data);
+    let signature = entry
+        .signature
+        .as_ref()
+        .map(|signature| builder.create_vector(&signature.0));
+    let public_key = entry
+        .author
+        .public_key
+        .as_ref()
+        .map(|key| builder.create_vector(&key.0));
+    let command_name = builder.create_string(&entry.payload.command_type);
+    let scope = encode_scope(builder, &entry.payload.scope);
+    let causal_context = entry.causal_context.as_ref().map(|context| {
+        let items: Vec<_> = context
+            .iter()
+            .map(|(author, lamport)| {
+                net::VersionVectorEntry::create(
+                    builder,
+                    &net::VersionVectorEntryArgs {
+                        author_id: author.0,
+                        lamport_clock: lamport,
+                    },
+                )
+            })
+            .collect();
+        builder.create_vector(&items)
+    });
+
+    net::CommandLogEntry::create(
+        builder,
+        &net::CommandLogEntryArgs {
+            lamport_clock: entry.id.lamport(),
+            author_id: Some(author_id),
+            command_type: command_type_hash(&entry.payload.command_type),
+            payload: Some(payload),
+            signature,
+            resolution_strategy: encode_strategy(entry.strategy),
+            command_name: Some(command_name),
+            timestamp_ms: entry.timestamp_ms,
+            author_role: encode_role(entry.author.role),
+            author_public_key: public_key,
+            scope: Some(scope),
+            causal_context,
+        },
+    )
+}
+
+fn decode_entry(entry: net::CommandLogEntry<'_>) -> Result<CommandEntry, String> {
+    let author_id = decode_author_id(entry.author_id())?;
+    let command_type = entry
+        .command_name()
+        .ok_or("command entry missing command name")?
+        .to_string();
+    if entry.command_type() != command_type_hash(&command_type) {
+        return Err(format!("command type hash mismatch for {command_type}"));
+    }
+
+    let mut author = CommandAuthor::new(author_id.clone(), decode_role(entry.author_role())?);
+    if let Some(key) = entry.author_public_key() {
+        author = author.with_public_key(AuthorPublicKey(key.bytes().to_vec()));
+    }
+
+    let payload = CommandPayload::new(
+        command_type,
+        decode_scope(entry.scope())?,
+        entry
+            .payload()
+            .map(|data| data.bytes().to_vec())
+            .unwrap_or_default(),
+    );
+    let signature = entry
+        .signature()
+        .map(|signature| CommandSignature(signature.bytes().to_vec()));
+
+    let mut decoded = CommandEntry::new(
+        CommandId::new(entry.lamport_clock(), author_id),
+        entry.timestamp_ms(),
+        payload,
+        decode_strategy(entry.resolution_strategy())?,
+        author,
+        signature,
+    );
+    if let Some(items) = entry.causal_context() {
+        let mut context = VersionVector::new();
+        for item in items.iter() {
+            context.observe(&AuthorId(item.author_id()), item.lamport_clock());
+        }
+        decoded = decoded.with_causal_context(context);
+    }
+    Ok(decoded)
+}
+
+fn encode_scope<'a>(
+    builder: &mut FlatBufferBuilder<'a>,
+    scope: &CommandScope,
+) -> WIPOffset<net::CommandScopeRef<'a>> {
+    let args = match scope {
+        CommandScope::Global => net::CommandScopeRefArgs {
+            kind: SCOPE_GLOBAL,
+            ..Default::default()
+        },
+        CommandScope::Entity(handle) => net::CommandScopeRefArgs {
+            kind: SCOPE_ENTITY,
+            entity_index: handle.index,
+            entity_generation: handle.generation,
+            tool_id: None,
+        },
+        CommandScope::Tool(tool_id) => net::CommandScopeRefArgs {
+            kind: SCOPE_TOOL,
+            tool_id: Some(builder.create_string(tool_id)),
+            ..Default::default()
+        },
+    };
+    net::CommandScopeRef::create(builder, &args)
+}
+
+fn decode_scope(scope: Option<net::CommandScopeRef<'_>>) -> Result<CommandScope, String> {
+    let Some(scope) = scope else {
+        return Ok(CommandScope::Global);
+    };
+    match scope.kind() {
+        SCOPE_GLOBAL => Ok(CommandScope::Global),
+        SCOPE_ENTITY => Ok(CommandScope::Entity(EntityHandle {
+            index: scope.entity_index(),
+            generation: scope.entity_generation(),
+        })),
+        SCOPE_TOOL => Ok(CommandScope::Tool(
+            scope
+                .tool_id()
+                .ok_or("tool scope missing tool id")?
+                .to_string(),
+        )),
+        other => Err(format!("unknown command scope kind {other}")),
+    }
+}
+
+fn decode_author_id(bytes: Option<flatbuffers::Vector<'_, u8>>) -> Result<AuthorId, String> {
+    let bytes: [u8; 8] = bytes
+        .ok_or("missing author id")?
+        .bytes()
+        .try_into()
+        .map_err(|_| "author id must be 8 bytes".to_string())?;
+    Ok(AuthorId(u64::from_le_bytes(bytes)))
+}
+
+fn encode_strategy(strategy: ConflictStrategy) -> ResolutionStrategy {
+    match strategy {
+        ConflictStrategy::LastWriteWins => ResolutionStrategy::LastWriterWins,
+        ConflictStrategy::Merge => ResolutionStrategy::Merge,
+        ConflictStrategy::Reject => ResolutionStrategy::Reject,
+    }
+}
+
+fn decode_strategy(strategy: ResolutionStrategy) -> Result<ConflictStrategy, String> {
+    match strategy {
+        ResolutionStrategy::LastWriterWins => Ok(ConflictStrategy::LastWriteWins),
+        ResolutionStrategy::Merge => Ok(ConflictStrategy::Merge),
+        ResolutionStrategy::Reject => Ok(ConflictStrategy::Reject),
+        other => Err(format!("unknown resolution strategy {other:?}")),
+    }
+}
+
+fn encode_role(role: CommandRole) -> u8 {
+    match role {
+        CommandRole::Viewer => 0,
+        CommandRole::Editor => 1,
+        CommandRole::Admin => 2,
+    }
+}
+
+fn decode_role(role: u8) -> Result<CommandRole, String> {
+    match role {
+        0 => Ok(CommandRole::Viewer),
+        1 => Ok(CommandRole::Editor),
+        2 => Ok(CommandRole::Admin),
+        other => Err(format!("unknown command role {other}")),
+    }
+}
+
+/// FNV-1a hash of the command name, kept in the legacy `command_type` slot.
+fn command_type_hash(command_type: &str) -> u64 {
+    command_type
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::command_log::{
+        CAPABILITY_FLATBUFFERS_COMMANDS, CommandPacket, PacketEncoding,
+    };
+
+    fn sample_batch() -> CommandBatch {
+        let mut context = VersionVector::new();
+        context.observe(&AuthorId(3), 7);
+        context.observe(&AuthorId(9), 2);
+
+        let editor = CommandAuthor::new(AuthorId(3), CommandRole::Editor)
+            .with_public_key(AuthorPublicKey(vec![5; 32]));
+        let entries = (0..24u64)
+            .map(|index| {
+                let scope = match index % 3 {
+                    0 => CommandScope::Global,
+                    1 => CommandScope::Entity(EntityHandle {
+                        index: index as u32,
+                        generation: 2,
+                    }),
+                    _ => CommandScope::Tool("sculpt".into()),
+                };
+                let entry = CommandEntry::new(
+                    CommandId::new(10 + index, AuthorId(3)),
+                    1_700_000_000_000 + index,
+                    CommandPayload::new("editor.entity.translate", scope, vec![index as u8; 24]),
+                    ConflictStrategy::Merge,
+                    editor.clone(),
+                    (index % 2 == 0).then(|| CommandSignature(vec![0xAB; 64])),
+                );
+                if index % 4 == 0 {
+                    entry.with_causal_context(context.clone())
+                } else {
+                    entry
+                }
+            })
+            .collect();
+
+        CommandBatch {
+            sequence: 12,
+            nonce: 99,
+            timestamp_ms: 1_700_000_000_500,
+            author: AuthorId(3),
+            entries,
+        }
+    }
+
+    #[test]
+    fn flatbuffer_batch_roundtrips() {
+        let batch = sample_batch();
+        let decoded = decode_command_batch(&encode_command_batch(&batch)).expect("decode batch");
+        assert_eq!(decoded, batch);
+    }
+
+    #[test]
+    fn codecs_agree_and_flatbuffers_are_smaller() {
+        let batch = sample_batch();
+        let json = CommandPacket::encode(&batch, PacketEncoding::Json).expect("json packet");
+        let flat =
+            CommandPacket::encode(&batch, PacketEncoding::FlatBuffers).expect("flatbuffer packet");
+        assert_eq!(flat.encoding, PacketEncoding::FlatBuffers);
+
+        assert_eq!(json.decode().unwrap(), flat.decode().unwrap());
+        assert!(
+            flat.payload.len() * 2 < json.payload.len(),
+            "flatbuffers {} bytes vs json {} bytes",
+            flat.payload.len(),
+            json.payload.len()
+        );
+    }
+
+    #[test]
+    fn packets_without_encoding_field_default_to_json() {
+        let packet = CommandPacket::from_batch(&sample_batch()).expect("json packet");
+        let mut value = serde_json::to_value(&packet).unwrap();
+        value.as_object_mut().unwrap().remove("encoding");
+        let legacy: CommandPacket = serde_json::from_value(value).unwrap();
+        assert_eq!(legacy.encoding, PacketEncoding::Json);
+        assert_eq!(legacy.decode().unwrap(), sample_batch());
+    }
+
+    #[test]
+    fn encoding_follows_negotiated_capabilities() {
+        assert_eq!(PacketEncoding::negotiate(&[1, 2]), PacketEncoding::Json);
+        assert_eq!(
+            PacketEncoding::negotiate(&[CAPABILITY_FLATBUFFERS_COMMANDS]),
+            PacketEncoding::FlatBuffers
+        );
+    }
+
+    #[test]
+    fn rejects_mismatched_command_hash() {
+        let mut batch = sample_batch();
+        batch.entries.truncate(1);
+        let mut bytes = encode_command_batch(&batch);
+        let needle = command_type_hash("editor.entity.translate").to_le_bytes();
+        let offset = bytes
+            .windows(needle.len())
+            .position(|window| window == needle)
+            .expect("hash present");
+        bytes[offset] ^= 0xFF;
+        assert!(decode_command_batch(&bytes).is_err());
+    }
+
+    #[test]
+    fn packets_travel_as_binary_frames() {
+        let batch = sample_batch();
+        let mask = PacketEncoding::capabilities();
+        let flat = CommandPacket::encode(&batch, PacketEncoding::negotiate(&mask)).unwrap();
+        assert_eq!(flat.encoding, PacketEncoding::FlatBuffers);
+
+        let wire = flat.to_wire();
+        assert!(wire.ends_with(&flat.payload));
+        assert!(wire.len() < flat.payload.len() + 64);
+        assert_eq!(CommandPacket::from_wire(&wire).unwrap(), flat);
+
+        let fragments =
+            CommandPacket::encode_fragmented(&batch, PacketEncoding::Json).expect("fragments");
+        for fragment in fragments {
+            assert_eq!(
+                CommandPacket::from_wire(&fragment.to_wire()).unwrap(),
+                fragment
+            );
+        }
+        let legacy = serde_json::to_vec(&flat).unwrap();
+        assert_eq!(CommandPacket::from_wire(&legacy).unwrap(), flat);
+        assert!(CommandPacket::from_wire(&wire[..20]).is_err());
+    }
+
+    #[test]
+    fn replication_messages_roundtrip() {
+        let key = ComponentKey {
+            type_name: "Health".into(),
+            type_hash: 0x5EED,
+        };
+        let hero = EntityHandle {
+            index: 4,
+            generation: 2,
+        };
+        let chunk = ReplicationMessage {
+            sequence: 1,
+            timestamp_ms: 1_700_000_000_000,
+            command_ack: 0,
+            body: ReplicationBody::Snapshot(WorldSnapshotChunk {
+                chunk_index: 1,
+                total_chunks: 3,
+                components: vec![SnapshotComponent {
+                    component: key.clone(),
+                    entity: hero,
+                    bytes: vec![1, 2, 3],
+                }],
+            }),
+        };
+        let decoded = decode_replication_message(&encode_replication_message(&chunk)).unwrap();
+        assert_eq!(decoded.sequence, 1);
+        let ReplicationBody::Snapshot(decoded) = decoded.body else {
+            panic!("expected snapshot chunk");
+        };
+        assert_eq!((decoded.chunk_index, decoded.total_chunks), (1, 3));
+        assert_eq!(decoded.components[0].entity, hero);
+        assert_eq!(decoded.components[0].component.type_hash, key.type_hash);
+        assert_eq!(decoded.components[0].bytes, vec![1, 2, 3]);
+
+        let villain = EntityHandle {
+            index: 9,
+            generation: 0,
+        };
+        let diff = |payload| ComponentDiff {
+            entity: hero,
+            component: key.clone(),
+            payload,
+        };
+        let delta = ReplicationDelta {
+            descriptors: vec![ComponentDescriptor { key: key.clone() }],
+            diffs: vec![
+                diff(DiffPayload::Insert { bytes: vec![7] }),
+                diff(DiffPayload::Update { bytes: vec![8] }),
+                diff(DiffPayload::Delta {
+                    baseline: 1,
+                    patch: vec![1, 0, 1, 6],
+                }),
+                diff(DiffPayload::Remove),
+            ],
+            spawned: vec![hero],
+            despawned: vec![villain],
+            unreliable: Vec::new(),
+        };
+        let message = ReplicationMessage {
+            sequence: 2,
+            timestamp_ms: 1_700_000_000_016,
+            command_ack: 12,
+            body: ReplicationBody::Delta(delta.clone()),
+        };
+        let encoded = encode_replication_message(&message);
+        let header = root_as_message_envelope(&encoded)
+            .unwrap()
+            .header()
+            .unwrap();
+        assert_eq!(header.compression(), Compression::Delta);
+        let decoded = decode_replication_message(&encoded).unwrap();
+        assert_eq!(decoded.command_ack, 12);
+        let ReplicationBody::Delta(decoded) = decoded.body else {
+            panic!("expected delta");
+        };
+        assert_eq!(decoded.descriptors, delta.descriptors);
+        assert_eq!(decoded.diffs, delta.diffs);
+        assert_eq!(decoded.spawned, delta.spawned);
+        assert_eq!(decoded.despawned, delta.despawned);
+
+        let update = ReplicationMessage {
+            sequence: 2,
+            timestamp_ms: 1_700_000_000_018,
+            command_ack: 12,
+            body: ReplicationBody::Unreliable {
+                tick: 5,
+                diffs: vec![diff(DiffPayload::Update { bytes: vec![9] })],
+            },
+        };
+        let decoded = decode_replication_message(&encode_replication_message(&update)).unwrap();
+        let ReplicationBody::Unreliable { tick, diffs } = decoded.body else {
+            panic!("expected unreliable update");
+        };
+        assert_eq!(tick, 5);
+        assert_eq!(diffs[0].payload, DiffPayload::Update { bytes: vec![9] });
+
+        let ack = encode_replication_ack(2, false, 1_700_000_000_020);
+        assert_eq!(decode_replication_ack(&ack), Ok((2, false)));
+        let resync = encode_replication_ack(2, true, 1_700_000_000_020);
+        assert_eq!(decode_replication_ack(&resync), Ok((2, true)));
+        assert!(decode_replication_message(&ack).is_err());
+    }
+}
@@ -0,0 +1,330 @@
+//! Client-side prediction for entities this peer moves itself, such as grabbed objects.
+//! Local commands apply to the world at once. When authoritative state arrives, predicted
+//! entities are rewound to it and the commands the host had not yet applied, per
+//! [`ReplicationMessage::command_ack`], are replayed on top.
+
+use crate::ecs::{Component, Entity, World};
+use crate::network::command_log::{AuthorId, CommandEntry};
+use crate::network::replication::{ApplyStats, ReplicationRegistry};
+use crate::network::replication_stream::{ReplicationMessage, ReplicationReceiver};
+use std::collections::{HashMap, VecDeque};
+
+/// Applies one predicted command to the local entity it targets.
+pub type ReplayFn<'a> = dyn FnMut(&mut World, Entity, &CommandEntry) + 'a;
+
+struct PredictedEntity<T> {
+    /// Last state received from the host; `None` until the entity has the component.
+    authoritative: Option<T>,
+    released: bool,
+}
+
+/// Local commands kept for replay; past this the oldest are dropped, so a host that
+/// stops acknowledging cannot grow the queue without bound.
+pub const MAX_PENDING_COMMANDS: usize = 1024;
+
+/// Predicts component `T` (usually the transform) of entities driven by local commands.
+pub struct ClientPrediction<T> {
+    author: AuthorId,
+    entities: HashMap<Entity, PredictedEntity<T>>,
+    pending: VecDeque<(Entity, CommandEntry)>,
+    /// A command applied locally but not yet logged, such as an open drag; replayed
+    /// after `pending`.
+    provisional: Option<(Entity, CommandEntry)>,
+    acknowledged: u64,
+}
+
+impl<T: Component + Clone> ClientPrediction<T> {
+    pub fn new(author: AuthorId) -> Self {
+        Self {
+            author,
+            entities: HashMap::new(),
+            pending: VecDeque::new(),
+            provisional: None,
+            acknowledged: 0,
+        }
+    }
+
+    pub fn author(&self) -> &AuthorId {
+        &self.author
+    }
+
+    /// Lamport clock of the newest local command the host has applied.
+    pub fn acknowledged(&self) -> u64 {
+        self.acknowledged
+    }
+
+    /// Local commands not yet reflected in authoritative state, in issue order.
+    pub fn pending(&self) -> impl Iterator<Item = &CommandEntry> {
+        self.pending.iter().map(|(_, entry)| entry)
+    }
+
+    pub fn is_predicting(&self, entity: Entity) -> bool {
+        self.entities.contains_key(&entity)
+    }
+
+    /// Applies a local command to `entity` ahead of the host and keeps it for replay.
+    /// Entries from other authors are ignored, since the host never acknowledges them
+    /// on this peer's behalf.
+    pub fn predict(
+        &mut self,
+        world: &mut World,
+        entity: Entity,
+        entry: CommandEntry,
+        replay: &mut ReplayFn<'_>,
+    ) {
+        if entry.id.author() != &self.author {
+            log::warn!(
+                "[prediction] ignoring {} from {:?}",
+                entry.payload.command_type,
+                entry.id.author()
+            );
+            return;
+        }
+        self.begin(world, entity);
+        replay(world, entity, &entry);
+        self.record(entity, entry);
+    }
+
+    /// Starts predicting `entity` from its current state. Call before applying a local
+    /// command yourself, then hand the entry to [`Self::record`].
+    pub fn begin(&mut self, world: &World, entity: Entity) {
+        let predicted = self
+            .entities
+            .entry(entity)
+            .or_insert_with(|| PredictedEntity {
+                authoritative: world.get::<T>(entity).cloned(),
+                released: false,
+            });
+        predicted.released = false;
+    }
+
+    /// Keeps a local command already applied to `entity` for replay.
+    pub fn record(&mut self, entity: Entity, entry: CommandEntry) {
+        if self.pending.len() >= MAX_PENDING_COMMANDS
+            && let Some((_, dropped)) = self.pending.pop_front()
+        {
+            log::warn!(
+                "[prediction] {MAX_PENDING_COMMANDS} commands unacknowledged; dropping {:?}",
+                dropped.id
+            );
+        }
+        self.pending.push_back((entity, entry));
+    }
+
+    /// Replaces the provisional command, e.g. with a drag's running total; `None` once
+    /// it is logged or abandoned.
+    pub fn set_provisional(&mut self, provisional: Option<(Entity, CommandEntry)>) {
+        self.provisional = provisional;
+    }
+
+    /// Stops predicting `entity`, e.g. when it is let go, once its pending commands
+    /// are acknowledged.
+    pub fn release(&mut self, entity: Entity) {
+        if let Some(predicted) = self.entities.get_mut(&entity) {
+            predicted.released = true;
+        }
+    }
+
+    /// Restores predicted entities to their last authoritative state. Call before
+    /// authoritative state is applied so relative commands are not applied twice.
+    pub fn rewind(&self, world: &mut World) {
+        for (entity, predicted) in &self.entities {
+            if let Some(value) = &predicted.authoritative {
+                let _ = world.insert(*entity, value.clone());
+            }
+        }
+    }
+
+    /// Takes the world's state as authoritative now that the host has applied local
+    /// commands up to `command_ack`, then replays the ones it has not.
+    pub fn reconcile(&mut self, world: &mut World, command_ack: u64, replay: &mut ReplayFn<'_>) {
+        self.acknowledged = self.acknowledged.max(command_ack);
+        let acknowledged = self.acknowledged;
+        self.pending
+            .retain(|(_, entry)| entry.id.lamport() > acknowledged);
+
+        let pending = &self.pending;
+        self.entities.retain(|entity, predicted| {
+            predicted.authoritative = world.get::<T>(*entity).cloned();
+            world.contains(*entity)
+                && !(predicted.released && pending.iter().all(|(other, _)| other != entity))
+        });
+        for (entity, entry) in self.pending.iter().chain(&self.provisional) {
+            if self.entities.contains_key(entity) {
+                replay(world, *entity, entry);
+            }
+        }
+    }
+
+    /// Applies a replication message through `receiver` between a rewind and a
+    /// reconcile.
+    pub fn receive(
+        &mut self,
+        receiver: &mut ReplicationReceiver,
+        registry: &ReplicationRegistry,
+        world: &mut World,
+        message: ReplicationMessage,
+        replay: &mut ReplayFn<'_>,
+    ) -> ApplyStats {
+        self.rewind(world);
+        let stats = receiver.receive(registry, world, message);
+        self.reconcile(world, receiver.command_ack(), replay);
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::commands::{CMD_ENTITY_TRANSLATE, EntityTranslateCommand};
+    use crate::network::EntityHandle;
+    use crate::network::command_log::{
+        CommandAuthor, CommandId, CommandPayload, CommandRole, CommandScope, ConflictStrategy,
+        VersionVector,
+    };
+    use crate::network::replication_stream::{ReplicationSender, ReplicationStreamConfig};
+    use serde::{Deserialize, Serialize};
+    use std::time::{Duration, Instant};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Position([f32; 3]);
+
+    fn nudge(author: &AuthorId, lamport: u64, entity: EntityHandle, dx: f32) -> CommandEntry {
+        let command = EntityTranslateCommand::new(entity, [dx, 0.0, 0.0]);
+        CommandEntry::new(
+            CommandId::new(lamport, author.clone()),
+            0,
+            CommandPayload::new(
+                CMD_ENTITY_TRANSLATE,
+                CommandScope::Entity(entity),
+                serde_json::to_vec(&command).unwrap(),
+            ),
+            ConflictStrategy::Merge,
+            CommandAuthor::new(author.clone(), CommandRole::Editor),
+            None,
+        )
+    }
+
+    fn apply_nudge(world: &mut World, entity: Entity, entry: &CommandEntry) {
+        let command: EntityTranslateCommand = serde_json::from_slice(&entry.payload.data).unwrap();
+        if let Some(position) = world.get_mut::<Position>(entity) {
+            for (axis, delta) in position.0.iter_mut().zip(command.delta) {
+                *axis += delta;
+            }
+        }
+    }
+
+    #[test]
+    fn grabbed_entity_moves_smoothly_under_150ms_latency() {
+        const FRAME: Duration = Duration::from_millis(10);
+        const LATENCY_FRAMES: u32 = 15;
+        const STEP: f32 = 0.01;
+
+        let mut registry = ReplicationRegistry::new();
+        registry.register::<Position>();
+        let client = AuthorId(7);
+        let mut host = World::new();
+        let crate_entity = host.spawn();
+        host.insert(crate_entity, Position([0.0; 3])).unwrap();
+        let handle = EntityHandle::from(crate_entity);
+
+        let mut sender = ReplicationSender::new(ReplicationStreamConfig::default());
+        let mut versions = VersionVector::new();
+        let mut receiver = ReplicationReceiver::new();
+        let mut prediction = ClientPrediction::<Position>::new(client.clone());
+        let mut mirror = World::new();
+        let mut replay = |world: &mut World, entity: Entity, entry: &CommandEntry| {
+            apply_nudge(world, entity, entry)
+        };
+
+        let mut to_host: VecDeque<(u32, CommandEntry)> = VecDeque::new();
+        let mut to_client: VecDeque<(u32, ReplicationMessage)> = VecDeque::new();
+        let mut observed = Vec::new();
+        let start = Instant::now();
+        let mut lamport = 0;
+        for frame in 0..160u32 {
+            let now = start + FRAME * frame;
+            let local = receiver.applier().local_entity(handle);
+            // The client drags the crate for 80 frames once it is mirrored.
+            if let Some(local) = local
+                && (20..100).contains(&frame)
+            {
+                lamport += 1;
+                let entry = nudge(&client, lamport, handle, STEP);
+                prediction.predict(&mut mirror, local, entry.clone(), &mut replay);
+                to_host.push_back((frame + LATENCY_FRAMES, entry));
+            }
+            if frame == 100 {
+                prediction.release(local.unwrap());
+            }
+
+            while to_host.front().is_some_and(|(due, _)| *due <= frame) {
+                let (_, entry) = to_host.pop_front().unwrap();
+                apply_nudge(&mut host, crate_entity, &entry);
+                versions.observe(entry.id.author(), entry.id.lamport());
+            }
+            sender.acknowledge_commands(versions.get(&client));
+            let outgoing = if frame == 0 {
+                sender.start(&registry, &host, now)
+            } else {
+                sender.tick(&registry, &host, now)
+            };
+            to_client.extend(
+                outgoing
+                    .into_iter()
+                    .map(|message| (frame + LATENCY_FRAMES, message)),
+            );
+
+            while to_client.front().is_some_and(|(due, _)| *due <= frame) {
+                let (_, message) = to_client.pop_front().unwrap();
+                prediction.receive(&mut receiver, &registry, &mut mirror, message, &mut replay);
+                sender.acknowledge(receiver.acknowledged());
+            }
+            if let Some(local) = receiver.applier().local_entity(handle) {
+                observed.push(mirror.get::<Position>(local).unwrap().0[0]);
+            }
+        }
+
+        // Every frame advances by at most one step: no rubber-banding back to the lagging
+        // host state and no catch-up jumps when acknowledgements arrive.
+        for pair in observed.windows(2) {
+            let step = pair[1] - pair[0];
+            assert!(
+                (-1e-5..=STEP + 1e-5).contains(&step),
+                "jump of {step} in {observed:?}"
+            );
+        }
+        let expected = STEP * 80.0;
+        let host_x = host.get::<Position>(crate_entity).unwrap().0[0];
+        assert!((host_x - expected).abs() < 1e-4);
+        assert!((observed.last().unwrap() - host_x).abs() < 1e-6);
+        assert_eq!(prediction.acknowledged(), 80);
+        assert_eq!(prediction.pending().count(), 0);
+        assert!(!prediction.is_predicting(receiver.applier().local_entity(handle).unwrap()));
+    }
+
+    #[test]
+    fn pending_commands_are_bounded() {
+        let author = AuthorId(7);
+        let mut world = World::new();
+        world.register_component::<Position>();
+        let entity = world.spawn();
+        world.insert(entity, Position([0.0; 3])).unwrap();
+        let handle = EntityHandle::from(entity);
+        let mut prediction = ClientPrediction::<Position>::new(author.clone());
+        let mut replay = |world: &mut World, entity: Entity, entry: &CommandEntry| {
+            apply_nudge(world, entity, entry)
+        };
+        let total = MAX_PENDING_COMMANDS as u64 + 10;
+        for lamport in 1..=total {
+            prediction.predict(
+                &mut world,
+                entity,
+                nudge(&author, lamport, handle, 1.0),
+                &mut replay,
+            );
+        }
+        assert_eq!(prediction.pending().count(), MAX_PENDING_COMMANDS);
+        assert_eq!(prediction.pending().next().unwrap().id.lamport(), 11);
+    }
+}
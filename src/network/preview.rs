@@ -0,0 +1,34 @@
+use super::EntityHandle;
+use super::command_log::AuthorId;
+use serde::{Deserialize, Serialize};
+
+/// Previews larger than this are dropped instead of being fragmented; they must fit a
+/// single datagram.
+pub const MAX_PREVIEW_PACKET_BYTES: usize = 1_024;
+
+/// Milliseconds a preview stays visible without a newer preview or the final commit.
+pub const PREVIEW_TIMEOUT_MS: u64 = 500;
+
+/// Provisional pose of an entity mid-drag. Previews travel unreliably and never enter
+/// the command log; the coalesced command that follows is authoritative.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PreviewPacket {
+    pub author: AuthorId,
+    /// Increases per author; receivers ignore previews older than the one shown.
+    pub sequence: u64,
+    pub entity: EntityHandle,
+    pub position: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+    pub timestamp_ms: u64,
+}
+
+impl PreviewPacket {
+    pub fn encode(&self) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(self)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+}
@@ -1,13 +1,26 @@
-use crate::ecs::World;
+use crate::ecs::{Entity, World};
+use crate::network::authority::AuthorityTable;
+use crate::network::command_log::AuthorId;
+use crate::network::network_id::{NetworkId, SharedEntityMap, lock_entity_map, network_handle};
 use crate::network::{ComponentDescriptor, ComponentDiff, ComponentKey, DiffPayload, EntityHandle};
-use serde::{Serialize, de::DeserializeOwned};
-use serde_json::to_vec;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use std::any::TypeId;
+use std::cmp::Reverse;
 use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
 
 const DEFAULT_CHUNK_LIMIT: usize = 16 * 1024;
 const SNAPSHOT_ENTRY_OVERHEAD: usize = 24;
 
+/// Compact binary encoding used for replicated component payloads.
+pub fn encode_component<T: Serialize>(component: &T) -> Result<Vec<u8>, bincode::Error> {
+    bincode::serialize(component)
+}
+
+pub fn decode_component<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, bincode::Error> {
+    bincode::deserialize(bytes)
+}
+
 /// Marker trait for ECS components that participate in network replication.
 pub trait ReplicatedComponent:
     crate::ecs::Component + Serialize + DeserializeOwned + Send + Sync + 'static
@@ -19,6 +32,85 @@ impl<T> ReplicatedComponent for T where
 {
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Reliability {
+    /// Resent until acknowledged.
+    #[default]
+    Reliable,
+    /// Updates are sent once and superseded by newer values; inserts and removals stay
+    /// reliable so the entity structure never diverges.
+    Unreliable,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Audience {
+    #[default]
+    Broadcast,
+    /// Only sent to the peer named by the entity's [`ReplicationOwner`].
+    OwnerOnly,
+}
+
+/// Peer a replicated entity belongs to, for [`Audience::OwnerOnly`] components. Kept in
+/// step with ownership by [`AuthorityTable::sync_components`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicationOwner(pub AuthorId);
+
+/// How often, how urgently and to whom a registered component is replicated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplicationPolicy {
+    /// Maximum sends per second; `None` diffs on every tick.
+    pub rate_hz: Option<f32>,
+    /// Higher priorities are sent first when a bandwidth budget is set.
+    pub priority: u8,
+    pub reliability: Reliability,
+    pub audience: Audience,
+}
+
+impl ReplicationPolicy {
+    /// Slowest rate [`Self::with_rate`] accepts; lower rates are raised to it.
+    pub const MIN_RATE_HZ: f32 = 1.0 / 3600.0;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Zero, negative, NaN and infinite rates diff on every tick.
+    pub fn with_rate(mut self, hz: f32) -> Self {
+        self.rate_hz = (hz > 0.0 && hz.is_finite()).then(|| hz.max(Self::MIN_RATE_HZ));
+        self
+    }
+
+    pub fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn unreliable(mut self) -> Self {
+        self.reliability = Reliability::Unreliable;
+        self
+    }
+
+    pub fn owner_only(mut self) -> Self {
+        self.audience = Audience::OwnerOnly;
+        self
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        self.rate_hz.map(|hz| Duration::from_secs_f32(1.0 / hz))
+    }
+}
+
+impl Default for ReplicationPolicy {
+    fn default() -> Self {
+        Self {
+            rate_hz: None,
+            priority: 128,
+            reliability: Reliability::Reliable,
+            audience: Audience::Broadcast,
+        }
+    }
+}
+
 struct ComponentPacket {
     entity: crate::ecs::Entity,
     bytes: Vec<u8>,
@@ -26,13 +118,26 @@ struct ComponentPacket {
 
 struct RegistryEntry {
     key: ComponentKey,
+    policy: ReplicationPolicy,
     dump: fn(&World) -> Vec<ComponentPacket>,
+    write: fn(&mut World, Entity, &[u8]) -> Result<(), bincode::Error>,
+    remove: fn(&mut World, Entity) -> bool,
+    contains: fn(&World, Entity) -> bool,
 }
 
 impl RegistryEntry {
     fn dump(&self, world: &World) -> Vec<ComponentPacket> {
         (self.dump)(world)
     }
+
+    fn visible_to(&self, world: &World, entity: Entity, recipient: Option<&AuthorId>) -> bool {
+        match self.policy.audience {
+            Audience::Broadcast => true,
+            Audience::OwnerOnly => world
+                .get::<ReplicationOwner>(entity)
+                .is_some_and(|owner| Some(&owner.0) == recipient),
+        }
+    }
 }
 
 /// Registry describing which ECS components should be replicated across the network.
@@ -48,8 +153,19 @@ impl ReplicationRegistry {
     }
 
     pub fn register<T: ReplicatedComponent>(&mut self) {
+        if !self.registered.contains(&TypeId::of::<T>()) {
+            self.register_with_policy::<T>(ReplicationPolicy::default());
+        }
+    }
+
+    /// Registers `T`, or replaces its policy if it is already registered.
+    pub fn register_with_policy<T: ReplicatedComponent>(&mut self, policy: ReplicationPolicy) {
         let type_id = TypeId::of::<T>();
         if self.registered.contains(&type_id) {
+            let key = ComponentKey::of::<T>();
+            if let Some(entry) = self.entries.iter_mut().find(|entry| entry.key == key) {
+                entry.policy = policy;
+            }
             return;
         }
 
@@ -59,17 +175,51 @@ impl ReplicationRegistry {
                 .into_iter()
                 .map(|(entity, component)| ComponentPacket {
                     entity,
-                    bytes: to_vec(component).expect("component serialization"),
+                    bytes: encode_component(component).expect("component serialization"),
                 })
                 .collect()
         }
 
+        fn write_component<T: ReplicatedComponent>(
+            world: &mut World,
+            entity: Entity,
+            bytes: &[u8],
+        ) -> Result<(), bincode::Error> {
+            let component: T = decode_component(bytes)?;
+            world
+                .insert(entity, component)
+                .expect("replicated entity is alive");
+            Ok(())
+        }
+
+        fn remove_component<T: ReplicatedComponent>(world: &mut World, entity: Entity) -> bool {
+            world.remove::<T>(entity).is_some()
+        }
+
+        fn contains_component<T: ReplicatedComponent>(world: &World, entity: Entity) -> bool {
+            world.get::<T>(entity).is_some()
+        }
+
         self.entries.push(RegistryEntry {
             key: ComponentKey::of::<T>(),
+            policy,
             dump: dump_components::<T>,
+            write: write_component::<T>,
+            remove: remove_component::<T>,
+            contains: contains_component::<T>,
         });
         self.registered.insert(type_id);
     }
+
+    pub fn policy(&self, key: &ComponentKey) -> Option<ReplicationPolicy> {
+        self.entry(key).map(|entry| entry.policy)
+    }
+
+    fn entry(&self, key: &ComponentKey) -> Option<&RegistryEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.key.type_hash == key.type_hash)
+    }
 }
 
 /// Describes a serialized component instance inside a snapshot chunk.
@@ -97,6 +247,12 @@ impl WorldSnapshot {
         Self { chunks: Vec::new() }
     }
 
+    /// Reassembles a snapshot from chunks received separately.
+    pub fn from_chunks(mut chunks: Vec<WorldSnapshotChunk>) -> Self {
+        chunks.sort_by_key(|chunk| chunk.chunk_index);
+        Self { chunks }
+    }
+
     pub fn chunks(&self) -> &[WorldSnapshotChunk] {
         &self.chunks
     }
@@ -113,6 +269,7 @@ impl WorldSnapshot {
 pub struct WorldSnapshotBuilder<'a> {
     registry: &'a ReplicationRegistry,
     max_chunk_bytes: usize,
+    recipient: Option<AuthorId>,
 }
 
 impl<'a> WorldSnapshotBuilder<'a> {
@@ -120,6 +277,7 @@ impl<'a> WorldSnapshotBuilder<'a> {
         Self {
             registry,
             max_chunk_bytes: DEFAULT_CHUNK_LIMIT,
+            recipient: None,
         }
     }
 
@@ -128,13 +286,33 @@ impl<'a> WorldSnapshotBuilder<'a> {
         self
     }
 
+    /// Includes [`Audience::OwnerOnly`] components of entities owned by `recipient`.
+    pub fn for_recipient(mut self, recipient: Option<AuthorId>) -> Self {
+        self.recipient = recipient;
+        self
+    }
+
     pub fn build(&self, world: &World) -> WorldSnapshot {
+        self.build_relevant(world, &|_| true)
+    }
+
+    /// Builds a snapshot of the entities `relevant` accepts.
+    pub fn build_relevant(
+        &self,
+        world: &World,
+        relevant: &dyn Fn(Entity) -> bool,
+    ) -> WorldSnapshot {
         let mut serialized = Vec::new();
         for entry in &self.registry.entries {
             for packet in entry.dump(world) {
+                if !relevant(packet.entity)
+                    || !entry.visible_to(world, packet.entity, self.recipient.as_ref())
+                {
+                    continue;
+                }
                 serialized.push(SnapshotComponent {
                     component: entry.key.clone(),
-                    entity: EntityHandle::from(packet.entity),
+                    entity: network_handle(world, packet.entity),
                     bytes: packet.bytes,
                 });
             }
@@ -194,76 +372,295 @@ impl ComponentEntryKey {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct ReplicationDelta {
     pub descriptors: Vec<ComponentDescriptor>,
+    /// Entities that gained their first replicated component since the last diff.
+    pub spawned: Vec<EntityHandle>,
     pub diffs: Vec<ComponentDiff>,
+    /// Previously replicated entities that are no longer alive or no longer relevant.
+    pub despawned: Vec<EntityHandle>,
+    /// Updates of [`Reliability::Unreliable`] components, sent apart from the rest.
+    pub unreliable: Vec<ComponentDiff>,
 }
 
 impl ReplicationDelta {
     pub fn is_empty(&self) -> bool {
         self.diffs.is_empty()
+            && self.spawned.is_empty()
+            && self.despawned.is_empty()
+            && self.unreliable.is_empty()
     }
 }
 
+struct PendingDiff {
+    key: ComponentEntryKey,
+    bytes: Vec<u8>,
+    insert: bool,
+    priority: u8,
+    reliability: Reliability,
+}
+
+/// Diffs a world against what was last sent to one peer, honouring each component's
+/// [`ReplicationPolicy`].
 pub struct DeltaTracker {
     last_state: HashMap<ComponentEntryKey, Vec<u8>>,
+    last_entities: HashSet<EntityHandle>,
+    /// Local entities behind the handles in `last_entities`.
+    locals: HashMap<EntityHandle, Entity>,
     advertised: HashSet<ComponentKey>,
+    last_sent: HashMap<u64, Instant>,
+    /// Values last sent unreliably; each is resent reliably once it stops changing.
+    unconfirmed: HashSet<ComponentEntryKey>,
+    /// How many diffs each change has been held back by the budget.
+    deferred: HashMap<ComponentEntryKey, u32>,
+    /// Newest recipient command the world reflects, per [`Self::observe_commands`].
+    commands_observed: u64,
+    /// Per component type, the observed command as of its last send with nothing held
+    /// back.
+    commands_sent: HashMap<u64, u64>,
+    command_ack: u64,
+    recipient: Option<AuthorId>,
+    budget: Option<usize>,
 }
 
 impl DeltaTracker {
     pub fn new() -> Self {
         Self {
             last_state: HashMap::new(),
+            last_entities: HashSet::new(),
+            locals: HashMap::new(),
             advertised: HashSet::new(),
+            last_sent: HashMap::new(),
+            unconfirmed: HashSet::new(),
+            deferred: HashMap::new(),
+            commands_observed: 0,
+            commands_sent: HashMap::new(),
+            command_ack: 0,
+            recipient: None,
+            budget: None,
+        }
+    }
+
+    /// Sends [`Audience::OwnerOnly`] components of entities owned by `recipient`; without
+    /// a recipient they are never sent.
+    pub fn with_recipient(mut self, recipient: AuthorId) -> Self {
+        self.recipient = Some(recipient);
+        self
+    }
+
+    /// Caps the payload bytes of one diff. Changes that do not fit, lowest priority
+    /// first, are deferred to a later diff; at least one change is always sent. Each
+    /// deferral raises a change's priority by one, so deferred changes take their turn.
+    pub fn with_bandwidth_budget(mut self, bytes: usize) -> Self {
+        self.budget = Some(bytes);
+        self
+    }
+
+    pub fn recipient(&self) -> Option<&AuthorId> {
+        self.recipient.as_ref()
+    }
+
+    /// The world reflects the recipient's commands up to `lamport`.
+    pub fn observe_commands(&mut self, lamport: u64) {
+        self.commands_observed = self.commands_observed.max(lamport);
+    }
+
+    /// Newest observed command whose effects every component has been sent with. Values
+    /// held back by a rate, the budget or an unreliable send hold it back too.
+    pub fn command_ack(&self) -> u64 {
+        self.command_ack
+    }
+
+    /// Records that the whole relevant world went out, as in a snapshot.
+    pub fn mark_sent(&mut self, registry: &ReplicationRegistry) {
+        for entry in &registry.entries {
+            self.commands_sent
+                .insert(entry.key.type_hash, self.commands_observed);
         }
+        self.command_ack = self.command_ack.max(self.commands_observed);
+    }
+
+    /// Forgets what was sent, keeping the recipient and budget.
+    pub fn reset(&mut self) {
+        self.last_state.clear();
+        self.last_entities.clear();
+        self.locals.clear();
+        self.advertised.clear();
+        self.last_sent.clear();
+        self.unconfirmed.clear();
+        self.deferred.clear();
+        self.commands_sent.clear();
     }
 
     pub fn diff(&mut self, registry: &ReplicationRegistry, world: &World) -> ReplicationDelta {
-        let mut diffs = Vec::new();
+        self.diff_at(registry, world, Instant::now())
+    }
+
+    pub fn diff_at(
+        &mut self,
+        registry: &ReplicationRegistry,
+        world: &World,
+        now: Instant,
+    ) -> ReplicationDelta {
+        self.diff_relevant(registry, world, now, &|_| true)
+    }
+
+    /// Diffs only the entities `relevant` accepts. Entities leaving relevance are
+    /// reported as removed and despawned; entering it, as spawned and inserted.
+    pub fn diff_relevant(
+        &mut self,
+        registry: &ReplicationRegistry,
+        world: &World,
+        now: Instant,
+        relevant: &dyn Fn(Entity) -> bool,
+    ) -> ReplicationDelta {
         let mut descriptors = Vec::new();
+        let mut pending = Vec::new();
         let mut next_state: HashMap<ComponentEntryKey, Vec<u8>> = HashMap::new();
+        let mut locals = HashMap::new();
+        let mut due_types = Vec::new();
 
         for entry in &registry.entries {
-            let packets = entry.dump(world);
-            for packet in packets {
-                let handle = EntityHandle::from(packet.entity);
+            let due = match (
+                entry.policy.interval(),
+                self.last_sent.get(&entry.key.type_hash),
+            ) {
+                (Some(interval), Some(last)) => now.duration_since(*last) >= interval,
+                _ => true,
+            };
+            if !due {
+                // Hold the last sent values so nothing reads as removed until the next send.
+                for (key, bytes) in &self.last_state {
+                    let Some(&entity) = self.locals.get(&key.entity) else {
+                        continue;
+                    };
+                    if key.component == entry.key && world.contains(entity) && relevant(entity) {
+                        next_state.insert(key.clone(), bytes.clone());
+                        locals.insert(key.entity, entity);
+                    }
+                }
+                continue;
+            }
+            self.last_sent.insert(entry.key.type_hash, now);
+            due_types.push(entry.key.type_hash);
+
+            for packet in entry.dump(world) {
+                if !relevant(packet.entity)
+                    || !entry.visible_to(world, packet.entity, self.recipient.as_ref())
+                {
+                    continue;
+                }
+                let handle = network_handle(world, packet.entity);
+                locals.insert(handle, packet.entity);
                 let key = ComponentEntryKey::new(entry.key.clone(), handle);
                 let bytes = packet.bytes;
 
                 match self.last_state.get(&key) {
                     Some(previous) if previous == &bytes => {
-                        next_state.insert(key, bytes);
-                    }
-                    Some(_) => {
-                        diffs.push(ComponentDiff {
-                            entity: handle,
-                            component: entry.key.clone(),
-                            payload: DiffPayload::Update {
+                        // A lost datagram would otherwise leave the peer on a stale value.
+                        if self.unconfirmed.contains(&key) {
+                            pending.push(PendingDiff {
+                                key: key.clone(),
                                 bytes: bytes.clone(),
-                            },
-                        });
+                                insert: false,
+                                priority: entry.policy.priority,
+                                reliability: Reliability::Reliable,
+                            });
+                        }
                         next_state.insert(key, bytes);
                     }
-                    None => {
-                        if self.advertised.insert(entry.key.clone()) {
+                    previous => {
+                        if previous.is_none() && self.advertised.insert(entry.key.clone()) {
                             descriptors.push(ComponentDescriptor {
                                 key: entry.key.clone(),
                             });
                         }
-                        diffs.push(ComponentDiff {
-                            entity: handle,
-                            component: entry.key.clone(),
-                            payload: DiffPayload::Insert {
-                                bytes: bytes.clone(),
-                            },
+                        // Deferred updates keep the last sent value until they go out.
+                        if let Some(previous) = previous {
+                            next_state.insert(key.clone(), previous.clone());
+                        }
+                        pending.push(PendingDiff {
+                            key,
+                            bytes,
+                            insert: previous.is_none(),
+                            priority: entry.policy.priority,
+                            reliability: entry.policy.reliability,
                         });
-                        next_state.insert(key, bytes);
                     }
                 }
             }
         }
 
+        let mut diffs = Vec::new();
+        let mut unreliable = Vec::new();
+        let mut used = 0usize;
+        let mut deferred = std::mem::take(&mut self.deferred);
+        pending.sort_by_key(|diff| {
+            let waited = deferred.get(&diff.key).copied().unwrap_or(0);
+            Reverse(u32::from(diff.priority) + waited)
+        });
+        for diff in pending {
+            let cost = diff.bytes.len() + SNAPSHOT_ENTRY_OVERHEAD;
+            if self
+                .budget
+                .is_some_and(|budget| used > 0 && used + cost > budget)
+            {
+                *deferred.entry(diff.key).or_default() += 1;
+                continue;
+            }
+            used += cost;
+            deferred.remove(&diff.key);
+            let component_diff = ComponentDiff {
+                entity: diff.key.entity,
+                component: diff.key.component.clone(),
+                payload: if diff.insert {
+                    DiffPayload::Insert {
+                        bytes: diff.bytes.clone(),
+                    }
+                } else {
+                    DiffPayload::Update {
+                        bytes: diff.bytes.clone(),
+                    }
+                },
+            };
+            if !diff.insert && diff.reliability == Reliability::Unreliable {
+                unreliable.push(component_diff);
+                self.unconfirmed.insert(diff.key.clone());
+            } else {
+                diffs.push(component_diff);
+                self.unconfirmed.remove(&diff.key);
+            }
+            next_state.insert(diff.key, diff.bytes);
+        }
+        deferred.retain(|key, _| locals.contains_key(&key.entity));
+        self.deferred = deferred;
+        self.unconfirmed.retain(|key| next_state.contains_key(key));
+
+        let held: HashSet<u64> = self
+            .deferred
+            .keys()
+            .chain(&self.unconfirmed)
+            .map(|key| key.component.type_hash)
+            .collect();
+        for type_hash in due_types {
+            if !held.contains(&type_hash) {
+                self.commands_sent.insert(type_hash, self.commands_observed);
+            }
+        }
+        let sent = registry
+            .entries
+            .iter()
+            .map(|entry| {
+                self.commands_sent
+                    .get(&entry.key.type_hash)
+                    .copied()
+                    .unwrap_or(0)
+            })
+            .min()
+            .unwrap_or(self.commands_observed);
+        self.command_ack = self.command_ack.max(sent);
+
         for (key, _) in self.last_state.iter() {
             if !next_state.contains_key(key) {
                 diffs.push(ComponentDiff {
@@ -274,9 +671,33 @@ impl DeltaTracker {
             }
         }
 
-        self.last_state = next_state;
+        let entities: HashSet<EntityHandle> = next_state.keys().map(|key| key.entity).collect();
+        let mut spawned: Vec<EntityHandle> =
+            entities.difference(&self.last_entities).copied().collect();
+        let mut despawned: Vec<EntityHandle> = self
+            .last_entities
+            .difference(&entities)
+            .filter(|handle| match self.locals.get(*handle) {
+                Some(&entity) => !world.contains(entity) || !relevant(entity),
+                None => true,
+            })
+            .copied()
+            .collect();
+        spawned.sort_by_key(|handle| (handle.index, handle.generation));
+        despawned.sort_by_key(|handle| (handle.index, handle.generation));
 
-        ReplicationDelta { descriptors, diffs }
+        self.last_state = next_state;
+        self.last_entities = entities;
+        locals.retain(|handle, _| self.last_entities.contains(handle));
+        self.locals = locals;
+
+        ReplicationDelta {
+            descriptors,
+            spawned,
+            diffs,
+            despawned,
+            unreliable,
+        }
     }
 }
 
@@ -286,6 +707,372 @@ impl Default for DeltaTracker {
     }
 }
 
+/// Counts of what a [`ReplicationApplier`] call changed in the local world.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ApplyStats {
+    pub spawned: usize,
+    pub despawned: usize,
+    pub inserted: usize,
+    pub updated: usize,
+    pub removed: usize,
+    /// Diffs for unregistered components or with undecodable bytes.
+    pub skipped: usize,
+    /// Diffs dropped because their sender lacks authority over the entity or names it
+    /// by an id outside the sender's prefix.
+    pub rejected: usize,
+}
+
+impl ApplyStats {
+    pub fn merge(&mut self, other: ApplyStats) {
+        self.spawned += other.spawned;
+        self.despawned += other.despawned;
+        self.inserted += other.inserted;
+        self.updated += other.updated;
+        self.removed += other.removed;
+        self.skipped += other.skipped;
+        self.rejected += other.rejected;
+    }
+}
+
+/// Mirrors a remote world into a local one. Remote entities get fresh local entities
+/// tagged with their [`NetworkId`], so the mirror can coexist with entities the client
+/// spawned itself.
+#[derive(Debug, Default)]
+pub struct ReplicationApplier {
+    entities: SharedEntityMap,
+    source_prefix: Option<u32>,
+    authority: Option<(AuthorityTable, AuthorId)>,
+}
+
+impl ReplicationApplier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mirrors into `entities`, shared with the command pipeline and a
+    /// [`crate::network::network_id::NetworkIdAllocator`], so entities this peer spawned
+    /// are updated in place when they come back.
+    pub fn with_entity_map(mut self, entities: SharedEntityMap) -> Self {
+        self.entities = entities;
+        self
+    }
+
+    /// Only admits ids allocated under `prefix`, the one the host assigned the sending
+    /// peer. Prefix 0 is the host's, whose stream may name any entity.
+    pub fn with_source_prefix(mut self, prefix: u32) -> Self {
+        self.source_prefix = Some(prefix);
+        self
+    }
+
+    /// Drops changes to entities `source` has no authority over in `table`.
+    pub fn with_authority(mut self, table: AuthorityTable, source: AuthorId) -> Self {
+        self.set_authority(table, source);
+        self
+    }
+
+    /// Replaces the authority table as ownership moves; see [`Self::with_authority`].
+    pub fn set_authority(&mut self, table: AuthorityTable, source: AuthorId) {
+        self.authority = Some((table, source));
+    }
+
+    pub fn entity_map(&self) -> &SharedEntityMap {
+        &self.entities
+    }
+
+    pub fn local_entity(&self, remote: EntityHandle) -> Option<Entity> {
+        lock_entity_map(&self.entities).entity(remote.into())
+    }
+
+    pub fn remote_entity(&self, local: Entity) -> Option<EntityHandle> {
+        lock_entity_map(&self.entities)
+            .id(local)
+            .map(EntityHandle::from)
+    }
+
+    /// Remote handles and the local entities mirroring them.
+    pub fn entities(&self) -> Vec<(EntityHandle, Entity)> {
+        lock_entity_map(&self.entities)
+            .iter()
+            .map(|(remote, local)| (remote.into(), local))
+            .collect()
+    }
+
+    pub fn mapped_entities(&self) -> usize {
+        lock_entity_map(&self.entities).len()
+    }
+
+    /// Whether the sender may name `remote`.
+    fn admits(&self, remote: EntityHandle) -> bool {
+        match self.source_prefix {
+            None | Some(0) => true,
+            Some(prefix) => {
+                let id = NetworkId::from(remote);
+                id.is_allocated() && id.prefix() == prefix
+            }
+        }
+    }
+
+    fn reject(&self, remote: EntityHandle, stats: &mut ApplyStats) -> bool {
+        if self.admits(remote) {
+            return false;
+        }
+        log::debug!(
+            "[replication] dropping {remote:?} outside prefix {:?}",
+            self.source_prefix
+        );
+        stats.rejected += 1;
+        true
+    }
+
+    /// Whether the authority table forbids the sender to change `remote`'s components.
+    fn denied(
+        &self,
+        remote: EntityHandle,
+        component: &ComponentKey,
+        stats: &mut ApplyStats,
+    ) -> bool {
+        let Some((table, source)) = &self.authority else {
+            return false;
+        };
+        if table.may_write(&remote, source) {
+            return false;
+        }
+        log::debug!(
+            "[replication] dropping {} for {remote:?} from {source:?} without authority",
+            component.type_name
+        );
+        stats.rejected += 1;
+        true
+    }
+
+    /// Replaces the mirror with `snapshot`: mirrored entities and components missing from
+    /// it are removed.
+    pub fn apply_snapshot(
+        &mut self,
+        registry: &ReplicationRegistry,
+        world: &mut World,
+        snapshot: &WorldSnapshot,
+    ) -> ApplyStats {
+        let mut stats = ApplyStats::default();
+        lock_entity_map(&self.entities).unbind_despawned(world);
+        let mut present: HashMap<EntityHandle, HashSet<u64>> = HashMap::new();
+        for component in snapshot.chunks().iter().flat_map(|chunk| &chunk.components) {
+            if self.reject(component.entity, &mut stats) {
+                continue;
+            }
+            present
+                .entry(component.entity)
+                .or_default()
+                .insert(component.component.type_hash);
+            let local = self.ensure_entity(world, component.entity, &mut stats);
+            if self.denied(component.entity, &component.component, &mut stats) {
+                continue;
+            }
+            if self.write(
+                registry,
+                world,
+                local,
+                &component.component,
+                &component.bytes,
+            ) {
+                stats.inserted += 1;
+            } else {
+                stats.skipped += 1;
+            }
+        }
+
+        // Entities this peer spawned may not have reached the host yet.
+        let stale: Vec<EntityHandle> = {
+            let entities = lock_entity_map(&self.entities);
+            entities
+                .iter()
+                .filter(|(id, _)| !entities.is_pending(*id))
+                .map(|(id, _)| EntityHandle::from(id))
+                .filter(|remote| self.admits(*remote) && !present.contains_key(remote))
+                .collect()
+        };
+        for remote in stale {
+            self.despawn(world, remote, &mut stats);
+        }
+        for (remote, hashes) in &present {
+            let Some(local) = self.local_entity(*remote) else {
+                continue;
+            };
+            for entry in &registry.entries {
+                if !hashes.contains(&entry.key.type_hash) && (entry.remove)(world, local) {
+                    stats.removed += 1;
+                }
+            }
+        }
+        stats
+    }
+
+    pub fn apply_delta(
+        &mut self,
+        registry: &ReplicationRegistry,
+        world: &mut World,
+        delta: &ReplicationDelta,
+    ) -> ApplyStats {
+        let mut stats = ApplyStats::default();
+        for remote in &delta.spawned {
+            if !self.reject(*remote, &mut stats) {
+                self.ensure_entity(world, *remote, &mut stats);
+            }
+        }
+        self.apply_diffs_into(registry, world, &delta.diffs, &mut stats);
+        for remote in &delta.despawned {
+            if !self.reject(*remote, &mut stats) {
+                self.despawn(world, *remote, &mut stats);
+            }
+        }
+        stats
+    }
+
+    /// Applies unreliable updates to components the mirror still has; late updates for
+    /// removed components or unknown entities are skipped rather than resurrecting them.
+    pub fn apply_unreliable(
+        &mut self,
+        registry: &ReplicationRegistry,
+        world: &mut World,
+        diffs: &[ComponentDiff],
+    ) -> ApplyStats {
+        let mut stats = ApplyStats::default();
+        for diff in diffs {
+            if self.reject(diff.entity, &mut stats)
+                || self.denied(diff.entity, &diff.component, &mut stats)
+            {
+                continue;
+            }
+            let DiffPayload::Update { bytes } = &diff.payload else {
+                stats.skipped += 1;
+                continue;
+            };
+            let present = self.local_entity(diff.entity).filter(|local| {
+                registry
+                    .entry(&diff.component)
+                    .is_some_and(|entry| (entry.contains)(world, *local))
+            });
+            match present {
+                Some(local) if self.write(registry, world, local, &diff.component, bytes) => {
+                    stats.updated += 1
+                }
+                _ => stats.skipped += 1,
+            }
+        }
+        stats
+    }
+
+    /// Applies diffs without entity lifecycle, e.g. from a [`crate::network::ChangeSet`].
+    /// Inserts and updates for unknown entities spawn them.
+    pub fn apply_diffs(
+        &mut self,
+        registry: &ReplicationRegistry,
+        world: &mut World,
+        diffs: &[ComponentDiff],
+    ) -> ApplyStats {
+        let mut stats = ApplyStats::default();
+        self.apply_diffs_into(registry, world, diffs, &mut stats);
+        stats
+    }
+
+    fn apply_diffs_into(
+        &mut self,
+        registry: &ReplicationRegistry,
+        world: &mut World,
+        diffs: &[ComponentDiff],
+        stats: &mut ApplyStats,
+    ) {
+        for diff in diffs {
+            if self.reject(diff.entity, stats) || self.denied(diff.entity, &diff.component, stats) {
+                continue;
+            }
+            match &diff.payload {
+                DiffPayload::Insert { bytes } | DiffPayload::Update { bytes } => {
+                    let local = self.ensure_entity(world, diff.entity, stats);
+                    if !self.write(registry, world, local, &diff.component, bytes) {
+                        stats.skipped += 1;
+                    } else if matches!(diff.payload, DiffPayload::Insert { .. }) {
+                        stats.inserted += 1;
+                    } else {
+                        stats.updated += 1;
+                    }
+                }
+                DiffPayload::Remove => {
+                    let Some(local) = self.local_entity(diff.entity) else {
+                        continue;
+                    };
+                    match registry.entry(&diff.component) {
+                        Some(entry) => {
+                            if (entry.remove)(world, local) {
+                                stats.removed += 1;
+                            }
+                        }
+                        None => stats.skipped += 1,
+                    }
+                }
+                // Patches need the baseline a `ReplicationReceiver` keeps.
+                DiffPayload::Delta { .. } => stats.skipped += 1,
+            }
+        }
+    }
+
+    fn write(
+        &self,
+        registry: &ReplicationRegistry,
+        world: &mut World,
+        local: Entity,
+        component: &ComponentKey,
+        bytes: &[u8],
+    ) -> bool {
+        let Some(entry) = registry.entry(component) else {
+            log::warn!(
+                "[replication] skipping unregistered component {}",
+                component.type_name
+            );
+            return false;
+        };
+        match (entry.write)(world, local, bytes) {
+            Ok(()) => true,
+            Err(err) => {
+                log::warn!(
+                    "[replication] failed to decode {} for {local:?}: {err}",
+                    component.type_name
+                );
+                false
+            }
+        }
+    }
+
+    fn ensure_entity(
+        &mut self,
+        world: &mut World,
+        remote: EntityHandle,
+        stats: &mut ApplyStats,
+    ) -> Entity {
+        if let Some(local) = self.local_entity(remote)
+            && world.contains(local)
+        {
+            return local;
+        }
+        let local = world.spawn();
+        let _ = world.insert(local, NetworkId::from(remote));
+        lock_entity_map(&self.entities).insert(remote.into(), local);
+        stats.spawned += 1;
+        local
+    }
+
+    fn despawn(&mut self, world: &mut World, remote: EntityHandle, stats: &mut ApplyStats) {
+        let local = lock_entity_map(&self.entities).remove_id(remote.into());
+        if let Some(local) = local
+            && world.despawn(local).is_ok()
+        {
+            stats.despawned += 1;
+        }
+    }
+}
+
+crate::register_component_types!(ReplicationOwner);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -348,7 +1135,7 @@ mod tests {
         assert_eq!(first.diffs.len(), 1);
         match &first.diffs[0].payload {
             DiffPayload::Insert { bytes } => {
-                let decoded: TestComponent = serde_json::from_slice(bytes).expect("decode");
+                let decoded: TestComponent = decode_component(bytes).expect("decode");
                 assert_eq!(decoded.value, 1);
             }
             other => panic!("expected insert, got {other:?}"),
@@ -363,7 +1150,7 @@ mod tests {
         assert_eq!(second.diffs.len(), 1);
         match &second.diffs[0].payload {
             DiffPayload::Update { bytes } => {
-                let decoded: TestComponent = serde_json::from_slice(bytes).expect("decode");
+                let decoded: TestComponent = decode_component(bytes).expect("decode");
                 assert_eq!(decoded.value, 5);
             }
             other => panic!("expected update, got {other:?}"),
@@ -587,4 +1374,178 @@ mod tests {
             .collect();
         assert_eq!(removes.len(), 2);
     }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+    struct Label(String);
+
+    #[test]
+    fn applier_mirrors_host_world_through_deltas_and_snapshots() {
+        let mut registry = setup_registry();
+        registry.register::<Label>();
+        let mut host = build_world();
+        let first = host.spawn();
+        let second = host.spawn();
+        host.insert(first, TestComponent { value: 1 }).unwrap();
+        host.insert(first, Label("first".into())).unwrap();
+        host.insert(second, TestComponent { value: 2 }).unwrap();
+
+        // The client owns an entity of its own that the mirror must leave alone.
+        let mut client = build_world();
+        let own = client.spawn();
+        client.insert(own, TestComponent { value: 99 }).unwrap();
+
+        let mut tracker = DeltaTracker::new();
+        let mut applier = ReplicationApplier::new();
+        let delta = tracker.diff(&registry, &host);
+        assert_eq!(delta.spawned.len(), 2);
+        let stats = applier.apply_delta(&registry, &mut client, &delta);
+        assert_eq!((stats.spawned, stats.inserted), (2, 3));
+
+        let mirror = |applier: &ReplicationApplier, entity: Entity| {
+            applier
+                .local_entity(EntityHandle::from(entity))
+                .expect("mirrored")
+        };
+        let local_first = mirror(&applier, first);
+        assert_ne!(local_first, own);
+        assert_eq!(
+            client.get::<Label>(local_first),
+            Some(&Label("first".into()))
+        );
+
+        host.get_mut::<TestComponent>(first).unwrap().value = 10;
+        host.remove::<Label>(first);
+        host.despawn(second).unwrap();
+        let stats = applier.apply_delta(&registry, &mut client, &tracker.diff(&registry, &host));
+        assert_eq!((stats.updated, stats.removed, stats.despawned), (1, 2, 1));
+        assert_eq!(
+            client.get::<TestComponent>(local_first),
+            Some(&TestComponent { value: 10 })
+        );
+        assert!(client.get::<Label>(local_first).is_none());
+        assert_eq!(applier.mapped_entities(), 1);
+        assert_eq!(client.get::<TestComponent>(own).unwrap().value, 99);
+
+        // A late joiner starts from a snapshot; a resync drops what the host no longer has.
+        let third = host.spawn();
+        host.insert(third, Label("third".into())).unwrap();
+        let snapshot = WorldSnapshotBuilder::new(&registry).build(&host);
+        let mut late = build_world();
+        let mut late_applier = ReplicationApplier::new();
+        let stats = late_applier.apply_snapshot(&registry, &mut late, &snapshot);
+        assert_eq!((stats.spawned, stats.inserted), (2, 2));
+
+        host.despawn(third).unwrap();
+        let resync = WorldSnapshotBuilder::new(&registry).build(&host);
+        let stats = late_applier.apply_snapshot(&registry, &mut late, &resync);
+        assert_eq!(stats.despawned, 1);
+        assert_eq!(late.component_entries::<Label>().len(), 0);
+        assert_eq!(late.component_entries::<TestComponent>().len(), 1);
+    }
+
+    #[test]
+    fn tracker_honours_rate_priority_reliability_and_audience() {
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct HeadPose(u32);
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Telemetry(u32);
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Inventory(u32);
+
+        let mut registry = ReplicationRegistry::new();
+        registry.register_with_policy::<HeadPose>(
+            ReplicationPolicy::new()
+                .with_rate(30.0)
+                .with_priority(200)
+                .unreliable(),
+        );
+        registry.register_with_policy::<Telemetry>(
+            ReplicationPolicy::new().with_rate(2.0).with_priority(10),
+        );
+        registry.register_with_policy::<Inventory>(ReplicationPolicy::new().owner_only());
+        let mut world = World::new();
+        let player = world.spawn();
+        world.insert(player, HeadPose(0)).unwrap();
+        world.insert(player, Telemetry(0)).unwrap();
+        world.insert(player, Inventory(5)).unwrap();
+        world.insert(player, ReplicationOwner(AuthorId(7))).unwrap();
+
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut owner = DeltaTracker::new().with_recipient(AuthorId(7));
+        let mut other = DeltaTracker::new().with_recipient(AuthorId(8));
+        assert_eq!(owner.diff_at(&registry, &world, at(0)).diffs.len(), 3);
+        assert_eq!(other.diff_at(&registry, &world, at(0)).diffs.len(), 2);
+
+        world.get_mut::<HeadPose>(player).unwrap().0 = 1;
+        world.get_mut::<Telemetry>(player).unwrap().0 = 1;
+        assert!(owner.diff_at(&registry, &world, at(10)).is_empty());
+        let pose = owner.diff_at(&registry, &world, at(50));
+        assert!(pose.diffs.is_empty());
+        assert_eq!(pose.unreliable.len(), 1);
+        assert_eq!(pose.unreliable[0].component, ComponentKey::of::<HeadPose>());
+        // Once the pose settles its last value is resent reliably, and only once.
+        let settled = owner.diff_at(&registry, &world, at(100));
+        assert!(settled.unreliable.is_empty());
+        assert_eq!(settled.diffs.len(), 1);
+        assert_eq!(settled.diffs[0].component, ComponentKey::of::<HeadPose>());
+        assert!(owner.diff_at(&registry, &world, at(150)).is_empty());
+        let telemetry = owner.diff_at(&registry, &world, at(600));
+        assert_eq!(telemetry.diffs.len(), 1);
+        assert_eq!(
+            telemetry.diffs[0].component,
+            ComponentKey::of::<Telemetry>()
+        );
+        assert!(telemetry.unreliable.is_empty());
+
+        let mut constrained = DeltaTracker::new()
+            .with_recipient(AuthorId(7))
+            .with_bandwidth_budget(1);
+        let order: Vec<ComponentKey> = (0..3)
+            .map(|tick| {
+                let delta = constrained.diff_at(&registry, &world, at(tick * 1000));
+                assert_eq!(delta.diffs.len(), 1);
+                delta.diffs[0].component.clone()
+            })
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                ComponentKey::of::<HeadPose>(),
+                ComponentKey::of::<Inventory>(),
+                ComponentKey::of::<Telemetry>(),
+            ]
+        );
+
+        // Changes held back by the budget overtake ones that keep winning on priority.
+        let mut starved = Vec::new();
+        for tick in 3..200 {
+            world.get_mut::<HeadPose>(player).unwrap().0 = tick;
+            world.get_mut::<Inventory>(player).unwrap().0 = tick;
+            world.get_mut::<Telemetry>(player).unwrap().0 = tick;
+            let delta = constrained.diff_at(&registry, &world, at(u64::from(tick) * 1000));
+            starved.extend(
+                delta
+                    .diffs
+                    .iter()
+                    .chain(&delta.unreliable)
+                    .map(|diff| diff.component.clone()),
+            );
+        }
+        assert!(starved.contains(&ComponentKey::of::<Telemetry>()));
+
+        for hz in [f32::MIN_POSITIVE, 1e-30, f32::INFINITY, f32::NAN, -1.0] {
+            let policy = ReplicationPolicy::new().with_rate(hz);
+            assert!(
+                policy
+                    .rate_hz
+                    .is_none_or(|rate| rate >= ReplicationPolicy::MIN_RATE_HZ)
+            );
+            let mut registry = ReplicationRegistry::new();
+            registry.register_with_policy::<Telemetry>(policy);
+            let mut tracker = DeltaTracker::new();
+            tracker.diff_at(&registry, &world, start);
+            tracker.diff_at(&registry, &world, at(1));
+        }
+    }
 }
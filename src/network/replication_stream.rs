@@ -0,0 +1,976 @@
+//! Reliable, ordered delivery of world replication over a transport that may drop or
+//! reorder messages. A peer starts with the host's snapshot, then applies per-tick
+//! deltas strictly in sequence; anything unacknowledged is resent.
+//!
+//! Both ends remember the last reliably sent value of every component, so updates can
+//! travel as [`DiffPayload::Delta`] patches against it.
+
+use crate::ecs::{Entity, World};
+use crate::network::authority::AuthorityTable;
+use crate::network::command_log::{AuthorId, VersionVector};
+use crate::network::current_time_millis;
+use crate::network::delta_encoding::{apply_patch, encode_patch};
+use crate::network::interest::InterestManager;
+use crate::network::network_id::SharedEntityMap;
+use crate::network::replication::{
+    ApplyStats, DeltaTracker, ReplicationApplier, ReplicationDelta, ReplicationRegistry,
+    WorldSnapshot, WorldSnapshotBuilder, WorldSnapshotChunk,
+};
+use crate::network::{ComponentDiff, DiffPayload, EntityHandle};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone)]
+pub enum ReplicationBody {
+    Snapshot(WorldSnapshotChunk),
+    Delta(ReplicationDelta),
+    /// Updates that are never resent; `tick` orders them among themselves.
+    Unreliable {
+        tick: u64,
+        diffs: Vec<ComponentDiff>,
+    },
+}
+
+impl ReplicationBody {
+    pub fn is_reliable(&self) -> bool {
+        !matches!(self, ReplicationBody::Unreliable { .. })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplicationMessage {
+    /// Starts at 1 and increases by one per reliable message on a stream. Unreliable
+    /// messages carry the sequence of the reliable message they follow.
+    pub sequence: u64,
+    pub timestamp_ms: u64,
+    /// Lamport clock of the recipient's last command the host had applied when it
+    /// produced this state; see [`crate::network::prediction`].
+    pub command_ack: u64,
+    pub body: ReplicationBody,
+}
+
+/// Most reliable messages a sender keeps unacknowledged and a receiver holds ahead of a
+/// gap. A sender past it falls back to a fresh snapshot, which lets the receiver skip
+/// the gap.
+pub const MAX_IN_FLIGHT_MESSAGES: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplicationStreamConfig {
+    /// Resend a message that has not been acknowledged for this long.
+    pub resend_after: Duration,
+    /// Snapshot chunk size in bytes.
+    pub chunk_limit: usize,
+    /// Payload bytes per delta; see [`DeltaTracker::with_bandwidth_budget`].
+    pub bandwidth_budget: Option<usize>,
+}
+
+impl Default for ReplicationStreamConfig {
+    fn default() -> Self {
+        Self {
+            resend_after: Duration::from_millis(250),
+            chunk_limit: 16 * 1024,
+            bandwidth_budget: None,
+        }
+    }
+}
+
+/// Last reliable value of each `(entity, component type hash)` and the sequence of the
+/// message that carried it.
+#[derive(Default)]
+struct Baselines {
+    values: HashMap<(EntityHandle, u64), (u64, Vec<u8>)>,
+}
+
+impl Baselines {
+    fn get(&self, diff: &ComponentDiff) -> Option<&(u64, Vec<u8>)> {
+        self.values.get(&(diff.entity, diff.component.type_hash))
+    }
+
+    fn record_snapshot(&mut self, sequence: u64, chunk: &WorldSnapshotChunk) {
+        for component in &chunk.components {
+            self.values.insert(
+                (component.entity, component.component.type_hash),
+                (sequence, component.bytes.clone()),
+            );
+        }
+    }
+
+    /// Tracks what a reliable delta leaves on the peer. Update and delta payloads must
+    /// already be resolved to full values in `values`.
+    fn record_delta(&mut self, sequence: u64, diff: &ComponentDiff, value: Option<&[u8]>) {
+        let key = (diff.entity, diff.component.type_hash);
+        match value {
+            Some(bytes) => {
+                self.values.insert(key, (sequence, bytes.to_vec()));
+            }
+            None => {
+                self.values.remove(&key);
+            }
+        }
+    }
+
+    fn forget_entities(&mut self, entities: &[EntityHandle]) {
+        if !entities.is_empty() {
+            self.values
+                .retain(|(entity, _), _| !entities.contains(entity));
+        }
+    }
+}
+
+struct InFlight {
+    message: ReplicationMessage,
+    sent_at: Instant,
+}
+
+/// Host side of one peer's replication stream.
+pub struct ReplicationSender {
+    config: ReplicationStreamConfig,
+    tracker: DeltaTracker,
+    next_sequence: u64,
+    unreliable_tick: u64,
+    acknowledged: u64,
+    in_flight: BTreeMap<u64, InFlight>,
+    baselines: Baselines,
+    resync: bool,
+}
+
+impl ReplicationSender {
+    pub fn new(config: ReplicationStreamConfig) -> Self {
+        let tracker = match config.bandwidth_budget {
+            Some(budget) => DeltaTracker::new().with_bandwidth_budget(budget),
+            None => DeltaTracker::new(),
+        };
+        Self {
+            config,
+            tracker,
+            next_sequence: 1,
+            unreliable_tick: 0,
+            acknowledged: 0,
+            in_flight: BTreeMap::new(),
+            baselines: Baselines::default(),
+            resync: false,
+        }
+    }
+
+    /// Streams to `peer`, which also receives owner-only components of its entities.
+    pub fn with_recipient(mut self, peer: AuthorId) -> Self {
+        self.tracker = self.tracker.with_recipient(peer);
+        self
+    }
+
+    /// Snapshot chunks describing `world` as it is now; later ticks only send changes
+    /// from this point. An empty world still yields one chunk so the peer resets.
+    pub fn start(
+        &mut self,
+        registry: &ReplicationRegistry,
+        world: &World,
+        now: Instant,
+    ) -> Vec<ReplicationMessage> {
+        self.start_relevant(registry, world, now, &|_| true)
+    }
+
+    /// Like [`Self::start`], limited to the entities `relevant` accepts.
+    pub fn start_relevant(
+        &mut self,
+        registry: &ReplicationRegistry,
+        world: &World,
+        now: Instant,
+        relevant: &dyn Fn(Entity) -> bool,
+    ) -> Vec<ReplicationMessage> {
+        self.tracker.reset();
+        self.baselines = Baselines::default();
+        self.resync = false;
+        self.tracker.diff_relevant(registry, world, now, relevant);
+        let snapshot = WorldSnapshotBuilder::new(registry)
+            .with_chunk_limit(self.config.chunk_limit)
+            .for_recipient(self.tracker.recipient().cloned())
+            .build_relevant(world, relevant);
+        self.tracker.mark_sent(registry);
+        let mut chunks = snapshot.chunks().to_vec();
+        if chunks.is_empty() {
+            chunks.push(WorldSnapshotChunk {
+                chunk_index: 0,
+                total_chunks: 1,
+                components: Vec::new(),
+            });
+        }
+        chunks
+            .into_iter()
+            .map(|chunk| {
+                self.baselines.record_snapshot(self.next_sequence, &chunk);
+                self.send(ReplicationBody::Snapshot(chunk), now)
+            })
+            .collect()
+    }
+
+    /// Resends of messages still unacknowledged after
+    /// [`ReplicationStreamConfig::resend_after`], then the delta since the previous tick
+    /// and its unreliable updates, if anything changed.
+    pub fn tick(
+        &mut self,
+        registry: &ReplicationRegistry,
+        world: &World,
+        now: Instant,
+    ) -> Vec<ReplicationMessage> {
+        self.tick_relevant(registry, world, now, &|_| true)
+    }
+
+    /// Like [`Self::tick`]; entities entering or leaving `relevant` are spawned or
+    /// despawned on the peer.
+    pub fn tick_relevant(
+        &mut self,
+        registry: &ReplicationRegistry,
+        world: &World,
+        now: Instant,
+        relevant: &dyn Fn(Entity) -> bool,
+    ) -> Vec<ReplicationMessage> {
+        if self.resync || self.in_flight.len() >= MAX_IN_FLIGHT_MESSAGES {
+            if !self.resync {
+                log::warn!(
+                    "[replication] {} messages unacknowledged; resynchronizing with a snapshot",
+                    self.in_flight.len()
+                );
+            }
+            self.in_flight.clear();
+            return self.start_relevant(registry, world, now, relevant);
+        }
+        let mut outgoing = Vec::new();
+        for in_flight in self.in_flight.values_mut() {
+            if now.duration_since(in_flight.sent_at) >= self.config.resend_after {
+                in_flight.sent_at = now;
+                outgoing.push(in_flight.message.clone());
+            }
+        }
+        let mut delta = self.tracker.diff_relevant(registry, world, now, relevant);
+        let mut unreliable = std::mem::take(&mut delta.unreliable);
+        if !delta.is_empty() {
+            let sequence = self.next_sequence;
+            for diff in &mut delta.diffs {
+                let value = match &diff.payload {
+                    DiffPayload::Insert { bytes } | DiffPayload::Update { bytes } => {
+                        Some(bytes.clone())
+                    }
+                    _ => None,
+                };
+                // Ordered delivery guarantees the peer holds the previous reliable value.
+                if let Some(bytes) = &value {
+                    self.compress(diff, bytes, u64::MAX);
+                }
+                self.baselines
+                    .record_delta(sequence, diff, value.as_deref());
+            }
+            self.baselines.forget_entities(&delta.despawned);
+            outgoing.push(self.send(ReplicationBody::Delta(delta), now));
+        }
+        if !unreliable.is_empty() {
+            // Unreliable updates may only patch values the peer has acknowledged.
+            for diff in &mut unreliable {
+                if let DiffPayload::Update { bytes } = &diff.payload {
+                    let bytes = bytes.clone();
+                    self.compress(diff, &bytes, self.acknowledged);
+                }
+            }
+            self.unreliable_tick += 1;
+            outgoing.push(ReplicationMessage {
+                sequence: self.next_sequence - 1,
+                timestamp_ms: current_time_millis(),
+                command_ack: self.tracker.command_ack(),
+                body: ReplicationBody::Unreliable {
+                    tick: self.unreliable_tick,
+                    diffs: unreliable,
+                },
+            });
+        }
+        outgoing
+    }
+
+    /// Returns the entities whose arrival the acknowledgement confirms, for
+    /// [`crate::network::network_id::NetworkEntityMap::confirm`].
+    pub fn acknowledge(&mut self, sequence: u64) -> Vec<EntityHandle> {
+        // Nothing past the last sequence sent can have arrived.
+        let sequence = sequence.min(self.next_sequence - 1);
+        self.acknowledged = self.acknowledged.max(sequence);
+        let remaining = self.in_flight.split_off(&(sequence + 1));
+        let delivered = std::mem::replace(&mut self.in_flight, remaining);
+        let mut entities: Vec<EntityHandle> = delivered
+            .into_values()
+            .flat_map(|in_flight| match in_flight.message.body {
+                ReplicationBody::Snapshot(chunk) => chunk
+                    .components
+                    .into_iter()
+                    .map(|component| component.entity)
+                    .collect(),
+                ReplicationBody::Delta(delta) => delta.spawned,
+                ReplicationBody::Unreliable { .. } => Vec::new(),
+            })
+            .collect();
+        entities.sort_by_key(|handle| (handle.generation, handle.index));
+        entities.dedup();
+        entities
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Restarts the stream with a snapshot on the next tick, e.g. when the peer lost the
+    /// baseline of a patch.
+    pub fn request_resync(&mut self) {
+        self.resync = true;
+    }
+
+    /// Marks the recipient's commands up to `lamport` as reflected in the world. Messages
+    /// carry it as their [`ReplicationMessage::command_ack`] once the state they changed
+    /// has been sent; see [`DeltaTracker::command_ack`].
+    pub fn acknowledge_commands(&mut self, lamport: u64) {
+        self.tracker.observe_commands(lamport);
+    }
+
+    /// Replaces an update with a patch against a baseline sent no later than
+    /// `newest_baseline`, when the patch is smaller.
+    fn compress(&self, diff: &mut ComponentDiff, bytes: &[u8], newest_baseline: u64) {
+        if !matches!(diff.payload, DiffPayload::Update { .. }) {
+            return;
+        }
+        let Some((baseline, base)) = self.baselines.get(diff) else {
+            return;
+        };
+        if *baseline > newest_baseline {
+            return;
+        }
+        let patch = encode_patch(base, bytes);
+        if patch.len() < bytes.len() {
+            diff.payload = DiffPayload::Delta {
+                baseline: *baseline,
+                patch,
+            };
+        }
+    }
+
+    fn send(&mut self, body: ReplicationBody, now: Instant) -> ReplicationMessage {
+        let message = ReplicationMessage {
+            sequence: self.next_sequence,
+            timestamp_ms: current_time_millis(),
+            command_ack: self.tracker.command_ack(),
+            body,
+        };
+        self.next_sequence += 1;
+        self.in_flight.insert(
+            message.sequence,
+            InFlight {
+                message: message.clone(),
+                sent_at: now,
+            },
+        );
+        message
+    }
+}
+
+impl Default for ReplicationSender {
+    fn default() -> Self {
+        Self::new(ReplicationStreamConfig::default())
+    }
+}
+
+struct PeerStream {
+    sender: ReplicationSender,
+    started: bool,
+}
+
+/// Host side of every peer's stream, each filtered by that peer's interest.
+pub struct ReplicationHost {
+    config: ReplicationStreamConfig,
+    interest: InterestManager,
+    peers: BTreeMap<AuthorId, PeerStream>,
+}
+
+impl ReplicationHost {
+    pub fn new(config: ReplicationStreamConfig, interest: InterestManager) -> Self {
+        Self {
+            config,
+            interest,
+            peers: BTreeMap::new(),
+        }
+    }
+
+    pub fn interest(&self) -> &InterestManager {
+        &self.interest
+    }
+
+    pub fn interest_mut(&mut self) -> &mut InterestManager {
+        &mut self.interest
+    }
+
+    /// The peer receives its snapshot on the next tick.
+    pub fn add_peer(&mut self, peer: AuthorId) {
+        let sender = ReplicationSender::new(self.config).with_recipient(peer.clone());
+        self.peers.insert(
+            peer,
+            PeerStream {
+                sender,
+                started: false,
+            },
+        );
+    }
+
+    pub fn remove_peer(&mut self, peer: &AuthorId) {
+        self.peers.remove(peer);
+        self.interest.remove_peer(peer);
+    }
+
+    pub fn peers(&self) -> impl Iterator<Item = &AuthorId> {
+        self.peers.keys()
+    }
+
+    /// See [`ReplicationSender::acknowledge`]; unknown peers confirm nothing.
+    pub fn acknowledge(&mut self, peer: &AuthorId, sequence: u64) -> Vec<EntityHandle> {
+        self.peers
+            .get_mut(peer)
+            .map(|stream| stream.sender.acknowledge(sequence))
+            .unwrap_or_default()
+    }
+
+    /// See [`ReplicationSender::request_resync`].
+    pub fn request_resync(&mut self, peer: &AuthorId) {
+        if let Some(stream) = self.peers.get_mut(peer) {
+            stream.sender.request_resync();
+        }
+    }
+
+    /// Passes each peer the newest of its own commands the host has applied, typically
+    /// the host command log's version vector.
+    pub fn acknowledge_commands(&mut self, versions: &VersionVector) {
+        for (peer, stream) in &mut self.peers {
+            stream.sender.acknowledge_commands(versions.get(peer));
+        }
+    }
+
+    /// Outgoing messages per peer; peers with nothing to send are left out.
+    pub fn tick(
+        &mut self,
+        registry: &ReplicationRegistry,
+        world: &World,
+        now: Instant,
+    ) -> Vec<(AuthorId, Vec<ReplicationMessage>)> {
+        let mut outgoing = Vec::new();
+        for (peer, stream) in &mut self.peers {
+            let relevant = |entity| self.interest.is_relevant(world, peer, entity);
+            let messages = if stream.started {
+                stream.sender.tick_relevant(registry, world, now, &relevant)
+            } else {
+                stream.started = true;
+                stream
+                    .sender
+                    .start_relevant(registry, world, now, &relevant)
+            };
+            if !messages.is_empty() {
+                outgoing.push((peer.clone(), messages));
+            }
+        }
+        outgoing
+    }
+}
+
+/// Peer side of a replication stream. Messages are applied in sequence order; early
+/// ones wait for the gap to fill and duplicates are dropped.
+#[derive(Default)]
+pub struct ReplicationReceiver {
+    applier: ReplicationApplier,
+    applied: u64,
+    unreliable_tick: u64,
+    command_ack: u64,
+    timestamp_ms: u64,
+    pending: BTreeMap<u64, ReplicationMessage>,
+    snapshot: Vec<WorldSnapshotChunk>,
+    baselines: Baselines,
+    resync: bool,
+}
+
+impl ReplicationReceiver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// See [`ReplicationApplier::with_entity_map`].
+    pub fn with_entity_map(mut self, entities: SharedEntityMap) -> Self {
+        self.applier = self.applier.with_entity_map(entities);
+        self
+    }
+
+    /// See [`ReplicationApplier::with_source_prefix`].
+    pub fn with_source_prefix(mut self, prefix: u32) -> Self {
+        self.applier = self.applier.with_source_prefix(prefix);
+        self
+    }
+
+    /// See [`ReplicationApplier::with_authority`].
+    pub fn with_authority(mut self, table: AuthorityTable, source: AuthorId) -> Self {
+        self.applier.set_authority(table, source);
+        self
+    }
+
+    pub fn set_authority(&mut self, table: AuthorityTable, source: AuthorId) {
+        self.applier.set_authority(table, source);
+    }
+
+    pub fn applier(&self) -> &ReplicationApplier {
+        &self.applier
+    }
+
+    pub fn applier_mut(&mut self) -> &mut ReplicationApplier {
+        &mut self.applier
+    }
+
+    /// Highest sequence applied so far; send it back as the cumulative acknowledgement.
+    pub fn acknowledged(&self) -> u64 {
+        self.applied
+    }
+
+    /// [`ReplicationMessage::command_ack`] of the newest state applied so far.
+    pub fn command_ack(&self) -> u64 {
+        self.command_ack
+    }
+
+    /// [`ReplicationMessage::timestamp_ms`] of the newest state applied so far.
+    pub fn timestamp_ms(&self) -> u64 {
+        self.timestamp_ms
+    }
+
+    /// Whether a patch arrived without its baseline since the last call; pass it back
+    /// so the sender calls [`ReplicationSender::request_resync`].
+    pub fn take_resync_request(&mut self) -> bool {
+        std::mem::take(&mut self.resync)
+    }
+
+    pub fn receive(
+        &mut self,
+        registry: &ReplicationRegistry,
+        world: &mut World,
+        message: ReplicationMessage,
+    ) -> ApplyStats {
+        let mut stats = ApplyStats::default();
+        if let ReplicationBody::Unreliable { tick, mut diffs } = message.body {
+            // Stale updates, or ones ahead of the reliable state they follow, are dropped.
+            if message.sequence > self.applied || tick <= self.unreliable_tick {
+                return stats;
+            }
+            self.unreliable_tick = tick;
+            self.command_ack = self.command_ack.max(message.command_ack);
+            self.timestamp_ms = self.timestamp_ms.max(message.timestamp_ms);
+            for diff in &mut diffs {
+                self.resolve(diff);
+            }
+            return self.applier.apply_unreliable(registry, world, &diffs);
+        }
+        if message.sequence <= self.applied {
+            return stats;
+        }
+        if let ReplicationBody::Snapshot(chunk) = &message.body
+            && chunk.chunk_index == 0
+            && message.sequence > self.applied + 1
+        {
+            // A resync snapshot replaces everything before it, so the gap never fills.
+            self.applied = message.sequence - 1;
+            self.pending = self.pending.split_off(&message.sequence);
+            self.snapshot.clear();
+        } else if self.pending.len() >= MAX_IN_FLIGHT_MESSAGES
+            && message.sequence != self.applied + 1
+        {
+            log::debug!(
+                "[replication] dropping message {} ahead of a full queue",
+                message.sequence
+            );
+            return stats;
+        }
+        self.pending.insert(message.sequence, message);
+        while let Some(message) = self.pending.remove(&(self.applied + 1)) {
+            self.applied = message.sequence;
+            self.command_ack = self.command_ack.max(message.command_ack);
+            self.timestamp_ms = self.timestamp_ms.max(message.timestamp_ms);
+            let applied = match message.body {
+                ReplicationBody::Snapshot(chunk) => {
+                    if chunk.chunk_index == 0 {
+                        self.baselines = Baselines::default();
+                        self.resync = false;
+                    }
+                    self.baselines.record_snapshot(message.sequence, &chunk);
+                    let complete = chunk.chunk_index + 1 >= chunk.total_chunks;
+                    self.snapshot.push(chunk);
+                    if !complete {
+                        continue;
+                    }
+                    let snapshot = WorldSnapshot::from_chunks(std::mem::take(&mut self.snapshot));
+                    self.applier.apply_snapshot(registry, world, &snapshot)
+                }
+                ReplicationBody::Delta(mut delta) => {
+                    for diff in &mut delta.diffs {
+                        self.resolve(diff);
+                        let value = match &diff.payload {
+                            DiffPayload::Insert { bytes } | DiffPayload::Update { bytes } => {
+                                Some(bytes.as_slice())
+                            }
+                            // The baseline stays until the resync snapshot replaces it.
+                            DiffPayload::Delta { .. } => continue,
+                            DiffPayload::Remove => None,
+                        };
+                        self.baselines.record_delta(message.sequence, diff, value);
+                    }
+                    self.baselines.forget_entities(&delta.despawned);
+                    self.applier.apply_delta(registry, world, &delta)
+                }
+                ReplicationBody::Unreliable { .. } => continue,
+            };
+            stats.merge(applied);
+        }
+        stats
+    }
+
+    /// Turns a patch back into a full update. Patches against a baseline this end does
+    /// not hold are left for the applier to skip, and a resync is requested.
+    fn resolve(&mut self, diff: &mut ComponentDiff) {
+        let DiffPayload::Delta { baseline, patch } = &diff.payload else {
+            return;
+        };
+        let resolved = self
+            .baselines
+            .get(diff)
+            .filter(|(sequence, _)| sequence == baseline)
+            .and_then(|(_, base)| apply_patch(base, patch));
+        match resolved {
+            Some(bytes) => diff.payload = DiffPayload::Update { bytes },
+            None => {
+                log::warn!(
+                    "[replication] dropping {} patch for {:?}: baseline {baseline} unavailable",
+                    diff.component.type_name,
+                    diff.entity
+                );
+                self.resync = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::replication::ReplicationPolicy;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Health(u32);
+
+    #[test]
+    fn unacknowledged_stream_falls_back_to_a_snapshot() {
+        let mut registry = ReplicationRegistry::new();
+        registry.register::<Health>();
+        let mut host = World::new();
+        let hero = host.spawn();
+        host.insert(hero, Health(0)).unwrap();
+
+        let mut sender = ReplicationSender::new(ReplicationStreamConfig::default());
+        let mut receiver = ReplicationReceiver::new();
+        let mut client = World::new();
+        let now = Instant::now();
+        sender.start(&registry, &host, now);
+        // Every message is lost, so the window fills up.
+        let mut last = Vec::new();
+        for value in 1..=MAX_IN_FLIGHT_MESSAGES as u32 {
+            host.get_mut::<Health>(hero).unwrap().0 = value;
+            last = sender.tick(&registry, &host, now);
+            assert!(sender.in_flight() <= MAX_IN_FLIGHT_MESSAGES);
+        }
+        let ReplicationBody::Snapshot(_) = &last[0].body else {
+            panic!("expected a resync snapshot, got {:?}", last[0].body);
+        };
+        assert_eq!(sender.in_flight(), 1);
+
+        for message in last {
+            receiver.receive(&registry, &mut client, message);
+        }
+        let mirrored = receiver.applier().local_entity(hero.into()).unwrap();
+        assert_eq!(
+            client.get::<Health>(mirrored),
+            Some(&Health(MAX_IN_FLIGHT_MESSAGES as u32))
+        );
+        assert!(sender.acknowledge(u64::MAX).contains(&hero.into()));
+        assert_eq!(sender.in_flight(), 0);
+    }
+
+    #[test]
+    fn lossy_reordered_stream_converges_after_resend() {
+        let mut registry = ReplicationRegistry::new();
+        registry.register::<Health>();
+        let mut host = World::new();
+        let hero = host.spawn();
+        host.insert(hero, Health(10)).unwrap();
+
+        let config = ReplicationStreamConfig {
+            resend_after: Duration::from_millis(100),
+            ..ReplicationStreamConfig::default()
+        };
+        let mut sender = ReplicationSender::new(config);
+        let mut receiver = ReplicationReceiver::new();
+        let mut client = World::new();
+        let start = Instant::now();
+
+        let snapshot = sender.start(&registry, &host, start);
+        host.get_mut::<Health>(hero).unwrap().0 = 7;
+        let first = sender.tick(&registry, &host, start);
+        host.get_mut::<Health>(hero).unwrap().0 = 3;
+        let second = sender.tick(&registry, &host, start);
+        assert_eq!((snapshot.len(), first.len(), second.len()), (1, 1, 1));
+
+        // The snapshot is lost and the deltas arrive out of order; nothing applies yet.
+        receiver.receive(&registry, &mut client, second[0].clone());
+        receiver.receive(&registry, &mut client, first[0].clone());
+        assert_eq!(receiver.acknowledged(), 0);
+        assert!(client.component_entries::<Health>().is_empty());
+
+        let resent = sender.tick(&registry, &host, start + Duration::from_millis(150));
+        assert_eq!(resent.len(), 3);
+        for message in resent {
+            receiver.receive(&registry, &mut client, message);
+        }
+        assert_eq!(receiver.acknowledged(), 3);
+        let mirrored = receiver
+            .applier()
+            .local_entity(hero.into())
+            .expect("hero mirrored");
+        assert_eq!(client.get::<Health>(mirrored), Some(&Health(3)));
+
+        sender.acknowledge(receiver.acknowledged());
+        assert_eq!(sender.in_flight(), 0);
+        assert!(
+            sender
+                .tick(&registry, &host, start + Duration::from_secs(1))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn stream_only_changes_what_the_sender_has_authority_over() {
+        use crate::network::authority::AuthorityModel;
+
+        let mut registry = ReplicationRegistry::new();
+        registry.register::<Health>();
+        let mut host = World::new();
+        let (hero, prop) = (host.spawn(), host.spawn());
+        host.insert(hero, Health(10)).unwrap();
+        host.insert(prop, Health(10)).unwrap();
+
+        // A relaying peer that is not the host only gets to change shared entities.
+        let relay = AuthorId(2);
+        let mut table = AuthorityTable::new(AuthorId(1));
+        table.set_model(prop.into(), AuthorityModel::Shared);
+        let mut sender = ReplicationSender::new(ReplicationStreamConfig::default());
+        let mut receiver = ReplicationReceiver::new().with_authority(table.clone(), relay.clone());
+        let mut client = World::new();
+        let now = Instant::now();
+        let health = |client: &World, receiver: &ReplicationReceiver, entity: Entity| {
+            let local = receiver.applier().local_entity(entity.into()).unwrap();
+            client.get::<Health>(local).cloned()
+        };
+
+        for message in sender.start(&registry, &host, now) {
+            receiver.receive(&registry, &mut client, message);
+        }
+        assert_eq!(health(&client, &receiver, hero), None);
+        assert_eq!(health(&client, &receiver, prop), Some(Health(10)));
+
+        host.get_mut::<Health>(prop).unwrap().0 = 4;
+        host.get_mut::<Health>(hero).unwrap().0 = 4;
+        let mut stats = ApplyStats::default();
+        for message in sender.tick(&registry, &host, now) {
+            stats.merge(receiver.receive(&registry, &mut client, message));
+        }
+        assert_eq!((stats.updated, stats.rejected), (1, 1));
+        assert_eq!(health(&client, &receiver, hero), None);
+        assert_eq!(health(&client, &receiver, prop), Some(Health(4)));
+
+        table.set_model(hero.into(), AuthorityModel::Shared);
+        receiver.set_authority(table, relay);
+        host.get_mut::<Health>(hero).unwrap().0 = 2;
+        for message in sender.tick(&registry, &host, now) {
+            receiver.receive(&registry, &mut client, message);
+        }
+        assert_eq!(health(&client, &receiver, hero), Some(Health(2)));
+    }
+
+    #[test]
+    fn unreliable_updates_skip_resends_and_drop_when_stale() {
+        let mut registry = ReplicationRegistry::new();
+        registry.register_with_policy::<Health>(ReplicationPolicy::new().unreliable());
+        let mut host = World::new();
+        let hero = host.spawn();
+        host.insert(hero, Health(10)).unwrap();
+
+        let mut sender = ReplicationSender::default();
+        let mut receiver = ReplicationReceiver::new();
+        let mut client = World::new();
+        let start = Instant::now();
+        for message in sender.start(&registry, &host, start) {
+            receiver.receive(&registry, &mut client, message);
+        }
+        sender.acknowledge(receiver.acknowledged());
+
+        host.get_mut::<Health>(hero).unwrap().0 = 7;
+        let older = sender.tick(&registry, &host, start);
+        host.get_mut::<Health>(hero).unwrap().0 = 3;
+        let newer = sender.tick(&registry, &host, start);
+        assert!(!older[0].body.is_reliable());
+        assert_eq!(sender.in_flight(), 0);
+
+        receiver.receive(&registry, &mut client, newer[0].clone());
+        let stale = receiver.receive(&registry, &mut client, older[0].clone());
+        assert_eq!(stale, ApplyStats::default());
+        let mirrored = receiver.applier().local_entity(hero.into()).unwrap();
+        assert_eq!(client.get::<Health>(mirrored), Some(&Health(3)));
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Inventory {
+        slots: [u32; 32],
+        gold: u32,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Aim([f32; 8]);
+
+    fn payloads(message: &ReplicationMessage) -> Vec<DiffPayload> {
+        let diffs = match &message.body {
+            ReplicationBody::Delta(delta) => &delta.diffs,
+            ReplicationBody::Unreliable { diffs, .. } => diffs,
+            ReplicationBody::Snapshot(_) => return Vec::new(),
+        };
+        diffs.iter().map(|diff| diff.payload.clone()).collect()
+    }
+
+    #[test]
+    fn updates_travel_as_patches_against_their_baseline() {
+        use crate::network::packet_codec::{
+            decode_replication_message, encode_replication_message,
+        };
+
+        let mut registry = ReplicationRegistry::new();
+        registry.register::<Inventory>();
+        registry.register_with_policy::<Aim>(ReplicationPolicy::new().unreliable());
+        let mut host = World::new();
+        let hero = host.spawn();
+        host.insert(
+            hero,
+            Inventory {
+                slots: [1; 32],
+                gold: 0,
+            },
+        )
+        .unwrap();
+        host.insert(hero, Aim([0.5; 8])).unwrap();
+
+        let mut sender = ReplicationSender::default();
+        let mut receiver = ReplicationReceiver::new();
+        let mut client = World::new();
+        let start = Instant::now();
+        let over_the_wire = |message: &ReplicationMessage| {
+            decode_replication_message(&encode_replication_message(message)).unwrap()
+        };
+
+        let snapshot = sender.start(&registry, &host, start);
+        host.get_mut::<Inventory>(hero).unwrap().gold = 50;
+        host.get_mut::<Aim>(hero).unwrap().0[0] = 1.0;
+        let first = sender.tick(&registry, &host, start);
+        let first_payloads = payloads(&first[0]);
+        let [DiffPayload::Delta { baseline: 1, patch }] = first_payloads.as_slice() else {
+            panic!("expected a patch against the snapshot");
+        };
+        assert!(patch.len() < 8, "patch is {} bytes", patch.len());
+        // Nothing is acknowledged yet, so the unreliable update goes out whole.
+        assert!(matches!(payloads(&first[1])[0], DiffPayload::Update { .. }));
+
+        // The first delta is lost; the second patches against it and waits for the resend.
+        host.get_mut::<Inventory>(hero).unwrap().slots[3] = 9;
+        let second = sender.tick(&registry, &host, start);
+        assert!(matches!(
+            payloads(&second[0])[0],
+            DiffPayload::Delta { baseline: 2, .. }
+        ));
+        // The aim stopped changing, so its last unreliable value rides along reliably.
+        assert_eq!(payloads(&second[0]).len(), 2);
+        for message in snapshot.iter().chain(&second) {
+            receiver.receive(&registry, &mut client, over_the_wire(message));
+        }
+        assert_eq!(receiver.acknowledged(), 1);
+        for message in sender.tick(&registry, &host, start + Duration::from_secs(1)) {
+            receiver.receive(&registry, &mut client, over_the_wire(&message));
+        }
+        assert_eq!(receiver.acknowledged(), 3);
+        let mirrored = receiver.applier().local_entity(hero.into()).unwrap();
+        assert_eq!(
+            client.get::<Inventory>(mirrored),
+            host.get::<Inventory>(hero)
+        );
+
+        sender.acknowledge(receiver.acknowledged());
+        host.get_mut::<Aim>(hero).unwrap().0[1] = 2.0;
+        let third = sender.tick(&registry, &host, start + Duration::from_secs(1));
+        assert!(matches!(
+            payloads(&third[0])[0],
+            DiffPayload::Delta { baseline: 3, .. }
+        ));
+        receiver.receive(&registry, &mut client, over_the_wire(&third[0]));
+        assert_eq!(client.get::<Aim>(mirrored), host.get::<Aim>(hero));
+    }
+
+    #[test]
+    fn missing_baselines_keep_the_old_one_and_request_a_resync() {
+        let mut registry = ReplicationRegistry::new();
+        registry.register::<Inventory>();
+        let mut host = World::new();
+        let hero = host.spawn();
+        host.insert(
+            hero,
+            Inventory {
+                slots: [1; 32],
+                gold: 0,
+            },
+        )
+        .unwrap();
+
+        let mut sender = ReplicationSender::default();
+        let mut receiver = ReplicationReceiver::new();
+        let mut client = World::new();
+        let now = Instant::now();
+        for message in sender.start(&registry, &host, now) {
+            receiver.receive(&registry, &mut client, message);
+        }
+        host.get_mut::<Inventory>(hero).unwrap().gold = 50;
+        let delta = sender.tick(&registry, &host, now).remove(0);
+
+        let mut orphaned = delta.clone();
+        let ReplicationBody::Delta(body) = &mut orphaned.body else {
+            panic!("expected a delta");
+        };
+        let DiffPayload::Delta { baseline, .. } = &mut body.diffs[0].payload else {
+            panic!("expected a patch");
+        };
+        *baseline = 7;
+        receiver.receive(&registry, &mut client, orphaned);
+        assert!(receiver.take_resync_request());
+        assert!(!receiver.take_resync_request());
+        let mirrored = receiver.applier().local_entity(hero.into()).unwrap();
+        let gold = |client: &World| client.get::<Inventory>(mirrored).map(|i| i.gold);
+        assert_eq!(gold(&client), Some(0));
+
+        // The snapshot value is still there to patch against.
+        let retried = ReplicationMessage {
+            sequence: 3,
+            ..delta
+        };
+        receiver.receive(&registry, &mut client, retried);
+        assert_eq!(gold(&client), Some(50));
+
+        sender.request_resync();
+        let resync = sender.tick(&registry, &host, now);
+        assert!(matches!(resync[0].body, ReplicationBody::Snapshot(_)));
+        assert_eq!(sender.in_flight(), 1);
+    }
+}
@@ -1,5 +1,14 @@
 use super::{TransportDiagnostics, TransportKind, current_time_millis};
-use crate::network::command_log::{CommandPacket, MAX_COMMAND_PACKET_BYTES};
+use crate::network::command_log::{CommandPacket, MAX_COMMAND_PACKET_BYTES, PacketEncoding};
+use crate::network::compression::{
+    CompressionConfig, CompressionError, CompressionStats, FrameCompressor,
+};
+use crate::network::packet_codec::{
+    decode_replication_ack, decode_replication_message, encode_replication_ack,
+    encode_replication_message,
+};
+use crate::network::preview::{MAX_PREVIEW_PACKET_BYTES, PreviewPacket};
+use crate::network::replication_stream::ReplicationMessage;
 use crate::network::voice::{VoiceDiagnosticsHandle, VoicePacket};
 use crate::network::wire;
 use bytes::Bytes;
@@ -7,6 +16,7 @@ use ed25519_dalek::SigningKey;
 use flatbuffers::{FlatBufferBuilder, UnionWIPOffset, WIPOffset};
 use quinn::{self, Connection, ReadExactError, ReadToEndError, RecvStream, SendStream};
 use rand::{RngCore, rngs::OsRng};
+use std::collections::VecDeque;
 use std::convert::TryInto;
 use std::net::SocketAddr;
 use std::sync::{Arc, Mutex};
@@ -44,6 +54,8 @@ const FRAME_HEADER_LEN: usize = 4;
 const HANDSHAKE_CAPACITY: usize = 1024;
 const FRAME_KIND_COMMAND_PACKET: u8 = 1;
 const FRAME_KIND_COMPONENT_DELTA: u8 = 2;
+const FRAME_KIND_REPLICATION_ACK: u8 = 3;
+// Kind 4 marks zstd frames; see `compression::FRAME_KIND_COMPRESSED`.
 const VOICE_FRAME_HEADER_BYTES: usize = 8 + 8 + 4;
 const LOCAL_SPEAKER_TAG: &str = "local";
 const REMOTE_SPEAKER_TAG: &str = "remote";
@@ -76,6 +88,8 @@ pub enum TransportError {
     Unsupported(&'static str),
     #[error("webrtc error: {0}")]
     WebRtc(String),
+    #[error("compression error: {0}")]
+    Compression(#[from] CompressionError),
 }
 
 #[cfg(test)]
@@ -176,6 +190,8 @@ mod webrtc_tests {
             nonce: 100,
             timestamp_ms: current_time_millis(),
             payload: vec![0u8; MAX_COMMAND_PACKET_BYTES + 1],
+            encoding: PacketEncoding::Json,
+            fragment: None,
         };
 
         let valid_entry = CommandEntry::new(
@@ -238,7 +254,7 @@ mod handshake_unit_tests {
 
     #[test]
     fn session_ack_rejects_empty_nonce() {
-        let message = build_session_ack(1, 0xABCDu64, &[], 42, 7, &[], &[0u8; 32]);
+        let message = build_session_ack(1, 0xABCDu64, &[], 42, 7, &[], &[0u8; 32], 0, 0);
         let result = parse_session_ack(&message, 1, 0xABCDu64);
         assert!(matches!(
             result,
@@ -250,24 +266,33 @@ mod handshake_unit_tests {
 struct FramedStream {
     send: Arc<TokioMutex<SendStream>>,
     recv: Arc<TokioMutex<RecvStream>>,
+    compressor: Option<Arc<FrameCompressor>>,
 }
 
 impl FramedStream {
-    fn new(send: SendStream, recv: RecvStream) -> Self {
+    fn new(send: SendStream, recv: RecvStream, compressor: Option<Arc<FrameCompressor>>) -> Self {
         Self {
             send: Arc::new(TokioMutex::new(send)),
             recv: Arc::new(TokioMutex::new(recv)),
+            compressor,
         }
     }
 
     async fn write_frame(&self, payload: &[u8]) -> Result<(), TransportError> {
         let mut guard = self.send.lock().await;
-        write_frame_raw(&mut guard, payload).await
+        match &self.compressor {
+            Some(compressor) => write_frame_raw(&mut guard, &compressor.compress(payload)).await,
+            None => write_frame_raw(&mut guard, payload).await,
+        }
     }
 
     async fn read_frame(&self, timeout: Duration) -> Result<Vec<u8>, TransportError> {
         let mut guard = self.recv.lock().await;
-        read_frame_raw(&mut guard, timeout).await
+        let frame = read_frame_raw(&mut guard, timeout).await?;
+        match &self.compressor {
+            Some(compressor) => Ok(compressor.decompress(&frame)?),
+            None => Ok(frame),
+        }
     }
 
     #[allow(dead_code)]
@@ -357,6 +382,8 @@ pub struct ClientHandshake {
     pub capabilities: Vec<u32>,
     pub auth_token: Option<String>,
     pub signing_key: SigningKey,
+    /// Its capabilities are advertised alongside `capabilities`.
+    pub compression: CompressionConfig,
     pub heartbeat: HeartbeatConfig,
     pub server_name: String,
 }
@@ -367,7 +394,14 @@ pub struct ServerHandshake {
     pub schema_hash: u64,
     pub capabilities: Vec<u32>,
     pub signing_key: SigningKey,
+    pub compression: CompressionConfig,
     pub heartbeat: HeartbeatConfig,
+    /// Network id prefix assigned to the accepted peer, e.g. from
+    /// [`crate::network::network_id::PeerPrefixes`]; the host keeps prefix 0.
+    pub network_prefix: u32,
+    /// Author id of the session host, for
+    /// [`crate::network::authority::AuthorityTable::set_host`].
+    pub host_author: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -375,6 +409,10 @@ pub struct HandshakeSummary {
     pub session_id: u64,
     pub assigned_role: u32,
     pub capability_mask: Vec<u32>,
+    /// Prefix of the network ids this end allocates; see
+    /// [`crate::network::network_id::NetworkIdAllocator`].
+    pub network_prefix: u32,
+    pub host_author: u64,
     pub client_public_key: [u8; 32],
     pub server_public_key: [u8; 32],
     pub client_nonce: Vec<u8>,
@@ -390,6 +428,8 @@ pub struct TransportSession {
     metrics: TransportMetricsHandle,
     heartbeat: HeartbeatActor,
     handshake: HandshakeSummary,
+    inbound: InboundFrames,
+    compressor: Option<Arc<FrameCompressor>>,
 }
 
 impl TransportSession {
@@ -404,6 +444,18 @@ impl TransportSession {
     pub fn kind(&self) -> TransportKind {
         TransportKind::Quic
     }
+
+    /// Frame compression totals; `None` unless zstd was negotiated.
+    pub fn compression_stats(&self) -> Option<CompressionStats> {
+        self.compressor
+            .as_ref()
+            .map(|compressor| compressor.stats())
+    }
+
+    fn compression_ratio(&self) -> f32 {
+        self.compression_stats().map_or(1.0, |stats| stats.ratio())
+    }
+
     pub async fn send_command_packets(
         &self,
         packets: &[CommandPacket],
@@ -427,7 +479,7 @@ impl TransportSession {
             m.packets_sent = m.packets_sent.saturating_add(sent);
             m.command_packets_sent = m.command_packets_sent.saturating_add(sent);
             if sent > 0 {
-                m.compression_ratio = 1.0;
+                m.compression_ratio = self.compression_ratio();
             }
             if total_bytes > 0 {
                 let bandwidth = if elapsed > 0.0 {
@@ -447,6 +499,9 @@ impl TransportSession {
         &self,
         timeout: Duration,
     ) -> Result<Option<CommandPacket>, TransportError> {
+        if let Some(packet) = self.inbound.pop_command() {
+            return Ok(Some(packet));
+        }
         loop {
             let frame = match self.replication.read_frame(timeout).await {
                 Ok(bytes) => bytes,
@@ -471,7 +526,7 @@ impl TransportSession {
                         m.kind = TransportKind::Quic;
                         m.packets_received = m.packets_received.saturating_add(1);
                         m.command_packets_received = m.command_packets_received.saturating_add(1);
-                        m.compression_ratio = 1.0;
+                        m.compression_ratio = self.compression_ratio();
                         m.command_bandwidth_bytes_per_sec = frame.len() as f32;
                         m.command_latency_ms = latency_ms;
                         let delta = (latency_ms - previous_latency).abs();
@@ -481,10 +536,13 @@ impl TransportSession {
                     return Ok(Some(packet));
                 }
                 Ok(DecodedReplicationFrame::ComponentDelta(bytes)) => {
-                    log::debug!(
-                        "[transport] received component delta ({} bytes) while awaiting command",
-                        bytes.len()
-                    );
+                    self.inbound
+                        .stash_replication(FRAME_KIND_COMPONENT_DELTA, &bytes);
+                    continue;
+                }
+                Ok(DecodedReplicationFrame::ReplicationAck(bytes)) => {
+                    self.inbound
+                        .stash_replication(FRAME_KIND_REPLICATION_ACK, &bytes);
                     continue;
                 }
                 Ok(DecodedReplicationFrame::Unknown(kind, payload)) => {
@@ -500,6 +558,116 @@ impl TransportSession {
         }
     }
 
+    /// Sends replication messages on the reliable stream shared with command packets.
+    pub async fn send_replication_messages(
+        &self,
+        messages: &[ReplicationMessage],
+    ) -> Result<(), TransportError> {
+        for message in messages {
+            let frame = encode_framed_payload(
+                FRAME_KIND_COMPONENT_DELTA,
+                encode_replication_message(message),
+            );
+            // Unreliable updates go out as datagrams when they fit.
+            let fits_datagram = self
+                .connection
+                .max_datagram_size()
+                .is_some_and(|max| frame.len() <= max);
+            if !message.body.is_reliable() && fits_datagram {
+                self.connection
+                    .send_datagram(Bytes::from(frame))
+                    .map_err(|err| {
+                        TransportError::Protocol(format!("replication datagram: {err}"))
+                    })?;
+            } else {
+                self.replication.write_frame(&frame).await?;
+            }
+        }
+        self.metrics.update(|m| {
+            m.kind = TransportKind::Quic;
+            m.packets_sent = m.packets_sent.saturating_add(messages.len() as u64);
+            m.compression_ratio = self.compression_ratio();
+        });
+        Ok(())
+    }
+
+    /// `resync` asks the publisher for a fresh snapshot.
+    pub async fn send_replication_ack(
+        &self,
+        sequence: u64,
+        resync: bool,
+    ) -> Result<(), TransportError> {
+        let ack = encode_replication_ack(sequence, resync, current_time_millis());
+        self.replication
+            .write_frame(&encode_framed_payload(FRAME_KIND_REPLICATION_ACK, ack))
+            .await
+    }
+
+    /// Next replication message or acknowledgement. Command packets and previews read
+    /// meanwhile are kept for their own receive calls.
+    pub async fn receive_replication_frame(
+        &self,
+        timeout: Duration,
+    ) -> Result<Option<ReplicationFrame>, TransportError> {
+        while let Ok(Ok(bytes)) =
+            tokio::time::timeout(Duration::ZERO, self.connection.read_datagram()).await
+        {
+            self.inbound.route_datagram(&bytes);
+        }
+        loop {
+            if let Some(frame) = self.inbound.pop_replication() {
+                return Ok(Some(frame));
+            }
+            let frame = match self.replication.read_frame(timeout).await {
+                Ok(bytes) => bytes,
+                Err(TransportError::Timeout(_)) => return Ok(None),
+                Err(err) => return Err(err),
+            };
+            self.inbound.route(&frame)?;
+        }
+    }
+
+    /// Sends previews as unreliable datagrams; lost or reordered previews are superseded
+    /// by newer ones or by the committed command.
+    pub async fn send_preview_packets(
+        &self,
+        packets: &[PreviewPacket],
+    ) -> Result<(), TransportError> {
+        for packet in packets {
+            let bytes = packet
+                .encode()
+                .map_err(|err| TransportError::Serialization(err.to_string()))?;
+            if bytes.len() > MAX_PREVIEW_PACKET_BYTES {
+                log::warn!(
+                    "[transport] dropping oversized preview {} ({} bytes)",
+                    packet.sequence,
+                    bytes.len()
+                );
+                continue;
+            }
+            self.connection
+                .send_datagram(Bytes::from(bytes))
+                .map_err(|err| TransportError::Protocol(format!("preview datagram: {err}")))?;
+        }
+        Ok(())
+    }
+
+    pub async fn receive_preview_packet(
+        &self,
+        timeout: Duration,
+    ) -> Result<Option<PreviewPacket>, TransportError> {
+        loop {
+            if let Some(packet) = self.inbound.pop_preview() {
+                return Ok(Some(packet));
+            }
+            let bytes = match tokio::time::timeout(timeout, self.connection.read_datagram()).await {
+                Ok(result) => result?,
+                Err(_) => return Ok(None),
+            };
+            self.inbound.route_datagram(&bytes);
+        }
+    }
+
     pub async fn close(self) {
         self.connection.close(0u32.into(), b"normal shutdown");
     }
@@ -516,6 +684,7 @@ pub struct WebRtcTransport {
     metrics: TransportMetricsHandle,
     voice_metrics: VoiceDiagnosticsHandle,
     voice_last_latency_ms: Arc<Mutex<Option<f32>>>,
+    inbound: InboundFrames,
 }
 
 impl WebRtcTransport {
@@ -630,6 +799,7 @@ impl WebRtcTransport {
             metrics,
             voice_metrics,
             voice_last_latency_ms,
+            inbound: InboundFrames::default(),
         }
     }
 
@@ -697,6 +867,9 @@ impl WebRtcTransport {
         &self,
         timeout: Duration,
     ) -> Result<Option<CommandPacket>, TransportError> {
+        if let Some(packet) = self.inbound.pop_command() {
+            return Ok(Some(packet));
+        }
         loop {
             let frame = {
                 let mut guard = self.command_inbox.lock().await;
@@ -734,10 +907,13 @@ impl WebRtcTransport {
                     return Ok(Some(packet));
                 }
                 Ok(DecodedReplicationFrame::ComponentDelta(bytes)) => {
-                    log::debug!(
-                        "[transport] received component delta over WebRTC ({} bytes) while awaiting command",
-                        bytes.len()
-                    );
+                    self.inbound
+                        .stash_replication(FRAME_KIND_COMPONENT_DELTA, &bytes);
+                    continue;
+                }
+                Ok(DecodedReplicationFrame::ReplicationAck(bytes)) => {
+                    self.inbound
+                        .stash_replication(FRAME_KIND_REPLICATION_ACK, &bytes);
                     continue;
                 }
                 Ok(DecodedReplicationFrame::Unknown(kind, payload)) => {
@@ -753,6 +929,63 @@ impl WebRtcTransport {
         }
     }
 
+    pub async fn send_replication_messages(
+        &self,
+        messages: &[ReplicationMessage],
+    ) -> Result<(), TransportError> {
+        for message in messages {
+            let frame = encode_framed_payload(
+                FRAME_KIND_COMPONENT_DELTA,
+                encode_replication_message(message),
+            );
+            self.command_channel
+                .send(&Bytes::from(frame))
+                .await
+                .map_err(|err| TransportError::WebRtc(err.to_string()))?;
+        }
+        self.metrics.update(|m| {
+            m.kind = TransportKind::WebRtc;
+            m.packets_sent = m.packets_sent.saturating_add(messages.len() as u64);
+        });
+        Ok(())
+    }
+
+    /// `resync` asks the publisher for a fresh snapshot.
+    pub async fn send_replication_ack(
+        &self,
+        sequence: u64,
+        resync: bool,
+    ) -> Result<(), TransportError> {
+        let ack = encode_replication_ack(sequence, resync, current_time_millis());
+        self.command_channel
+            .send(&Bytes::from(encode_framed_payload(
+                FRAME_KIND_REPLICATION_ACK,
+                ack,
+            )))
+            .await
+            .map_err(|err| TransportError::WebRtc(err.to_string()))?;
+        Ok(())
+    }
+
+    pub async fn receive_replication_frame(
+        &self,
+        timeout: Duration,
+    ) -> Result<Option<ReplicationFrame>, TransportError> {
+        loop {
+            if let Some(frame) = self.inbound.pop_replication() {
+                return Ok(Some(frame));
+            }
+            let frame = {
+                let mut guard = self.command_inbox.lock().await;
+                match tokio::time::timeout(timeout, guard.recv()).await {
+                    Ok(Some(frame)) => frame,
+                    Ok(None) | Err(_) => return Ok(None),
+                }
+            };
+            self.inbound.route(&frame)?;
+        }
+    }
+
     pub async fn send_voice_packet(&self, packet: &VoicePacket) -> Result<(), TransportError> {
         let channel = self
             .voice_channel
@@ -1095,6 +1328,16 @@ impl CommandTransport {
         }
     }
 
+    /// WebRTC peers skip the QUIC handshake, so they stay on the JSON fallback.
+    pub fn packet_encoding(&self) -> PacketEncoding {
+        match self {
+            CommandTransport::Quic(session) => {
+                PacketEncoding::negotiate(&session.handshake().capability_mask)
+            }
+            CommandTransport::WebRtc(_) => PacketEncoding::Json,
+        }
+    }
+
     pub fn voice_metrics_handle(&self) -> Option<VoiceDiagnosticsHandle> {
         match self {
             CommandTransport::Quic(_) => None,
@@ -1122,6 +1365,65 @@ impl CommandTransport {
         }
     }
 
+    pub async fn send_preview_packets(
+        &self,
+        packets: &[PreviewPacket],
+    ) -> Result<(), TransportError> {
+        match self {
+            CommandTransport::Quic(session) => session.send_preview_packets(packets).await,
+            CommandTransport::WebRtc(_) => Err(TransportError::Unsupported(
+                "preview datagrams not available on WebRTC transport",
+            )),
+        }
+    }
+
+    pub async fn receive_preview_packet(
+        &self,
+        timeout: Duration,
+    ) -> Result<Option<PreviewPacket>, TransportError> {
+        match self {
+            CommandTransport::Quic(session) => session.receive_preview_packet(timeout).await,
+            CommandTransport::WebRtc(_) => Ok(None),
+        }
+    }
+
+    pub async fn send_replication_messages(
+        &self,
+        messages: &[ReplicationMessage],
+    ) -> Result<(), TransportError> {
+        match self {
+            CommandTransport::Quic(session) => session.send_replication_messages(messages).await,
+            CommandTransport::WebRtc(transport) => {
+                transport.send_replication_messages(messages).await
+            }
+        }
+    }
+
+    pub async fn send_replication_ack(
+        &self,
+        sequence: u64,
+        resync: bool,
+    ) -> Result<(), TransportError> {
+        match self {
+            CommandTransport::Quic(session) => session.send_replication_ack(sequence, resync).await,
+            CommandTransport::WebRtc(transport) => {
+                transport.send_replication_ack(sequence, resync).await
+            }
+        }
+    }
+
+    pub async fn receive_replication_frame(
+        &self,
+        timeout: Duration,
+    ) -> Result<Option<ReplicationFrame>, TransportError> {
+        match self {
+            CommandTransport::Quic(session) => session.receive_replication_frame(timeout).await,
+            CommandTransport::WebRtc(transport) => {
+                transport.receive_replication_frame(timeout).await
+            }
+        }
+    }
+
     pub async fn send_voice_packet(&self, packet: &VoicePacket) -> Result<(), TransportError> {
         match self {
             CommandTransport::Quic(_) => Err(TransportError::Unsupported(
@@ -1199,11 +1501,14 @@ async fn establish_client_session(
     let client_nonce = random_nonce();
     let client_public_key = handshake.signing_key.verifying_key().to_bytes();
 
+    let mut capabilities = handshake.capabilities.clone();
+    capabilities.extend(PacketEncoding::capabilities());
+    capabilities.extend(handshake.compression.capabilities());
     let hello_bytes = build_session_hello(
         handshake.protocol_version,
         handshake.schema_hash,
         &client_nonce,
-        &handshake.capabilities,
+        &capabilities,
         handshake.auth_token.as_deref(),
         &client_public_key,
     );
@@ -1217,6 +1522,8 @@ async fn establish_client_session(
         handshake.schema_hash,
     )?;
 
+    let compressor =
+        FrameCompressor::negotiate(handshake.compression, &ack.capability_mask)?.map(Arc::new);
     let replication = connection.open_bi().await?;
     let assets = connection.open_bi().await?;
 
@@ -1239,14 +1546,18 @@ async fn establish_client_session(
     Ok(TransportSession {
         connection,
         control_send: control_send_arc,
-        replication: FramedStream::new(replication.0, replication.1),
-        assets: FramedStream::new(assets.0, assets.1),
+        replication: FramedStream::new(replication.0, replication.1, compressor.clone()),
+        assets: FramedStream::new(assets.0, assets.1, compressor.clone()),
         metrics,
         heartbeat,
+        inbound: InboundFrames::default(),
+        compressor,
         handshake: HandshakeSummary {
             session_id: ack.session_id,
             assigned_role: ack.assigned_role,
             capability_mask: ack.capability_mask,
+            network_prefix: ack.network_prefix,
+            host_author: ack.host_author,
             client_public_key,
             server_public_key: ack.server_public_key,
             client_nonce,
@@ -1268,8 +1579,10 @@ async fn establish_server_session(
         handshake.schema_hash,
     )?;
 
-    let capability_mask =
-        negotiate_capabilities(&handshake.capabilities, &session_request.capabilities);
+    let mut offered = handshake.capabilities.clone();
+    offered.extend(PacketEncoding::capabilities());
+    offered.extend(handshake.compression.capabilities());
+    let capability_mask = negotiate_capabilities(&offered, &session_request.capabilities);
 
     let server_nonce = random_nonce();
     let session_id = rand::random::<u64>();
@@ -1284,10 +1597,14 @@ async fn establish_server_session(
         assigned_role,
         &capability_mask,
         &server_public_key,
+        handshake.network_prefix,
+        handshake.host_author,
     );
 
     write_frame_raw(&mut control_send, &ack_bytes).await?;
 
+    let compressor =
+        FrameCompressor::negotiate(handshake.compression, &capability_mask)?.map(Arc::new);
     let replication = connection.accept_bi().await?;
     let assets = connection.accept_bi().await?;
 
@@ -1309,14 +1626,18 @@ async fn establish_server_session(
     Ok(TransportSession {
         connection,
         control_send: control_send_arc,
-        replication: FramedStream::new(replication.0, replication.1),
-        assets: FramedStream::new(assets.0, assets.1),
+        replication: FramedStream::new(replication.0, replication.1, compressor.clone()),
+        assets: FramedStream::new(assets.0, assets.1, compressor.clone()),
         metrics,
         heartbeat,
+        inbound: InboundFrames::default(),
+        compressor,
         handshake: HandshakeSummary {
             session_id,
             assigned_role,
             capability_mask,
+            network_prefix: 0,
+            host_author: handshake.host_author,
             client_public_key: session_request.client_public_key,
             server_public_key,
             client_nonce: session_request.client_nonce,
@@ -1335,6 +1656,8 @@ struct SessionAckData {
     session_id: u64,
     assigned_role: u32,
     capability_mask: Vec<u32>,
+    network_prefix: u32,
+    host_author: u64,
     server_public_key: [u8; 32],
     server_nonce: Vec<u8>,
 }
@@ -1443,6 +1766,8 @@ fn parse_session_ack(
         session_id: ack.session_id(),
         assigned_role: ack.assigned_role(),
         capability_mask,
+        network_prefix: ack.network_prefix(),
+        host_author: ack.host_author(),
         server_public_key: public_key_bytes.as_slice().try_into().unwrap(),
         server_nonce,
     })
@@ -1510,6 +1835,8 @@ fn build_session_ack(
     assigned_role: u32,
     capability_mask: &[u32],
     server_public_key: &[u8],
+    network_prefix: u32,
+    host_author: u64,
 ) -> Vec<u8> {
     let mut builder = FlatBufferBuilder::with_capacity(HANDSHAKE_CAPACITY);
     let nonce_vec = builder.create_vector(server_nonce);
@@ -1525,6 +1852,8 @@ fn build_session_ack(
             assigned_role,
             capability_mask: Some(mask_vec),
             server_public_key: Some(public_key_vec),
+            network_prefix,
+            host_author,
         },
     );
     let header = net::PacketHeader::create(
@@ -1626,7 +1955,6 @@ impl HeartbeatActor {
                 send_handle_metrics.update(|m| {
                     m.kind = TransportKind::Quic;
                     m.packets_sent = m.packets_sent.saturating_add(1);
-                    m.compression_ratio = 1.0;
                 });
             }
         });
@@ -1659,7 +1987,6 @@ impl HeartbeatActor {
                                 m.rtt_ms = diff;
                                 m.jitter_ms = (diff - prev).abs();
                                 m.packets_received = m.packets_received.saturating_add(1);
-                                m.compression_ratio = 1.0;
                             });
                         }
                     }
@@ -1695,13 +2022,105 @@ fn random_nonce() -> Vec<u8> {
 enum DecodedReplicationFrame {
     Command(CommandPacket),
     ComponentDelta(Vec<u8>),
+    ReplicationAck(Vec<u8>),
     Unknown(u8, Vec<u8>),
 }
 
+#[derive(Debug, Clone)]
+pub enum ReplicationFrame {
+    Message(ReplicationMessage),
+    Ack { sequence: u64, resync: bool },
+}
+
+/// Frames and datagrams read by a receive call that was not waiting for them, held until
+/// the matching receive call.
+#[derive(Default)]
+struct InboundFrames {
+    commands: Mutex<VecDeque<CommandPacket>>,
+    replication: Mutex<VecDeque<ReplicationFrame>>,
+    previews: Mutex<VecDeque<PreviewPacket>>,
+}
+
+impl InboundFrames {
+    fn pop_command(&self) -> Option<CommandPacket> {
+        self.commands.lock().ok()?.pop_front()
+    }
+
+    fn pop_preview(&self) -> Option<PreviewPacket> {
+        self.previews.lock().ok()?.pop_front()
+    }
+
+    /// Datagrams carry JSON previews or kind-prefixed unreliable replication updates.
+    fn route_datagram(&self, bytes: &[u8]) {
+        if bytes.first() == Some(&FRAME_KIND_COMPONENT_DELTA) {
+            self.stash_replication(FRAME_KIND_COMPONENT_DELTA, &bytes[1..]);
+            return;
+        }
+        if bytes.len() > MAX_PREVIEW_PACKET_BYTES {
+            return;
+        }
+        match PreviewPacket::decode(bytes) {
+            Ok(packet) => {
+                if let Ok(mut queue) = self.previews.lock() {
+                    queue.push_back(packet);
+                }
+            }
+            Err(err) => log::debug!("[transport] ignoring malformed preview datagram: {err}"),
+        }
+    }
+
+    fn pop_replication(&self) -> Option<ReplicationFrame> {
+        self.replication.lock().ok()?.pop_front()
+    }
+
+    fn stash_replication(&self, kind: u8, bytes: &[u8]) {
+        let frame = if kind == FRAME_KIND_REPLICATION_ACK {
+            decode_replication_ack(bytes)
+                .map(|(sequence, resync)| ReplicationFrame::Ack { sequence, resync })
+        } else {
+            decode_replication_message(bytes).map(ReplicationFrame::Message)
+        };
+        match frame {
+            Ok(frame) => {
+                if let Ok(mut queue) = self.replication.lock() {
+                    queue.push_back(frame);
+                }
+            }
+            Err(err) => log::debug!(
+                "[transport] dropping malformed replication frame ({} bytes): {err}",
+                bytes.len()
+            ),
+        }
+    }
+
+    fn route(&self, frame: &[u8]) -> Result<(), TransportError> {
+        match decode_replication_frame(frame)? {
+            DecodedReplicationFrame::Command(packet) => {
+                if let Ok(mut queue) = self.commands.lock() {
+                    queue.push_back(packet);
+                }
+            }
+            DecodedReplicationFrame::ComponentDelta(bytes) => {
+                self.stash_replication(FRAME_KIND_COMPONENT_DELTA, &bytes)
+            }
+            DecodedReplicationFrame::ReplicationAck(bytes) => {
+                self.stash_replication(FRAME_KIND_REPLICATION_ACK, &bytes)
+            }
+            DecodedReplicationFrame::Unknown(kind, payload) => log::warn!(
+                "[transport] ignoring unknown replication frame kind {} ({} bytes)",
+                kind,
+                payload.len()
+            ),
+        }
+        Ok(())
+    }
+}
+
 fn encode_command_packet_frame(packet: &CommandPacket) -> Result<Vec<u8>, TransportError> {
-    let payload =
-        serde_json::to_vec(packet).map_err(|err| TransportError::Serialization(err.to_string()))?;
-    Ok(encode_framed_payload(FRAME_KIND_COMMAND_PACKET, payload))
+    Ok(encode_framed_payload(
+        FRAME_KIND_COMMAND_PACKET,
+        packet.to_wire(),
+    ))
 }
 
 #[cfg(test)]
@@ -1726,11 +2145,12 @@ fn decode_replication_frame(bytes: &[u8]) -> Result<DecodedReplicationFrame, Tra
     let payload = bytes[1..].to_vec();
     match bytes[0] {
         FRAME_KIND_COMMAND_PACKET => {
-            let packet = serde_json::from_slice::<CommandPacket>(&payload)
+            let packet = CommandPacket::from_wire(&payload)
                 .map_err(|err| TransportError::Serialization(err.to_string()))?;
             Ok(DecodedReplicationFrame::Command(packet))
         }
         FRAME_KIND_COMPONENT_DELTA => Ok(DecodedReplicationFrame::ComponentDelta(payload)),
+        FRAME_KIND_REPLICATION_ACK => Ok(DecodedReplicationFrame::ReplicationAck(payload)),
         other => Ok(DecodedReplicationFrame::Unknown(other, payload)),
     }
 }
@@ -1809,6 +2229,7 @@ mod tests {
                 capabilities: vec![1, 4],
                 auth_token: None,
                 signing_key: SigningKey::generate(&mut OsRng),
+                compression: CompressionConfig::default(),
                 heartbeat: heartbeat_cfg,
                 server_name: "localhost".into(),
             },
@@ -1841,7 +2262,10 @@ mod tests {
             schema_hash: 0xABCDu64,
             capabilities: vec![1, 2, 3],
             signing_key: SigningKey::generate(&mut OsRng),
+            compression: CompressionConfig::default(),
             heartbeat: heartbeat_cfg.clone(),
+            network_prefix: 1,
+            host_author: 0,
         };
 
         let server_task = tokio::spawn(async move {
@@ -1866,6 +2290,7 @@ mod tests {
                 capabilities: vec![1, 4],
                 auth_token: None,
                 signing_key: SigningKey::generate(&mut OsRng),
+                compression: CompressionConfig::default(),
                 heartbeat: heartbeat_cfg,
                 server_name: "localhost".into(),
             },
@@ -1916,7 +2341,10 @@ mod tests {
                         schema_hash: 0xABCDu64,
                         capabilities: vec![1, 2, 3],
                         signing_key: server_signing_key,
+                        compression: CompressionConfig::default(),
                         heartbeat: server_heartbeat,
+                        network_prefix: 1,
+                        host_author: 0,
                     },
                 )
                 .await
@@ -1935,6 +2363,7 @@ mod tests {
                 capabilities: vec![1, 4],
                 auth_token: None,
                 signing_key: client_signing_key,
+                compression: CompressionConfig::default(),
                 heartbeat: heartbeat_cfg,
                 server_name: "localhost".into(),
             },
@@ -1982,7 +2411,10 @@ mod tests {
                         schema_hash: 0xABCDu64,
                         capabilities: vec![],
                         signing_key: server_signing_key,
+                        compression: CompressionConfig::default(),
                         heartbeat: HeartbeatConfig::default(),
+                        network_prefix: 1,
+                        host_author: 0,
                     },
                 )
                 .await;
@@ -2000,6 +2432,7 @@ mod tests {
                 capabilities: vec![],
                 auth_token: None,
                 signing_key: client_signing_key,
+                compression: CompressionConfig::default(),
                 heartbeat: HeartbeatConfig {
                     interval: Duration::from_millis(100),
                     timeout: Duration::from_millis(500),
@@ -2042,7 +2475,10 @@ mod tests {
                         schema_hash: 0xABCDu64,
                         capabilities: vec![],
                         signing_key: server_signing_key,
+                        compression: CompressionConfig::default(),
                         heartbeat: HeartbeatConfig::default(),
+                        network_prefix: 1,
+                        host_author: 0,
                     },
                 )
                 .await;
@@ -2060,6 +2496,7 @@ mod tests {
                 capabilities: vec![],
                 auth_token: None,
                 signing_key: client_signing_key,
+                compression: CompressionConfig::default(),
                 heartbeat: HeartbeatConfig {
                     interval: Duration::from_millis(100),
                     timeout: Duration::from_millis(500),
@@ -2101,7 +2538,10 @@ mod tests {
                         schema_hash: 0xABCDu64,
                         capabilities: vec![1, 2, 3],
                         signing_key: server_signing_key,
+                        compression: CompressionConfig::default(),
                         heartbeat: HeartbeatConfig::default(),
+                        network_prefix: 1,
+                        host_author: 5,
                     },
                 )
                 .await
@@ -2120,6 +2560,7 @@ mod tests {
                 capabilities: vec![2, 4, 5],
                 auth_token: None,
                 signing_key: client_signing_key,
+                compression: CompressionConfig::default(),
                 heartbeat: HeartbeatConfig::default(),
                 server_name: "localhost".into(),
             },
@@ -2130,6 +2571,8 @@ mod tests {
         let handshake = client_session.handshake();
         // Only capability 2 is in both client and server sets
         assert_eq!(handshake.capability_mask, vec![2]);
+        assert_eq!(handshake.network_prefix, 1);
+        assert_eq!(handshake.host_author, 5);
 
         client_session.close().await;
     }
@@ -2163,7 +2606,10 @@ mod tests {
                         schema_hash: 0xABCDu64,
                         capabilities: vec![],
                         signing_key: server_signing_key,
+                        compression: CompressionConfig::default(),
                         heartbeat: HeartbeatConfig::default(),
+                        network_prefix: 1,
+                        host_author: 0,
                     },
                 )
                 .await
@@ -2184,6 +2630,7 @@ mod tests {
                 capabilities: vec![],
                 auth_token: None,
                 signing_key: client_signing_key,
+                compression: CompressionConfig::default(),
                 heartbeat: HeartbeatConfig::default(),
                 server_name: "localhost".into(),
             },
@@ -2224,7 +2671,10 @@ mod tests {
                     schema_hash: 0xABCDu64,
                     capabilities: vec![1, 2, 3],
                     signing_key: SigningKey::generate(&mut OsRng),
+                    compression: CompressionConfig::default(),
                     heartbeat: server_heartbeat_cfg.clone(),
+                    network_prefix: 1,
+                    host_author: 0,
                 };
                 if let Some(connecting) = server_endpoint.accept().await
                     && let Ok(session) = accept(connecting, server_handshake).await
@@ -2258,6 +2708,7 @@ mod tests {
                 capabilities: vec![1, 4],
                 auth_token: None,
                 signing_key: client1_key,
+                compression: CompressionConfig::default(),
                 heartbeat: heartbeat_cfg.clone(),
                 server_name: "localhost".into(),
             },
@@ -2283,6 +2734,7 @@ mod tests {
                 capabilities: vec![2, 5],
                 auth_token: None,
                 signing_key: client2_key,
+                compression: CompressionConfig::default(),
                 heartbeat: heartbeat_cfg.clone(),
                 server_name: "localhost".into(),
             },
@@ -2302,6 +2754,94 @@ mod tests {
         let _ = server_task.await;
     }
 
+    #[cfg(feature = "network-zstd")]
+    #[tokio::test]
+    async fn negotiated_zstd_compresses_replication_frames() {
+        let cert_key = build_certified_key();
+        let transport = Arc::new(quinn::TransportConfig::default());
+        let heartbeat_cfg = HeartbeatConfig {
+            interval: Duration::from_millis(250),
+            timeout: Duration::from_secs(1),
+        };
+
+        let mut server_cfg = server_config(&cert_key);
+        server_cfg.transport_config(transport.clone());
+        let server_endpoint =
+            Endpoint::server(server_cfg, "127.0.0.1:0".parse().unwrap()).expect("server endpoint");
+        let server_addr = server_endpoint.local_addr().expect("server addr");
+        let frame = vec![7u8; 64 * 1024];
+        let expected = frame.clone();
+
+        let server_task = tokio::spawn(async move {
+            if let Some(connecting) = server_endpoint.accept().await
+                && let Ok(session) = accept(
+                    connecting,
+                    ServerHandshake {
+                        protocol_version: 1,
+                        schema_hash: 0xABCDu64,
+                        capabilities: vec![1, 2, 3],
+                        signing_key: SigningKey::generate(&mut OsRng),
+                        compression: CompressionConfig::default(),
+                        heartbeat: heartbeat_cfg,
+                        network_prefix: 1,
+                        host_author: 0,
+                    },
+                )
+                .await
+            {
+                let received = session
+                    .replication
+                    .read_frame(Duration::from_secs(1))
+                    .await
+                    .expect("read frame");
+                assert_eq!(received, expected);
+                session.close().await;
+            }
+        });
+
+        let mut client_cfg = client_config(&cert_key);
+        client_cfg.transport_config(transport);
+        let client_endpoint = client_endpoint(client_cfg);
+
+        let client_session = connect(
+            &client_endpoint,
+            server_addr,
+            ClientHandshake {
+                protocol_version: 1,
+                schema_hash: 0xABCDu64,
+                capabilities: vec![1, 4],
+                auth_token: None,
+                signing_key: SigningKey::generate(&mut OsRng),
+                compression: CompressionConfig::default(),
+                heartbeat: HeartbeatConfig {
+                    interval: Duration::from_millis(250),
+                    timeout: Duration::from_secs(1),
+                },
+                server_name: "localhost".into(),
+            },
+        )
+        .await
+        .expect("client handshake");
+
+        assert!(
+            client_session
+                .handshake()
+                .capability_mask
+                .contains(&crate::network::compression::CAPABILITY_ZSTD_FRAMES)
+        );
+        client_session
+            .replication
+            .write_frame(&frame)
+            .await
+            .expect("write frame");
+        let stats = client_session.compression_stats().expect("zstd negotiated");
+        assert_eq!(stats.compressed_frames, 1);
+        assert!(stats.ratio() > 10.0);
+
+        let _ = server_task.await;
+        client_session.close().await;
+    }
+
     #[tokio::test]
     async fn assets_stream_transfers_large_payloads() {
         const PAYLOAD_SIZE: usize = 2 * 1024 * 1024; // 2 MiB
@@ -2329,7 +2869,10 @@ mod tests {
                         schema_hash: 0xABCDu64,
                         capabilities: vec![1, 2, 3],
                         signing_key: SigningKey::generate(&mut OsRng),
+                        compression: CompressionConfig::default(),
                         heartbeat: heartbeat_cfg,
+                        network_prefix: 1,
+                        host_author: 0,
                     },
                 )
                 .await
@@ -2358,6 +2901,7 @@ mod tests {
                 capabilities: vec![1, 4],
                 auth_token: None,
                 signing_key: SigningKey::generate(&mut OsRng),
+                compression: CompressionConfig::default(),
                 heartbeat: HeartbeatConfig {
                     interval: Duration::from_millis(250),
                     timeout: Duration::from_secs(1),
@@ -2415,7 +2959,10 @@ mod tests {
                         schema_hash: 0xABCDu64,
                         capabilities: vec![1, 2, 3],
                         signing_key: server_signing_key,
+                        compression: CompressionConfig::default(),
                         heartbeat: heartbeat_cfg,
+                        network_prefix: 1,
+                        host_author: 0,
                     },
                 )
                 .await
@@ -2452,6 +2999,7 @@ mod tests {
                 capabilities: vec![1, 2, 3],
                 auth_token: None,
                 signing_key: client_signing_key,
+                compression: CompressionConfig::default(),
                 heartbeat: HeartbeatConfig {
                     interval: Duration::from_millis(200),
                     timeout: Duration::from_secs(1),
@@ -2536,7 +3084,10 @@ mod tests {
                         schema_hash: 0xABCDu64,
                         capabilities: vec![1, 2, 3],
                         signing_key: server_signing_key,
+                        compression: CompressionConfig::default(),
                         heartbeat: heartbeat_cfg,
+                        network_prefix: 1,
+                        host_author: 0,
                     },
                 )
                 .await
@@ -2569,6 +3120,7 @@ mod tests {
                 capabilities: vec![1, 2, 3],
                 auth_token: None,
                 signing_key: SigningKey::generate(&mut OsRng),
+                compression: CompressionConfig::default(),
                 heartbeat: HeartbeatConfig {
                     interval: Duration::from_millis(200),
                     timeout: Duration::from_secs(1),
@@ -2586,6 +3138,8 @@ mod tests {
             nonce: 9,
             timestamp_ms: current_time_millis(),
             payload: vec![0u8; MAX_COMMAND_PACKET_BYTES + 1],
+            encoding: PacketEncoding::Json,
+            fragment: None,
         };
 
         let valid_entry = CommandEntry::new(
@@ -2616,6 +3170,42 @@ mod tests {
         let _ = server_task.await;
     }
 
+    #[test]
+    fn replication_frames_wait_for_their_receiver() {
+        use crate::network::command_log::{AuthorId, CommandBatch};
+
+        let inbound = InboundFrames::default();
+        let batch = CommandBatch {
+            sequence: 3,
+            nonce: 1,
+            timestamp_ms: 10,
+            author: AuthorId(2),
+            entries: Vec::new(),
+        };
+        let packet = CommandPacket::from_batch(&batch).expect("command packet");
+        let ack = encode_framed_payload(
+            FRAME_KIND_REPLICATION_ACK,
+            encode_replication_ack(7, true, 10),
+        );
+        inbound.route(&ack).expect("route ack");
+        inbound
+            .route(&encode_command_packet_frame(&packet).expect("command frame"))
+            .expect("route command");
+        inbound
+            .route(&encode_component_delta_frame(&[0xAA, 0xBB]))
+            .expect("malformed delta is dropped");
+
+        assert!(matches!(
+            inbound.pop_replication(),
+            Some(ReplicationFrame::Ack {
+                sequence: 7,
+                resync: true
+            })
+        ));
+        assert!(inbound.pop_replication().is_none());
+        assert_eq!(inbound.pop_command(), Some(packet));
+    }
+
     #[test]
     fn replication_frame_decoding_classifies_component_delta() {
         let payload = vec![1, 2, 3, 4];
@@ -2660,7 +3250,10 @@ mod tests {
                         schema_hash: 0xABCDu64,
                         capabilities: vec![1, 2, 3],
                         signing_key: SigningKey::generate(&mut OsRng),
+                        compression: CompressionConfig::default(),
                         heartbeat: heartbeat_cfg,
+                        network_prefix: 1,
+                        host_author: 0,
                     },
                 )
                 .await
@@ -2683,6 +3276,7 @@ mod tests {
                 capabilities: vec![1, 4],
                 auth_token: None,
                 signing_key: SigningKey::generate(&mut OsRng),
+                compression: CompressionConfig::default(),
                 heartbeat: HeartbeatConfig {
                     interval: Duration::from_millis(50),
                     timeout: Duration::from_millis(200),
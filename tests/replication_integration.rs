@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
 use theta_engine::ecs::World;
 use theta_engine::network::DiffPayload;
-use theta_engine::network::replication::{DeltaTracker, ReplicationRegistry, WorldSnapshotBuilder};
+use theta_engine::network::replication::{
+    DeltaTracker, ReplicationRegistry, WorldSnapshotBuilder, decode_component,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 struct Position {
@@ -70,11 +72,11 @@ fn full_snapshot_to_delta_convergence() {
             // For this test, verify serialized bytes match
             if component.component.type_name.contains("Position") {
                 let pos: Position =
-                    serde_json::from_slice(&component.bytes).expect("deserialize position");
+                    decode_component(&component.bytes).expect("deserialize position");
                 assert_eq!(pos.x, 1.0);
             } else if component.component.type_name.contains("Velocity") {
                 let vel: Velocity =
-                    serde_json::from_slice(&component.bytes).expect("deserialize velocity");
+                    decode_component(&component.bytes).expect("deserialize velocity");
                 assert_eq!(vel.dx, 0.1);
             }
         }